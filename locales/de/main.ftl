@@ -0,0 +1,356 @@
+# Shown in the language picker, in the language itself
+language-name = Deutsch
+
+## Menus
+
+menu-login = Anmelden
+menu-register = Registrieren
+menu-profile = Profil
+menu-messages = Nachrichten
+menu-messages-unread = Nachrichten ({ $count } ungelesen)
+menu-security = Sicherheit
+footer-text = Dies ist die Fußzeile
+footer-submit-banner = Banner einreichen
+verify-notice = Bitte sieh in { $email } nach einem Link, um deine E-Mail-Adresse zu bestätigen.
+verify-notice-resend = Erneut senden
+banner-submit-header = Banner einreichen
+banner-submit-explanation = Banner werden mit 400 mal 100 Pixeln oben auf jeder Seite angezeigt, Cornerpets mit 88 mal 88 Pixeln links und rechts davon. Ein Admin sieht sich deine Einsendung an, bevor sie angezeigt wird.
+banner-submit-thanks = Danke, dein Banner wartet auf Freigabe.
+banner-submit-kind = Art
+banner-submit-file = PNG-, JPEG-, GIF- oder WebP-Bild, höchstens { $size } MiB
+banner-submit = Einreichen
+verify-email = E-Mail-Bestätigung
+verify-email-resent = Wir haben einen neuen Bestätigungslink an { $email } geschickt.
+verify-email-verified = Deine E-Mail-Adresse wurde bestätigt.
+verify-email-invalid = Dieser Bestätigungslink ist ungültig, abgelaufen oder wurde schon verwendet.
+banner-submit-too-large = Banner dürfen höchstens { $size } MiB groß sein
+banner-submit-unsupported-type = Banner müssen PNG-, JPEG-, GIF- oder WebP-Bilder sein
+
+## Listings
+
+pagination-previous = Zurück
+pagination-next = Weiter
+pagination-page = Seite { $page }
+topics-by = von { $author }
+topics-none = Es gibt noch keine Themen
+topics-feed = Feed neuer Themen
+topic-started-by = Begonnen von { $author }
+topic-feed = Feed neuer Beiträge
+topic-report = Thema melden
+post-report = Melden
+pending-marker = Wartet auf Freigabe
+topic-pending-notice = Nur du siehst dieses Thema, bis es von einem Moderator freigegeben wird
+post-attachment-size = { $count ->
+    [one] { $count } Byte
+   *[other] { $count } Bytes
+}
+# The notice on the topic list as new topics come in. $shown is what the page fills the count into.
+live-new-topics = { $count ->
+    [one] { $shown } neues Thema
+   *[other] { $shown } neue Themen
+}
+topics-feed-title = Neueste Themen
+topic-feed-title = Neue Beiträge in { $title }
+
+## Logging in and registering
+
+form-register = Registrieren
+form-login = Anmelden
+form-user-name = Benutzername
+form-email = E-Mail
+form-password = Passwort
+form-confirm-password = Passwort bestätigen
+form-forgot-password = Passwort vergessen?
+form-honeypot = Lass dieses Feld leer
+login-incorrect = Benutzername oder Passwort ist falsch
+banned = Du bist bis { $until } gesperrt. Grund: { $reason }
+banned-permanently = Du bist dauerhaft gesperrt. Grund: { $reason }
+banned-from-posting = Du darfst bis { $until } nichts mehr schreiben. Grund: { $reason }
+banned-from-posting-permanently = Du darfst dauerhaft nichts mehr schreiben. Grund: { $reason }
+two-factor-code-invalid = Dieser Code ist ungültig
+two-factor-locked-out = Zu viele falsche Codes, bitte versuch es nach { $until } erneut
+register-user-name-length = Der Benutzername muss zwischen { $min } und { $max } Zeichen lang sein
+register-user-name-characters = Der Benutzername darf nur aus Buchstaben, Ziffern und Leerzeichen bestehen
+register-email-invalid = Die E-Mail-Adresse ist ungültig
+register-password-insecure = Das Passwort ist nicht sicher
+register-password-length = Das Passwort muss mindestens { $min } Zeichen lang sein
+register-password-characters = Das Passwort darf nur aus Buchstaben, Ziffern und Leerzeichen bestehen
+register-password-mismatch = Die Passwörter stimmen nicht überein
+register-user-name-taken = Der Benutzername ist schon vergeben
+register-email-taken = Die E-Mail-Adresse wird schon verwendet
+spam-rejected = Das sieht nach Spam aus und wurde deshalb nicht angenommen. Falls es keiner ist, warte bitte eine Weile und versuch es erneut
+forgot-password = Passwort vergessen
+forgot-password-sent = Falls ein Konto diese E-Mail-Adresse verwendet, haben wir ihm einen Link zum Zurücksetzen des Passworts geschickt.
+forgot-password-send = Link zum Zurücksetzen senden
+reset-password = Passwort zurücksetzen
+reset-password-new = Neues Passwort
+reset-password-confirm = Neues Passwort bestätigen
+reset-password-save = Passwort festlegen
+reset-password-request-again = Einen neuen Link anfordern
+two-factor-login-instructions = Gib den Code aus deiner Authenticator-App oder einen deiner Wiederherstellungscodes ein.
+two-factor-verify = Bestätigen
+reset-password-link-invalid = Dieser Link ist ungültig
+reset-password-link-expired = Dieser Link ist abgelaufen
+reset-password-link-used = Dieser Link wurde schon verwendet
+
+## Profile
+
+profile-language = Sprache
+profile-language-browser = Wie im Browser
+profile-language-save = Sprache speichern
+profile-language-unknown = Unbekannte Sprache
+profile = Profil
+profile-avatar = Avatar
+profile-avatar-file = PNG-, JPEG-, GIF- oder WebP-Bild, wird quadratisch zugeschnitten
+profile-avatar-upload = Avatar hochladen
+profile-avatar-remove = Avatar entfernen
+profile-signature = Signatur
+profile-signature-explanation = Wird unter jedem deiner Beiträge angezeigt, höchstens { $count } Zeichen
+profile-signature-save = Signatur speichern
+api-tokens = API-Tokens
+api-tokens-explanation = Mit API-Tokens können Skripte und Bots die API unter /api/v1 in deinem Namen verwenden, indem sie diesen Header senden:
+api-tokens-explanation-revoke = Jeder, der ein Token hat, kann mit dessen Berechtigungen handeln, also widerrufe alle, die du nicht mehr verwendest.
+api-tokens-created = Hier ist dein neues Token. Kopiere es jetzt, es wird nicht noch einmal angezeigt:
+api-tokens-read-only = nur lesen
+api-tokens-created-at = erstellt { $time }
+api-tokens-last-used = zuletzt verwendet
+api-tokens-never-used = nie verwendet
+api-tokens-revoke = Widerrufen
+api-tokens-none = Du hast keine API-Tokens
+api-tokens-new = Neues Token
+api-tokens-name = Name
+api-tokens-scope-write = Deine Themen und Beiträge erstellen, bearbeiten und löschen
+api-tokens-scope-moderate = Deine Moderationsrechte verwenden
+api-tokens-create = Token erstellen
+profile-signature-length = Signaturen dürfen höchstens { $max } Zeichen lang sein
+profile-signature-lines = { $max ->
+    [one] Signaturen dürfen höchstens { $max } Zeile lang sein
+   *[other] Signaturen dürfen höchstens { $max } Zeilen lang sein
+}
+upload-too-large = Dateien dürfen höchstens { $size } MiB groß sein
+upload-too-many = { $count ->
+    [one] Es kann höchstens { $count } Datei auf einmal hochgeladen werden
+   *[other] Es können höchstens { $count } Dateien auf einmal hochgeladen werden
+}
+upload-no-file = Es wurde keine Datei hochgeladen
+upload-invalid-image = Das Bild konnte nicht gelesen werden: { $error }
+attachment-too-large = Anhänge dürfen höchstens { $size } MiB groß sein
+attachment-too-many = Beiträge können höchstens { $count } Anhänge haben
+attachment-unsupported-type = Nur PNG-, JPEG-, GIF- und WebP-Bilder, PDFs und reiner Text können angehängt werden
+api-tokens-name-length = Der Name muss zwischen 1 und { $max } Zeichen lang sein
+
+## Settings
+
+settings-timezone-unknown = Unbekannte Zeitzone
+settings-per-page-range = Seiten müssen zwischen { $min } und { $max } Einträge zeigen
+settings-theme-unknown = Unbekanntes Theme
+settings = Einstellungen
+settings-display = Anzeige
+settings-timezone = Zeitzone
+settings-theme = Theme
+settings-theme-default = Standard des Forums ({ $theme })
+settings-topics-per-page = Themen pro Seite
+settings-posts-per-page = Beiträge pro Seite
+settings-email = E-Mail
+settings-email-notifications = Schick mir eine E-Mail, wenn ich eine private Nachricht bekomme
+settings-save = Speichern
+
+## Security
+
+two-factor = Zwei-Faktor-Authentifizierung
+two-factor-code = Code
+two-factor-required = Deine Rolle erfordert die Zwei-Faktor-Authentifizierung. Ihre Rechte stehen dir erst zur Verfügung, wenn du sie einschaltest.
+two-factor-recovery-codes-new = Das sind deine Wiederherstellungscodes. Jeder kann einmal statt eines Codes aus deiner Authenticator-App verwendet werden. Bewahre sie sicher auf, sie werden nicht noch einmal angezeigt.
+two-factor-enabled = { $count ->
+    [one] Die Zwei-Faktor-Authentifizierung ist eingeschaltet. Du hast noch { $count } unbenutzten Wiederherstellungscode.
+   *[other] Die Zwei-Faktor-Authentifizierung ist eingeschaltet. Du hast noch { $count } unbenutzte Wiederherstellungscodes.
+}
+two-factor-recovery-codes-replace = Neue Wiederherstellungscodes
+two-factor-recovery-codes-replace-submit = Wiederherstellungscodes ersetzen
+two-factor-disable = Ausschalten
+two-factor-enroll-instructions = Scanne diesen QR-Code mit deiner Authenticator-App oder gib den Schlüssel von Hand ein, und gib dann den angezeigten Code ein.
+two-factor-key = Schlüssel:
+two-factor-open-app = In der Authenticator-App öffnen
+two-factor-enable = Einschalten
+two-factor-start-over = Mit einem neuen Schlüssel neu beginnen
+two-factor-explanation = Die Zwei-Faktor-Authentifizierung fragt beim Anmelden nach einem Code aus einer Authenticator-App auf deinem Handy, damit dein Passwort allein nicht für den Zugang zu deinem Konto reicht.
+two-factor-set-up = Zwei-Faktor-Authentifizierung einrichten
+two-factor-not-enrolled = Die Zwei-Faktor-Authentifizierung wurde nicht eingerichtet
+two-factor-password-incorrect = Falsches Passwort
+two-factor-required-cannot-disable = Deine Rolle erfordert, dass die Zwei-Faktor-Authentifizierung eingeschaltet ist
+
+## Dates
+
+time-just-now = gerade eben
+time-minutes-ago = { $count ->
+    [one] vor { $count } Minute
+   *[other] vor { $count } Minuten
+}
+time-in-minutes = { $count ->
+    [one] in { $count } Minute
+   *[other] in { $count } Minuten
+}
+time-hours-ago = { $count ->
+    [one] vor { $count } Stunde
+   *[other] vor { $count } Stunden
+}
+time-in-hours = { $count ->
+    [one] in { $count } Stunde
+   *[other] in { $count } Stunden
+}
+time-days-ago = { $count ->
+    [one] vor { $count } Tag
+   *[other] vor { $count } Tagen
+}
+time-in-days = { $count ->
+    [one] in { $count } Tag
+   *[other] in { $count } Tagen
+}
+time-months-ago = { $count ->
+    [one] vor { $count } Monat
+   *[other] vor { $count } Monaten
+}
+time-in-months = { $count ->
+    [one] in { $count } Monat
+   *[other] in { $count } Monaten
+}
+time-years-ago = { $count ->
+    [one] vor { $count } Jahr
+   *[other] vor { $count } Jahren
+}
+time-in-years = { $count ->
+    [one] in { $count } Jahr
+   *[other] in { $count } Jahren
+}
+
+## Messages
+
+messages-inbox = Posteingang
+messages-sent = Gesendet
+messages-new = Neue Nachricht
+messages-blocked = Blockierte Benutzer
+messages-blocked-explanation = Blockierte Benutzer können keine Unterhaltungen mit dir beginnen und nicht auf Unterhaltungen antworten, an denen du teilnimmst.
+messages-blocked-none = Du hast niemanden blockiert
+messages-block-user = Benutzer blockieren
+messages-block = Blockieren
+messages-unblock = Nicht mehr blockieren
+messages-participants = Teilnehmer:
+messages-leave = Unterhaltung verlassen
+messages-with = mit
+messages-unread = { $count } ungelesen
+messages-none = Du hast keine Unterhaltungen
+messages-sent-none = Du hast noch keine Nachrichten gesendet
+messages-recipients = Empfänger (durch Kommas getrennt)
+messages-subject = Betreff
+messages-content = Nachricht
+messages-reply = Antworten
+messages-send = Senden
+messages-recipients-none = Mindestens ein Empfänger ist erforderlich
+messages-recipients-too-many = Eine Unterhaltung kann höchstens { $max } Empfänger haben
+messages-recipient-unknown = Es gibt keinen Benutzer namens „{ $user }“
+messages-subject-empty = Der Betreff darf nicht leer sein
+messages-subject-length = Der Betreff darf höchstens { $max } Zeichen lang sein
+messages-content-empty = Die Nachricht darf nicht leer sein
+messages-content-length = Die Nachricht darf höchstens { $max } Zeichen lang sein
+messages-blocked-by = { $user } nimmt keine Nachrichten von dir an
+messages-block-self = Du kannst dich nicht selbst blockieren
+
+## Reports
+
+report-post = Einen Beitrag melden
+report-topic = Ein Thema melden
+report-post-by = Ein Beitrag von { $author } in
+report-topic-by = begonnen von { $author }
+report-thanks = Danke, ein Moderator wird sich deine Meldung ansehen.
+report-reason = Was stimmt damit nicht? Moderatoren sehen deinen Namen bei der Meldung.
+report-submit = Melden
+report-reason-empty = Bitte gib einen Grund für die Meldung an
+report-reason-length = Gründe dürfen höchstens { $max } Zeichen lang sein
+report-already-reported = Du hast das schon gemeldet, ein Moderator wird es sich bald ansehen
+report-already-handled = Diese Meldung wurde schon bearbeitet
+report-unknown = Diese Meldung gibt es nicht
+
+## Moderation
+
+moderation-reports = Meldungen
+moderation-pending = Wartend
+moderation-ips = IP-Suche
+reports-open = Offene Meldungen
+reports-explanation = Meldungen zum selben Beitrag oder Thema werden zusammen angezeigt. Erledige sie, sobald du dich um das Problem gekümmert hast, oder verwirf sie, wenn nichts zu tun ist. Beides gilt für alle Meldungen, die damit angezeigt werden.
+reports-post = Beitrag
+reports-topic = Thema
+reports-post-by = von { $author } in
+reports-count = { $count ->
+    [one] { $count } Meldung
+   *[other] { $count } Meldungen
+}
+reports-resolve = Erledigt
+reports-dismiss = Verwerfen
+reports-none = Es gibt keine offenen Meldungen
+reports-handled = Kürzlich bearbeitet
+reports-handled-by = gemeldet von { $reporter } ({ $reason }); { $status ->
+    [resolved] erledigt
+   *[dismissed] verworfen
+} von { $moderator }
+reports-handled-none = Es wurden noch keine Meldungen bearbeitet
+pending = Wartet auf Freigabe
+pending-explanation = Zurückgehaltene Beiträge sind nur hier zu sehen, bis sie freigegeben werden. Wird der erste Beitrag eines zurückgehaltenen Themas freigegeben, wird das Thema mit ihm veröffentlicht, und wird er abgelehnt, wird das Thema gelöscht.
+pending-new-topic = Neues Thema
+pending-post-by = Beitrag von { $author } in
+pending-approve = Freigeben
+pending-reject = Ablehnen
+pending-none = Nichts wartet auf Freigabe
+ips-explanation = Adressen werden erfasst, wenn sich Mitglieder registrieren, anmelden und schreiben. Such nach einem Mitglied, um zu sehen, woher es kam und welche anderen Konten dieselbe Adresse oder dasselbe Subnetz verwendet haben, oder nach einer Adresse oder einem CIDR-Bereich, um alle dort gesehenen Konten zu sehen.
+ips-address = oder IP-Adresse oder -Bereich, z. B. 192.0.2.0/24
+ips-look-up = Suchen
+ips-addresses-used = Von { $user } verwendete Adressen
+ips-addresses-none = Für { $user } wurden keine Adressen erfasst
+ips-anonymized = auf das Subnetz anonymisiert
+ips-same-address = gleiche Adresse
+ips-times = { $count ->
+    [one] { $count } Mal
+   *[other] { $count } Mal
+}
+ips-first-last = zuerst { $first }, zuletzt { $last }
+ips-last = zuletzt { $last }
+ips-ban = sperren
+ips-ban-address = Adresse sperren
+ips-ban-subnet = Subnetz sperren
+ips-bans = Sperren
+ips-other-accounts = Andere Konten mit denselben Adressen oder Subnetzen
+ips-other-accounts-none = Keine anderen Konten
+ips-accounts-seen = In { $network } gesehene Konten
+ips-user-unknown = Es gibt keinen Benutzer namens „{ $user }“
+ips-address-invalid = „{ $ip }“ ist keine IP-Adresse und kein CIDR-Bereich
+
+## Emails
+
+email-greeting = Hallo { $user },
+email-footer = Diese E-Mail wurde von RYOB gesendet, weil diese Adresse zu einem Konto dort gehört.
+email-reset-password-subject = Setze dein Passwort zurück
+email-reset-password = Jemand hat darum gebeten, das Passwort deines RYOB-Kontos zurückzusetzen. Folge dem Link unten, um ein neues Passwort zu wählen. { $hours ->
+    [one] Der Link läuft in { $hours } Stunde ab.
+   *[other] Der Link läuft in { $hours } Stunden ab.
+}
+email-reset-password-ignore = Falls du nicht darum gebeten hast, kannst du diese E-Mail ignorieren, und dein Passwort bleibt unverändert.
+email-verify-email-subject = Bestätige deine E-Mail-Adresse
+email-verify-email = Bitte bestätige, dass dies deine E-Mail-Adresse für RYOB ist, indem du dem Link unten folgst. { $hours ->
+    [one] Der Link läuft in { $hours } Stunde ab.
+   *[other] Der Link läuft in { $hours } Stunden ab.
+}
+email-verify-email-ignore = Falls du dich nicht registriert hast, kannst du diese E-Mail ignorieren.
+email-new-message-subject = Neue Nachricht von { $sender }: { $subject }
+email-new-message = { $sender } hat dir eine Nachricht in „{ $subject }“ geschickt:
+email-new-message-read = Unterhaltung lesen
+email-new-message-settings = Du kannst diese E-Mails in deinen Einstellungen abschalten:
+
+## Admin
+
+admin-ban-scope-unknown = Unbekannter Bereich „{ $scope }“
+admin-ban-days = Sperren dauern eine ganze Zahl von Tagen, oder lass das Feld für eine dauerhafte Sperre leer
+admin-ban-self = Du kannst dich nicht selbst sperren
+admin-ban-reason-empty = Bitte gib einen Grund für die Sperre an, er wird dem gesperrten Benutzer angezeigt
+admin-ban-reason-length = Gründe dürfen höchstens { $max } Zeichen lang sein
+admin-ban-expired = Sperren müssen in der Zukunft enden
+admin-ban-lifted = Diese Sperre gilt nicht mehr
+admin-ban-unknown = Diese Sperre gibt es nicht
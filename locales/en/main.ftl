@@ -0,0 +1,359 @@
+# Shown in the language picker, in the language itself
+language-name = English
+
+## Menus
+
+menu-login = Login
+menu-register = Register
+menu-profile = Profile
+menu-messages = Messages
+menu-messages-unread = Messages ({ $count } unread)
+menu-security = Security
+footer-text = This is the footer
+footer-submit-banner = Submit a banner
+verify-notice = Please check { $email } for a link to verify your email address.
+verify-notice-resend = Send it again
+banner-submit-header = Submit a Banner
+banner-submit-explanation = Banners are shown at 400 by 100 pixels across the top of every page and cornerpets at 88 by 88 pixels either side of them. An admin will look at your submission before it is shown.
+banner-submit-thanks = Thanks, your banner is waiting for approval.
+banner-submit-kind = Kind
+banner-submit-file = PNG, JPEG, GIF or WebP image, at most { $size } MiB
+banner-submit = Submit
+verify-email = Email Verification
+verify-email-resent = We have sent a new verification link to { $email }.
+verify-email-verified = Your email address has been verified.
+verify-email-invalid = This verification link is not valid, has expired or has already been used.
+banner-submit-too-large = Banners must be at most { $size } MiB
+banner-submit-unsupported-type = Banners must be PNG, JPEG, GIF or WebP images
+
+## Listings
+
+pagination-previous = Previous
+pagination-next = Next
+pagination-page = Page { $page }
+topics-by = by { $author }
+topics-none = There are no topics yet
+topics-feed = Feed of new topics
+topic-started-by = Started by { $author }
+topic-feed = Feed of new posts
+topic-report = Report topic
+post-report = Report
+pending-marker = Awaiting approval
+topic-pending-notice = Only you can see this topic until a moderator approves it
+post-attachment-size = { $count ->
+    [one] { $count } byte
+   *[other] { $count } bytes
+}
+# The notice on the topic list as new topics come in. $shown is what the page fills the count into.
+live-new-topics = { $count ->
+    [one] { $shown } new topic
+   *[other] { $shown } new topics
+}
+topics-feed-title = Newest topics
+topic-feed-title = New posts in { $title }
+
+## Logging in and registering
+
+form-register = Register
+form-login = Login
+form-user-name = Username
+form-email = Email
+form-password = Password
+form-confirm-password = Confirm Password
+form-forgot-password = Forgot your password?
+form-honeypot = Leave this field empty
+login-incorrect = Incorrect username or password
+banned = You have been banned until { $until }. Reason: { $reason }
+banned-permanently = You have been banned permanently. Reason: { $reason }
+banned-from-posting = You have been banned from posting until { $until }. Reason: { $reason }
+banned-from-posting-permanently = You have been banned from posting permanently. Reason: { $reason }
+two-factor-code-invalid = That code is not valid
+two-factor-locked-out = Too many incorrect codes, please try again after { $until }
+register-user-name-length = Username must be between { $min } and { $max } characters long
+register-user-name-characters = Username must consist of alphanumeric characters and spaces
+register-email-invalid = Email address is not valid
+register-password-insecure = Password not secure
+register-password-length = { $min ->
+    [one] Password must be { $min } or more character long
+   *[other] Password must be { $min } or more characters long
+}
+register-password-characters = Password must consist of alphanumeric characters and spaces
+register-password-mismatch = Passwords did not match
+register-user-name-taken = Username is already in use
+register-email-taken = Email address is already in use
+spam-rejected = That looks like spam, so it has not been accepted. If it isn't, please wait a while and try again
+forgot-password = Forgot Password
+forgot-password-sent = If an account uses that email address, we have sent it a link to reset the password.
+forgot-password-send = Send Reset Link
+reset-password = Reset Password
+reset-password-new = New Password
+reset-password-confirm = Confirm New Password
+reset-password-save = Set Password
+reset-password-request-again = Request a new reset link
+two-factor-login-instructions = Enter the code from your authenticator app, or one of your recovery codes.
+two-factor-verify = Verify
+reset-password-link-invalid = This link is not valid
+reset-password-link-expired = This link has expired
+reset-password-link-used = This link has already been used
+
+## Profile
+
+profile-language = Language
+profile-language-browser = Same as the browser
+profile-language-save = Save Language
+profile-language-unknown = Unknown language
+profile = Profile
+profile-avatar = Avatar
+profile-avatar-file = PNG, JPEG, GIF or WebP image, cropped to a square
+profile-avatar-upload = Upload Avatar
+profile-avatar-remove = Remove Avatar
+profile-signature = Signature
+profile-signature-explanation = Shown under each of your posts, at most { $count } characters
+profile-signature-save = Save Signature
+api-tokens = API Tokens
+api-tokens-explanation = API tokens let scripts and bots use the API at /api/v1 as you, by sending this header:
+api-tokens-explanation-revoke = Anyone with a token can act with its scopes, so revoke any you no longer use.
+api-tokens-created = Your new token is below. Copy it now, it will not be shown again:
+api-tokens-read-only = read only
+api-tokens-created-at = created { $time }
+api-tokens-last-used = last used
+api-tokens-never-used = never used
+api-tokens-revoke = Revoke
+api-tokens-none = You have no API tokens
+api-tokens-new = New Token
+api-tokens-name = Name
+api-tokens-scope-write = Create, edit and delete your topics and posts
+api-tokens-scope-moderate = Use your moderator privileges
+api-tokens-create = Create Token
+profile-signature-length = Signatures must be at most { $max } characters long
+profile-signature-lines = { $max ->
+    [one] Signatures must be at most { $max } line long
+   *[other] Signatures must be at most { $max } lines long
+}
+upload-too-large = Files must be at most { $size } MiB
+upload-too-many = { $count ->
+    [one] At most { $count } file can be uploaded at once
+   *[other] At most { $count } files can be uploaded at once
+}
+upload-no-file = No file was uploaded
+upload-invalid-image = The image could not be read: { $error }
+attachment-too-large = Attachments must be at most { $size } MiB
+attachment-too-many = Posts can have at most { $count } attachments
+attachment-unsupported-type = Only PNG, JPEG, GIF and WebP images, PDFs and plain text can be attached
+api-tokens-name-length = Name must be between 1 and { $max } characters long
+
+## Settings
+
+settings-timezone-unknown = Unknown timezone
+settings-per-page-range = Pages must show between { $min } and { $max } items
+settings-theme-unknown = Unknown theme
+settings = Settings
+settings-display = Display
+settings-timezone = Timezone
+settings-theme = Theme
+settings-theme-default = Board default ({ $theme })
+settings-topics-per-page = Topics per page
+settings-posts-per-page = Posts per page
+settings-email = Email
+settings-email-notifications = Email me when I am sent a private message
+settings-save = Save
+
+## Security
+
+two-factor = Two-Factor Authentication
+two-factor-code = Code
+two-factor-required = Your role requires two-factor authentication. Its privileges are unavailable until you enable it.
+two-factor-recovery-codes-new = These are your recovery codes. Each can be used once in place of a code from your authenticator app. Keep them somewhere safe, they will not be shown again.
+two-factor-enabled = { $count ->
+    [one] Two-factor authentication is enabled. You have { $count } unused recovery code.
+   *[other] Two-factor authentication is enabled. You have { $count } unused recovery codes.
+}
+two-factor-recovery-codes-replace = New Recovery Codes
+two-factor-recovery-codes-replace-submit = Replace Recovery Codes
+two-factor-disable = Disable
+two-factor-enroll-instructions = Scan this QR code with your authenticator app, or enter the key by hand, then enter the code it shows.
+two-factor-key = Key:
+two-factor-open-app = Open in authenticator app
+two-factor-enable = Enable
+two-factor-start-over = Start over with a new key
+two-factor-explanation = Two-factor authentication asks for a code from an authenticator app on your phone when you log in, so your password alone is not enough to get into your account.
+two-factor-set-up = Set up two-factor authentication
+two-factor-not-enrolled = Two-factor authentication has not been set up
+two-factor-password-incorrect = Incorrect password
+two-factor-required-cannot-disable = Your role requires two-factor authentication to be enabled
+
+## Dates
+
+time-just-now = just now
+time-minutes-ago = { $count ->
+    [one] { $count } minute ago
+   *[other] { $count } minutes ago
+}
+time-in-minutes = { $count ->
+    [one] in { $count } minute
+   *[other] in { $count } minutes
+}
+time-hours-ago = { $count ->
+    [one] { $count } hour ago
+   *[other] { $count } hours ago
+}
+time-in-hours = { $count ->
+    [one] in { $count } hour
+   *[other] in { $count } hours
+}
+time-days-ago = { $count ->
+    [one] { $count } day ago
+   *[other] { $count } days ago
+}
+time-in-days = { $count ->
+    [one] in { $count } day
+   *[other] in { $count } days
+}
+time-months-ago = { $count ->
+    [one] { $count } month ago
+   *[other] { $count } months ago
+}
+time-in-months = { $count ->
+    [one] in { $count } month
+   *[other] in { $count } months
+}
+time-years-ago = { $count ->
+    [one] { $count } year ago
+   *[other] { $count } years ago
+}
+time-in-years = { $count ->
+    [one] in { $count } year
+   *[other] in { $count } years
+}
+
+## Messages
+
+messages-inbox = Inbox
+messages-sent = Sent
+messages-new = New Message
+messages-blocked = Blocked Users
+messages-blocked-explanation = Blocked users cannot start conversations with you or reply to conversations you are in.
+messages-blocked-none = You have not blocked anyone
+messages-block-user = Block a user
+messages-block = Block
+messages-unblock = Unblock
+messages-participants = Participants:
+messages-leave = Leave conversation
+messages-with = with
+messages-unread = { $count } unread
+messages-none = You have no conversations
+messages-sent-none = You have not sent any messages
+messages-recipients = Recipients (separated by commas)
+messages-subject = Subject
+messages-content = Message
+messages-reply = Reply
+messages-send = Send
+messages-recipients-none = At least one recipient is required
+messages-recipients-too-many = A conversation can have at most { $max } recipients
+messages-recipient-unknown = No user named "{ $user }"
+messages-subject-empty = Subject must not be empty
+messages-subject-length = Subject must be at most { $max } characters long
+messages-content-empty = Message must not be empty
+messages-content-length = Message must be at most { $max } characters long
+messages-blocked-by = { $user } is not accepting messages from you
+messages-block-self = You cannot block yourself
+
+## Reports
+
+report-post = Report a Post
+report-topic = Report a Topic
+report-post-by = A post by { $author } in
+report-topic-by = started by { $author }
+report-thanks = Thanks, a moderator will look at your report.
+report-reason = What is wrong with it? Moderators will see your name with the report.
+report-submit = Report
+report-reason-empty = Please give a reason for the report
+report-reason-length = Reasons must be at most { $max } characters long
+report-already-reported = You have already reported this, a moderator will look at it soon
+report-already-handled = This report has already been handled
+report-unknown = No such report
+
+## Moderation
+
+moderation-reports = Reports
+moderation-pending = Pending
+moderation-ips = IP Lookup
+reports-open = Open Reports
+reports-explanation = Reports about the same post or topic are shown together. Resolve them once you have dealt with the problem, or dismiss them if nothing needs to be done. Either handles all of the reports shown with it.
+reports-post = Post
+reports-topic = Topic
+reports-post-by = by { $author } in
+reports-count = { $count ->
+    [one] { $count } report
+   *[other] { $count } reports
+}
+reports-resolve = Resolve
+reports-dismiss = Dismiss
+reports-none = There are no open reports
+reports-handled = Recently Handled
+reports-handled-by = reported by { $reporter } ({ $reason }); { $status ->
+    [resolved] resolved
+   *[dismissed] dismissed
+} by { $moderator }
+reports-handled-none = No reports have been handled yet
+pending = Awaiting Approval
+pending-explanation = Held posts are only visible here until they are approved. Approving the first post of a held topic publishes the topic with it, and rejecting it deletes the topic.
+pending-new-topic = New topic
+pending-post-by = Post by { $author } in
+pending-approve = Approve
+pending-reject = Reject
+pending-none = Nothing is waiting for approval
+ips-explanation = Addresses are recorded when members register, log in and post. Look up a member to see where they have been and which other accounts used the same address or subnet, or look up an address or CIDR range to see every account seen there.
+ips-address = or IP address or range, e.g. 192.0.2.0/24
+ips-look-up = Look Up
+ips-addresses-used = Addresses used by { $user }
+ips-addresses-none = No addresses have been recorded for { $user }
+ips-anonymized = anonymized to its subnet
+ips-same-address = same address
+ips-times = { $count ->
+    [one] { $count } time
+   *[other] { $count } times
+}
+ips-first-last = first { $first }, last { $last }
+ips-last = last { $last }
+ips-ban = ban
+ips-ban-address = ban address
+ips-ban-subnet = ban subnet
+ips-bans = bans
+ips-other-accounts = Other accounts from the same addresses or subnets
+ips-other-accounts-none = No other accounts
+ips-accounts-seen = Accounts seen in { $network }
+ips-user-unknown = No user named "{ $user }"
+ips-address-invalid = "{ $ip }" is not an IP address or CIDR range
+
+## Emails
+
+email-greeting = Hi { $user },
+email-footer = This email was sent by RYOB because this address belongs to an account there.
+email-reset-password-subject = Reset your password
+email-reset-password = Someone asked to reset the password for your RYOB account. Follow the link below to choose a new password. { $hours ->
+    [one] The link expires in { $hours } hour.
+   *[other] The link expires in { $hours } hours.
+}
+email-reset-password-ignore = If you did not ask for this, you can ignore this email and your password will stay the same.
+email-verify-email-subject = Confirm your email address
+email-verify-email = Please confirm that this is your email address for RYOB by following the link below. { $hours ->
+    [one] The link expires in { $hours } hour.
+   *[other] The link expires in { $hours } hours.
+}
+email-verify-email-ignore = If you did not register, you can ignore this email.
+email-new-message-subject = New message from { $sender }: { $subject }
+email-new-message = { $sender } sent you a message in "{ $subject }":
+email-new-message-read = Read the conversation
+email-new-message-settings = You can turn these emails off in your settings:
+
+## Admin
+
+admin-ban-scope-unknown = Unknown scope "{ $scope }"
+admin-ban-days = Bans last a whole number of days, or leave it empty for a permanent ban
+admin-ban-self = You cannot ban yourself
+admin-ban-reason-empty = Please give a reason for the ban, it is shown to the banned user
+admin-ban-reason-length = Reasons must be at most { $max } characters long
+admin-ban-expired = Bans have to end in the future
+admin-ban-lifted = This ban is no longer in effect
+admin-ban-unknown = No such ban
@@ -0,0 +1,36 @@
+CREATE TABLE conversations (
+    id BIGSERIAL PRIMARY KEY,
+    subject TEXT NOT NULL,
+    created_by BIGINT REFERENCES users(id) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE TABLE conversation_participants (
+    conversation_id BIGINT REFERENCES conversations(id) NOT NULL,
+    user_id BIGINT REFERENCES users(id) NOT NULL,
+    joined_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    last_read_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    left_at TIMESTAMP WITH TIME ZONE,
+    PRIMARY KEY (conversation_id, user_id)
+);
+
+CREATE INDEX conversation_participants_user_id_idx ON conversation_participants (user_id);
+
+CREATE TABLE messages (
+    id BIGSERIAL PRIMARY KEY,
+    conversation_id BIGINT REFERENCES conversations(id) NOT NULL,
+    sent_by BIGINT REFERENCES users(id) NOT NULL,
+    sent_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    content TEXT NOT NULL
+);
+
+CREATE INDEX messages_conversation_id_sent_at_idx ON messages (conversation_id, sent_at);
+CREATE INDEX messages_sent_by_idx ON messages (sent_by);
+
+CREATE TABLE user_blocks (
+    blocker BIGINT REFERENCES users(id) NOT NULL,
+    blocked BIGINT REFERENCES users(id) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (blocker, blocked)
+);
@@ -0,0 +1,2 @@
+DROP TABLE outgoing_emails;
+ALTER TABLE users DROP COLUMN email;
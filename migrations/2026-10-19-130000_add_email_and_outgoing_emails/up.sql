@@ -0,0 +1,16 @@
+ALTER TABLE users ADD COLUMN email TEXT UNIQUE;
+
+CREATE TABLE outgoing_emails (
+    id BIGSERIAL PRIMARY KEY,
+    recipient TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    text_body TEXT NOT NULL,
+    html_body TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    last_error TEXT,
+    sent_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX outgoing_emails_pending_idx ON outgoing_emails (next_attempt_at) WHERE sent_at IS NULL;
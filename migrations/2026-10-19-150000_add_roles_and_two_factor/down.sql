@@ -0,0 +1,4 @@
+DROP TABLE two_factor_recovery_codes;
+DROP TABLE two_factor_secrets;
+DROP TABLE settings;
+ALTER TABLE users DROP COLUMN role;
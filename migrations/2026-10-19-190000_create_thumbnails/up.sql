@@ -0,0 +1,8 @@
+CREATE TABLE thumbnails (
+    attachment_id BIGINT REFERENCES attachments(id) ON DELETE CASCADE NOT NULL,
+    size TEXT NOT NULL,
+    file_id BIGINT REFERENCES stored_files(id) NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    PRIMARY KEY (attachment_id, size)
+);
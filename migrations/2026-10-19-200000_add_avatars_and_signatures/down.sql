@@ -0,0 +1,2 @@
+ALTER TABLE users DROP COLUMN signature;
+ALTER TABLE users DROP COLUMN avatar_file_id;
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE users ADD COLUMN avatar_file_id BIGINT REFERENCES stored_files(id);
+ALTER TABLE users ADD COLUMN signature TEXT NOT NULL DEFAULT '';
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE banners;
\ No newline at end of file
@@ -0,0 +1,13 @@
+CREATE TABLE banners (
+    id BIGSERIAL PRIMARY KEY,
+    kind TEXT NOT NULL,
+    file_id BIGINT REFERENCES stored_files(id) NOT NULL,
+    weight INTEGER NOT NULL DEFAULT 1,
+    status TEXT NOT NULL,
+    submitted_by BIGINT REFERENCES users(id) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    reviewed_by BIGINT REFERENCES users(id),
+    reviewed_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX banners_kind_status_idx ON banners (kind, status);
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN theme TEXT;
\ No newline at end of file
@@ -0,0 +1,6 @@
+ALTER TABLE users ADD COLUMN theme TEXT, ADD COLUMN locale TEXT;
+
+UPDATE users SET theme = user_preferences.theme, locale = user_preferences.locale
+    FROM user_preferences WHERE user_preferences.user_id = users.id;
+
+DROP TABLE user_preferences;
\ No newline at end of file
@@ -0,0 +1,14 @@
+CREATE TABLE user_preferences (
+    user_id BIGINT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    timezone TEXT,
+    locale TEXT,
+    theme TEXT,
+    topics_per_page INTEGER,
+    posts_per_page INTEGER,
+    email_notifications BOOLEAN NOT NULL DEFAULT TRUE
+);
+
+INSERT INTO user_preferences (user_id, locale, theme)
+    SELECT id, locale, theme FROM users WHERE locale IS NOT NULL OR theme IS NOT NULL;
+
+ALTER TABLE users DROP COLUMN locale, DROP COLUMN theme;
\ No newline at end of file
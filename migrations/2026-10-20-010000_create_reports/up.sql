@@ -0,0 +1,14 @@
+CREATE TABLE reports (
+    id BIGSERIAL PRIMARY KEY,
+    topic_id BIGINT REFERENCES topics(id) ON DELETE CASCADE NOT NULL,
+    post_id BIGINT REFERENCES posts(id) ON DELETE CASCADE,
+    reported_by BIGINT REFERENCES users(id) NOT NULL,
+    reason TEXT NOT NULL,
+    status TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    handled_by BIGINT REFERENCES users(id),
+    handled_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX reports_status_created_at_idx ON reports (status, created_at);
+CREATE INDEX reports_topic_id_post_id_idx ON reports (topic_id, post_id);
\ No newline at end of file
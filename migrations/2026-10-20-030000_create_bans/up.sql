@@ -0,0 +1,13 @@
+CREATE TABLE bans (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT REFERENCES users(id) ON DELETE CASCADE NOT NULL,
+    scope TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    banned_by BIGINT REFERENCES users(id) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE,
+    lifted_by BIGINT REFERENCES users(id),
+    lifted_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX bans_user_id_idx ON bans (user_id);
\ No newline at end of file
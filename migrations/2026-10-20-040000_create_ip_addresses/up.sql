@@ -0,0 +1,22 @@
+CREATE TABLE ip_addresses (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT REFERENCES users(id) ON DELETE CASCADE NOT NULL,
+    post_id BIGINT REFERENCES posts(id) ON DELETE SET NULL,
+    event TEXT NOT NULL,
+    ip TEXT NOT NULL,
+    anonymized BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX ip_addresses_user_id_idx ON ip_addresses (user_id);
+CREATE INDEX ip_addresses_ip_idx ON ip_addresses USING GIST ((ip::inet) inet_ops);
+CREATE INDEX ip_addresses_created_at_idx ON ip_addresses (created_at) WHERE NOT anonymized;
+
+CREATE TABLE ip_bans (
+    id BIGSERIAL PRIMARY KEY,
+    network TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    created_by BIGINT REFERENCES users(id) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE
+);
\ No newline at end of file
@@ -0,0 +1,3 @@
+DROP TABLE word_filters;
+ALTER TABLE topics DROP COLUMN pending;
+ALTER TABLE posts DROP COLUMN hold_reason, DROP COLUMN pending;
\ No newline at end of file
@@ -0,0 +1,18 @@
+CREATE TABLE word_filters (
+    id BIGSERIAL PRIMARY KEY,
+    pattern TEXT NOT NULL,
+    is_regex BOOLEAN NOT NULL,
+    action TEXT NOT NULL,
+    replacement TEXT NOT NULL DEFAULT '',
+    message TEXT NOT NULL DEFAULT '',
+    apply_to_content BOOLEAN NOT NULL,
+    apply_to_user_names BOOLEAN NOT NULL,
+    created_by BIGINT REFERENCES users(id) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+-- Held posts and topics wait for a moderator and are hidden from everyone else until then
+ALTER TABLE posts ADD COLUMN pending BOOLEAN NOT NULL DEFAULT FALSE, ADD COLUMN hold_reason TEXT NOT NULL DEFAULT '';
+ALTER TABLE topics ADD COLUMN pending BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX posts_pending_idx ON posts (created_at) WHERE pending;
\ No newline at end of file
@@ -0,0 +1,11 @@
+ALTER TABLE users ADD COLUMN created_at TIMESTAMPTZ;
+-- Existing accounts are dated by the earliest thing recorded about them, or as long established
+-- when there is nothing, so turning on probation doesn't hold back members who have been around
+UPDATE users SET created_at = COALESCE(
+    LEAST(
+        (SELECT MIN(created_at) FROM ip_addresses WHERE ip_addresses.user_id = users.id),
+        (SELECT MIN(created_at) FROM posts WHERE posts.created_by = users.id)
+    ),
+    'epoch'
+);
+ALTER TABLE users ALTER COLUMN created_at SET NOT NULL, ALTER COLUMN created_at SET DEFAULT NOW();
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE users DROP COLUMN spam_held;
+DROP TABLE spam_log;
\ No newline at end of file
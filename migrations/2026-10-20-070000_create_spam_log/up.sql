@@ -0,0 +1,18 @@
+CREATE TABLE spam_log (
+    id BIGSERIAL PRIMARY KEY,
+    kind TEXT NOT NULL,
+    user_id BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    post_id BIGINT REFERENCES posts(id) ON DELETE SET NULL,
+    content_hash TEXT,
+    score INTEGER NOT NULL,
+    decision TEXT NOT NULL,
+    signals TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX spam_log_created_at_idx ON spam_log (created_at);
+CREATE INDEX spam_log_content_hash_idx ON spam_log (content_hash, created_at) WHERE content_hash IS NOT NULL;
+
+-- Accounts the spam checks were unsure about when they registered have their posts held until
+-- one is approved
+ALTER TABLE users ADD COLUMN spam_held BOOLEAN NOT NULL DEFAULT FALSE;
\ No newline at end of file
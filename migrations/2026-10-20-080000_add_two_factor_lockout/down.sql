@@ -0,0 +1 @@
+ALTER TABLE two_factor_secrets DROP COLUMN failed_attempts, DROP COLUMN locked_until;
\ No newline at end of file
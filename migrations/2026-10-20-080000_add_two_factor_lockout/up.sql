@@ -0,0 +1,2 @@
+-- Failed codes are counted here rather than in the session, which the client could replay
+ALTER TABLE two_factor_secrets ADD COLUMN failed_attempts INTEGER NOT NULL DEFAULT 0, ADD COLUMN locked_until TIMESTAMPTZ;
\ No newline at end of file
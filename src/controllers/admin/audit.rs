@@ -0,0 +1,272 @@
+use super::index::required_admin;
+use crate::database::types::*;
+use crate::models::audit_log::{AuditAction, AuditEntry, AuditFilter};
+use crate::models::message::Message;
+use crate::models::user::{User, UserError};
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use crate::utils::pagination::{PageQuery, Pagination};
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use diesel::result::Error as DieselError;
+use serde::{Deserialize, Serialize};
+
+const ENTRIES_PER_PAGE: i64 = 50;
+const MAX_EXPORTED_ENTRIES: i64 = 100_000;
+
+// The filters as submitted, kept as text so they can be put back into the form and links
+#[derive(Deserialize, Serialize, Default)]
+pub struct AuditQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    action: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    actor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_id: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AuditRow {
+    id: Id<AuditEntry>,
+    created_at: chrono::DateTime<chrono::Utc>,
+    actor_id: Id<User>,
+    actor_name: String,
+    action: String,
+    target_type: String,
+    target_id: i64,
+    reason: String,
+    before: Option<serde_json::Value>,
+    after: Option<serde_json::Value>,
+}
+
+impl From<(AuditEntry, User)> for AuditRow {
+    fn from((entry, actor): (AuditEntry, User)) -> AuditRow {
+        let parse = |snapshot: Option<String>| snapshot.and_then(|snapshot| serde_json::from_str(&snapshot).ok());
+        AuditRow {
+            id: entry.id,
+            created_at: entry.created_at,
+            actor_id: actor.id,
+            actor_name: actor.user_name,
+            action: entry.action,
+            target_type: entry.target_type,
+            target_id: entry.target_id,
+            reason: entry.reason,
+            before: parse(entry.before),
+            after: parse(entry.after),
+        }
+    }
+}
+
+fn non_empty(value: &Option<String>) -> Option<String> {
+    value.as_ref().map(|value| value.trim().to_owned()).filter(|value| !value.is_empty())
+}
+
+impl AuditQuery {
+    fn normalized(&self) -> AuditQuery {
+        AuditQuery {
+            action: non_empty(&self.action),
+            actor: non_empty(&self.actor),
+            target_type: non_empty(&self.target_type),
+            target_id: non_empty(&self.target_id),
+        }
+    }
+
+    fn to_filter(&self, connection: &DatabaseConnection) -> Result<Result<AuditFilter, Vec<String>>, RyobError> {
+        let mut filter = AuditFilter::default();
+        let mut errors = vec![];
+        if let Some(action) = &self.action {
+            match AuditAction::parse(action) {
+                Some(action) => filter.action = Some(action),
+                None => errors.push(format!("Unknown action \"{}\"", action)),
+            }
+        }
+        if let Some(actor) = &self.actor {
+            match User::by_user_name(connection, actor) {
+                Ok(actor) => filter.actor = Some(actor.id),
+                Err(UserError::UnknownDatabaseError(DieselError::NotFound)) => errors.push(format!("No user named \"{}\"", actor)),
+                Err(error) => return Err(RyobError::from(error)),
+            }
+        }
+        filter.target_type = self.target_type.clone();
+        if let Some(target_id) = &self.target_id {
+            match target_id.parse::<i64>() {
+                Ok(target_id) => filter.target_id = Some(target_id),
+                Err(_) => errors.push("Target ids are numbers".to_owned()),
+            }
+        }
+        Ok(if errors.is_empty() { Ok(filter) } else { Err(errors) })
+    }
+}
+
+// Targets that can still be looked at. Deleted ones will be missing, but the snapshot is in the entry.
+fn target_url(row: &AuditRow) -> Option<String> {
+    match row.target_type.as_str() {
+        "topic" => Some(format!("/topics/{}", row.target_id)),
+        "post" => Some(format!("/posts/{}", row.target_id)),
+        "attachment" => Some(format!("/attachments/{}", row.target_id)),
+        "user" => Some(format!("/admin/users/{}", row.target_id)),
+        "ip_ban" => Some("/admin/ip_bans".to_owned()),
+        "filter" => Some("/admin/filters".to_owned()),
+        _ => None,
+    }
+}
+
+fn target_types() -> Vec<&'static str> {
+    let mut types: Vec<&'static str> = AuditAction::ALL.iter().map(|action| action.target_type()).collect();
+    types.sort();
+    types.dedup();
+    types
+}
+
+pub fn list(
+    hb: Renderer,
+    pool: web::Data<DatabasePool>,
+    session: Session,
+    page: web::Query<PageQuery>,
+    query: web::Query<AuditQuery>,
+) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    let query = query.normalized();
+
+    let (entries, pagination, errors) = match query.to_filter(&connection)? {
+        Ok(filter) => {
+            let mut entries = AuditEntry::search(&connection, &filter, page.offset(ENTRIES_PER_PAGE), ENTRIES_PER_PAGE + 1)?;
+            let pagination = Pagination::from_results(&page, ENTRIES_PER_PAGE, &mut entries);
+            let entries: Vec<serde_json::Value> = entries
+                .into_iter()
+                .map(AuditRow::from)
+                .map(|row| {
+                    json!({
+                        "target_url": target_url(&row),
+                        "before": row.before.as_ref().map(|value| value.to_string()),
+                        "after": row.after.as_ref().map(|value| value.to_string()),
+                        "entry": row,
+                    })
+                })
+                .collect();
+            (entries, Some(pagination), vec![])
+        }
+        Err(errors) => (vec![], None, errors),
+    };
+
+    let selected_action = query.action.clone().unwrap_or_default();
+    let selected_type = query.target_type.clone().unwrap_or_default();
+    let unread_message_count = Message::unread_count(&connection, user.id, None)?;
+    let data = json!({
+        "user": user,
+        "unread_message_count": unread_message_count,
+        "entries": entries,
+        "pagination": pagination,
+        "filter": query,
+        "filter_query": serde_urlencoded::to_string(&query).unwrap_or_default(),
+        "actions": AuditAction::ALL.iter().map(|action| json!({ "name": action.as_str(), "selected": action.as_str() == selected_action })).collect::<Vec<_>>(),
+        "target_types": target_types().into_iter().map(|name| json!({ "name": name, "selected": name == selected_type })).collect::<Vec<_>>(),
+        "errors": errors,
+    });
+    let page = hb.render("pages/admin/audit", &data)?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+fn exported_rows(pool: &web::Data<DatabasePool>, session: &Session, query: &AuditQuery) -> Result<Result<Vec<AuditRow>, HttpResponse>, RyobError> {
+    let connection = pool.get()?;
+    if let Err(redirect) = required_admin(&connection, session)? {
+        return Ok(Err(redirect));
+    }
+    let filter = query
+        .normalized()
+        .to_filter(&connection)?
+        .map_err(|errors| RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, errors.join(", ")))?;
+    let rows = AuditEntry::search(&connection, &filter, 0, MAX_EXPORTED_ENTRIES)?
+        .into_iter()
+        .map(AuditRow::from)
+        .collect();
+    Ok(Ok(rows))
+}
+
+fn download(content_type: &str, file_name: &str, body: String) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .header(actix_web::http::header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", file_name))
+        .body(body)
+}
+
+// Quotes fields when they need it, as described in RFC 4180. Spreadsheets run cells starting with
+// one of the formula characters, or a tab or carriage return in front of one, so those are made to
+// start with a quote instead.
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_owned()
+    };
+    if value.contains(&[',', '"', '\r', '\n'][..]) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+pub fn export_csv(pool: web::Data<DatabasePool>, session: Session, query: web::Query<AuditQuery>) -> Result<HttpResponse, RyobError> {
+    let rows = match exported_rows(&pool, &session, &query)? {
+        Ok(rows) => rows,
+        Err(redirect) => return Ok(redirect),
+    };
+    let snapshot = |value: &Option<serde_json::Value>| value.as_ref().map(|value| value.to_string()).unwrap_or_default();
+    let mut csv = String::from("id,created_at,actor_id,actor_name,action,target_type,target_id,reason,before,after\r\n");
+    for row in rows {
+        let fields = [
+            row.id.value().to_string(),
+            row.created_at.to_rfc3339(),
+            row.actor_id.value().to_string(),
+            row.actor_name,
+            row.action,
+            row.target_type,
+            row.target_id.to_string(),
+            row.reason,
+            snapshot(&row.before),
+            snapshot(&row.after),
+        ];
+        csv.push_str(&fields.iter().map(|field| csv_field(field)).collect::<Vec<_>>().join(","));
+        csv.push_str("\r\n");
+    }
+    Ok(download("text/csv; charset=utf-8", "audit_log.csv", csv))
+}
+
+pub fn export_json(pool: web::Data<DatabasePool>, session: Session, query: web::Query<AuditQuery>) -> Result<HttpResponse, RyobError> {
+    let rows = match exported_rows(&pool, &session, &query)? {
+        Ok(rows) => rows,
+        Err(redirect) => return Ok(redirect),
+    };
+    Ok(download("application/json", "audit_log.json", json!({ "data": rows }).to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_csv_fields() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn defuses_formulas() {
+        assert_eq!(csv_field("=HYPERLINK(\"http://example.com\")"), "\"'=HYPERLINK(\"\"http://example.com\"\")\"");
+        assert_eq!(csv_field("+1"), "'+1");
+        assert_eq!(csv_field("-1"), "'-1");
+        assert_eq!(csv_field("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(csv_field("\t=1+1"), "'\t=1+1");
+        assert_eq!(csv_field("\r=1+1"), "\"'\r=1+1\"");
+        assert_eq!(csv_field("a=b"), "a=b");
+    }
+}
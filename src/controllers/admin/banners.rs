@@ -0,0 +1,150 @@
+use super::index::required_admin;
+use crate::database::types::*;
+use crate::models::banner::{Banner, BannerError, BannerKind, BannerStatus, MAX_BANNER_SIZE, MAX_WEIGHT};
+use crate::models::message::Message;
+use crate::models::user::User;
+use crate::storage::Storage;
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use crate::utils::uploads;
+use actix_multipart::Multipart;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use futures::future::{self, Either};
+use futures::Future;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct StatusForm {
+    status: String,
+}
+
+#[derive(Deserialize)]
+pub struct WeightForm {
+    weight: String,
+}
+
+#[derive(Serialize)]
+struct BannerEntry {
+    id: Id<Banner>,
+    url: String,
+    kind: String,
+    weight: i32,
+    submitted_by: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+fn render_banners(hb: &Renderer, connection: &DatabaseConnection, user: &User, errors: Vec<String>) -> Result<String, RyobError> {
+    let mut pending = vec![];
+    let mut approved = vec![];
+    let mut disabled = vec![];
+    for (banner, submitter) in Banner::all(connection)? {
+        let status = banner.status();
+        let entry = BannerEntry {
+            url: banner.url(),
+            id: banner.id,
+            kind: banner.kind,
+            weight: banner.weight,
+            submitted_by: submitter.user_name,
+            created_at: banner.created_at,
+        };
+        match status {
+            BannerStatus::Pending => pending.push(entry),
+            BannerStatus::Approved => approved.push(entry),
+            BannerStatus::Disabled => disabled.push(entry),
+        }
+    }
+
+    let unread_message_count = Message::unread_count(connection, user.id, None)?;
+    let data = json!({
+        "user": user,
+        "unread_message_count": unread_message_count,
+        "pending": pending,
+        "approved": approved,
+        "disabled": disabled,
+        "kinds": BannerKind::ALL.iter().map(|kind| kind.as_str()).collect::<Vec<_>>(),
+        "max_weight": MAX_WEIGHT,
+        "errors": errors,
+    });
+    Ok(hb.render("pages/admin/banners", &data)?)
+}
+
+fn redirect_to_banners() -> HttpResponse {
+    HttpResponse::Found().header(actix_web::http::header::LOCATION, "/admin/banners").finish()
+}
+
+pub fn list(hb: Renderer, pool: web::Data<DatabasePool>, session: Session) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    let page = render_banners(&hb, &connection, &user, vec![])?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+// Banners uploaded here are approved straight away
+pub fn create(
+    hb: Renderer,
+    pool: web::Data<DatabasePool>,
+    storage: web::Data<Box<dyn Storage>>,
+    session: Session,
+    multipart: Multipart,
+) -> impl Future<Item = HttpResponse, Error = RyobError> {
+    let user = match pool.get().map_err(RyobError::from).and_then(|connection| required_admin(&connection, &session)) {
+        Ok(Ok(user)) => user,
+        Ok(Err(redirect)) => return Either::A(future::ok(redirect)),
+        Err(err) => return Either::A(future::err(err)),
+    };
+
+    Either::B(uploads::read_form(multipart, MAX_BANNER_SIZE, 1).then(move |form| {
+        let connection = pool.get()?;
+        let created = form.map_err(BannerError::from).and_then(|form| {
+            let kind = form.values.get("kind").and_then(|kind| BannerKind::parse(kind)).unwrap_or(BannerKind::Banner);
+            let weight = form.values.get("weight").and_then(|weight| weight.trim().parse::<i32>().ok()).unwrap_or(0);
+            let contents = form.uploads.first().map(|upload| upload.contents.as_slice()).unwrap_or(&[]);
+            Banner::create(&connection, storage.as_ref(), user.id, Some(user.id), kind, weight, contents)
+        });
+        match created {
+            Ok(_) => Ok(redirect_to_banners()),
+            Err(error @ BannerError::File(_)) | Err(error @ BannerError::UnknownDatabaseError(_)) | Err(error @ BannerError::Unknown(_)) => {
+                Err(RyobError::from(error))
+            }
+            Err(error) => {
+                let page = render_banners(&hb, &connection, &user, vec![format!("{}", error)])?;
+                Ok(HttpResponse::BadRequest().body(page))
+            }
+        }
+    }))
+}
+
+pub fn status(pool: web::Data<DatabasePool>, session: Session, path: web::Path<Id<Banner>>, form: web::Form<StatusForm>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    let status = BannerStatus::parse(&form.status).ok_or_else(|| RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, "Unknown status"))?;
+    Banner::set_status(&connection, path.into_inner(), user.id, status)?;
+    Ok(redirect_to_banners())
+}
+
+pub fn weight(pool: web::Data<DatabasePool>, session: Session, path: web::Path<Id<Banner>>, form: web::Form<WeightForm>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    if let Err(redirect) = required_admin(&connection, &session)? {
+        return Ok(redirect);
+    }
+    let weight = form.weight.trim().parse::<i32>().map_err(|_| BannerError::InvalidWeight)?;
+    Banner::set_weight(&connection, path.into_inner(), weight)?;
+    Ok(redirect_to_banners())
+}
+
+pub fn delete(pool: web::Data<DatabasePool>, session: Session, path: web::Path<Id<Banner>>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    if let Err(redirect) = required_admin(&connection, &session)? {
+        return Ok(redirect);
+    }
+    Banner::delete(&connection, path.into_inner())?;
+    Ok(redirect_to_banners())
+}
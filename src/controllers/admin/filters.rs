@@ -0,0 +1,237 @@
+use super::index::required_admin;
+use crate::database::types::*;
+use crate::models::audit_log::{AuditAction, AuditEntry};
+use crate::models::message::Message;
+use crate::models::user::User;
+use crate::models::word_filter::{FilterAction, FilterRule, FilterTarget, Filtered, WordFilter, WordFilterError, MAX_MESSAGE_SIZE, MAX_PATTERN_SIZE};
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+// Unchecked checkboxes aren't submitted at all
+#[derive(Deserialize)]
+pub struct FilterForm {
+    pattern: String,
+    is_regex: Option<String>,
+    action: String,
+    #[serde(default)]
+    replacement: String,
+    #[serde(default)]
+    message: String,
+    apply_to_content: Option<String>,
+    apply_to_user_names: Option<String>,
+    // Only used when testing
+    #[serde(default)]
+    sample: String,
+}
+
+#[derive(Serialize)]
+struct ActionOption {
+    value: &'static str,
+    selected: bool,
+}
+
+// What the sample text turned into, or why it didn't get through
+#[derive(Serialize)]
+struct TestOutcome {
+    text: Option<String>,
+    hold_reason: Option<String>,
+    rejected: Option<String>,
+}
+
+impl FilterForm {
+    fn rule(&self) -> FilterRule {
+        FilterRule {
+            pattern: self.pattern.clone(),
+            is_regex: self.is_regex.is_some(),
+            action: self.action.clone(),
+            replacement: self.replacement.clone(),
+            message: self.message.clone(),
+        }
+    }
+
+    fn target(&self) -> FilterTarget {
+        if self.apply_to_content.is_none() && self.apply_to_user_names.is_some() {
+            FilterTarget::UserName
+        } else {
+            FilterTarget::Content
+        }
+    }
+
+    fn previous(&self) -> serde_json::Value {
+        json!({
+            "pattern": self.pattern,
+            "is_regex": self.is_regex.is_some(),
+            "action": self.action,
+            "replacement": self.replacement,
+            "message": self.message,
+            "apply_to_content": self.apply_to_content.is_some(),
+            "apply_to_user_names": self.apply_to_user_names.is_some(),
+            "sample": self.sample,
+        })
+    }
+}
+
+impl TestOutcome {
+    fn from_result(result: Result<Filtered, WordFilterError>) -> Result<TestOutcome, WordFilterError> {
+        match result {
+            Ok(filtered) => Ok(TestOutcome {
+                text: Some(filtered.text),
+                hold_reason: filtered.hold_reason,
+                rejected: None,
+            }),
+            Err(WordFilterError::Rejected(message)) => Ok(TestOutcome {
+                text: None,
+                hold_reason: None,
+                rejected: Some(message),
+            }),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+fn render_filters(
+    hb: &Renderer,
+    connection: &DatabaseConnection,
+    user: &User,
+    previous: serde_json::Value,
+    results: serde_json::Value,
+    errors: Vec<String>,
+) -> Result<String, RyobError> {
+    let selected_action = previous["action"].as_str().unwrap_or("replace").to_owned();
+    let actions: Vec<ActionOption> = FilterAction::ALL
+        .iter()
+        .map(|action| ActionOption {
+            value: action.as_str(),
+            selected: action.as_str() == selected_action,
+        })
+        .collect();
+    let unread_message_count = Message::unread_count(connection, user.id, None)?;
+    let data = json!({
+        "user": user,
+        "unread_message_count": unread_message_count,
+        "filters": WordFilter::all(connection)?,
+        "actions": actions,
+        "max_pattern_size": MAX_PATTERN_SIZE,
+        "max_message_size": MAX_MESSAGE_SIZE,
+        "previous": previous,
+        "results": results,
+        "errors": errors,
+    });
+    Ok(hb.render("pages/admin/filters", &data)?)
+}
+
+fn redirect_to_filters() -> HttpResponse {
+    HttpResponse::Found().header(actix_web::http::header::LOCATION, "/admin/filters").finish()
+}
+
+pub fn list(hb: Renderer, pool: web::Data<DatabasePool>, session: Session) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    let previous = json!({ "apply_to_content": true });
+    let page = render_filters(&hb, &connection, &user, previous, json!(null), vec![])?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+pub fn create(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, form: web::Form<FilterForm>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+
+    let created = {
+        use diesel::Connection;
+        connection.transaction::<_, RyobError, _>(|| {
+            match WordFilter::create(
+                &connection,
+                user.id,
+                &form.rule(),
+                form.apply_to_content.is_some(),
+                form.apply_to_user_names.is_some(),
+            ) {
+                Ok(filter) => {
+                    AuditEntry::record(
+                        &connection,
+                        user.id,
+                        AuditAction::FilterCreate,
+                        filter.id.value(),
+                        None,
+                        Some(json!(filter)),
+                        "",
+                    )?;
+                    Ok(Ok(filter))
+                }
+                Err(error @ WordFilterError::UnknownDatabaseError(_)) | Err(error @ WordFilterError::Unknown(_)) => Err(RyobError::from(error)),
+                Err(error) => Ok(Err(error)),
+            }
+        })?
+    };
+    match created {
+        Ok(_) => Ok(redirect_to_filters()),
+        Err(error) => {
+            let page = render_filters(&hb, &connection, &user, form.previous(), json!(null), vec![format!("{}", error)])?;
+            Ok(HttpResponse::BadRequest().body(page))
+        }
+    }
+}
+
+// Shows what a rule would do to some sample text without saving it, and what the saved filters do
+// to the same text for comparison
+pub fn test(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, form: web::Form<FilterForm>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+
+    let previous = form.previous();
+    let target = form.target();
+    let rule = match TestOutcome::from_result(form.rule().preview(target, &form.sample)) {
+        Ok(outcome) => outcome,
+        Err(error @ WordFilterError::UnknownDatabaseError(_)) | Err(error @ WordFilterError::Unknown(_)) => return Err(RyobError::from(error)),
+        Err(error) => {
+            let page = render_filters(&hb, &connection, &user, previous, json!(null), vec![format!("{}", error)])?;
+            return Ok(HttpResponse::BadRequest().body(page));
+        }
+    };
+    let saved = TestOutcome::from_result(WordFilter::apply(&connection, target, &form.sample))?;
+    let results = json!({
+        "target": if target == FilterTarget::UserName { "a user name" } else { "post content" },
+        "rule": rule,
+        "saved": saved,
+    });
+    let page = render_filters(&hb, &connection, &user, previous, results, vec![])?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+pub fn delete(pool: web::Data<DatabasePool>, session: Session, path: web::Path<Id<WordFilter>>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    {
+        use diesel::Connection;
+        connection.transaction::<_, RyobError, _>(|| {
+            let filter = WordFilter::delete(&connection, path.into_inner())?;
+            AuditEntry::record(
+                &connection,
+                user.id,
+                AuditAction::FilterDelete,
+                filter.id.value(),
+                Some(json!(filter)),
+                None,
+                "",
+            )?;
+            Ok(())
+        })?;
+    }
+    Ok(redirect_to_filters())
+}
@@ -0,0 +1,66 @@
+use crate::database::types::*;
+use crate::models::message::Message;
+use crate::models::setting::{self, Setting};
+use crate::models::two_factor::TwoFactorSecret;
+use crate::models::user::{Role, User, UserError};
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct StaffMember {
+    id: Id<User>,
+    user_name: String,
+    role: String,
+    two_factor_enabled: bool,
+}
+
+pub fn render_admin(hb: &Renderer, connection: &DatabaseConnection, user: &User, errors: Vec<String>) -> Result<String, RyobError> {
+    let staff = User::staff(connection)?
+        .into_iter()
+        .map(|member| {
+            Ok(StaffMember {
+                two_factor_enabled: TwoFactorSecret::is_enabled(connection, member.id)?,
+                id: member.id,
+                user_name: member.user_name,
+                role: member.role,
+            })
+        })
+        .collect::<Result<Vec<StaffMember>, RyobError>>()?;
+    let unread_message_count = Message::unread_count(connection, user.id, None)?;
+    let data = json!({
+        "user": user,
+        "unread_message_count": unread_message_count,
+        "require_two_factor_for_moderators": Setting::get_bool(connection, setting::REQUIRE_TWO_FACTOR_FOR_MODERATORS, false)?,
+        "ip_retention_days": Setting::get_int(connection, setting::IP_RETENTION_DAYS, 0)?,
+        "probation_days": Setting::get_int(connection, setting::PROBATION_DAYS, 0)?,
+        "probation_posts": Setting::get_int(connection, setting::PROBATION_POSTS, 0)?,
+        "themes": hb.options(&hb.default_theme()),
+        "staff": staff,
+        "errors": errors,
+    });
+    Ok(hb.render("pages/admin/index", &data)?)
+}
+
+// Admins whose role requires 2FA are sent to set it up rather than shown a bare 403
+pub fn required_admin(connection: &DatabaseConnection, session: &Session) -> Result<Result<User, HttpResponse>, RyobError> {
+    match User::required_with_role(connection, session, Role::Admin) {
+        Err(UserError::TwoFactorRequired) => Ok(Err(HttpResponse::Found()
+            .header(actix_web::http::header::LOCATION, "/users/two_factor")
+            .finish())),
+        result => Ok(Ok(result?)),
+    }
+}
+
+pub fn get(hb: Renderer, pool: web::Data<DatabasePool>, session: Session) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    let page = render_admin(&hb, &connection, &user, vec![])?;
+    Ok(HttpResponse::Ok().body(page))
+}
@@ -0,0 +1,154 @@
+use super::index::required_admin;
+use crate::database::types::*;
+use crate::models::audit_log::{AuditAction, AuditEntry};
+use crate::models::ban::MAX_REASON_SIZE;
+use crate::models::ip_ban::{IpBan, IpBanError};
+use crate::models::message::Message;
+use crate::models::user::User;
+use crate::themes::Renderer;
+use crate::utils::client_ip::{self, ClientIp};
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+// Links from the IP lookup fill in the address or range
+#[derive(Deserialize)]
+pub struct IpBanQuery {
+    network: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct IpBanForm {
+    network: String,
+    // Empty for a permanent ban
+    #[serde(default)]
+    days: String,
+    reason: String,
+}
+
+#[derive(Serialize)]
+struct IpBanRow {
+    id: Id<IpBan>,
+    network: String,
+    reason: String,
+    created_by: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    active: bool,
+}
+
+fn render_ip_bans(hb: &Renderer, connection: &DatabaseConnection, user: &User, previous: serde_json::Value, errors: Vec<String>) -> Result<String, RyobError> {
+    let bans: Vec<IpBanRow> = IpBan::all(connection)?
+        .into_iter()
+        .map(|(ban, creator)| IpBanRow {
+            active: ban.is_active(),
+            id: ban.id,
+            network: ban.network,
+            reason: ban.reason,
+            created_by: creator.user_name,
+            created_at: ban.created_at,
+            expires_at: ban.expires_at,
+        })
+        .collect();
+    let unread_message_count = Message::unread_count(connection, user.id, None)?;
+    let data = json!({
+        "user": user,
+        "unread_message_count": unread_message_count,
+        "bans": bans,
+        "max_reason_size": MAX_REASON_SIZE,
+        "previous": previous,
+        "errors": errors,
+    });
+    Ok(hb.render("pages/admin/ip_bans", &data)?)
+}
+
+fn redirect_to_ip_bans() -> HttpResponse {
+    HttpResponse::Found().header(actix_web::http::header::LOCATION, "/admin/ip_bans").finish()
+}
+
+pub fn list(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, query: web::Query<IpBanQuery>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    let page = render_ip_bans(&hb, &connection, &user, json!({ "network": query.network }), vec![])?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+pub fn create(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, ip: ClientIp, form: web::Form<IpBanForm>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+
+    let mut errors = vec![];
+    let expires_at = match form.days.trim() {
+        "" => None,
+        days => match days.parse::<i64>() {
+            Ok(days) if days > 0 && days <= 36500 => Some(chrono::Utc::now() + chrono::Duration::days(days)),
+            _ => {
+                errors.push("Bans last a whole number of days, or leave it empty for a permanent ban".to_owned());
+                None
+            }
+        },
+    };
+    // Stops admins from locking themselves out
+    let own_network = client_ip::parse_network(form.network.trim())
+        .zip(ip.0)
+        .is_some_and(|(network, ip)| network.contains(&ip));
+    if own_network {
+        errors.push("You cannot ban the address you are using".to_owned());
+    }
+    if !errors.is_empty() {
+        let page = render_ip_bans(&hb, &connection, &user, json!(*form), errors)?;
+        return Ok(HttpResponse::BadRequest().body(page));
+    }
+
+    let created = {
+        use diesel::Connection;
+        connection.transaction::<_, RyobError, _>(|| match IpBan::create(&connection, &form.network, user.id, &form.reason, expires_at) {
+            Ok(ban) => {
+                AuditEntry::record(
+                    &connection,
+                    user.id,
+                    AuditAction::IpBanCreate,
+                    ban.id.value(),
+                    None,
+                    Some(json!(ban)),
+                    &ban.reason,
+                )?;
+                Ok(Ok(ban))
+            }
+            Err(error @ IpBanError::UnknownDatabaseError(_)) | Err(error @ IpBanError::Unknown(_)) => Err(RyobError::from(error)),
+            Err(error) => Ok(Err(error)),
+        })?
+    };
+    match created {
+        Ok(_) => Ok(redirect_to_ip_bans()),
+        Err(error) => {
+            let page = render_ip_bans(&hb, &connection, &user, json!(*form), vec![format!("{}", error)])?;
+            Ok(HttpResponse::BadRequest().body(page))
+        }
+    }
+}
+
+pub fn delete(pool: web::Data<DatabasePool>, session: Session, path: web::Path<Id<IpBan>>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    {
+        use diesel::Connection;
+        connection.transaction::<_, RyobError, _>(|| {
+            let ban = IpBan::delete(&connection, path.into_inner())?;
+            AuditEntry::record(&connection, user.id, AuditAction::IpBanDelete, ban.id.value(), Some(json!(ban)), None, "")?;
+            Ok(())
+        })?;
+    }
+    Ok(redirect_to_ip_bans())
+}
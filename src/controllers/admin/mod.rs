@@ -0,0 +1,10 @@
+pub mod audit;
+pub mod banners;
+pub mod filters;
+pub mod index;
+pub mod ip_bans;
+pub mod roles;
+pub mod settings;
+pub mod spam;
+pub mod users;
+pub mod webhooks;
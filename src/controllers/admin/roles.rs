@@ -0,0 +1,69 @@
+use super::index::{render_admin, required_admin};
+use crate::database::types::*;
+use crate::models::audit_log::{AuditAction, AuditEntry};
+use crate::models::user::{Role, User, UserError};
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use diesel::result::Error as DieselError;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct RoleForm {
+    user_name: String,
+    role: String,
+    #[serde(default)]
+    reason: String,
+}
+
+pub fn post(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, form: web::Form<RoleForm>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    let user_name = form.user_name.trim().to_owned();
+
+    let role = match Role::parse(&form.role) {
+        Some(role) => role,
+        None => {
+            let page = render_admin(&hb, &connection, &user, vec![format!("Unknown role \"{}\"", form.role)])?;
+            return Ok(HttpResponse::BadRequest().body(page));
+        }
+    };
+
+    let target = match User::by_user_name(&connection, &user_name) {
+        Err(UserError::UnknownDatabaseError(DieselError::NotFound)) => {
+            let page = render_admin(&hb, &connection, &user, vec![format!("No user named \"{}\"", user_name)])?;
+            return Ok(HttpResponse::BadRequest().body(page));
+        }
+        result => result?,
+    };
+
+    // Stops the last admin from locking everyone out of this page
+    if target.id == user.id && role < Role::Admin {
+        let page = render_admin(&hb, &connection, &user, vec!["You cannot remove your own admin role".to_owned()])?;
+        return Ok(HttpResponse::BadRequest().body(page));
+    }
+
+    {
+        use diesel::Connection;
+        connection.transaction::<_, RyobError, _>(|| {
+            User::set_role(&connection, target.id, role)?;
+            let before = json!({ "role": target.role });
+            let after = json!({ "role": role.as_str() });
+            AuditEntry::record(
+                &connection,
+                user.id,
+                AuditAction::RoleChange,
+                target.id.value(),
+                Some(before),
+                Some(after),
+                &form.reason,
+            )?;
+            Ok(())
+        })?;
+    }
+    Ok(HttpResponse::Found().header(actix_web::http::header::LOCATION, "/admin").finish())
+}
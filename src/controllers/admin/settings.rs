@@ -0,0 +1,106 @@
+use super::index::{render_admin, required_admin};
+use crate::database::types::*;
+use crate::models::setting::{self, Setting};
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+
+// Unchecked checkboxes aren't submitted at all
+#[derive(Deserialize)]
+pub struct SettingsForm {
+    require_two_factor_for_moderators: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ThemeForm {
+    default_theme: String,
+}
+
+#[derive(Deserialize)]
+pub struct IpRetentionForm {
+    ip_retention_days: String,
+}
+
+#[derive(Deserialize)]
+pub struct ProbationForm {
+    probation_days: String,
+    probation_posts: String,
+}
+
+pub fn post(pool: web::Data<DatabasePool>, session: Session, form: web::Form<SettingsForm>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    if let Err(redirect) = required_admin(&connection, &session)? {
+        return Ok(redirect);
+    }
+    Setting::set_bool(
+        &connection,
+        setting::REQUIRE_TWO_FACTOR_FOR_MODERATORS,
+        form.require_two_factor_for_moderators.is_some(),
+    )?;
+    Ok(HttpResponse::Found().header(actix_web::http::header::LOCATION, "/admin").finish())
+}
+
+// Used by everyone who hasn't picked a theme of their own
+pub fn theme(themes: Renderer, pool: web::Data<DatabasePool>, session: Session, form: web::Form<ThemeForm>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    if let Err(redirect) = required_admin(&connection, &session)? {
+        return Ok(redirect);
+    }
+    if !themes.exists(&form.default_theme) {
+        return Err(RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, "Unknown theme"));
+    }
+    Setting::set(&connection, setting::DEFAULT_THEME, &form.default_theme)?;
+    themes.set_default_theme(&form.default_theme);
+    Ok(HttpResponse::Found().header(actix_web::http::header::LOCATION, "/admin").finish())
+}
+
+// Empty or 0 keeps recorded IP addresses as they are
+pub fn ip_retention(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, form: web::Form<IpRetentionForm>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    let days = match form.ip_retention_days.trim() {
+        "" => 0,
+        days => match days.parse::<i64>() {
+            Ok(days) if days >= 0 => days,
+            _ => {
+                let page = render_admin(&hb, &connection, &user, vec!["IP addresses are kept for a whole number of days".to_owned()])?;
+                return Ok(HttpResponse::BadRequest().body(page));
+            }
+        },
+    };
+    Setting::set(&connection, setting::IP_RETENTION_DAYS, &days.to_string())?;
+    Ok(HttpResponse::Found().header(actix_web::http::header::LOCATION, "/admin").finish())
+}
+
+// Empty or 0 for both holds nobody's posts back
+pub fn probation(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, form: web::Form<ProbationForm>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    let parse = |value: &str| match value.trim() {
+        "" => Some(0),
+        value => value.parse::<i64>().ok().filter(|value| *value >= 0),
+    };
+    let (days, posts) = match (parse(&form.probation_days), parse(&form.probation_posts)) {
+        (Some(days), Some(posts)) => (days, posts),
+        _ => {
+            let page = render_admin(
+                &hb,
+                &connection,
+                &user,
+                vec!["New accounts are held for a whole number of days and approved posts".to_owned()],
+            )?;
+            return Ok(HttpResponse::BadRequest().body(page));
+        }
+    };
+    Setting::set(&connection, setting::PROBATION_DAYS, &days.to_string())?;
+    Setting::set(&connection, setting::PROBATION_POSTS, &posts.to_string())?;
+    Ok(HttpResponse::Found().header(actix_web::http::header::LOCATION, "/admin").finish())
+}
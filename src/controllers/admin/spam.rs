@@ -0,0 +1,116 @@
+use super::index::required_admin;
+use crate::database::types::*;
+use crate::models::message::Message;
+use crate::models::setting::{self, Setting};
+use crate::models::spam_log::{SpamDecision, SpamLogEntry};
+use crate::models::user::User;
+use crate::spam::{DEFAULT_HOLD_SCORE, DEFAULT_REJECT_SCORE};
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use crate::utils::pagination::{PageQuery, Pagination};
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+
+const ENTRIES_PER_PAGE: i64 = 50;
+
+#[derive(Deserialize)]
+pub struct SpamLogQuery {
+    #[serde(default)]
+    decision: String,
+}
+
+#[derive(Deserialize)]
+pub struct ThresholdsForm {
+    hold_score: String,
+    reject_score: String,
+}
+
+fn render_spam_log(
+    hb: &Renderer,
+    connection: &DatabaseConnection,
+    user: &User,
+    page: &PageQuery,
+    only: Option<SpamDecision>,
+    errors: Vec<String>,
+) -> Result<String, RyobError> {
+    let mut entries = SpamLogEntry::recent(connection, only, page.offset(ENTRIES_PER_PAGE), ENTRIES_PER_PAGE + 1)?;
+    let pagination = Pagination::from_results(page, ENTRIES_PER_PAGE, &mut entries);
+    let entries: Vec<serde_json::Value> = entries
+        .into_iter()
+        .map(|(entry, submitter)| {
+            let signals: serde_json::Value = serde_json::from_str(&entry.signals).unwrap_or_else(|_| json!([]));
+            json!({
+                "entry": entry,
+                "submitter": submitter.map(|submitter| submitter.user_name),
+                "signals": signals,
+            })
+        })
+        .collect();
+
+    let selected = only.map_or("", |decision| decision.as_str());
+    let decisions: Vec<serde_json::Value> = SpamDecision::ALL
+        .iter()
+        .map(|decision| json!({ "name": decision.as_str(), "selected": decision.as_str() == selected }))
+        .collect();
+    let unread_message_count = Message::unread_count(connection, user.id, None)?;
+    let data = json!({
+        "user": user,
+        "unread_message_count": unread_message_count,
+        "entries": entries,
+        "pagination": pagination,
+        "decision": selected,
+        "decisions": decisions,
+        "hold_score": Setting::get_int(connection, setting::SPAM_HOLD_SCORE, DEFAULT_HOLD_SCORE)?,
+        "reject_score": Setting::get_int(connection, setting::SPAM_REJECT_SCORE, DEFAULT_REJECT_SCORE)?,
+        "errors": errors,
+    });
+    Ok(hb.render("pages/admin/spam", &data)?)
+}
+
+// Every decision the spam checks made that wasn't for staff, newest first, so the thresholds can be tuned
+pub fn list(
+    hb: Renderer,
+    pool: web::Data<DatabasePool>,
+    session: Session,
+    page: web::Query<PageQuery>,
+    query: web::Query<SpamLogQuery>,
+) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    let (only, errors) = match query.decision.trim() {
+        "" => (None, vec![]),
+        decision => match SpamDecision::parse(decision) {
+            Some(decision) => (Some(decision), vec![]),
+            None => (None, vec![format!("Unknown decision \"{}\"", decision)]),
+        },
+    };
+    let page = render_spam_log(&hb, &connection, &user, &page, only, errors)?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+pub fn thresholds(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, form: web::Form<ThresholdsForm>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    let parse = |value: &str| match value.trim() {
+        "" => Some(0),
+        value => value.parse::<i64>().ok().filter(|value| *value >= 0),
+    };
+    let (hold_score, reject_score) = match (parse(&form.hold_score), parse(&form.reject_score)) {
+        (Some(hold_score), Some(reject_score)) => (hold_score, reject_score),
+        _ => {
+            let errors = vec!["Scores are whole numbers, or 0 to turn that decision off".to_owned()];
+            let page = render_spam_log(&hb, &connection, &user, &PageQuery::default(), None, errors)?;
+            return Ok(HttpResponse::BadRequest().body(page));
+        }
+    };
+    Setting::set(&connection, setting::SPAM_HOLD_SCORE, &hold_score.to_string())?;
+    Setting::set(&connection, setting::SPAM_REJECT_SCORE, &reject_score.to_string())?;
+    Ok(HttpResponse::Found().header(actix_web::http::header::LOCATION, "/admin/spam").finish())
+}
@@ -0,0 +1,255 @@
+use super::index::{render_admin, required_admin};
+use crate::database::types::*;
+use crate::models::audit_log::{AuditAction, AuditEntry};
+use crate::models::ban::{Ban, BanError, BanScope, MAX_REASON_SIZE};
+use crate::models::message::Message;
+use crate::models::user::{User, UserError};
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use diesel::result::Error as DieselError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Deserialize)]
+pub struct FindUserQuery {
+    user_name: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct BanForm {
+    scope: String,
+    // Empty for a permanent ban
+    #[serde(default)]
+    days: String,
+    reason: String,
+}
+
+#[derive(Serialize)]
+struct BanRow {
+    id: Id<Ban>,
+    scope: String,
+    reason: String,
+    banned_by: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    lifted_by: Option<String>,
+    lifted_at: Option<chrono::DateTime<chrono::Utc>>,
+    active: bool,
+}
+
+fn user_name(connection: &DatabaseConnection, names: &mut HashMap<Id<User>, String>, user: Id<User>) -> Result<String, RyobError> {
+    if let Some(name) = names.get(&user) {
+        return Ok(name.clone());
+    }
+    let name = User::by_user_id(connection, user)?.user_name;
+    names.insert(user, name.clone());
+    Ok(name)
+}
+
+fn ban_rows(connection: &DatabaseConnection, target: Id<User>) -> Result<Vec<BanRow>, RyobError> {
+    let mut names = HashMap::new();
+    Ban::history(connection, target)?
+        .into_iter()
+        .map(|ban| {
+            Ok(BanRow {
+                active: ban.is_active(),
+                banned_by: user_name(connection, &mut names, ban.banned_by)?,
+                lifted_by: ban.lifted_by.map(|lifter| user_name(connection, &mut names, lifter)).transpose()?,
+                id: ban.id,
+                scope: ban.scope,
+                reason: ban.reason,
+                created_at: ban.created_at,
+                expires_at: ban.expires_at,
+                lifted_at: ban.lifted_at,
+            })
+        })
+        .collect()
+}
+
+fn scope_label(scope: BanScope) -> &'static str {
+    match scope {
+        BanScope::Global => "Global, cannot log in",
+        BanScope::Posting => "Posting, can still log in and read",
+    }
+}
+
+fn ban_error_to_string(hb: &Renderer, error: &BanError) -> String {
+    match error {
+        BanError::ReasonEmpty => hb.translate("admin-ban-reason-empty", &[]),
+        BanError::ReasonTooLong => hb.translate("admin-ban-reason-length", &[("max", MAX_REASON_SIZE.into())]),
+        BanError::AlreadyExpired => hb.translate("admin-ban-expired", &[]),
+        BanError::AlreadyLifted => hb.translate("admin-ban-lifted", &[]),
+        BanError::NoSuchBan => hb.translate("admin-ban-unknown", &[]),
+        BanError::UnknownDatabaseError(_) | BanError::Unknown(_) => error.to_string(),
+    }
+}
+
+fn render_user(
+    hb: &Renderer,
+    connection: &DatabaseConnection,
+    user: &User,
+    target: &User,
+    previous: Option<&BanForm>,
+    errors: Vec<String>,
+) -> Result<String, RyobError> {
+    let selected_scope = previous.map(|form| form.scope.as_str()).unwrap_or_else(|| BanScope::Global.as_str());
+    let unread_message_count = Message::unread_count(connection, user.id, None)?;
+    let data = json!({
+        "user": user,
+        "unread_message_count": unread_message_count,
+        "target": { "id": target.id, "user_name": target.user_name, "role": target.role },
+        "bans": ban_rows(connection, target.id)?,
+        "scopes": BanScope::ALL
+            .iter()
+            .map(|scope| json!({ "name": scope.as_str(), "label": scope_label(*scope), "selected": scope.as_str() == selected_scope }))
+            .collect::<Vec<_>>(),
+        "max_reason_size": MAX_REASON_SIZE,
+        "previous": previous,
+        "errors": errors,
+    });
+    Ok(hb.render("pages/admin/user", &data)?)
+}
+
+fn target_user(connection: &DatabaseConnection, target: Id<User>) -> Result<User, RyobError> {
+    match User::by_user_id(connection, target) {
+        Err(UserError::UnknownDatabaseError(DieselError::NotFound)) => Err(RyobError::from(UserError::NoSuchUser)),
+        result => Ok(result?),
+    }
+}
+
+fn redirect_to_user(target: Id<User>) -> HttpResponse {
+    HttpResponse::Found()
+        .header(actix_web::http::header::LOCATION, format!("/admin/users/{}", target.value()))
+        .finish()
+}
+
+// The user name lookup on the overview page
+pub fn find(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, query: web::Query<FindUserQuery>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    let user_name = query.user_name.trim().to_owned();
+    match User::by_user_name(&connection, &user_name) {
+        Err(UserError::UnknownDatabaseError(DieselError::NotFound)) => {
+            let page = render_admin(&hb, &connection, &user, vec![format!("No user named \"{}\"", user_name)])?;
+            Ok(HttpResponse::NotFound().body(page))
+        }
+        result => Ok(redirect_to_user(result?.id)),
+    }
+}
+
+pub fn get(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, path: web::Path<Id<User>>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    let target = target_user(&connection, path.into_inner())?;
+    let page = render_user(&hb, &connection, &user, &target, None, vec![])?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+pub fn ban(
+    hb: Renderer,
+    pool: web::Data<DatabasePool>,
+    session: Session,
+    path: web::Path<Id<User>>,
+    form: web::Form<BanForm>,
+) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    let target = target_user(&connection, path.into_inner())?;
+
+    let mut errors = vec![];
+    let scope = BanScope::parse(&form.scope);
+    if scope.is_none() {
+        errors.push(hb.translate("admin-ban-scope-unknown", &[("scope", form.scope.as_str().into())]));
+    }
+    let expires_at = match form.days.trim() {
+        "" => None,
+        days => match days.parse::<i64>() {
+            Ok(days) if days > 0 && days <= 36500 => Some(chrono::Utc::now() + chrono::Duration::days(days)),
+            _ => {
+                errors.push(hb.translate("admin-ban-days", &[]));
+                None
+            }
+        },
+    };
+    // Stops admins from locking themselves out
+    if target.id == user.id {
+        errors.push(hb.translate("admin-ban-self", &[]));
+    }
+    let scope = match scope {
+        Some(scope) if errors.is_empty() => scope,
+        _ => {
+            let page = render_user(&hb, &connection, &user, &target, Some(&form), errors)?;
+            return Ok(HttpResponse::BadRequest().body(page));
+        }
+    };
+
+    let created = {
+        use diesel::Connection;
+        connection.transaction::<_, RyobError, _>(|| match Ban::create(&connection, target.id, user.id, scope, &form.reason, expires_at) {
+            Ok(ban) => {
+                AuditEntry::record(
+                    &connection,
+                    user.id,
+                    AuditAction::UserBan,
+                    target.id.value(),
+                    None,
+                    Some(json!(ban)),
+                    &ban.reason,
+                )?;
+                Ok(Ok(ban))
+            }
+            Err(error @ BanError::UnknownDatabaseError(_)) | Err(error @ BanError::Unknown(_)) => Err(RyobError::from(error)),
+            Err(error) => Ok(Err(error)),
+        })?
+    };
+    match created {
+        Ok(_) => Ok(redirect_to_user(target.id)),
+        Err(error) => {
+            let page = render_user(&hb, &connection, &user, &target, Some(&form), vec![ban_error_to_string(&hb, &error)])?;
+            Ok(HttpResponse::BadRequest().body(page))
+        }
+    }
+}
+
+pub fn lift(pool: web::Data<DatabasePool>, session: Session, path: web::Path<(Id<User>, Id<Ban>)>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    let (target, ban) = path.into_inner();
+    let ban = Ban::by_id(&connection, ban)?;
+    if ban.user_id != target {
+        return Err(RyobError::from(BanError::NoSuchBan));
+    }
+
+    {
+        use diesel::Connection;
+        connection.transaction::<_, RyobError, _>(|| {
+            let lifted = Ban::lift(&connection, ban.id, user.id)?;
+            AuditEntry::record(
+                &connection,
+                user.id,
+                AuditAction::UserUnban,
+                target.value(),
+                Some(json!(ban)),
+                Some(json!(lifted)),
+                "",
+            )?;
+            Ok(())
+        })?;
+    }
+    Ok(redirect_to_user(target))
+}
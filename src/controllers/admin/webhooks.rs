@@ -0,0 +1,118 @@
+use super::index::required_admin;
+use crate::database::types::*;
+use crate::models::message::Message;
+use crate::models::user::User;
+use crate::models::webhook::{Webhook, WebhookDelivery, WebhookError, WebhookEvent};
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use crate::utils::pagination::{PageQuery, Pagination};
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+
+const DELIVERIES_PER_PAGE: i64 = 50;
+
+fn event_names() -> Vec<&'static str> {
+    WebhookEvent::SUBSCRIBABLE.iter().map(|event| event.as_str()).collect()
+}
+
+fn render_webhooks(hb: &Renderer, connection: &DatabaseConnection, user: &User, errors: Vec<String>, previous_url: &str) -> Result<String, RyobError> {
+    let webhooks = Webhook::active(connection)?;
+    let unread_message_count = Message::unread_count(connection, user.id, None)?;
+    let data = json!({
+        "user": user,
+        "unread_message_count": unread_message_count,
+        "webhooks": webhooks,
+        "event_names": event_names(),
+        "errors": errors,
+        "previous": { "url": previous_url },
+    });
+    Ok(hb.render("pages/admin/webhooks", &data)?)
+}
+
+fn redirect_to(location: &str) -> HttpResponse {
+    HttpResponse::Found().header(actix_web::http::header::LOCATION, location).finish()
+}
+
+pub fn list(hb: Renderer, pool: web::Data<DatabasePool>, session: Session) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    let page = render_webhooks(&hb, &connection, &user, vec![], "")?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+// The form repeats the "events" field once for each ticked box, which only a list of pairs can hold
+pub fn create(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, form: web::Form<Vec<(String, String)>>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+
+    let url = form.iter().find(|(key, _)| key == "url").map(|(_, value)| value.trim()).unwrap_or("");
+    let events: Vec<WebhookEvent> = form
+        .iter()
+        .filter(|(key, _)| key == "events")
+        .filter_map(|(_, value)| WebhookEvent::parse(value))
+        .collect();
+
+    match Webhook::create(&connection, user.id, url, &events) {
+        Ok(webhook) => Ok(redirect_to(&format!("/admin/webhooks/{}", webhook.id.value()))),
+        Err(error @ WebhookError::InvalidUrl) | Err(error @ WebhookError::NoEvents) => {
+            let page = render_webhooks(&hb, &connection, &user, vec![format!("{}", error)], url)?;
+            Ok(HttpResponse::BadRequest().body(page))
+        }
+        Err(error) => Err(RyobError::from(error)),
+    }
+}
+
+pub fn get(
+    hb: Renderer,
+    pool: web::Data<DatabasePool>,
+    session: Session,
+    path: web::Path<Id<Webhook>>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_admin(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+
+    let webhook = Webhook::by_id(&connection, path.into_inner())?;
+    let mut deliveries = WebhookDelivery::for_webhook(&connection, webhook.id, query.offset(DELIVERIES_PER_PAGE), DELIVERIES_PER_PAGE + 1)?;
+    let pagination = Pagination::from_results(&query, DELIVERIES_PER_PAGE, &mut deliveries);
+    let unread_message_count = Message::unread_count(&connection, user.id, None)?;
+    let data = json!({
+        "user": user,
+        "unread_message_count": unread_message_count,
+        "webhook": webhook,
+        "deliveries": deliveries,
+        "pagination": pagination,
+    });
+    let page = hb.render("pages/admin/webhook", &data)?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+pub fn ping(pool: web::Data<DatabasePool>, session: Session, path: web::Path<Id<Webhook>>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    if let Err(redirect) = required_admin(&connection, &session)? {
+        return Ok(redirect);
+    }
+    let webhook = Webhook::by_id(&connection, path.into_inner())?;
+    webhook.ping(&connection)?;
+    Ok(redirect_to(&format!("/admin/webhooks/{}", webhook.id.value())))
+}
+
+pub fn delete(pool: web::Data<DatabasePool>, session: Session, path: web::Path<Id<Webhook>>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    if let Err(redirect) = required_admin(&connection, &session)? {
+        return Ok(redirect);
+    }
+    let webhook = Webhook::by_id(&connection, path.into_inner())?;
+    Webhook::delete(&connection, webhook.id)?;
+    Ok(redirect_to("/admin/webhooks"))
+}
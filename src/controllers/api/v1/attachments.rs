@@ -0,0 +1,101 @@
+use super::auth::{ApiUser, ReasonQuery};
+use super::views::{AttachmentView, Item};
+use crate::database::types::*;
+use crate::models::api_token::Scope;
+use crate::models::attachment::{Attachment, AttachmentError, MAX_ATTACHMENTS_PER_POST, MAX_ATTACHMENT_SIZE};
+use crate::models::audit_log::{AuditAction, AuditEntry};
+use crate::models::post::Post;
+use crate::models::user::UserError;
+use crate::storage::Storage;
+use crate::utils::errors::ApiError;
+use crate::utils::id::Id;
+use crate::utils::uploads;
+use actix_multipart::Multipart;
+use actix_web::{web, HttpResponse};
+use futures::future::{self, Either};
+use futures::Future;
+
+fn attachable_post(connection: &DatabaseConnection, caller: &ApiUser, post: Id<Post>) -> Result<Post, ApiError> {
+    caller.require_scope(Scope::Write)?;
+    let (post, _) = Post::by_id(connection, post)?;
+    if !caller.may_edit(connection, post.created_by)? {
+        return Err(ApiError::from(UserError::Forbidden));
+    }
+    Ok(post)
+}
+
+pub fn list(pool: web::Data<DatabasePool>, path: web::Path<Id<Post>>) -> Result<HttpResponse, ApiError> {
+    let connection = pool.get()?;
+    let (post, _) = Post::visible_by_id(&connection, path.into_inner(), None)?;
+    let data: Vec<AttachmentView> = Attachment::for_posts(&connection, &[post.id])?.into_iter().map(AttachmentView::from).collect();
+    Ok(HttpResponse::Ok().json(Item { data }))
+}
+
+// Takes one or more files as multipart/form-data, all of which are attached or none are
+pub fn create(
+    pool: web::Data<DatabasePool>,
+    storage: web::Data<Box<dyn Storage>>,
+    caller: ApiUser,
+    path: web::Path<Id<Post>>,
+    multipart: Multipart,
+) -> impl Future<Item = HttpResponse, Error = ApiError> {
+    let post = match pool.get().map_err(ApiError::from).and_then(|connection| {
+        caller.user.check_may_post(&connection)?;
+        attachable_post(&connection, &caller, path.into_inner())
+    }) {
+        Ok(post) => post,
+        Err(err) => return Either::A(future::err(err)),
+    };
+
+    let read = uploads::read_uploads(multipart, MAX_ATTACHMENT_SIZE, MAX_ATTACHMENTS_PER_POST as usize).map_err(AttachmentError::from);
+    Either::B(read.map_err(ApiError::from).and_then(move |uploads| {
+        if uploads.is_empty() {
+            return Err(ApiError::from(AttachmentError::NoFile));
+        }
+        let connection = pool.get()?;
+        if Attachment::count_for_post(&connection, post.id)? + uploads.len() as i64 > MAX_ATTACHMENTS_PER_POST {
+            return Err(ApiError::from(AttachmentError::TooManyAttachments));
+        }
+
+        use diesel::Connection;
+        let created = connection.transaction::<_, AttachmentError, _>(|| {
+            uploads
+                .iter()
+                .map(|upload| Attachment::create(&connection, storage.as_ref(), caller.user.id, post.id, &upload.file_name, &upload.contents))
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+        let data: Vec<AttachmentView> = created.into_iter().map(AttachmentView::from).collect();
+        Ok(HttpResponse::Created().json(Item { data }))
+    }))
+}
+
+pub fn delete(
+    pool: web::Data<DatabasePool>,
+    caller: ApiUser,
+    path: web::Path<Id<Attachment>>,
+    query: web::Query<ReasonQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let connection = pool.get()?;
+    let (attachment, _) = Attachment::by_id(&connection, path.into_inner())?;
+    let post = attachable_post(&connection, &caller, attachment.post_id)?;
+    {
+        use diesel::Connection;
+        connection.transaction::<_, ApiError, _>(|| {
+            Attachment::delete(&connection, attachment.id)?;
+            if caller.is_moderating(post.created_by) {
+                let reason = query.reason.as_deref().unwrap_or("");
+                AuditEntry::record(
+                    &connection,
+                    caller.user.id,
+                    AuditAction::AttachmentDelete,
+                    attachment.id.value(),
+                    Some(json!(attachment)),
+                    None,
+                    reason,
+                )?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,84 @@
+use crate::database::types::*;
+use crate::models::api_token::{ApiToken, ApiTokenError, Scope};
+use crate::models::ban::{Ban, BanScope};
+use crate::models::user::{User, UserError};
+use crate::utils::errors::ApiError;
+use crate::utils::id::Id;
+use actix_session::UserSession;
+use actix_web::dev::Payload;
+use actix_web::{FromRequest, HttpRequest};
+use serde::Deserialize;
+
+// The user making an API call, either from an "Authorization: Bearer" API token or from the
+// same session cookie the HTML pages use. Session callers have every scope.
+pub struct ApiUser {
+    pub user: User,
+    pub token: Option<ApiToken>,
+}
+
+impl ApiUser {
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.token.as_ref().map(|token| token.has_scope(scope)).unwrap_or(true)
+    }
+
+    pub fn require_scope(&self, scope: Scope) -> Result<(), ApiError> {
+        if self.has_scope(scope) {
+            Ok(())
+        } else {
+            Err(ApiError(
+                actix_web::http::StatusCode::FORBIDDEN,
+                vec![format!("This API token does not have the \"{}\" scope", scope.as_str())],
+            ))
+        }
+    }
+
+    // Like User::may_edit, but a token also needs the moderate scope to touch other people's content
+    pub fn may_edit(&self, connection: &DatabaseConnection, author: Id<User>) -> Result<bool, ApiError> {
+        if !self.has_scope(Scope::Moderate) {
+            return Ok(self.user.id == author);
+        }
+        Ok(self.user.may_edit(connection, author)?)
+    }
+
+    // Changes to other people's content are moderation, and go in the audit log
+    pub fn is_moderating(&self, author: Id<User>) -> bool {
+        self.user.id != author
+    }
+}
+
+// Moderators can give a reason for the audit log when deleting, e.g. DELETE /api/v1/posts/1?reason=spam
+#[derive(Deserialize)]
+pub struct ReasonQuery {
+    pub reason: Option<String>,
+}
+
+fn from_bearer_token(connection: &DatabaseConnection, header: &actix_web::http::HeaderValue) -> Result<ApiUser, ApiError> {
+    let value = header.to_str().map_err(|_| ApiTokenError::InvalidToken)?;
+    let token_value = value.strip_prefix("Bearer ").ok_or(ApiTokenError::InvalidToken)?;
+    let token = ApiToken::authenticate(connection, token_value.trim())?;
+    let user = User::by_user_id(connection, token.user_id)?;
+    if let Some(ban) = Ban::blocking(connection, user.id, BanScope::Global)? {
+        return Err(ApiError::from(UserError::Banned(Box::new(ban))));
+    }
+    Ok(ApiUser { user, token: Some(token) })
+}
+
+impl FromRequest for ApiUser {
+    type Error = ApiError;
+    type Future = Result<ApiUser, ApiError>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let pool = req
+            .get_app_data::<DatabasePool>()
+            .ok_or_else(|| ApiError::from(UserError::Unknown("No database pool".to_owned())))?;
+        let connection = pool.get()?;
+        match req.headers().get(actix_web::http::header::AUTHORIZATION) {
+            Some(header) => from_bearer_token(&connection, header),
+            None => Ok(ApiUser {
+                user: User::required_from_session(&connection, &req.clone().get_session())?,
+                token: None,
+            }),
+        }
+    }
+}
@@ -0,0 +1,31 @@
+pub mod attachments;
+pub mod auth;
+pub mod openapi;
+pub mod posts;
+pub mod topics;
+pub mod users;
+pub mod views;
+
+use actix_web::web;
+
+// docs/openapi.json has to describe each of these, which its tests check
+pub fn configure(config: &mut web::ServiceConfig) {
+    config
+        .route("/api/v1/openapi.json", web::get().to(openapi::get))
+        .route("/api/v1/users", web::get().to(users::list))
+        .route("/api/v1/users", web::post().to(users::create))
+        .route("/api/v1/users/{user_id}", web::get().to(users::get))
+        .route("/api/v1/topics", web::get().to(topics::list))
+        .route("/api/v1/topics", web::post().to(topics::create))
+        .route("/api/v1/topics/{topic_id}", web::get().to(topics::get))
+        .route("/api/v1/topics/{topic_id}", web::patch().to(topics::edit))
+        .route("/api/v1/topics/{topic_id}", web::delete().to(topics::delete))
+        .route("/api/v1/topics/{topic_id}/posts", web::get().to(posts::list))
+        .route("/api/v1/topics/{topic_id}/posts", web::post().to(posts::create))
+        .route("/api/v1/posts/{post_id}", web::get().to(posts::get))
+        .route("/api/v1/posts/{post_id}", web::patch().to(posts::edit))
+        .route("/api/v1/posts/{post_id}", web::delete().to(posts::delete))
+        .route("/api/v1/posts/{post_id}/attachments", web::get().to(attachments::list))
+        .route("/api/v1/posts/{post_id}/attachments", web::post().to_async(attachments::create))
+        .route("/api/v1/attachments/{attachment_id}", web::delete().to(attachments::delete));
+}
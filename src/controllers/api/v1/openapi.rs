@@ -0,0 +1,74 @@
+use actix_web::HttpResponse;
+
+// The description of the API is kept by hand next to the rest of the documentation, and checked
+// against the registered routes by the tests below
+const OPENAPI_DOCUMENT: &str = include_str!("../../../../docs/openapi.json");
+
+pub fn get() -> HttpResponse {
+    HttpResponse::Ok().content_type("application/json").body(OPENAPI_DOCUMENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    const SERVER_URL: &str = "/api/v1";
+    const METHODS: [&str; 5] = ["get", "post", "put", "patch", "delete"];
+
+    // Read from the source of configure, as actix has no way to list what was registered
+    fn registered_routes() -> BTreeSet<(String, String)> {
+        let route = regex::Regex::new(r#"\.route\("([^"]+)", web::(\w+)\(\)"#).unwrap();
+        route
+            .captures_iter(include_str!("mod.rs"))
+            .map(|captures| (captures[1].trim_start_matches(SERVER_URL).to_owned(), captures[2].to_owned()))
+            .filter(|(path, _)| path != "/openapi.json")
+            .collect()
+    }
+
+    fn documented_routes(document: &serde_json::Value) -> BTreeSet<(String, String)> {
+        let paths = document["paths"].as_object().expect("The document has paths");
+        paths
+            .iter()
+            .flat_map(|(path, item)| {
+                let item = item.as_object().expect("Each path is an object");
+                item.keys()
+                    .filter(|key| METHODS.contains(&key.as_str()))
+                    .map(move |method| (path.clone(), method.clone()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn document_is_valid_json_for_the_api() {
+        let document: serde_json::Value = serde_json::from_str(OPENAPI_DOCUMENT).expect("docs/openapi.json is valid JSON");
+        assert!(document["openapi"].as_str().is_some_and(|version| version.starts_with("3.")));
+        assert_eq!(document["servers"][0]["url"], SERVER_URL);
+    }
+
+    #[test]
+    fn document_describes_every_route() {
+        let document: serde_json::Value = serde_json::from_str(OPENAPI_DOCUMENT).unwrap();
+        let registered = registered_routes();
+        let documented = documented_routes(&document);
+        assert!(!registered.is_empty());
+        let undocumented: Vec<_> = registered.difference(&documented).collect();
+        let unregistered: Vec<_> = documented.difference(&registered).collect();
+        assert!(undocumented.is_empty(), "Routes missing from docs/openapi.json: {:?}", undocumented);
+        assert!(
+            unregistered.is_empty(),
+            "Routes in docs/openapi.json that aren't registered: {:?}",
+            unregistered
+        );
+    }
+
+    #[test]
+    fn documented_references_resolve() {
+        let document: serde_json::Value = serde_json::from_str(OPENAPI_DOCUMENT).unwrap();
+        let reference = regex::Regex::new(r##""\$ref":"#/([^"]+)""##).unwrap();
+        for captures in reference.captures_iter(&document.to_string()) {
+            let pointer = format!("/{}", &captures[1]);
+            assert!(document.pointer(&pointer).is_some(), "{} does not resolve", &captures[1]);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use super::auth::{ApiUser, ReasonQuery};
+use super::views::{Item, Page, PostView};
+use crate::database::types::*;
+use crate::models::api_token::Scope;
+use crate::models::audit_log::{AuditAction, AuditEntry};
+use crate::models::ip_address::{IpEvent, IpRecord};
+use crate::models::post::Post;
+use crate::models::spam_log::SpamKind;
+use crate::models::topic::Topic;
+use crate::models::user::UserError;
+use crate::spam::{Pipeline, Submission};
+use crate::utils::client_ip::ClientIp;
+use crate::utils::errors::ApiError;
+use crate::utils::id::Id;
+use crate::utils::pagination::{PageQuery, Pagination};
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+
+const POSTS_PER_PAGE: i64 = 50;
+const MAX_CONTENT_SIZE: usize = 20000;
+
+#[derive(Deserialize)]
+pub struct PostRequest {
+    content: String,
+}
+
+#[derive(Deserialize)]
+pub struct EditPostRequest {
+    content: String,
+    // For the audit log, when editing someone else's post
+    reason: Option<String>,
+}
+
+pub fn validate_content(content: &str) -> Vec<String> {
+    let mut errors = vec![];
+    if content.trim().is_empty() {
+        errors.push("Content must not be empty".to_owned());
+    } else if content.chars().count() > MAX_CONTENT_SIZE {
+        errors.push(format!("Content must be at most {} characters long", MAX_CONTENT_SIZE));
+    }
+    errors
+}
+
+pub fn list(pool: web::Data<DatabasePool>, path: web::Path<Id<Topic>>, query: web::Query<PageQuery>) -> Result<HttpResponse, ApiError> {
+    let connection = pool.get()?;
+    let (topic, _) = Topic::visible_by_id(&connection, path.into_inner(), None)?;
+    let mut posts = Post::in_topic_by_date(&connection, topic.id, None, query.offset(POSTS_PER_PAGE), POSTS_PER_PAGE + 1)?;
+    let pagination = Pagination::from_results(&query, POSTS_PER_PAGE, &mut posts);
+    let data = posts.into_iter().map(PostView::from).collect();
+    Ok(HttpResponse::Ok().json(Page { data, pagination }))
+}
+
+pub fn get(pool: web::Data<DatabasePool>, path: web::Path<Id<Post>>) -> Result<HttpResponse, ApiError> {
+    let connection = pool.get()?;
+    let post = Post::visible_by_id(&connection, path.into_inner(), None)?;
+    Ok(HttpResponse::Ok().json(Item { data: PostView::from(post) }))
+}
+
+pub fn create(
+    pool: web::Data<DatabasePool>,
+    spam: web::Data<Pipeline>,
+    caller: ApiUser,
+    ip: ClientIp,
+    path: web::Path<Id<Topic>>,
+    request: web::Json<PostRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let connection = pool.get()?;
+    caller.require_scope(Scope::Write)?;
+    caller.user.check_may_post(&connection)?;
+    let (topic, _) = Topic::visible_by_id(&connection, path.into_inner(), None)?;
+
+    let content = request.content.trim_end().to_owned();
+    let errors = validate_content(&content);
+    if !errors.is_empty() {
+        return Err(ApiError::bad_request(errors));
+    }
+
+    let verdict = spam.run(
+        &connection,
+        &Submission {
+            kind: SpamKind::Post,
+            user: Some(&caller.user),
+            ip: ip.0,
+            text: &content,
+            form: None,
+        },
+    )?;
+    verdict.enforce(&connection, Some(caller.user.id))?;
+
+    let post = {
+        use diesel::Connection;
+        connection.transaction::<_, ApiError, _>(|| {
+            let post = Post::create(&connection, caller.user.id, topic.id, &content, verdict.hold_reason())?;
+            IpRecord::record(&connection, caller.user.id, Some(post.id), IpEvent::Post, ip.0)?;
+            verdict.record(&connection, Some(caller.user.id), Some(post.id))?;
+            Ok(post)
+        })?
+    };
+    let mut response = if post.pending { HttpResponse::Accepted() } else { HttpResponse::Created() };
+    Ok(response.json(Item {
+        data: PostView::from((post, caller.user)),
+    }))
+}
+
+pub fn edit(pool: web::Data<DatabasePool>, caller: ApiUser, path: web::Path<Id<Post>>, request: web::Json<EditPostRequest>) -> Result<HttpResponse, ApiError> {
+    let connection = pool.get()?;
+    caller.require_scope(Scope::Write)?;
+    caller.user.check_may_post(&connection)?;
+    let (post, _) = Post::by_id(&connection, path.into_inner())?;
+    if !caller.may_edit(&connection, post.created_by)? {
+        return Err(ApiError::from(UserError::Forbidden));
+    }
+
+    let content = request.content.trim_end().to_owned();
+    let errors = validate_content(&content);
+    if !errors.is_empty() {
+        return Err(ApiError::bad_request(errors));
+    }
+
+    let edited = {
+        use diesel::Connection;
+        connection.transaction::<_, ApiError, _>(|| {
+            Post::set_content(&connection, post.id, &content)?;
+            let (edited, author) = Post::by_id(&connection, post.id)?;
+            if caller.is_moderating(post.created_by) {
+                let reason = request.reason.as_deref().unwrap_or("");
+                AuditEntry::record(
+                    &connection,
+                    caller.user.id,
+                    AuditAction::PostEdit,
+                    post.id.value(),
+                    Some(json!(post)),
+                    Some(json!(edited)),
+                    reason,
+                )?;
+            }
+            Ok((edited, author))
+        })?
+    };
+    // Held by the word filters, like new ones can be
+    let mut response = if edited.0.pending { HttpResponse::Accepted() } else { HttpResponse::Ok() };
+    Ok(response.json(Item { data: PostView::from(edited) }))
+}
+
+pub fn delete(pool: web::Data<DatabasePool>, caller: ApiUser, path: web::Path<Id<Post>>, query: web::Query<ReasonQuery>) -> Result<HttpResponse, ApiError> {
+    let connection = pool.get()?;
+    caller.require_scope(Scope::Write)?;
+    let (post, _) = Post::by_id(&connection, path.into_inner())?;
+    if !caller.may_edit(&connection, post.created_by)? {
+        return Err(ApiError::from(UserError::Forbidden));
+    }
+
+    {
+        use diesel::Connection;
+        connection.transaction::<_, ApiError, _>(|| {
+            Post::delete(&connection, post.id)?;
+            if caller.is_moderating(post.created_by) {
+                let reason = query.reason.as_deref().unwrap_or("");
+                AuditEntry::record(
+                    &connection,
+                    caller.user.id,
+                    AuditAction::PostDelete,
+                    post.id.value(),
+                    Some(json!(post)),
+                    None,
+                    reason,
+                )?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,181 @@
+use super::auth::{ApiUser, ReasonQuery};
+use super::posts::validate_content;
+use super::views::{Item, Page, TopicView};
+use crate::database::types::*;
+use crate::models::api_token::Scope;
+use crate::models::audit_log::{AuditAction, AuditEntry};
+use crate::models::ip_address::{IpEvent, IpRecord};
+use crate::models::spam_log::SpamKind;
+use crate::models::topic::Topic;
+use crate::models::user::UserError;
+use crate::spam::{Pipeline, Submission};
+use crate::utils::client_ip::ClientIp;
+use crate::utils::errors::ApiError;
+use crate::utils::id::Id;
+use crate::utils::pagination::{PageQuery, Pagination};
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+
+const TOPICS_PER_PAGE: i64 = 25;
+const MAX_TITLE_SIZE: usize = 200;
+
+#[derive(Deserialize)]
+pub struct NewTopicRequest {
+    title: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+pub struct EditTopicRequest {
+    title: String,
+    // For the audit log, when editing someone else's topic
+    reason: Option<String>,
+}
+
+pub fn validate_title(title: &str) -> Vec<String> {
+    let mut errors = vec![];
+    if title.is_empty() {
+        errors.push("Title must not be empty".to_owned());
+    } else if title.chars().count() > MAX_TITLE_SIZE {
+        errors.push(format!("Title must be at most {} characters long", MAX_TITLE_SIZE));
+    }
+    errors
+}
+
+pub fn list(pool: web::Data<DatabasePool>, query: web::Query<PageQuery>) -> Result<HttpResponse, ApiError> {
+    let connection = pool.get()?;
+    let mut topics = Topic::by_date(&connection, None, query.offset(TOPICS_PER_PAGE), TOPICS_PER_PAGE + 1)?;
+    let pagination = Pagination::from_results(&query, TOPICS_PER_PAGE, &mut topics);
+    let data = topics.into_iter().map(TopicView::from).collect();
+    Ok(HttpResponse::Ok().json(Page { data, pagination }))
+}
+
+pub fn get(pool: web::Data<DatabasePool>, path: web::Path<Id<Topic>>) -> Result<HttpResponse, ApiError> {
+    let connection = pool.get()?;
+    let topic = Topic::visible_by_id(&connection, path.into_inner(), None)?;
+    Ok(HttpResponse::Ok().json(Item { data: TopicView::from(topic) }))
+}
+
+// A topic is created together with its first post. Either may be held for approval by the word
+// filters, in which case the topic is accepted but not yet visible.
+pub fn create(
+    pool: web::Data<DatabasePool>,
+    spam: web::Data<Pipeline>,
+    caller: ApiUser,
+    ip: ClientIp,
+    request: web::Json<NewTopicRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let connection = pool.get()?;
+    caller.require_scope(Scope::Write)?;
+    caller.user.check_may_post(&connection)?;
+    let title = request.title.trim().to_owned();
+    let content = request.content.trim_end().to_owned();
+
+    let mut errors = validate_title(&title);
+    errors.append(&mut validate_content(&content));
+    if !errors.is_empty() {
+        return Err(ApiError::bad_request(errors));
+    }
+
+    let verdict = spam.run(
+        &connection,
+        &Submission {
+            kind: SpamKind::Topic,
+            user: Some(&caller.user),
+            ip: ip.0,
+            text: &content,
+            form: None,
+        },
+    )?;
+    verdict.enforce(&connection, Some(caller.user.id))?;
+
+    let topic = {
+        use diesel::Connection;
+        connection.transaction::<_, ApiError, _>(|| {
+            let (topic, post) = Topic::create(&connection, caller.user.id, &title, &content, verdict.hold_reason())?;
+            IpRecord::record(&connection, caller.user.id, Some(post.id), IpEvent::Post, ip.0)?;
+            verdict.record(&connection, Some(caller.user.id), Some(post.id))?;
+            Ok(topic)
+        })?
+    };
+
+    let mut response = if topic.pending { HttpResponse::Accepted() } else { HttpResponse::Created() };
+    Ok(response.json(Item {
+        data: TopicView::from((topic, caller.user)),
+    }))
+}
+
+pub fn edit(
+    pool: web::Data<DatabasePool>,
+    caller: ApiUser,
+    path: web::Path<Id<Topic>>,
+    request: web::Json<EditTopicRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let connection = pool.get()?;
+    caller.require_scope(Scope::Write)?;
+    caller.user.check_may_post(&connection)?;
+    let (topic, _) = Topic::by_id(&connection, path.into_inner())?;
+    if !caller.may_edit(&connection, topic.created_by)? {
+        return Err(ApiError::from(UserError::Forbidden));
+    }
+
+    let title = request.title.trim().to_owned();
+    let errors = validate_title(&title);
+    if !errors.is_empty() {
+        return Err(ApiError::bad_request(errors));
+    }
+
+    let edited = {
+        use diesel::Connection;
+        connection.transaction::<_, ApiError, _>(|| {
+            Topic::set_title(&connection, topic.id, &title)?;
+            let (edited, author) = Topic::by_id(&connection, topic.id)?;
+            if caller.is_moderating(topic.created_by) {
+                let reason = request.reason.as_deref().unwrap_or("");
+                AuditEntry::record(
+                    &connection,
+                    caller.user.id,
+                    AuditAction::TopicEdit,
+                    topic.id.value(),
+                    Some(json!(topic)),
+                    Some(json!(edited)),
+                    reason,
+                )?;
+            }
+            Ok((edited, author))
+        })?
+    };
+    // Held by the word filters, like new ones can be
+    let mut response = if edited.0.pending { HttpResponse::Accepted() } else { HttpResponse::Ok() };
+    Ok(response.json(Item { data: TopicView::from(edited) }))
+}
+
+pub fn delete(pool: web::Data<DatabasePool>, caller: ApiUser, path: web::Path<Id<Topic>>, query: web::Query<ReasonQuery>) -> Result<HttpResponse, ApiError> {
+    let connection = pool.get()?;
+    caller.require_scope(Scope::Write)?;
+    let (topic, _) = Topic::by_id(&connection, path.into_inner())?;
+    if !caller.may_edit(&connection, topic.created_by)? {
+        return Err(ApiError::from(UserError::Forbidden));
+    }
+
+    {
+        use diesel::Connection;
+        connection.transaction::<_, ApiError, _>(|| {
+            Topic::delete(&connection, topic.id)?;
+            if caller.is_moderating(topic.created_by) {
+                let reason = query.reason.as_deref().unwrap_or("");
+                AuditEntry::record(
+                    &connection,
+                    caller.user.id,
+                    AuditAction::TopicDelete,
+                    topic.id.value(),
+                    Some(json!(topic)),
+                    None,
+                    reason,
+                )?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,107 @@
+use super::views::{Item, Page, UserView};
+use crate::controllers::users::register::{sanitize_register_form, validate_register_form, validation_error_to_string, RegisterForm};
+use crate::controllers::users::verify_email::send_verification;
+use crate::database::types::*;
+use crate::models::ip_address::{IpEvent, IpRecord};
+use crate::models::spam_log::{SpamDecision, SpamKind};
+use crate::models::user::{User, UserError};
+use crate::spam::{Pipeline, Submission};
+use crate::themes::Renderer;
+use crate::utils::client_ip::ClientIp;
+use crate::utils::errors::ApiError;
+use crate::utils::id::Id;
+use crate::utils::pagination::{PageQuery, Pagination};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse};
+use diesel::result::Error as DieselError;
+use serde::Deserialize;
+
+const USERS_PER_PAGE: i64 = 50;
+// Stands in for the honeypot and form token checks, which need a form. Counts registrations made
+// either way from the same address.
+const MAX_REGISTRATIONS_PER_HOUR: i64 = 2;
+
+#[derive(Deserialize)]
+pub struct NewUserRequest {
+    user_name: String,
+    email: String,
+    password: String,
+}
+
+pub fn list(pool: web::Data<DatabasePool>, query: web::Query<PageQuery>) -> Result<HttpResponse, ApiError> {
+    let connection = pool.get()?;
+    let mut users = User::by_name(&connection, query.offset(USERS_PER_PAGE), USERS_PER_PAGE + 1)?;
+    let pagination = Pagination::from_results(&query, USERS_PER_PAGE, &mut users);
+    let data = users.into_iter().map(UserView::from).collect();
+    Ok(HttpResponse::Ok().json(Page { data, pagination }))
+}
+
+pub fn get(pool: web::Data<DatabasePool>, path: web::Path<Id<User>>) -> Result<HttpResponse, ApiError> {
+    let connection = pool.get()?;
+    let user = match User::by_user_id(&connection, path.into_inner()) {
+        Err(UserError::UnknownDatabaseError(DieselError::NotFound)) => return Err(ApiError::from(UserError::NoSuchUser)),
+        result => result?,
+    };
+    Ok(HttpResponse::Ok().json(Item { data: UserView::from(user) }))
+}
+
+// Registers an account the same way the form does, without logging in as it. There is no form to carry a
+// honeypot or token, so the honeypot and time to submit checks don't apply. Instead each address gets a
+// few registrations an hour, on top of the content and velocity checks.
+pub fn create(
+    hb: Renderer,
+    pool: web::Data<DatabasePool>,
+    spam: web::Data<Pipeline>,
+    ip: ClientIp,
+    request: web::Json<NewUserRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let form = sanitize_register_form(&RegisterForm {
+        user_name: request.user_name.clone(),
+        email: request.email.clone(),
+        password: request.password.clone(),
+        confirm_password: request.password.clone(),
+        website: String::new(),
+        form_token: String::new(),
+    });
+    let validation_errors = validate_register_form(&form);
+    if !validation_errors.is_empty() {
+        return Err(ApiError::bad_request(
+            validation_errors.iter().map(|e| validation_error_to_string(&hb, *e)).collect(),
+        ));
+    }
+
+    let connection = pool.get()?;
+    if let Some(address) = ip.0 {
+        let since = chrono::Utc::now() - chrono::Duration::hours(1);
+        if IpRecord::count_since(&connection, address, IpEvent::Register, since)? >= MAX_REGISTRATIONS_PER_HOUR {
+            let message = "Too many accounts have been registered from this address, please try again later".to_owned();
+            return Err(ApiError(StatusCode::TOO_MANY_REQUESTS, vec![message]));
+        }
+    }
+    let verdict = spam.run(
+        &connection,
+        &Submission {
+            kind: SpamKind::Register,
+            user: None,
+            ip: ip.0,
+            text: &form.user_name,
+            form: None,
+        },
+    )?;
+    verdict.enforce(&connection, None)?;
+
+    let user = {
+        use diesel::Connection;
+        connection.transaction::<_, ApiError, _>(|| {
+            let user = User::register(&connection, &form.user_name, &form.password, &form.email)?;
+            IpRecord::record(&connection, user.id, None, IpEvent::Register, ip.0)?;
+            if verdict.decision == SpamDecision::Hold {
+                User::set_spam_held(&connection, user.id)?;
+            }
+            verdict.record(&connection, Some(user.id), None)?;
+            Ok(user)
+        })?
+    };
+    send_verification(&connection, &hb, &user, &form.email)?;
+    Ok(HttpResponse::Created().json(Item { data: UserView::from(user) }))
+}
@@ -0,0 +1,113 @@
+// The public shapes returned by the JSON API. Models are never serialized directly so that
+// private columns (password hashes, email addresses) can't leak out through it.
+
+use crate::models::attachment::{Attachment, StoredFile};
+use crate::models::post::Post;
+use crate::models::topic::Topic;
+use crate::models::user::User;
+use crate::utils::id::Id;
+use crate::utils::pagination::Pagination;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct UserView {
+    pub id: Id<User>,
+    pub user_name: String,
+    pub role: String,
+    pub avatar_url: String,
+}
+
+#[derive(Serialize)]
+pub struct TopicView {
+    pub id: Id<Topic>,
+    pub title: String,
+    pub created_by: UserView,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    // Waiting for a moderator; only ever true in the response to creating it
+    pub pending: bool,
+}
+
+#[derive(Serialize)]
+pub struct PostView {
+    pub id: Id<Post>,
+    pub topic_id: Id<Topic>,
+    pub created_by: UserView,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub content: String,
+    pub content_html: String,
+    pub pending: bool,
+}
+
+#[derive(Serialize)]
+pub struct AttachmentView {
+    pub id: Id<Attachment>,
+    pub post_id: Id<Post>,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: i64,
+    pub url: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+// Every response is wrapped so listings and single items have the same outer shape
+#[derive(Serialize)]
+pub struct Item<T> {
+    pub data: T,
+}
+
+#[derive(Serialize)]
+pub struct Page<T> {
+    pub data: Vec<T>,
+    pub pagination: Pagination,
+}
+
+impl From<User> for UserView {
+    fn from(user: User) -> UserView {
+        UserView {
+            avatar_url: user.avatar_url(),
+            id: user.id,
+            user_name: user.user_name,
+            role: user.role,
+        }
+    }
+}
+
+impl From<(Topic, User)> for TopicView {
+    fn from((topic, user): (Topic, User)) -> TopicView {
+        TopicView {
+            id: topic.id,
+            title: topic.title,
+            created_by: UserView::from(user),
+            created_at: topic.created_at,
+            pending: topic.pending,
+        }
+    }
+}
+
+impl From<(Post, User)> for PostView {
+    fn from((post, user): (Post, User)) -> PostView {
+        PostView {
+            content_html: post.rendered_content(),
+            id: post.id,
+            topic_id: post.posted_in,
+            created_by: UserView::from(user),
+            created_at: post.created_at,
+            pending: post.pending,
+            content: post.content,
+        }
+    }
+}
+
+impl From<(Attachment, StoredFile)> for AttachmentView {
+    fn from((attachment, file): (Attachment, StoredFile)) -> AttachmentView {
+        AttachmentView {
+            url: attachment.url(),
+            id: attachment.id,
+            post_id: attachment.post_id,
+            file_name: attachment.file_name,
+            content_type: file.content_type,
+            size: file.size,
+            created_at: attachment.created_at,
+        }
+    }
+}
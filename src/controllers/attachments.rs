@@ -0,0 +1,108 @@
+// Serves attachment files. Uploads come from anyone, so they are served with headers that stop a
+// browser treating them as anything but the type they were sniffed as, and only images display
+// inline.
+
+use crate::database::types::*;
+use crate::models::attachment::{Attachment, AttachmentError, StoredFile, Thumbnail};
+use crate::models::post::Post;
+use crate::models::user::User;
+use crate::storage::Storage;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use crate::utils::sniff;
+use actix_session::Session;
+use actix_web::http::header::{self, Charset, ContentDisposition, DispositionParam, DispositionType, ExtendedValue};
+use actix_web::{web, HttpRequest, HttpResponse};
+
+// Attachments never change, but can be deleted by moderators, so caches are asked to check daily
+const CACHE_CONTROL: &str = "public, max-age=86400";
+// Files on held posts are only for their author and moderators, and go away if the post is rejected
+const PENDING_CACHE_CONTROL: &str = "private, no-cache";
+
+fn content_disposition(file_name: &str, inline: bool) -> ContentDisposition {
+    let ascii_name: String = file_name.chars().map(|c| if c.is_ascii() { c } else { '_' }).collect();
+    ContentDisposition {
+        disposition: if inline { DispositionType::Inline } else { DispositionType::Attachment },
+        parameters: vec![
+            DispositionParam::Filename(ascii_name),
+            DispositionParam::FilenameExt(ExtendedValue {
+                charset: Charset::Ext("UTF-8".to_owned()),
+                language_tag: None,
+                value: file_name.as_bytes().to_vec(),
+            }),
+        ],
+    }
+}
+
+pub fn serve(storage: &dyn Storage, request: &HttpRequest, file: &StoredFile, file_name: &str) -> Result<HttpResponse, RyobError> {
+    respond(storage, request, file, file_name, CACHE_CONTROL)
+}
+
+fn respond(storage: &dyn Storage, request: &HttpRequest, file: &StoredFile, file_name: &str, cache_control: &str) -> Result<HttpResponse, RyobError> {
+    // The contents are addressed by their hash, which makes it a strong validator
+    let etag = format!("\"{}\"", file.content_hash);
+    if let Some(if_none_match) = request.headers().get(header::IF_NONE_MATCH) {
+        if if_none_match
+            .to_str()
+            .ok()
+            .map(|value| value.split(',').any(|tag| tag.trim() == etag))
+            .unwrap_or(false)
+        {
+            return Ok(HttpResponse::NotModified().header(header::ETAG, etag).finish());
+        }
+    }
+
+    let contents = storage.get(&file.content_hash)?;
+    Ok(HttpResponse::Ok()
+        .content_type(file.content_type.as_str())
+        .set(content_disposition(file_name, sniff::is_image(&file.content_type)))
+        .header(header::ETAG, etag)
+        .header(header::CACHE_CONTROL, cache_control)
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .header(header::CONTENT_SECURITY_POLICY, "default-src 'none'; sandbox")
+        .body(contents))
+}
+
+// Like Attachment::by_id, but as if attachments on held posts did not exist for anyone who can't
+// see those posts. Also returns the caching to serve it with.
+fn visible_attachment(
+    connection: &DatabaseConnection,
+    session: &Session,
+    attachment: Id<Attachment>,
+) -> Result<(Attachment, StoredFile, &'static str), RyobError> {
+    let (attachment, file) = Attachment::by_id(connection, attachment)?;
+    let (post, _) = Post::by_id(connection, attachment.post_id)?;
+    if !post.pending {
+        return Ok((attachment, file, CACHE_CONTROL));
+    }
+    match User::from_session(connection, session)? {
+        Some(viewer) if viewer.may_edit(connection, post.created_by)? => Ok((attachment, file, PENDING_CACHE_CONTROL)),
+        _ => Err(RyobError::from(AttachmentError::NoSuchAttachment)),
+    }
+}
+
+pub fn get(
+    pool: web::Data<DatabasePool>,
+    storage: web::Data<Box<dyn Storage>>,
+    session: Session,
+    request: HttpRequest,
+    path: web::Path<Id<Attachment>>,
+) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let (attachment, file, cache_control) = visible_attachment(&connection, &session, path.into_inner())?;
+    respond(storage.as_ref(), &request, &file, &attachment.file_name, cache_control)
+}
+
+pub fn thumbnail(
+    pool: web::Data<DatabasePool>,
+    storage: web::Data<Box<dyn Storage>>,
+    session: Session,
+    request: HttpRequest,
+    path: web::Path<(Id<Attachment>, String)>,
+) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let (attachment_id, size) = path.into_inner();
+    let (attachment, _, cache_control) = visible_attachment(&connection, &session, attachment_id)?;
+    let (_, file) = Thumbnail::by_attachment(&connection, attachment.id, &size)?;
+    respond(storage.as_ref(), &request, &file, &attachment.file_name, cache_control)
+}
@@ -0,0 +1,105 @@
+use crate::database::types::*;
+use crate::models::banner::{Banner, BannerError, BannerKind, BannerStatus, MAX_BANNER_SIZE};
+use crate::models::message::Message;
+use crate::models::user::{Role, User};
+use crate::storage::Storage;
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use crate::utils::uploads;
+use actix_multipart::Multipart;
+use actix_session::Session;
+use actix_web::{web, HttpRequest, HttpResponse};
+use futures::future::{self, Either};
+use futures::Future;
+
+fn banner_error_to_string(hb: &Renderer, error: &BannerError) -> String {
+    match error {
+        BannerError::TooLarge => hb.translate("banner-submit-too-large", &[("size", (MAX_BANNER_SIZE / 1024 / 1024).into())]),
+        BannerError::UnsupportedType => hb.translate("banner-submit-unsupported-type", &[]),
+        BannerError::NoFile => hb.translate("upload-no-file", &[]),
+        BannerError::InvalidImage(err) => hb.translate("upload-invalid-image", &[("error", err.as_str().into())]),
+        BannerError::Upload(err) => hb.translate_upload_error(err),
+        BannerError::File(err) => hb.translate_attachment_error(err),
+        _ => error.to_string(),
+    }
+}
+
+fn render_submit(hb: &Renderer, connection: &DatabaseConnection, user: &User, submitted: bool, errors: Vec<String>) -> Result<String, RyobError> {
+    let unread_message_count = Message::unread_count(connection, user.id, None)?;
+    let data = json!({
+        "user": user,
+        "unread_message_count": unread_message_count,
+        "kinds": BannerKind::ALL.iter().map(|kind| kind.as_str()).collect::<Vec<_>>(),
+        "max_size_mib": MAX_BANNER_SIZE / 1024 / 1024,
+        "submitted": submitted,
+        "errors": errors,
+    });
+    Ok(hb.render("pages/submit_banner", &data)?)
+}
+
+// Banners waiting for approval or disabled are only shown to admins
+pub fn get(
+    pool: web::Data<DatabasePool>,
+    storage: web::Data<Box<dyn Storage>>,
+    session: Session,
+    request: HttpRequest,
+    path: web::Path<Id<Banner>>,
+) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let (banner, file) = Banner::by_id(&connection, path.into_inner())?;
+    if banner.status() != BannerStatus::Approved {
+        let is_admin = match User::from_session(&connection, &session)? {
+            Some(user) => user.has_role(&connection, Role::Admin)?,
+            None => false,
+        };
+        if !is_admin {
+            return Err(RyobError::from(BannerError::NoSuchBanner));
+        }
+    }
+    crate::controllers::attachments::serve(storage.as_ref(), &request, &file, banner.kind.as_str())
+}
+
+pub fn new(hb: Renderer, pool: web::Data<DatabasePool>, session: Session) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    let page = render_submit(&hb, &connection, &user, false, vec![])?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+// Submissions are queued for an admin to approve
+pub fn create(
+    hb: Renderer,
+    pool: web::Data<DatabasePool>,
+    storage: web::Data<Box<dyn Storage>>,
+    session: Session,
+    multipart: Multipart,
+) -> impl Future<Item = HttpResponse, Error = RyobError> {
+    let user = match pool.get().map_err(RyobError::from).and_then(|connection| {
+        let user = User::required_from_session(&connection, &session)?;
+        user.check_may_post(&connection)?;
+        Ok(user)
+    }) {
+        Ok(user) => user,
+        Err(err) => return Either::A(future::err(err)),
+    };
+
+    Either::B(uploads::read_form(multipart, MAX_BANNER_SIZE, 1).then(move |form| {
+        let connection = pool.get()?;
+        let created = form.map_err(BannerError::from).and_then(|form| {
+            let kind = form.values.get("kind").and_then(|kind| BannerKind::parse(kind)).unwrap_or(BannerKind::Banner);
+            let contents = form.uploads.first().map(|upload| upload.contents.as_slice()).unwrap_or(&[]);
+            Banner::create(&connection, storage.as_ref(), user.id, None, kind, 1, contents)
+        });
+        match created {
+            Ok(_) => Ok(HttpResponse::Ok().body(render_submit(&hb, &connection, &user, true, vec![])?)),
+            Err(error @ BannerError::File(_)) | Err(error @ BannerError::UnknownDatabaseError(_)) | Err(error @ BannerError::Unknown(_)) => {
+                Err(RyobError::from(error))
+            }
+            Err(error) => {
+                let page = render_submit(&hb, &connection, &user, false, vec![banner_error_to_string(&hb, &error)])?;
+                Ok(HttpResponse::BadRequest().body(page))
+            }
+        }
+    }))
+}
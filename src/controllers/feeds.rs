@@ -0,0 +1,162 @@
+use crate::database::types::*;
+use crate::mailer::absolute_url;
+use crate::models::post::Post;
+use crate::models::topic::Topic;
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use crate::utils::signing;
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::Serialize;
+
+// Atom and RSS 2.0 feeds of the newest topics and of the newest posts in a topic
+
+const ENTRIES_PER_FEED: i64 = 50;
+const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+#[derive(Clone, Copy)]
+enum FeedFormat {
+    Atom,
+    Rss,
+}
+
+impl FeedFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            FeedFormat::Atom => "atom",
+            FeedFormat::Rss => "rss",
+        }
+    }
+
+    fn template(self) -> &'static str {
+        match self {
+            FeedFormat::Atom => "feeds/feed",
+            FeedFormat::Rss => "feeds/rss",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            FeedFormat::Atom => "application/atom+xml; charset=utf-8",
+            FeedFormat::Rss => "application/rss+xml; charset=utf-8",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct FeedEntry {
+    id: String,
+    url: String,
+    title: String,
+    author_name: String,
+    updated: chrono::DateTime<chrono::Utc>,
+    content_html: Option<String>,
+}
+
+// Edits and deletions don't move any timestamp, so the ETag comes from the feed itself and
+// Last-Modified is only used by clients that don't send If-None-Match
+fn respond(req: &HttpRequest, format: FeedFormat, body: String, last_modified: chrono::DateTime<chrono::Utc>) -> HttpResponse {
+    let etag = format!("\"{}\"", signing::hash(&body));
+    let last_modified_header = last_modified.format(HTTP_DATE_FORMAT).to_string();
+
+    let not_modified = match req.headers().get(header::IF_NONE_MATCH) {
+        Some(if_none_match) => if_none_match
+            .to_str()
+            .map(|value| value.split(',').any(|tag| tag.trim() == etag || tag.trim() == "*"))
+            .unwrap_or(false),
+        None => req
+            .headers()
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| chrono::DateTime::parse_from_rfc2822(value).ok())
+            .map(|since| last_modified.timestamp() <= since.timestamp())
+            .unwrap_or(false),
+    };
+
+    let mut response = if not_modified { HttpResponse::NotModified() } else { HttpResponse::Ok() };
+    response.header(header::ETAG, etag).header(header::LAST_MODIFIED, last_modified_header);
+    if not_modified {
+        response.finish()
+    } else {
+        response.content_type(format.content_type()).body(body)
+    }
+}
+
+pub fn topics(req: HttpRequest, hb: Renderer, pool: web::Data<DatabasePool>) -> Result<HttpResponse, RyobError> {
+    topics_as(req, hb, pool, FeedFormat::Atom)
+}
+
+pub fn topics_rss(req: HttpRequest, hb: Renderer, pool: web::Data<DatabasePool>) -> Result<HttpResponse, RyobError> {
+    topics_as(req, hb, pool, FeedFormat::Rss)
+}
+
+pub fn topic(req: HttpRequest, hb: Renderer, pool: web::Data<DatabasePool>, path: web::Path<Id<Topic>>) -> Result<HttpResponse, RyobError> {
+    topic_as(req, hb, pool, path.into_inner(), FeedFormat::Atom)
+}
+
+pub fn topic_rss(req: HttpRequest, hb: Renderer, pool: web::Data<DatabasePool>, path: web::Path<Id<Topic>>) -> Result<HttpResponse, RyobError> {
+    topic_as(req, hb, pool, path.into_inner(), FeedFormat::Rss)
+}
+
+fn topics_as(req: HttpRequest, hb: Renderer, pool: web::Data<DatabasePool>, format: FeedFormat) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let entries: Vec<FeedEntry> = Topic::by_date(&connection, None, 0, ENTRIES_PER_FEED)?
+        .into_iter()
+        .map(|(topic, author)| {
+            let url = absolute_url(&format!("/topics/{}", topic.id.value()));
+            FeedEntry {
+                id: url.clone(),
+                url,
+                title: topic.title,
+                author_name: author.user_name,
+                updated: topic.created_at,
+                content_html: None,
+            }
+        })
+        .collect();
+
+    let updated = entries
+        .first()
+        .map(|entry| entry.updated)
+        .unwrap_or_else(|| chrono::DateTime::from(std::time::UNIX_EPOCH));
+    let data = json!({
+        "id": absolute_url("/"),
+        "url": absolute_url("/"),
+        "self_url": absolute_url(&format!("/feed.{}", format.extension())),
+        "title": "Newest topics",
+        "updated": updated,
+        "entries": entries,
+    });
+    let body = hb.render(format.template(), &data)?;
+    Ok(respond(&req, format, body, updated))
+}
+
+fn topic_as(req: HttpRequest, hb: Renderer, pool: web::Data<DatabasePool>, topic: Id<Topic>, format: FeedFormat) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let (topic, _) = Topic::visible_by_id(&connection, topic, None)?;
+    let topic_url = absolute_url(&format!("/topics/{}", topic.id.value()));
+    let entries: Vec<FeedEntry> = Post::in_topic_by_date(&connection, topic.id, None, 0, ENTRIES_PER_FEED)?
+        .into_iter()
+        .map(|(post, author)| FeedEntry {
+            id: format!("{}#post-{}", topic_url, post.id.value()),
+            url: format!("{}#post-{}", topic_url, post.id.value()),
+            title: format!("Post by {} in {}", author.user_name, topic.title),
+            author_name: author.user_name,
+            updated: post.created_at,
+            content_html: Some(post.rendered_content()),
+        })
+        .collect();
+
+    let updated = entries.first().map(|entry| entry.updated).unwrap_or(topic.created_at);
+    let data = json!({
+        "id": topic_url,
+        "url": topic_url,
+        "self_url": absolute_url(&format!("/topics/{}/feed.{}", topic.id.value(), format.extension())),
+        "title": topic.title,
+        "updated": updated,
+        "entries": entries,
+    });
+    let body = hb.render(format.template(), &data)?;
+    Ok(respond(&req, format, body, updated))
+}
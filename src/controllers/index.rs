@@ -1,14 +1,56 @@
 use crate::database::types::*;
+use crate::models::message::Message;
+use crate::models::topic::Topic;
 use crate::models::user::User;
+use crate::themes::Renderer;
 use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use crate::utils::pagination::{PageQuery, Pagination};
 use actix_session::Session;
 use actix_web::{web, HttpResponse};
-use handlebars::Handlebars;
+use serde::Serialize;
 
-pub fn get(hb: web::Data<Handlebars>, pool: web::Data<DatabasePool>, session: Session) -> Result<HttpResponse, RyobError> {
+#[derive(Serialize)]
+struct TopicEntry {
+    topic: Topic,
+    author_id: Id<User>,
+    author_name: String,
+}
+
+pub fn get(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, query: web::Query<PageQuery>) -> Result<HttpResponse, RyobError> {
     let connection = pool.get()?;
     let user = User::from_session(&connection, &session)?;
-    let data = json!({ "user": user });
+    let unread_message_count = match &user {
+        Some(user) => Message::unread_count(&connection, user.id, None)?,
+        None => 0,
+    };
+
+    let per_page = hb.topics_per_page();
+    let mut topics = Topic::by_date(&connection, user.as_ref().map(|user| user.id), query.offset(per_page), per_page + 1)?;
+    let pagination = Pagination::from_results(&query, per_page, &mut topics);
+    let topics: Vec<TopicEntry> = topics
+        .into_iter()
+        .map(|(topic, author)| TopicEntry {
+            topic,
+            author_id: author.id,
+            author_name: author.user_name,
+        })
+        .collect();
+    let live = if pagination.page == 1 {
+        let last_seen = topics.first().map(|entry| entry.topic.id.value()).unwrap_or(0);
+        Some(format!("/live?after={}", last_seen))
+    } else {
+        None
+    };
+
+    let data = json!({
+        "user": user,
+        "unread_message_count": unread_message_count,
+        "topics": topics,
+        "pagination": pagination,
+        "live": live,
+        "feed": { "atom_url": "/feed.atom", "rss_url": "/feed.rss", "title": hb.translate("topics-feed-title", &[]) },
+    });
     let page = hb.render("pages/index", &data)?;
     Ok(HttpResponse::Ok().body(page))
 }
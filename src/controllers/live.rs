@@ -0,0 +1,83 @@
+// Server-Sent Event streams behind the live updates on the topic list and topic pages. The pages
+// are complete without them, they only save readers from refreshing.
+
+use crate::controllers::topics::topic::render_post;
+use crate::database::types::*;
+use crate::live::{self, Broker, Feed};
+use crate::models::post::Post;
+use crate::models::topic::Topic;
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use actix_web::web::Bytes;
+use actix_web::{web, HttpRequest, HttpResponse};
+use futures::sync::mpsc;
+use futures::{stream, Stream};
+use serde::Deserialize;
+
+// Anything further behind than this is better served by reloading the page
+const CATCH_UP_LIMIT: i64 = 50;
+
+#[derive(Deserialize)]
+pub struct LiveQuery {
+    after: Option<i64>,
+}
+
+// A reconnecting browser's Last-Event-ID is more recent than the page it was opened from
+fn last_seen(request: &HttpRequest, query: &LiveQuery) -> Option<i64> {
+    request
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<i64>().ok())
+        .or(query.after)
+}
+
+// Subscribing before catching up means nothing is missed in between, the page skips repeats
+fn respond(missed: Vec<Bytes>, receiver: mpsc::UnboundedReceiver<Bytes>) -> HttpResponse {
+    let events = stream::iter_ok::<_, ()>(missed)
+        .chain(receiver)
+        .map_err(|_| actix_web::error::ErrorInternalServerError("Live updates have stopped"));
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .header(actix_web::http::header::CACHE_CONTROL, "no-cache")
+        .streaming(events)
+}
+
+pub fn topics(pool: web::Data<DatabasePool>, broker: web::Data<Broker>, request: HttpRequest, query: web::Query<LiveQuery>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let receiver = broker.subscribe(Feed::Topics);
+    let missed = match last_seen(&request, &query) {
+        Some(after) => Topic::ids_after(&connection, Id::new(after), CATCH_UP_LIMIT)?
+            .into_iter()
+            .map(live::topic_event)
+            .collect(),
+        None => vec![],
+    };
+    Ok(respond(missed, receiver))
+}
+
+pub fn topic(
+    hb: Renderer,
+    pool: web::Data<DatabasePool>,
+    broker: web::Data<Broker>,
+    request: HttpRequest,
+    path: web::Path<Id<Topic>>,
+    query: web::Query<LiveQuery>,
+) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let (topic, _) = Topic::visible_by_id(&connection, path.into_inner(), None)?;
+    let key = hb.key();
+    let receiver = broker.subscribe(Feed::Topic(topic.id, key.clone()));
+    let keys = [key];
+    let mut missed = vec![];
+    if let Some(after) = last_seen(&request, &query) {
+        for (post, author) in Post::in_topic_after(&connection, topic.id, Id::new(after), CATCH_UP_LIMIT)? {
+            let post_id = post.id;
+            for html in render_post(&hb, &keys, &connection, post, author)? {
+                missed.push(live::post_event(post_id, &html));
+            }
+        }
+    }
+    Ok(respond(missed, receiver))
+}
@@ -0,0 +1,79 @@
+use crate::database::types::*;
+use crate::models::message::Message;
+use crate::models::user::{User, UserError};
+use crate::models::user_block::{UserBlock, UserBlockError};
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use diesel::result::Error as DieselError;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct BlockForm {
+    user_name: String,
+}
+
+#[derive(Serialize)]
+struct BlockedUser {
+    id: Id<User>,
+    user_name: String,
+    blocked_at: chrono::DateTime<chrono::Utc>,
+}
+
+fn render_blocks(hb: &Renderer, connection: &DatabaseConnection, user: &User, errors: Vec<String>) -> Result<String, RyobError> {
+    let blocked: Vec<BlockedUser> = UserBlock::blocked_by(connection, user.id)?
+        .into_iter()
+        .map(|(block, blocked_user)| BlockedUser {
+            id: blocked_user.id,
+            user_name: blocked_user.user_name,
+            blocked_at: block.created_at,
+        })
+        .collect();
+    let unread_message_count = Message::unread_count(connection, user.id, None)?;
+    let data = json!({ "user": user, "unread_message_count": unread_message_count, "blocked": blocked, "errors": errors });
+    Ok(hb.render("pages/messages/blocks", &data)?)
+}
+
+pub fn get(hb: Renderer, pool: web::Data<DatabasePool>, session: Session) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    let page = render_blocks(&hb, &connection, &user, vec![])?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+pub fn post(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, form: web::Form<BlockForm>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    let user_name = form.user_name.trim().to_owned();
+
+    let blocked_user = match User::by_user_name(&connection, &user_name) {
+        Err(UserError::UnknownDatabaseError(DieselError::NotFound)) => {
+            let page = render_blocks(
+                &hb,
+                &connection,
+                &user,
+                vec![hb.translate("messages-recipient-unknown", &[("user", user_name.as_str().into())])],
+            )?;
+            return Ok(HttpResponse::BadRequest().body(page));
+        }
+        result => result?,
+    };
+
+    match UserBlock::block(&connection, user.id, blocked_user.id) {
+        Err(UserBlockError::CannotBlockSelf) => {
+            let page = render_blocks(&hb, &connection, &user, vec![hb.translate("messages-block-self", &[])])?;
+            Ok(HttpResponse::BadRequest().body(page))
+        }
+        Err(error) => Err(RyobError::from(error)),
+        Ok(()) => Ok(HttpResponse::Found().header(actix_web::http::header::LOCATION, "/messages/blocks").finish()),
+    }
+}
+
+pub fn remove(pool: web::Data<DatabasePool>, session: Session, path: web::Path<Id<User>>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    UserBlock::unblock(&connection, user.id, path.into_inner())?;
+    Ok(HttpResponse::Found().header(actix_web::http::header::LOCATION, "/messages/blocks").finish())
+}
@@ -0,0 +1,185 @@
+use super::conversation::notify_participants;
+use crate::database::types::*;
+use crate::models::conversation::{Conversation, ConversationError, MAX_PARTICIPANTS};
+use crate::models::message::Message;
+use crate::models::user::{User, UserError};
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct ComposeForm {
+    recipients: String,
+    subject: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+pub struct ComposeQuery {
+    to: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PreviousComposeForm {
+    recipients: String,
+    subject: String,
+    content: String,
+}
+
+#[derive(Clone, Copy)]
+pub enum MessageFormValidationError {
+    NoRecipients,
+    TooManyRecipients,
+    SubjectEmpty,
+    SubjectTooLong,
+    ContentEmpty,
+    ContentTooLong,
+}
+
+const MAX_SUBJECT_SIZE: usize = 200;
+const MAX_CONTENT_SIZE: usize = 20000;
+
+fn sanitize_compose_form(form: &ComposeForm) -> ComposeForm {
+    ComposeForm {
+        recipients: form.recipients.trim().to_owned(),
+        subject: form.subject.trim().to_owned(),
+        content: form.content.trim_end().to_owned(),
+    }
+}
+
+fn compose_form_to_previous(form: &ComposeForm) -> PreviousComposeForm {
+    PreviousComposeForm {
+        recipients: form.recipients.to_owned(),
+        subject: form.subject.to_owned(),
+        content: form.content.to_owned(),
+    }
+}
+
+fn recipient_names(recipients: &str) -> Vec<String> {
+    let mut names: Vec<String> = recipients
+        .split(',')
+        .map(|name| name.trim().to_owned())
+        .filter(|name| !name.is_empty())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+pub fn validate_content(content: &str) -> Vec<MessageFormValidationError> {
+    let mut errors: Vec<MessageFormValidationError> = vec![];
+    if content.trim().is_empty() {
+        errors.push(MessageFormValidationError::ContentEmpty);
+    } else if content.chars().count() > MAX_CONTENT_SIZE {
+        errors.push(MessageFormValidationError::ContentTooLong);
+    }
+    errors
+}
+
+fn validate_compose_form(form: &ComposeForm) -> Vec<MessageFormValidationError> {
+    let mut errors: Vec<MessageFormValidationError> = vec![];
+    let recipient_count = recipient_names(&form.recipients).len();
+    if recipient_count == 0 {
+        errors.push(MessageFormValidationError::NoRecipients);
+    } else if recipient_count + 1 > MAX_PARTICIPANTS {
+        errors.push(MessageFormValidationError::TooManyRecipients);
+    }
+
+    if form.subject.is_empty() {
+        errors.push(MessageFormValidationError::SubjectEmpty);
+    } else if form.subject.chars().count() > MAX_SUBJECT_SIZE {
+        errors.push(MessageFormValidationError::SubjectTooLong);
+    }
+
+    errors.append(&mut validate_content(&form.content));
+    errors
+}
+
+pub fn validation_error_to_string(hb: &Renderer, error: MessageFormValidationError) -> String {
+    match error {
+        MessageFormValidationError::NoRecipients => hb.translate("messages-recipients-none", &[]),
+        MessageFormValidationError::TooManyRecipients => hb.translate("messages-recipients-too-many", &[("max", (MAX_PARTICIPANTS - 1).into())]),
+        MessageFormValidationError::SubjectEmpty => hb.translate("messages-subject-empty", &[]),
+        MessageFormValidationError::SubjectTooLong => hb.translate("messages-subject-length", &[("max", MAX_SUBJECT_SIZE.into())]),
+        MessageFormValidationError::ContentEmpty => hb.translate("messages-content-empty", &[]),
+        MessageFormValidationError::ContentTooLong => hb.translate("messages-content-length", &[("max", MAX_CONTENT_SIZE.into())]),
+    }
+}
+
+fn conversation_error_to_string(hb: &Renderer, error: &ConversationError) -> String {
+    match error {
+        ConversationError::NoRecipients => hb.translate("messages-recipients-none", &[]),
+        ConversationError::BlockedByRecipient(name) => hb.translate("messages-blocked-by", &[("user", name.as_str().into())]),
+        other => other.to_string(),
+    }
+}
+
+fn render_errors(hb: &Renderer, user: &User, unread_message_count: i64, form: &ComposeForm, errors: Vec<String>) -> Result<String, RyobError> {
+    let previous = compose_form_to_previous(form);
+    let data = json!({ "user": user, "unread_message_count": unread_message_count, "errors": errors, "previous": previous });
+    Ok(hb.render("pages/messages/compose", &data)?)
+}
+
+pub fn post(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, form: web::Form<ComposeForm>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    let unread_message_count = Message::unread_count(&connection, user.id, None)?;
+    let sanitized_form = sanitize_compose_form(&form);
+    match user.check_may_post(&connection) {
+        Err(UserError::Banned(ban)) => {
+            let page = render_errors(&hb, &user, unread_message_count, &sanitized_form, vec![hb.translate_ban(&ban)])?;
+            return Ok(HttpResponse::Forbidden().body(page));
+        }
+        result => result?,
+    }
+    let validation_errors = validate_compose_form(&sanitized_form);
+    if !validation_errors.is_empty() {
+        let validation_error_strings: Vec<String> = validation_errors.iter().map(|e| validation_error_to_string(&hb, *e)).collect();
+        let page = render_errors(&hb, &user, unread_message_count, &sanitized_form, validation_error_strings)?;
+        return Ok(HttpResponse::BadRequest().body(page));
+    }
+
+    let names = recipient_names(&sanitized_form.recipients);
+    let recipients = User::by_user_names(&connection, &names)?;
+    let unknown_names: Vec<String> = names
+        .iter()
+        .filter(|name| !recipients.iter().any(|recipient| &recipient.user_name == *name))
+        .map(|name| hb.translate("messages-recipient-unknown", &[("user", name.as_str().into())]))
+        .collect();
+    if !unknown_names.is_empty() {
+        let page = render_errors(&hb, &user, unread_message_count, &sanitized_form, unknown_names)?;
+        return Ok(HttpResponse::BadRequest().body(page));
+    }
+
+    let conversation_result = Conversation::start(&connection, user.id, &recipients, &sanitized_form.subject, &sanitized_form.content);
+    match conversation_result {
+        Err(error @ ConversationError::BlockedByRecipient(_)) | Err(error @ ConversationError::NoRecipients) => {
+            let page = render_errors(
+                &hb,
+                &user,
+                unread_message_count,
+                &sanitized_form,
+                vec![conversation_error_to_string(&hb, &error)],
+            )?;
+            Ok(HttpResponse::BadRequest().body(page))
+        }
+        Err(error) => Err(RyobError::from(error)),
+        Ok(conversation) => {
+            notify_participants(&connection, &hb, &conversation, &user, &sanitized_form.content)?;
+            let location = format!("/messages/{}", conversation.id.value());
+            Ok(HttpResponse::Found().header(actix_web::http::header::LOCATION, location).finish())
+        }
+    }
+}
+
+pub fn get(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, query: web::Query<ComposeQuery>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    let unread_message_count = Message::unread_count(&connection, user.id, None)?;
+    let previous = json!({ "recipients": query.to.clone().unwrap_or_default() });
+    let data = json!({ "user": user, "unread_message_count": unread_message_count, "previous": previous });
+    let page = hb.render("pages/messages/compose", &data)?;
+    Ok(HttpResponse::Ok().body(page))
+}
@@ -0,0 +1,196 @@
+use super::compose::{validate_content, validation_error_to_string};
+use crate::database::types::*;
+use crate::i18n::DEFAULT_LOCALE;
+use crate::mailer;
+use crate::models::conversation::Conversation;
+use crate::models::message::{Message, MessageError};
+use crate::models::user::{User, UserError};
+use crate::models::user_preferences::UserPreferences;
+use crate::themes::{Renderer, Themes};
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use crate::utils::pagination::{PageQuery, Pagination};
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+const MESSAGES_PER_PAGE: i64 = 50;
+
+// Emails everyone else still in the conversation about a new message, if they have a verified
+// address and haven't turned notifications off. Each email uses its recipient's own preferences.
+pub fn notify_participants(connection: &DatabaseConnection, hb: &Themes, conversation: &Conversation, sender: &User, content: &str) -> Result<(), RyobError> {
+    for (participant, recipient) in Conversation::participants(connection, conversation.id)? {
+        if participant.left_at.is_some() || recipient.id == sender.id {
+            continue;
+        }
+        let email = match (&recipient.email, recipient.email_verified_at) {
+            (Some(email), Some(_)) => email,
+            _ => continue,
+        };
+        let preferences = UserPreferences::for_user(connection, recipient.id)?;
+        if !preferences.email_notifications {
+            continue;
+        }
+        let data = json!({
+            "user_name": recipient.user_name,
+            "sender_name": sender.user_name,
+            "subject": conversation.subject,
+            "content": content,
+            "link": mailer::absolute_url(&format!("/messages/{}", conversation.id.value())),
+            "settings_link": mailer::absolute_url("/users/settings"),
+            "preferences": preferences,
+        });
+        mailer::enqueue(connection, hb, DEFAULT_LOCALE, "new_message", email, &data)?;
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct ReplyForm {
+    content: String,
+}
+
+#[derive(Serialize)]
+struct PreviousReplyForm {
+    content: String,
+}
+
+#[derive(Serialize)]
+struct MessageSender {
+    id: Id<User>,
+    user_name: String,
+}
+
+#[derive(Serialize)]
+struct ConversationEntry {
+    message: Message,
+    content_html: String,
+    sender: MessageSender,
+}
+
+fn render_conversation(
+    hb: &Renderer,
+    connection: &DatabaseConnection,
+    user: &User,
+    conversation_id: Id<Conversation>,
+    query: &PageQuery,
+    errors: Vec<String>,
+    previous: Option<PreviousReplyForm>,
+) -> Result<String, RyobError> {
+    let conversation = Conversation::by_id(connection, conversation_id)?;
+    let participants: Vec<MessageSender> = Conversation::participants(connection, conversation_id)?
+        .into_iter()
+        .map(|(_, participant)| MessageSender {
+            id: participant.id,
+            user_name: participant.user_name,
+        })
+        .collect();
+
+    let mut messages = Message::in_conversation_by_date(connection, conversation_id, query.offset(MESSAGES_PER_PAGE), MESSAGES_PER_PAGE + 1)?;
+    let pagination = Pagination::from_results(query, MESSAGES_PER_PAGE, &mut messages);
+    // Pages run newest first, but each page reads top to bottom
+    let entries: Vec<ConversationEntry> = messages
+        .into_iter()
+        .rev()
+        .map(|(message, sender)| ConversationEntry {
+            content_html: message.rendered_content(),
+            message,
+            sender: MessageSender {
+                id: sender.id,
+                user_name: sender.user_name,
+            },
+        })
+        .collect();
+
+    Conversation::mark_read(connection, conversation_id, user.id)?;
+    let unread_message_count = Message::unread_count(connection, user.id, None)?;
+
+    let data = json!({
+        "user": user,
+        "unread_message_count": unread_message_count,
+        "conversation": conversation,
+        "participants": participants,
+        "messages": entries,
+        "pagination": pagination,
+        "errors": errors,
+        "previous": previous,
+    });
+    Ok(hb.render("pages/messages/conversation", &data)?)
+}
+
+pub fn get(
+    hb: Renderer,
+    pool: web::Data<DatabasePool>,
+    session: Session,
+    path: web::Path<Id<Conversation>>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    let conversation_id = path.into_inner();
+    Conversation::participation(&connection, conversation_id, user.id)?;
+    let page = render_conversation(&hb, &connection, &user, conversation_id, &query, vec![], None)?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+pub fn post(
+    hb: Renderer,
+    pool: web::Data<DatabasePool>,
+    session: Session,
+    path: web::Path<Id<Conversation>>,
+    form: web::Form<ReplyForm>,
+) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    let conversation_id = path.into_inner();
+    Conversation::participation(&connection, conversation_id, user.id)?;
+    let first_page = PageQuery::default();
+
+    let content = form.content.trim_end().to_owned();
+    match user.check_may_post(&connection) {
+        Err(UserError::Banned(ban)) => {
+            let previous = PreviousReplyForm { content };
+            let page = render_conversation(
+                &hb,
+                &connection,
+                &user,
+                conversation_id,
+                &first_page,
+                vec![hb.translate_ban(&ban)],
+                Some(previous),
+            )?;
+            return Ok(HttpResponse::Forbidden().body(page));
+        }
+        result => result?,
+    }
+    let validation_errors = validate_content(&content);
+    if !validation_errors.is_empty() {
+        let validation_error_strings: Vec<String> = validation_errors.iter().map(|e| validation_error_to_string(&hb, *e)).collect();
+        let previous = PreviousReplyForm { content };
+        let page = render_conversation(&hb, &connection, &user, conversation_id, &first_page, validation_error_strings, Some(previous))?;
+        return Ok(HttpResponse::BadRequest().body(page));
+    }
+
+    match Message::send(&connection, user.id, conversation_id, &content) {
+        Err(MessageError::BlockedByParticipant(name)) => {
+            let previous = PreviousReplyForm { content };
+            let page = render_conversation(
+                &hb,
+                &connection,
+                &user,
+                conversation_id,
+                &first_page,
+                vec![hb.translate("messages-blocked-by", &[("user", name.as_str().into())])],
+                Some(previous),
+            )?;
+            Ok(HttpResponse::Forbidden().body(page))
+        }
+        Err(error) => Err(RyobError::from(error)),
+        Ok(_) => {
+            let conversation = Conversation::by_id(&connection, conversation_id)?;
+            notify_participants(&connection, &hb, &conversation, &user, &content)?;
+            let location = format!("/messages/{}", conversation_id.value());
+            Ok(HttpResponse::Found().header(actix_web::http::header::LOCATION, location).finish())
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use crate::database::types::*;
+use crate::models::conversation::Conversation;
+use crate::models::message::Message;
+use crate::models::user::User;
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use crate::utils::pagination::{PageQuery, Pagination};
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+
+const CONVERSATIONS_PER_PAGE: i64 = 25;
+
+#[derive(Serialize)]
+struct InboxEntry {
+    conversation: Conversation,
+    unread_count: i64,
+    participant_names: Vec<String>,
+}
+
+pub fn get(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, query: web::Query<PageQuery>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    let mut conversations = Conversation::for_user_by_date(&connection, user.id, query.offset(CONVERSATIONS_PER_PAGE), CONVERSATIONS_PER_PAGE + 1)?;
+    let pagination = Pagination::from_results(&query, CONVERSATIONS_PER_PAGE, &mut conversations);
+
+    let mut entries = vec![];
+    for (conversation, _) in conversations {
+        let unread_count = Message::unread_count(&connection, user.id, Some(conversation.id))?;
+        let participant_names = Conversation::participants(&connection, conversation.id)?
+            .into_iter()
+            .filter(|(participant, _)| participant.user_id != user.id)
+            .map(|(_, participant_user)| participant_user.user_name)
+            .collect();
+        entries.push(InboxEntry {
+            conversation,
+            unread_count,
+            participant_names,
+        });
+    }
+
+    let unread_message_count = Message::unread_count(&connection, user.id, None)?;
+    let data = json!({ "user": user, "unread_message_count": unread_message_count, "conversations": entries, "pagination": pagination });
+    let page = hb.render("pages/messages/inbox", &data)?;
+    Ok(HttpResponse::Ok().body(page))
+}
@@ -0,0 +1,14 @@
+use crate::database::types::*;
+use crate::models::conversation::Conversation;
+use crate::models::user::User;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+
+pub fn post(pool: web::Data<DatabasePool>, session: Session, path: web::Path<Id<Conversation>>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    Conversation::leave(&connection, path.into_inner(), user.id)?;
+    Ok(HttpResponse::Found().header(actix_web::http::header::LOCATION, "/messages").finish())
+}
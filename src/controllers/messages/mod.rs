@@ -0,0 +1,6 @@
+pub mod blocks;
+pub mod compose;
+pub mod conversation;
+pub mod inbox;
+pub mod leave;
+pub mod outbox;
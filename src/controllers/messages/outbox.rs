@@ -0,0 +1,40 @@
+use crate::database::types::*;
+use crate::models::conversation::Conversation;
+use crate::models::message::Message;
+use crate::models::user::User;
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use crate::utils::pagination::{PageQuery, Pagination};
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+
+const MESSAGES_PER_PAGE: i64 = 25;
+
+#[derive(Serialize)]
+struct OutboxEntry {
+    message: Message,
+    content_html: String,
+    conversation: Conversation,
+}
+
+pub fn get(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, query: web::Query<PageQuery>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    let mut messages = Message::sent_by_date(&connection, user.id, query.offset(MESSAGES_PER_PAGE), MESSAGES_PER_PAGE + 1)?;
+    let pagination = Pagination::from_results(&query, MESSAGES_PER_PAGE, &mut messages);
+
+    let entries: Vec<OutboxEntry> = messages
+        .into_iter()
+        .map(|(message, conversation)| OutboxEntry {
+            content_html: message.rendered_content(),
+            message,
+            conversation,
+        })
+        .collect();
+
+    let unread_message_count = Message::unread_count(&connection, user.id, None)?;
+    let data = json!({ "user": user, "unread_message_count": unread_message_count, "messages": entries, "pagination": pagination });
+    let page = hb.render("pages/messages/outbox", &data)?;
+    Ok(HttpResponse::Ok().body(page))
+}
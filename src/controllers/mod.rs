@@ -1 +1,12 @@
-pub mod index;pub mod users;
+pub mod admin;
+pub mod api;
+pub mod attachments;
+pub mod banners;
+pub mod feeds;
+pub mod index;
+pub mod live;
+pub mod messages;
+pub mod moderation;
+pub mod reports;
+pub mod topics;
+pub mod users;
@@ -0,0 +1,169 @@
+use super::reports::required_moderator;
+use crate::database::types::*;
+use crate::models::ip_address::IpRecord;
+use crate::models::message::Message;
+use crate::models::user::{Role, User, UserError};
+use crate::themes::Renderer;
+use crate::utils::client_ip;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use diesel::result::Error as DieselError;
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+
+// Enough to cover a busy account for a good while without the page getting out of hand
+const MAX_RECORDS: i64 = 1000;
+
+#[derive(Deserialize)]
+pub struct IpQuery {
+    #[serde(default)]
+    user_name: String,
+    #[serde(default)]
+    ip: String,
+}
+
+#[derive(Serialize)]
+struct AddressUsage {
+    ip: String,
+    subnet: String,
+    anonymized: bool,
+    count: usize,
+    first_seen: chrono::DateTime<chrono::Utc>,
+    last_seen: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Serialize)]
+struct AccountMatch {
+    id: Id<User>,
+    user_name: String,
+    addresses: Vec<String>,
+    // Seen on one of the exact addresses, rather than only somewhere in the same subnet
+    same_address: bool,
+    count: usize,
+    last_seen: chrono::DateTime<chrono::Utc>,
+}
+
+// Records come newest first, so the first one seen for each address is the latest
+fn address_usage(records: Vec<IpRecord>) -> Vec<AddressUsage> {
+    let mut usage: Vec<AddressUsage> = vec![];
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for record in records {
+        if let Some(position) = positions.get(&record.ip) {
+            usage[*position].count += 1;
+            usage[*position].first_seen = record.created_at;
+            continue;
+        }
+        let subnet = match record.ip.parse::<IpAddr>() {
+            Ok(ip) => client_ip::subnet(ip).to_string(),
+            Err(_) => continue,
+        };
+        positions.insert(record.ip.clone(), usage.len());
+        usage.push(AddressUsage {
+            ip: record.ip,
+            subnet,
+            anonymized: record.anonymized,
+            count: 1,
+            first_seen: record.created_at,
+            last_seen: record.created_at,
+        });
+    }
+    usage
+}
+
+fn accounts_in(connection: &DatabaseConnection, networks: &[IpNet], exact: &[String], exclude: Option<Id<User>>) -> Result<Vec<AccountMatch>, RyobError> {
+    let mut accounts: Vec<AccountMatch> = vec![];
+    let mut positions: HashMap<Id<User>, usize> = HashMap::new();
+    let mut seen_records = HashSet::new();
+    for network in networks {
+        for (record, user) in IpRecord::in_network(connection, *network, MAX_RECORDS)? {
+            if Some(user.id) == exclude || !seen_records.insert(record.id) {
+                continue;
+            }
+            let same_address = exact.contains(&record.ip);
+            let position = *positions.entry(user.id).or_insert_with(|| {
+                accounts.push(AccountMatch {
+                    id: user.id,
+                    user_name: user.user_name,
+                    addresses: vec![],
+                    same_address: false,
+                    count: 0,
+                    last_seen: record.created_at,
+                });
+                accounts.len() - 1
+            });
+            let account = &mut accounts[position];
+            account.count += 1;
+            account.same_address |= same_address;
+            account.last_seen = account.last_seen.max(record.created_at);
+            if !account.addresses.contains(&record.ip) {
+                account.addresses.push(record.ip);
+            }
+        }
+    }
+    accounts.sort_by(|a, b| b.same_address.cmp(&a.same_address).then(b.last_seen.cmp(&a.last_seen)));
+    Ok(accounts)
+}
+
+// Look up by user to see where they have been and who else was there, or by address or CIDR
+// range to see everyone who was
+pub fn get(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, query: web::Query<IpQuery>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_moderator(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    let user_name = query.user_name.trim().to_owned();
+    let ip = query.ip.trim().to_owned();
+
+    let mut errors = vec![];
+    let mut target = None;
+    let mut addresses = vec![];
+    let mut accounts = vec![];
+    let mut network = None;
+    if !user_name.is_empty() {
+        match User::by_user_name(&connection, &user_name) {
+            Ok(found) => {
+                addresses = address_usage(IpRecord::for_user(&connection, found.id, MAX_RECORDS)?);
+                let exact: Vec<String> = addresses.iter().map(|usage| usage.ip.clone()).collect();
+                let mut subnets: Vec<IpNet> = addresses.iter().filter_map(|usage| usage.subnet.parse().ok()).collect();
+                subnets.sort();
+                subnets.dedup();
+                accounts = accounts_in(&connection, &subnets, &exact, Some(found.id))?;
+                target = Some(json!({ "id": found.id, "user_name": found.user_name }));
+            }
+            Err(UserError::UnknownDatabaseError(DieselError::NotFound)) => {
+                errors.push(hb.translate("ips-user-unknown", &[("user", user_name.as_str().into())]))
+            }
+            Err(error) => return Err(RyobError::from(error)),
+        }
+    } else if !ip.is_empty() {
+        match client_ip::parse_network(&ip) {
+            Some(parsed) => {
+                accounts = accounts_in(&connection, &[parsed], &[parsed.addr().to_string()], None)?;
+                network = Some(parsed.to_string());
+            }
+            None => errors.push(hb.translate("ips-address-invalid", &[("ip", ip.as_str().into())])),
+        }
+    }
+
+    let unread_message_count = Message::unread_count(&connection, user.id, None)?;
+    let data = json!({
+        "user": user,
+        "unread_message_count": unread_message_count,
+        "is_admin": user.has_role(&connection, Role::Admin)?,
+        "query": { "user_name": user_name, "ip": ip },
+        "show_accounts": target.is_some() || network.is_some(),
+        "target": target,
+        "network": network,
+        "addresses": addresses,
+        "accounts": accounts,
+        "account_count": accounts.len(),
+        "errors": errors,
+    });
+    let page = hb.render("pages/moderation/ips", &data)?;
+    Ok(HttpResponse::Ok().body(page))
+}
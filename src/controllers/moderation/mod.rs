@@ -0,0 +1,3 @@
+pub mod ips;
+pub mod pending;
+pub mod reports;
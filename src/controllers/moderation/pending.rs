@@ -0,0 +1,111 @@
+use super::reports::required_moderator;
+use crate::database::types::*;
+use crate::models::audit_log::{AuditAction, AuditEntry};
+use crate::models::message::Message;
+use crate::models::post::Post;
+use crate::models::topic::Topic;
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+
+const PENDING_LIMIT: i64 = 100;
+
+#[derive(Serialize)]
+struct PendingEntry {
+    id: Id<Post>,
+    topic_id: Id<Topic>,
+    topic_title: String,
+    // The post is the first in a topic that is held along with it
+    new_topic: bool,
+    author_name: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+    content_html: String,
+    hold_reason: String,
+}
+
+fn redirect_to_pending() -> HttpResponse {
+    HttpResponse::Found().header(actix_web::http::header::LOCATION, "/moderation/pending").finish()
+}
+
+pub fn list(hb: Renderer, pool: web::Data<DatabasePool>, session: Session) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_moderator(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    let entries: Vec<PendingEntry> = Post::pending(&connection, PENDING_LIMIT)?
+        .into_iter()
+        .map(|(post, author, topic)| PendingEntry {
+            content_html: post.rendered_content(),
+            id: post.id,
+            topic_id: topic.id,
+            topic_title: topic.title,
+            new_topic: topic.pending,
+            author_name: author.user_name,
+            created_at: post.created_at,
+            hold_reason: post.hold_reason,
+        })
+        .collect();
+    let unread_message_count = Message::unread_count(&connection, user.id, None)?;
+    let data = json!({
+        "user": user,
+        "unread_message_count": unread_message_count,
+        "pending": entries,
+    });
+    let page = hb.render("pages/moderation/pending", &data)?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+pub fn approve(pool: web::Data<DatabasePool>, session: Session, path: web::Path<Id<Post>>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_moderator(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    {
+        use diesel::Connection;
+        connection.transaction::<_, RyobError, _>(|| {
+            let approved = Post::approve(&connection, path.into_inner())?;
+            AuditEntry::record(
+                &connection,
+                user.id,
+                AuditAction::PostApprove,
+                approved.id.value(),
+                None,
+                Some(json!(approved)),
+                "",
+            )?;
+            Ok(())
+        })?;
+    }
+    Ok(redirect_to_pending())
+}
+
+// Rejecting the first post of a held topic throws the topic away too
+pub fn reject(pool: web::Data<DatabasePool>, session: Session, path: web::Path<Id<Post>>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_moderator(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    {
+        use diesel::Connection;
+        connection.transaction::<_, RyobError, _>(|| {
+            let rejected = Post::reject(&connection, path.into_inner())?;
+            AuditEntry::record(
+                &connection,
+                user.id,
+                AuditAction::PostReject,
+                rejected.id.value(),
+                Some(json!(rejected)),
+                None,
+                "",
+            )?;
+            Ok(())
+        })?;
+    }
+    Ok(redirect_to_pending())
+}
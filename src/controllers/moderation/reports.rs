@@ -0,0 +1,198 @@
+use crate::database::types::*;
+use crate::models::audit_log::{AuditAction, AuditEntry};
+use crate::models::message::Message;
+use crate::models::post::Post;
+use crate::models::report::{Report, ReportStatus};
+use crate::models::topic::Topic;
+use crate::models::user::{Role, User, UserError};
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+use std::collections::HashMap;
+
+const RECENTLY_HANDLED_COUNT: i64 = 20;
+// Reports, not entries, as several reports about the same thing are shown together
+const OPEN_LIMIT: i64 = 200;
+
+#[derive(Serialize)]
+struct ReportReason {
+    reported_by: String,
+    reason: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+// Every open report about the same post or topic is shown, and handled, as one entry
+#[derive(Serialize)]
+struct ReportEntry {
+    // Any of the reports, to handle them all by
+    id: Id<Report>,
+    topic_id: Id<Topic>,
+    topic_title: String,
+    post_id: Option<Id<Post>>,
+    content_html: Option<String>,
+    author_name: String,
+    report_count: usize,
+    reasons: Vec<ReportReason>,
+}
+
+#[derive(Serialize)]
+struct HandledEntry {
+    topic_id: Id<Topic>,
+    post_id: Option<Id<Post>>,
+    reported_by: String,
+    reason: String,
+    status: String,
+    handled_by: String,
+    handled_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+// Moderators whose role requires 2FA are sent to set it up rather than shown a bare 403
+pub fn required_moderator(connection: &DatabaseConnection, session: &Session) -> Result<Result<User, HttpResponse>, RyobError> {
+    match User::required_with_role(connection, session, Role::Moderator) {
+        Err(UserError::TwoFactorRequired) => Ok(Err(HttpResponse::Found()
+            .header(actix_web::http::header::LOCATION, "/users/two_factor")
+            .finish())),
+        result => Ok(Ok(result?)),
+    }
+}
+
+fn open_entries(connection: &DatabaseConnection) -> Result<Vec<ReportEntry>, RyobError> {
+    let open = Report::open(connection, OPEN_LIMIT)?;
+    let topic_ids: Vec<Id<Topic>> = open.iter().map(|(report, _)| report.topic_id).collect();
+    let post_ids: Vec<Id<Post>> = open.iter().filter_map(|(report, _)| report.post_id).collect();
+    let topics: HashMap<Id<Topic>, (Topic, User)> = Topic::by_ids(connection, &topic_ids)?
+        .into_iter()
+        .map(|(topic, author)| (topic.id, (topic, author)))
+        .collect();
+    let mut posts: HashMap<Id<Post>, (Post, User)> = Post::by_ids(connection, &post_ids)?
+        .into_iter()
+        .map(|(post, author)| (post.id, (post, author)))
+        .collect();
+
+    let mut entries: Vec<ReportEntry> = vec![];
+    let mut positions: HashMap<(Id<Topic>, Option<Id<Post>>), usize> = HashMap::new();
+    for (report, reporter) in open {
+        let reason = ReportReason {
+            reported_by: reporter.user_name,
+            reason: report.reason,
+            created_at: report.created_at,
+        };
+        if let Some(position) = positions.get(&(report.topic_id, report.post_id)) {
+            entries[*position].report_count += 1;
+            entries[*position].reasons.push(reason);
+            continue;
+        }
+
+        // Deleted since the reports were loaded
+        let (topic_title, topic_author) = match topics.get(&report.topic_id) {
+            Some((topic, author)) => (topic.title.clone(), author.user_name.clone()),
+            None => continue,
+        };
+        let (content_html, author_name) = match report.post_id {
+            Some(post) => match posts.remove(&post) {
+                Some((post, post_author)) => (Some(post.rendered_content()), post_author.user_name),
+                None => continue,
+            },
+            None => (None, topic_author),
+        };
+        positions.insert((report.topic_id, report.post_id), entries.len());
+        entries.push(ReportEntry {
+            id: report.id,
+            topic_id: report.topic_id,
+            topic_title,
+            post_id: report.post_id,
+            content_html,
+            author_name,
+            report_count: 1,
+            reasons: vec![reason],
+        });
+    }
+    Ok(entries)
+}
+
+fn handled_entries(connection: &DatabaseConnection) -> Result<Vec<HandledEntry>, RyobError> {
+    let mut handler_names: HashMap<Id<User>, String> = HashMap::new();
+    Report::recently_handled(connection, RECENTLY_HANDLED_COUNT)?
+        .into_iter()
+        .map(|(report, reporter)| {
+            let handled_by = match report.handled_by {
+                Some(handler) => match handler_names.get(&handler) {
+                    Some(name) => name.clone(),
+                    None => {
+                        let name = User::by_user_id(connection, handler)?.user_name;
+                        handler_names.insert(handler, name.clone());
+                        name
+                    }
+                },
+                None => String::new(),
+            };
+            Ok(HandledEntry {
+                topic_id: report.topic_id,
+                post_id: report.post_id,
+                reported_by: reporter.user_name,
+                reason: report.reason,
+                status: report.status,
+                handled_by,
+                handled_at: report.handled_at,
+            })
+        })
+        .collect()
+}
+
+fn redirect_to_reports() -> HttpResponse {
+    HttpResponse::Found().header(actix_web::http::header::LOCATION, "/moderation/reports").finish()
+}
+
+pub fn list(hb: Renderer, pool: web::Data<DatabasePool>, session: Session) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_moderator(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    let unread_message_count = Message::unread_count(&connection, user.id, None)?;
+    let data = json!({
+        "user": user,
+        "unread_message_count": unread_message_count,
+        "open": open_entries(&connection)?,
+        "handled": handled_entries(&connection)?,
+    });
+    let page = hb.render("pages/moderation/reports", &data)?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+fn handle(pool: web::Data<DatabasePool>, session: Session, path: web::Path<Id<Report>>, status: ReportStatus) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match required_moderator(&connection, &session)? {
+        Ok(user) => user,
+        Err(redirect) => return Ok(redirect),
+    };
+    let report = Report::by_id(&connection, path.into_inner())?;
+    {
+        use diesel::Connection;
+        connection.transaction::<_, RyobError, _>(|| {
+            let handled = Report::handle(&connection, report.id, user.id, status)?;
+            let action = if status == ReportStatus::Resolved {
+                AuditAction::ReportResolve
+            } else {
+                AuditAction::ReportDismiss
+            };
+            let after = json!({ "status": status.as_str(), "reports_handled": handled });
+            AuditEntry::record(&connection, user.id, action, report.id.value(), Some(json!(report)), Some(after), "")?;
+            Ok(())
+        })?;
+    }
+    Ok(redirect_to_reports())
+}
+
+// For reports that were acted on, e.g. by deleting the post
+pub fn resolve(pool: web::Data<DatabasePool>, session: Session, path: web::Path<Id<Report>>) -> Result<HttpResponse, RyobError> {
+    handle(pool, session, path, ReportStatus::Resolved)
+}
+
+// For reports that needed no action
+pub fn dismiss(pool: web::Data<DatabasePool>, session: Session, path: web::Path<Id<Report>>) -> Result<HttpResponse, RyobError> {
+    handle(pool, session, path, ReportStatus::Dismissed)
+}
@@ -0,0 +1,147 @@
+use crate::database::types::*;
+use crate::models::message::Message;
+use crate::models::post::Post;
+use crate::models::report::{Report, ReportError, MAX_REASON_SIZE};
+use crate::models::topic::Topic;
+use crate::models::user::{User, UserError};
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct ReportForm {
+    reason: String,
+}
+
+// What is being reported, shown above the form so members can check they picked the right thing
+struct ReportTarget {
+    topic: Topic,
+    post: Option<Post>,
+    author_name: String,
+}
+
+impl ReportTarget {
+    fn action(&self) -> String {
+        match &self.post {
+            Some(post) => format!("/posts/{}/report", post.id.value()),
+            None => format!("/topics/{}/report", self.topic.id.value()),
+        }
+    }
+}
+
+fn post_target(connection: &DatabaseConnection, post: Id<Post>) -> Result<ReportTarget, RyobError> {
+    let (post, author) = Post::visible_by_id(connection, post, None)?;
+    let (topic, _) = Topic::visible_by_id(connection, post.posted_in, None)?;
+    Ok(ReportTarget {
+        topic,
+        post: Some(post),
+        author_name: author.user_name,
+    })
+}
+
+fn topic_target(connection: &DatabaseConnection, topic: Id<Topic>) -> Result<ReportTarget, RyobError> {
+    let (topic, author) = Topic::visible_by_id(connection, topic, None)?;
+    Ok(ReportTarget {
+        topic,
+        post: None,
+        author_name: author.user_name,
+    })
+}
+
+fn render_report(
+    hb: &Renderer,
+    connection: &DatabaseConnection,
+    user: &User,
+    target: &ReportTarget,
+    reported: bool,
+    errors: Vec<String>,
+) -> Result<String, RyobError> {
+    let unread_message_count = Message::unread_count(connection, user.id, None)?;
+    let data = json!({
+        "user": user,
+        "unread_message_count": unread_message_count,
+        "action": target.action(),
+        "topic": target.topic,
+        "post": target.post.as_ref().map(|post| json!({ "id": post.id, "content_html": post.rendered_content() })),
+        "author_name": target.author_name,
+        "max_reason_size": MAX_REASON_SIZE,
+        "reported": reported,
+        "errors": errors,
+    });
+    Ok(hb.render("pages/report", &data)?)
+}
+
+fn show_form(hb: &Renderer, connection: &DatabaseConnection, session: &Session, target: ReportTarget) -> Result<HttpResponse, RyobError> {
+    let user = User::required_from_session(connection, session)?;
+    let page = render_report(hb, connection, &user, &target, false, vec![])?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+fn report_error_to_string(hb: &Renderer, error: &ReportError) -> String {
+    match error {
+        ReportError::ReasonEmpty => hb.translate("report-reason-empty", &[]),
+        ReportError::ReasonTooLong => hb.translate("report-reason-length", &[("max", MAX_REASON_SIZE.into())]),
+        ReportError::AlreadyReported => hb.translate("report-already-reported", &[]),
+        ReportError::AlreadyHandled => hb.translate("report-already-handled", &[]),
+        ReportError::NoSuchReport => hb.translate("report-unknown", &[]),
+        ReportError::UnknownDatabaseError(_) | ReportError::Unknown(_) => error.to_string(),
+    }
+}
+
+fn file_report(hb: &Renderer, connection: &DatabaseConnection, session: &Session, target: ReportTarget, reason: &str) -> Result<HttpResponse, RyobError> {
+    let user = User::required_from_session(connection, session)?;
+    match user.check_may_post(connection) {
+        Err(UserError::Banned(ban)) => {
+            let page = render_report(hb, connection, &user, &target, false, vec![hb.translate_ban(&ban)])?;
+            return Ok(HttpResponse::Forbidden().body(page));
+        }
+        result => result?,
+    }
+    match Report::file(connection, user.id, target.topic.id, target.post.as_ref().map(|post| post.id), reason) {
+        Ok(_) => Ok(HttpResponse::Ok().body(render_report(hb, connection, &user, &target, true, vec![])?)),
+        Err(error @ ReportError::UnknownDatabaseError(_)) | Err(error @ ReportError::Unknown(_)) => Err(RyobError::from(error)),
+        Err(error) => {
+            let page = render_report(hb, connection, &user, &target, false, vec![report_error_to_string(hb, &error)])?;
+            Ok(HttpResponse::BadRequest().body(page))
+        }
+    }
+}
+
+pub fn new_post(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, path: web::Path<Id<Post>>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let target = post_target(&connection, path.into_inner())?;
+    show_form(&hb, &connection, &session, target)
+}
+
+pub fn create_post(
+    hb: Renderer,
+    pool: web::Data<DatabasePool>,
+    session: Session,
+    path: web::Path<Id<Post>>,
+    form: web::Form<ReportForm>,
+) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let target = post_target(&connection, path.into_inner())?;
+    file_report(&hb, &connection, &session, target, &form.reason)
+}
+
+pub fn new_topic(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, path: web::Path<Id<Topic>>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let target = topic_target(&connection, path.into_inner())?;
+    show_form(&hb, &connection, &session, target)
+}
+
+pub fn create_topic(
+    hb: Renderer,
+    pool: web::Data<DatabasePool>,
+    session: Session,
+    path: web::Path<Id<Topic>>,
+    form: web::Form<ReportForm>,
+) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let target = topic_target(&connection, path.into_inner())?;
+    file_report(&hb, &connection, &session, target, &form.reason)
+}
@@ -0,0 +1,170 @@
+use crate::database::types::*;
+use crate::models::attachment::{Attachment, StoredFile, Thumbnail};
+use crate::models::message::Message;
+use crate::models::post::Post;
+use crate::models::topic::Topic;
+use crate::models::user::User;
+use crate::themes::{RenderKey, Renderer, Themes};
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use crate::utils::pagination::{PageQuery, Pagination};
+use crate::utils::sniff;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+use std::collections::HashMap;
+
+const THUMBNAIL_SIZE: &str = "medium";
+
+#[derive(Serialize)]
+struct ThumbnailEntry {
+    url: String,
+    width: i32,
+    height: i32,
+}
+
+#[derive(Serialize)]
+struct AttachmentEntry {
+    url: String,
+    file_name: String,
+    size: i64,
+    is_image: bool,
+    thumbnail: Option<ThumbnailEntry>,
+}
+
+#[derive(Serialize)]
+struct PostEntry {
+    id: Id<Post>,
+    created_at: chrono::DateTime<chrono::Utc>,
+    content_html: String,
+    author_id: Id<User>,
+    author_name: String,
+    author_avatar_url: String,
+    signature_html: String,
+    attachments: Vec<AttachmentEntry>,
+    // Only ever shown to the author, until a moderator approves it
+    pending: bool,
+}
+
+impl PostEntry {
+    fn new(post: Post, author: User, attachments: Vec<AttachmentEntry>) -> PostEntry {
+        PostEntry {
+            content_html: post.rendered_content(),
+            id: post.id,
+            created_at: post.created_at,
+            pending: post.pending,
+            author_avatar_url: author.avatar_url(),
+            signature_html: author.rendered_signature(),
+            author_id: author.id,
+            author_name: author.user_name,
+            attachments,
+        }
+    }
+}
+
+fn attachments_by_post(connection: &DatabaseConnection, post_ids: &[Id<Post>]) -> Result<HashMap<Id<Post>, Vec<AttachmentEntry>>, RyobError> {
+    let attachments = Attachment::for_posts(connection, post_ids)?;
+    let attachment_ids: Vec<Id<Attachment>> = attachments.iter().map(|(attachment, _)| attachment.id).collect();
+    let mut thumbnails: HashMap<Id<Attachment>, Thumbnail> = Thumbnail::for_attachments(connection, &attachment_ids, THUMBNAIL_SIZE)?
+        .into_iter()
+        .map(|thumbnail| (thumbnail.attachment_id, thumbnail))
+        .collect();
+
+    let mut by_post: HashMap<Id<Post>, Vec<AttachmentEntry>> = HashMap::new();
+    for (attachment, file) in attachments {
+        let StoredFile { content_type, size, .. } = file;
+        let thumbnail = thumbnails.remove(&attachment.id).map(|thumbnail| ThumbnailEntry {
+            url: thumbnail.url(),
+            width: thumbnail.width,
+            height: thumbnail.height,
+        });
+        by_post.entry(attachment.post_id).or_default().push(AttachmentEntry {
+            url: attachment.url(),
+            file_name: attachment.file_name,
+            size,
+            is_image: sniff::is_image(&content_type),
+            thumbnail,
+        });
+    }
+    Ok(by_post)
+}
+
+// Also used to push new posts to open topic pages, once for each key among the viewers
+pub fn render_post(hb: &Themes, keys: &[RenderKey], connection: &DatabaseConnection, post: Post, author: User) -> Result<Vec<String>, RyobError> {
+    let attachments = attachments_by_post(connection, &[post.id])?.remove(&post.id).unwrap_or_default();
+    let entry = PostEntry::new(post, author, attachments);
+    Ok(keys
+        .iter()
+        .map(|key| hb.render_for(key, "partials/topics/post", &entry))
+        .collect::<Result<_, _>>()?)
+}
+
+pub fn get(
+    hb: Renderer,
+    pool: web::Data<DatabasePool>,
+    session: Session,
+    path: web::Path<Id<Topic>>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::from_session(&connection, &session)?;
+    let unread_message_count = match &user {
+        Some(user) => Message::unread_count(&connection, user.id, None)?,
+        None => 0,
+    };
+
+    let viewer = user.as_ref().map(|user| user.id);
+    let (topic, author) = Topic::visible_by_id(&connection, path.into_inner(), viewer)?;
+    let per_page = hb.posts_per_page();
+    let mut posts = Post::in_topic_by_date(&connection, topic.id, viewer, query.offset(per_page), per_page + 1)?;
+    let pagination = Pagination::from_results(&query, per_page, &mut posts);
+    // Pages run newest first, but each page reads top to bottom
+    let mut attachments = attachments_by_post(&connection, &posts.iter().map(|(post, _)| post.id).collect::<Vec<_>>())?;
+    let posts: Vec<PostEntry> = posts
+        .into_iter()
+        .rev()
+        .map(|(post, post_author)| {
+            let post_attachments = attachments.remove(&post.id).unwrap_or_default();
+            PostEntry::new(post, post_author, post_attachments)
+        })
+        .collect();
+    // Only the first page holds the newest posts, so that is the one new replies are added to
+    let live = if pagination.page == 1 {
+        let last_seen = posts.last().map(|post| post.id.value()).unwrap_or(0);
+        Some(format!("/topics/{}/live?after={}", topic.id.value(), last_seen))
+    } else {
+        None
+    };
+
+    let data = json!({
+        "user": user,
+        "unread_message_count": unread_message_count,
+        "topic": topic,
+        "author_name": author.user_name,
+        "posts": posts,
+        "pagination": pagination,
+        "live": live,
+        "feed": {
+            "atom_url": format!("/topics/{}/feed.atom", topic.id.value()),
+            "rss_url": format!("/topics/{}/feed.rss", topic.id.value()),
+            "title": hb.translate("topic-feed-title", &[("title", topic.title.as_str().into())]),
+        },
+    });
+    let page = hb.render("pages/topics/topic", &data)?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+// Permalinks to posts, sent on to whichever page of the topic the post is on now, with the
+// reader's own page size
+pub fn post(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, path: web::Path<Id<Post>>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let viewer = User::from_session(&connection, &session)?.map(|user| user.id);
+    let (post, _) = Post::visible_by_id(&connection, path.into_inner(), viewer)?;
+    let page = Post::newer_count(&connection, &post, viewer)? / hb.posts_per_page() + 1;
+    let location = if page > 1 {
+        format!("/topics/{}?page={}#post-{}", post.posted_in.value(), page, post.id.value())
+    } else {
+        format!("/topics/{}#post-{}", post.posted_in.value(), post.id.value())
+    };
+    Ok(HttpResponse::Found().header(actix_web::http::header::LOCATION, location).finish())
+}
@@ -0,0 +1,73 @@
+use crate::database::types::*;
+use crate::models::api_token::{ApiToken, Scope};
+use crate::models::message::Message;
+use crate::models::user::{Role, User};
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+
+const MAX_NAME_SIZE: usize = 100;
+
+// Unchecked checkboxes aren't submitted at all
+#[derive(Deserialize)]
+pub struct NewTokenForm {
+    name: String,
+    write: Option<String>,
+    moderate: Option<String>,
+}
+
+fn render_tokens(hb: &Renderer, connection: &DatabaseConnection, user: &User, created_token: Option<String>, errors: Vec<String>) -> Result<String, RyobError> {
+    let tokens = ApiToken::for_user(connection, user.id)?;
+    let unread_message_count = Message::unread_count(connection, user.id, None)?;
+    let data = json!({
+        "user": user,
+        "unread_message_count": unread_message_count,
+        "tokens": tokens,
+        "created_token": created_token,
+        "can_moderate": user.role() >= Role::Moderator,
+        "errors": errors,
+    });
+    Ok(hb.render("pages/api_tokens", &data)?)
+}
+
+pub fn get(hb: Renderer, pool: web::Data<DatabasePool>, session: Session) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    let page = render_tokens(&hb, &connection, &user, None, vec![])?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+pub fn post(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, form: web::Form<NewTokenForm>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    let name = form.name.trim();
+
+    if name.is_empty() || name.chars().count() > MAX_NAME_SIZE {
+        let errors = vec![hb.translate("api-tokens-name-length", &[("max", MAX_NAME_SIZE.into())])];
+        let page = render_tokens(&hb, &connection, &user, None, errors)?;
+        return Ok(HttpResponse::BadRequest().body(page));
+    }
+
+    let mut scopes = vec![];
+    if form.write.is_some() {
+        scopes.push(Scope::Write);
+    }
+    if form.moderate.is_some() {
+        scopes.push(Scope::Moderate);
+    }
+
+    // The token is only ever shown in this response
+    let (_, token) = ApiToken::create(&connection, user.id, name, &scopes)?;
+    let page = render_tokens(&hb, &connection, &user, Some(token), vec![])?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+pub fn revoke(pool: web::Data<DatabasePool>, session: Session, path: web::Path<Id<ApiToken>>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    ApiToken::revoke(&connection, user.id, path.into_inner())?;
+    Ok(HttpResponse::Found().header(actix_web::http::header::LOCATION, "/users/tokens").finish())
+}
@@ -0,0 +1,43 @@
+use crate::database::types::*;
+use crate::mailer;
+use crate::models::user::User;
+use crate::models::user_preferences::UserPreferences;
+use crate::models::user_token::{TokenPurpose, UserToken};
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct ForgotPasswordForm {
+    email: String,
+}
+
+pub fn post(hb: Renderer, pool: web::Data<DatabasePool>, form: web::Form<ForgotPasswordForm>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let email = form.email.trim().to_lowercase();
+
+    if let Some(user) = User::by_email(&connection, &email)? {
+        UserToken::revoke_all(&connection, user.id, TokenPurpose::ResetPassword)?;
+        let token = UserToken::issue(&connection, user.id, TokenPurpose::ResetPassword, Some(&email))?;
+        let data = json!({
+            "user_name": user.user_name,
+            "link": mailer::absolute_url(&format!("/users/password/reset?token={}", token)),
+            "expires_in_hours": TokenPurpose::ResetPassword.lifetime().num_hours(),
+            "preferences": UserPreferences::for_user(&connection, user.id)?,
+        });
+        mailer::enqueue(&connection, &hb, hb.locale(), "reset_password", &email, &data)?;
+    }
+
+    // The response is the same whether or not the address is known, so this form can't be used
+    // to find out who has an account
+    let data = json!({ "sent": true });
+    let page = hb.render("pages/forgot_password", &data)?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+pub fn get(hb: Renderer) -> Result<HttpResponse, RyobError> {
+    let data = json!({});
+    let page = hb.render("pages/forgot_password", &data)?;
+    Ok(HttpResponse::Ok().body(page))
+}
@@ -1,9 +1,12 @@
 use crate::database::types::*;
+use crate::models::ip_address::{IpEvent, IpRecord};
+use crate::models::two_factor::TwoFactorSecret;
 use crate::models::user::{User, UserError};
+use crate::themes::Renderer;
+use crate::utils::client_ip::ClientIp;
 use crate::utils::errors::RyobError;
 use actix_session::Session;
 use actix_web::{web, HttpResponse};
-use handlebars::Handlebars;
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize)]
@@ -30,26 +33,39 @@ fn login_form_to_previous(form: &LoginForm) -> PreviousLoginForm {
     }
 }
 
-pub fn post(hb: web::Data<Handlebars>, pool: web::Data<DatabasePool>, session: Session, form: web::Form<LoginForm>) -> Result<HttpResponse, RyobError> {
+pub fn post(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, ip: ClientIp, form: web::Form<LoginForm>) -> Result<HttpResponse, RyobError> {
     let connection = pool.get()?;
     let sanitized_form = sanitize_login_form(&form);
     let user_result = User::login(&connection, &form.user_name, &form.password);
     match user_result {
         Err(UserError::BadLogin) => {
             let previous = login_form_to_previous(&sanitized_form);
-            let data = json!({ "errors": ["Incorrect username or password"], "previous": previous });
+            let data = json!({ "errors": [hb.translate("login-incorrect", &[])], "previous": previous });
             let page = hb.render("pages/login", &data)?;
             Ok(HttpResponse::BadRequest().body(page))
         }
+        Err(UserError::Banned(ban)) => {
+            let previous = login_form_to_previous(&sanitized_form);
+            let data = json!({ "errors": [hb.translate_ban(&ban)], "previous": previous });
+            let page = hb.render("pages/login", &data)?;
+            Ok(HttpResponse::Forbidden().body(page))
+        }
         Err(error) => Err(RyobError::from(error)),
         Ok(user) => {
+            if TwoFactorSecret::is_enabled(&connection, user.id)? {
+                super::two_factor_login::begin(&session, &user)?;
+                return Ok(HttpResponse::Found()
+                    .header(actix_web::http::header::LOCATION, "/users/login/two_factor")
+                    .finish());
+            }
+            IpRecord::record(&connection, user.id, None, IpEvent::Login, ip.0)?;
             user.to_session(&session)?;
             Ok(HttpResponse::Found().header(actix_web::http::header::LOCATION, "/").finish())
         }
     }
 }
 
-pub fn get(hb: web::Data<Handlebars>) -> Result<HttpResponse, RyobError> {
+pub fn get(hb: Renderer) -> Result<HttpResponse, RyobError> {
     let data = json!({});
     let page = hb.render("pages/login", &data)?;
     Ok(HttpResponse::Ok().body(page))
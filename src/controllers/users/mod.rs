@@ -1 +1,10 @@
-pub mod login;pub mod register;
+pub mod api_tokens;
+pub mod forgot_password;
+pub mod login;
+pub mod profile;
+pub mod register;
+pub mod reset_password;
+pub mod settings;
+pub mod two_factor;
+pub mod two_factor_login;
+pub mod verify_email;
@@ -0,0 +1,149 @@
+use crate::database::types::*;
+use crate::models::attachment::StoredFile;
+use crate::models::message::Message;
+use crate::models::user::{User, UserError, MAX_SIGNATURE_LINES, MAX_SIGNATURE_SIZE};
+use crate::storage::Storage;
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use crate::utils::{identicon, uploads};
+use actix_multipart::Multipart;
+use actix_session::Session;
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse};
+use diesel::result::Error as DieselError;
+use futures::future::{self, Either};
+use futures::Future;
+use serde::Deserialize;
+
+// Uploads are cropped and shrunk, so this only has to allow for a photo straight off a phone
+const MAX_AVATAR_UPLOAD_SIZE: usize = 8 * 1024 * 1024;
+
+#[derive(Deserialize)]
+pub struct SignatureForm {
+    signature: String,
+}
+
+fn render_profile(hb: &Renderer, connection: &DatabaseConnection, user: &User, signature: &str, errors: Vec<String>) -> Result<String, RyobError> {
+    let unread_message_count = Message::unread_count(connection, user.id, None)?;
+    let data = json!({
+        "user": user,
+        "unread_message_count": unread_message_count,
+        "avatar_url": user.avatar_url(),
+        "has_avatar": user.avatar_file_id.is_some(),
+        "signature": signature,
+        "signature_html": user.rendered_signature(),
+        "max_signature_size": MAX_SIGNATURE_SIZE,
+        "errors": errors,
+    });
+    Ok(hb.render("pages/profile", &data)?)
+}
+
+fn redirect_to_profile() -> HttpResponse {
+    HttpResponse::Found().header(header::LOCATION, "/users/profile").finish()
+}
+
+pub fn get(hb: Renderer, pool: web::Data<DatabasePool>, session: Session) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    let page = render_profile(&hb, &connection, &user, &user.signature, vec![])?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+pub fn avatar(
+    hb: Renderer,
+    pool: web::Data<DatabasePool>,
+    storage: web::Data<Box<dyn Storage>>,
+    session: Session,
+    multipart: Multipart,
+) -> impl Future<Item = HttpResponse, Error = RyobError> {
+    let user = match pool.get().map_err(RyobError::from).and_then(|connection| {
+        let user = User::required_from_session(&connection, &session)?;
+        user.check_may_post(&connection)?;
+        Ok(user)
+    }) {
+        Ok(user) => user,
+        Err(err) => return Either::A(future::err(err)),
+    };
+
+    Either::B(uploads::read_uploads(multipart, MAX_AVATAR_UPLOAD_SIZE, 1).then(move |uploads| {
+        let connection = pool.get()?;
+        let stored = match uploads {
+            Ok(uploads) => {
+                // Only the first file is used if more were sent
+                let contents = uploads.first().map(|upload| upload.contents.as_slice()).unwrap_or(&[]);
+                StoredFile::store_avatar(&connection, storage.as_ref(), contents).map_err(|err| hb.translate_attachment_error(&err))
+            }
+            Err(err) => Err(hb.translate_upload_error(&err)),
+        };
+        match stored {
+            Ok(file) => {
+                User::set_avatar(&connection, user.id, Some(file.id))?;
+                Ok(redirect_to_profile())
+            }
+            Err(err) => {
+                let page = render_profile(&hb, &connection, &user, &user.signature, vec![err])?;
+                Ok(HttpResponse::BadRequest().body(page))
+            }
+        }
+    }))
+}
+
+pub fn remove_avatar(pool: web::Data<DatabasePool>, session: Session) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    User::set_avatar(&connection, user.id, None)?;
+    Ok(redirect_to_profile())
+}
+
+pub fn signature(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, form: web::Form<SignatureForm>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    let signature = form.signature.trim_end();
+
+    let mut errors = vec![];
+    match user.check_may_post(&connection) {
+        Err(UserError::Banned(ban)) => errors.push(hb.translate_ban(&ban)),
+        result => result?,
+    }
+    if signature.chars().count() > MAX_SIGNATURE_SIZE {
+        errors.push(hb.translate("profile-signature-length", &[("max", MAX_SIGNATURE_SIZE.into())]));
+    }
+    if signature.lines().count() > MAX_SIGNATURE_LINES {
+        errors.push(hb.translate("profile-signature-lines", &[("max", MAX_SIGNATURE_LINES.into())]));
+    }
+    if !errors.is_empty() {
+        let page = render_profile(&hb, &connection, &user, signature, errors)?;
+        return Ok(HttpResponse::BadRequest().body(page));
+    }
+
+    User::set_signature(&connection, user.id, signature)?;
+    Ok(redirect_to_profile())
+}
+
+// Everyone has an avatar, users who haven't uploaded one get an identicon made from their id
+pub fn get_avatar(
+    pool: web::Data<DatabasePool>,
+    storage: web::Data<Box<dyn Storage>>,
+    request: HttpRequest,
+    path: web::Path<Id<User>>,
+) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = match User::by_user_id(&connection, path.into_inner()) {
+        Err(UserError::UnknownDatabaseError(DieselError::NotFound)) => return Err(RyobError::from(UserError::NoSuchUser)),
+        result => result?,
+    };
+
+    match user.avatar_file_id {
+        Some(file) => {
+            let file = StoredFile::by_id(&connection, file)?;
+            crate::controllers::attachments::serve(storage.as_ref(), &request, &file, "avatar")
+        }
+        None => Ok(HttpResponse::Ok()
+            .content_type("image/svg+xml")
+            .header(header::CACHE_CONTROL, "public, max-age=86400")
+            .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+            .header(header::CONTENT_SECURITY_POLICY, "default-src 'none'; sandbox")
+            .body(identicon::svg(user.id.value()))),
+    }
+}
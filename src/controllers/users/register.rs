@@ -1,28 +1,40 @@
 use crate::database::types::*;
+use crate::models::ip_address::{IpEvent, IpRecord};
+use crate::models::spam_log::{SpamDecision, SpamKind};
 use crate::models::user::{User, UserError};
+use crate::spam::{self, FormFields, Pipeline, Submission};
+use crate::themes::Renderer;
+use crate::utils::client_ip::ClientIp;
 use crate::utils::errors::RyobError;
 use actix_session::Session;
 use actix_web::{web, HttpResponse};
-use handlebars::Handlebars;
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize)]
 pub struct RegisterForm {
-    user_name: String,
-    password: String,
-    confirm_password: String,
+    pub user_name: String,
+    pub email: String,
+    pub password: String,
+    pub confirm_password: String,
+    // Honeypot, hidden from people but filled in by bots that complete every field
+    #[serde(default)]
+    pub website: String,
+    #[serde(default)]
+    pub form_token: String,
 }
 
 #[derive(Serialize)]
 struct PreviousRegisterForm {
     user_name: String,
+    email: String,
 }
 
 #[derive(Clone, Copy)]
-enum RegisterFormValidationError {
+pub enum RegisterFormValidationError {
     UserNameTooShort,
     UserNameTooLong,
     UserNameInvalidCharacters,
+    EmailInvalid,
     PasswordTooShort,
     PasswordInsecure,
     PasswordNotConfirmed,
@@ -32,18 +44,23 @@ enum RegisterFormValidationError {
 const MIN_PASSWORD_SIZE: usize = 8;
 const MIN_USER_NAME_SIZE: usize = 2;
 const MAX_USER_NAME_SIZE: usize = 128;
+const MAX_EMAIL_SIZE: usize = 254;
 
-fn sanitize_register_form(form: &RegisterForm) -> RegisterForm {
+pub fn sanitize_register_form(form: &RegisterForm) -> RegisterForm {
     RegisterForm {
         user_name: form.user_name.trim().to_owned(),
+        email: form.email.trim().to_lowercase(),
         password: form.password.clone(),
         confirm_password: form.confirm_password.clone(),
+        website: form.website.clone(),
+        form_token: form.form_token.clone(),
     }
 }
 
 fn register_form_to_previous(form: &RegisterForm) -> PreviousRegisterForm {
     PreviousRegisterForm {
         user_name: form.user_name.to_owned(),
+        email: form.email.to_owned(),
     }
 }
 
@@ -65,11 +82,27 @@ fn validate_user_name(name: &String) -> Vec<RegisterFormValidationError> {
     errors
 }
 
+// Deliberately loose: the only real test of an address is whether mail sent to it arrives
+fn validate_email(email: &str) -> Vec<RegisterFormValidationError> {
+    let mut errors: Vec<RegisterFormValidationError> = vec![];
+    let well_formed = match email.rfind('@') {
+        Some(at) => {
+            let (local, domain) = (&email[..at], &email[at + 1..]);
+            !local.is_empty() && domain.contains('.') && !domain.starts_with('.') && !domain.ends_with('.')
+        }
+        None => false,
+    };
+    if !well_formed || email.len() > MAX_EMAIL_SIZE || email.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        errors.push(RegisterFormValidationError::EmailInvalid);
+    }
+    errors
+}
+
 fn is_valid_password_char(password_char: char) -> bool {
     password_char.is_alphanumeric() || password_char == ' '
 }
 
-fn validate_password(password: &String, confirm_password: &String) -> Vec<RegisterFormValidationError> {
+pub fn validate_password(password: &String, confirm_password: &String) -> Vec<RegisterFormValidationError> {
     let mut errors: Vec<RegisterFormValidationError> = vec![];
     if password != confirm_password {
         errors.push(RegisterFormValidationError::PasswordNotConfirmed);
@@ -83,48 +116,107 @@ fn validate_password(password: &String, confirm_password: &String) -> Vec<Regist
     errors
 }
 
-fn validate_register_form(form: &RegisterForm) -> Vec<RegisterFormValidationError> {
+pub fn validate_register_form(form: &RegisterForm) -> Vec<RegisterFormValidationError> {
     let mut user_name_errors = validate_user_name(&form.user_name);
+    let mut email_errors = validate_email(&form.email);
     let mut password_errors = validate_password(&form.password, &form.confirm_password);
     let mut errors = vec![];
     errors.append(&mut user_name_errors);
+    errors.append(&mut email_errors);
     errors.append(&mut password_errors);
     errors
 }
 
-fn validation_error_to_string(error: RegisterFormValidationError) -> String {
+pub fn validation_error_to_string(hb: &Renderer, error: RegisterFormValidationError) -> String {
+    let user_name_length = [("min", MIN_USER_NAME_SIZE.into()), ("max", MAX_USER_NAME_SIZE.into())];
     match error {
-        RegisterFormValidationError::UserNameTooShort => format!("Username must be between {} and {} characters long", MIN_USER_NAME_SIZE, MAX_USER_NAME_SIZE),
-        RegisterFormValidationError::UserNameTooLong => format!("Username must be between {} and {} characters long", MIN_USER_NAME_SIZE, MAX_USER_NAME_SIZE),
-        RegisterFormValidationError::UserNameInvalidCharacters => "Username must consist of alphanumeric characters and spaces".to_owned(),
-        RegisterFormValidationError::PasswordInsecure => "Password not secure".to_owned(),
-        RegisterFormValidationError::PasswordTooShort => format!("Password must be {} or more characters long", MIN_PASSWORD_SIZE),
-        RegisterFormValidationError::PasswordInvalidCharacters => "Password must consist of alphanumeric characters and spaces".to_owned(),
-        RegisterFormValidationError::PasswordNotConfirmed => "Passwords did not match".to_owned(),
+        RegisterFormValidationError::UserNameTooShort => hb.translate("register-user-name-length", &user_name_length),
+        RegisterFormValidationError::UserNameTooLong => hb.translate("register-user-name-length", &user_name_length),
+        RegisterFormValidationError::UserNameInvalidCharacters => hb.translate("register-user-name-characters", &[]),
+        RegisterFormValidationError::EmailInvalid => hb.translate("register-email-invalid", &[]),
+        RegisterFormValidationError::PasswordInsecure => hb.translate("register-password-insecure", &[]),
+        RegisterFormValidationError::PasswordTooShort => hb.translate("register-password-length", &[("min", MIN_PASSWORD_SIZE.into())]),
+        RegisterFormValidationError::PasswordInvalidCharacters => hb.translate("register-password-characters", &[]),
+        RegisterFormValidationError::PasswordNotConfirmed => hb.translate("register-password-mismatch", &[]),
     }
 }
 
-pub fn post(hb: web::Data<Handlebars>, pool: web::Data<DatabasePool>, session: Session, form: web::Form<RegisterForm>) -> Result<HttpResponse, RyobError> {
+pub fn post(
+    hb: Renderer,
+    pool: web::Data<DatabasePool>,
+    spam: web::Data<Pipeline>,
+    session: Session,
+    ip: ClientIp,
+    form: web::Form<RegisterForm>,
+) -> Result<HttpResponse, RyobError> {
     let sanitized_form = sanitize_register_form(&form);
     let validation_errors = validate_register_form(&sanitized_form);
     if validation_errors.len() > 0 {
         let previous = register_form_to_previous(&sanitized_form);
-        let validation_error_strings: Vec<String> = validation_errors.iter().map(|e| validation_error_to_string(*e)).collect();
-        let data = json!({ "errors": validation_error_strings, "previous": previous });
+        let validation_error_strings: Vec<String> = validation_errors.iter().map(|e| validation_error_to_string(&hb, *e)).collect();
+        let data = json!({ "errors": validation_error_strings, "previous": previous, "form_token": spam::form_token() });
         let page = hb.render("pages/register", &data)?;
         Ok(HttpResponse::BadRequest().body(page))
     } else {
         let connection = pool.get()?;
-        let registered_user_result = User::register(&connection, &sanitized_form.user_name, &sanitized_form.password);
+        let verdict = spam.run(
+            &connection,
+            &Submission {
+                kind: SpamKind::Register,
+                user: None,
+                ip: ip.0,
+                text: &sanitized_form.user_name,
+                form: Some(FormFields {
+                    honeypot: &sanitized_form.website,
+                    form_token: &sanitized_form.form_token,
+                }),
+            },
+        )?;
+        if verdict.decision == SpamDecision::Reject {
+            verdict.record(&connection, None, None)?;
+            let previous = register_form_to_previous(&sanitized_form);
+            let data = json!({"errors": [hb.translate("spam-rejected", &[])], "previous": previous, "form_token": spam::form_token() });
+            let page = hb.render("pages/register", &data)?;
+            return Ok(HttpResponse::Forbidden().body(page));
+        }
+        // The account is only kept along with its address, hold and spam log entry
+        let registered_user_result = {
+            use diesel::Connection;
+            connection.transaction::<_, RyobError, _>(|| {
+                let registered_user = match User::register(&connection, &sanitized_form.user_name, &sanitized_form.password, &sanitized_form.email) {
+                    Ok(registered_user) => registered_user,
+                    Err(error) => return Ok(Err(error)),
+                };
+                IpRecord::record(&connection, registered_user.id, None, IpEvent::Register, ip.0)?;
+                if verdict.decision == SpamDecision::Hold {
+                    User::set_spam_held(&connection, registered_user.id)?;
+                }
+                verdict.record(&connection, Some(registered_user.id), None)?;
+                Ok(Ok(registered_user))
+            })?
+        };
         match registered_user_result {
             Err(UserError::NameAlreadyInUse) => {
                 let previous = register_form_to_previous(&sanitized_form);
-                let data = json!({"errors": ["Username is already in use"], "previous": previous });
+                let data = json!({"errors": [hb.translate("register-user-name-taken", &[])], "previous": previous, "form_token": spam::form_token() });
+                let page = hb.render("pages/register", &data)?;
+                Ok(HttpResponse::Conflict().body(page))
+            }
+            Err(UserError::NameRejected(message)) => {
+                let previous = register_form_to_previous(&sanitized_form);
+                let data = json!({"errors": [message], "previous": previous, "form_token": spam::form_token() });
+                let page = hb.render("pages/register", &data)?;
+                Ok(HttpResponse::BadRequest().body(page))
+            }
+            Err(UserError::EmailAlreadyInUse) => {
+                let previous = register_form_to_previous(&sanitized_form);
+                let data = json!({"errors": [hb.translate("register-email-taken", &[])], "previous": previous, "form_token": spam::form_token() });
                 let page = hb.render("pages/register", &data)?;
                 Ok(HttpResponse::Conflict().body(page))
             }
             Err(error) => Err(RyobError::from(error)),
             Ok(registered_user) => {
+                super::verify_email::send_verification(&connection, &hb, &registered_user, &sanitized_form.email)?;
                 registered_user.to_session(&session)?;
                 Ok(HttpResponse::Found().header(actix_web::http::header::LOCATION, "/").finish())
             }
@@ -132,8 +224,8 @@ pub fn post(hb: web::Data<Handlebars>, pool: web::Data<DatabasePool>, session: S
     }
 }
 
-pub fn get(hb: web::Data<Handlebars>) -> Result<HttpResponse, RyobError> {
-    let data = json!({});
+pub fn get(hb: Renderer) -> Result<HttpResponse, RyobError> {
+    let data = json!({ "form_token": spam::form_token() });
     let page = hb.render("pages/register", &data)?;
     Ok(HttpResponse::Ok().body(page))
 }
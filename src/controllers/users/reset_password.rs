@@ -0,0 +1,84 @@
+use super::register::{validate_password, validation_error_to_string};
+use crate::database::types::*;
+use crate::models::user::User;
+use crate::models::user_token::{TokenPurpose, UserToken, UserTokenError};
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct ResetPasswordQuery {
+    token: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResetPasswordForm {
+    token: String,
+    password: String,
+    confirm_password: String,
+}
+
+fn render_bad_token(hb: &Renderer, error: UserTokenError) -> Result<HttpResponse, RyobError> {
+    let message = match error {
+        UserTokenError::InvalidToken => hb.translate("reset-password-link-invalid", &[]),
+        UserTokenError::ExpiredToken => hb.translate("reset-password-link-expired", &[]),
+        UserTokenError::UsedToken => hb.translate("reset-password-link-used", &[]),
+        _ => return Err(RyobError::from(error)),
+    };
+    let data = json!({ "errors": [message] });
+    let page = hb.render("pages/reset_password", &data)?;
+    Ok(HttpResponse::BadRequest().body(page))
+}
+
+pub fn post(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, form: web::Form<ResetPasswordForm>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+
+    let validation_errors = validate_password(&form.password, &form.confirm_password);
+    if !validation_errors.is_empty() {
+        if let Err(error) = UserToken::check(&connection, &form.token, TokenPurpose::ResetPassword) {
+            return render_bad_token(&hb, error);
+        }
+        let validation_error_strings: Vec<String> = validation_errors.iter().map(|e| validation_error_to_string(&hb, *e)).collect();
+        let data = json!({ "errors": validation_error_strings, "token": form.token });
+        let page = hb.render("pages/reset_password", &data)?;
+        return Ok(HttpResponse::BadRequest().body(page));
+    }
+
+    // The token is only used up if the new password is saved with it
+    let consumed = {
+        use diesel::Connection;
+        connection.transaction::<_, RyobError, _>(|| {
+            let token = match UserToken::consume(&connection, &form.token, TokenPurpose::ResetPassword) {
+                Ok(token) => token,
+                Err(error) => return Ok(Err(error)),
+            };
+
+            User::set_password(&connection, token.user_id, &form.password)?;
+            UserToken::revoke_all(&connection, token.user_id, TokenPurpose::ResetPassword)?;
+
+            // Following the emailed link proves ownership of the address as well
+            if let Some(email) = &token.email {
+                User::mark_email_verified(&connection, token.user_id, email)?;
+            }
+            Ok(Ok(token))
+        })?
+    };
+    if let Err(error) = consumed {
+        return render_bad_token(&hb, error);
+    }
+
+    session.clear();
+    Ok(HttpResponse::Found().header(actix_web::http::header::LOCATION, "/users/login").finish())
+}
+
+pub fn get(hb: Renderer, pool: web::Data<DatabasePool>, query: web::Query<ResetPasswordQuery>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    if let Err(error) = UserToken::check(&connection, &query.token, TokenPurpose::ResetPassword) {
+        return render_bad_token(&hb, error);
+    }
+    let data = json!({ "token": query.token });
+    let page = hb.render("pages/reset_password", &data)?;
+    Ok(HttpResponse::Ok().body(page))
+}
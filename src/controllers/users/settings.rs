@@ -0,0 +1,125 @@
+use crate::database::types::*;
+use crate::models::message::Message;
+use crate::models::user::User;
+use crate::models::user_preferences::{UserPreferences, UserPreferencesError, DEFAULT_POSTS_PER_PAGE, DEFAULT_TOPICS_PER_PAGE, MAX_PER_PAGE, MIN_PER_PAGE};
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+
+// Empty fields go back to the defaults. Unchecked checkboxes aren't submitted at all.
+#[derive(Deserialize)]
+pub struct SettingsForm {
+    timezone: String,
+    locale: String,
+    theme: String,
+    topics_per_page: String,
+    posts_per_page: String,
+    email_notifications: Option<String>,
+}
+
+fn optional(value: &str) -> Option<String> {
+    match value.trim() {
+        "" => None,
+        value => Some(value.to_owned()),
+    }
+}
+
+fn validation_error_to_string(hb: &Renderer, error: &UserPreferencesError) -> String {
+    match error {
+        UserPreferencesError::InvalidTimezone => hb.translate("settings-timezone-unknown", &[]),
+        UserPreferencesError::InvalidPerPage => hb.translate("settings-per-page-range", &[("min", MIN_PER_PAGE.into()), ("max", MAX_PER_PAGE.into())]),
+        _ => format!("{}", error),
+    }
+}
+
+fn render_settings(
+    hb: &Renderer,
+    connection: &DatabaseConnection,
+    user: &User,
+    preferences: &UserPreferences,
+    errors: Vec<String>,
+) -> Result<String, RyobError> {
+    let unread_message_count = Message::unread_count(connection, user.id, None)?;
+    let timezone = preferences.timezone.clone().unwrap_or_default();
+    let locale = preferences.locale.clone().unwrap_or_default();
+    let data = json!({
+        "user": user,
+        "unread_message_count": unread_message_count,
+        "preferences": preferences,
+        "timezones": chrono_tz::TZ_VARIANTS
+            .iter()
+            .map(|tz| json!({ "name": tz.name(), "selected": tz.name() == timezone }))
+            .collect::<Vec<_>>(),
+        "locales": hb
+            .catalog()
+            .names()
+            .into_iter()
+            .map(|name| json!({
+                "label": hb.catalog().translate(&name, "language-name", &[]),
+                "selected": name == locale,
+                "name": name,
+            }))
+            .collect::<Vec<_>>(),
+        "themes": hb.options(preferences.theme.as_deref().unwrap_or("")),
+        "default_theme": hb.default_theme(),
+        "default_topics_per_page": DEFAULT_TOPICS_PER_PAGE,
+        "default_posts_per_page": DEFAULT_POSTS_PER_PAGE,
+        "min_per_page": MIN_PER_PAGE,
+        "max_per_page": MAX_PER_PAGE,
+        "errors": errors,
+    });
+    Ok(hb.render("pages/settings", &data)?)
+}
+
+pub fn get(hb: Renderer, pool: web::Data<DatabasePool>, session: Session) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    let preferences = UserPreferences::for_user(&connection, user.id)?;
+    let page = render_settings(&hb, &connection, &user, &preferences, vec![])?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+pub fn post(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, form: web::Form<SettingsForm>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+
+    let mut invalid_per_page = false;
+    let mut per_page = |value: &str| match optional(value).map(|value| value.parse::<i32>()) {
+        None => None,
+        Some(Ok(per_page)) => Some(per_page),
+        Some(Err(_)) => {
+            invalid_per_page = true;
+            None
+        }
+    };
+    let preferences = UserPreferences {
+        user_id: user.id,
+        timezone: optional(&form.timezone),
+        locale: optional(&form.locale),
+        theme: optional(&form.theme),
+        topics_per_page: per_page(&form.topics_per_page),
+        posts_per_page: per_page(&form.posts_per_page),
+        email_notifications: form.email_notifications.is_some(),
+    };
+
+    let mut validation_errors = preferences.validate();
+    if invalid_per_page && !validation_errors.iter().any(|error| matches!(error, UserPreferencesError::InvalidPerPage)) {
+        validation_errors.push(UserPreferencesError::InvalidPerPage);
+    }
+    let mut errors: Vec<String> = validation_errors.iter().map(|error| validation_error_to_string(&hb, error)).collect();
+    if preferences.locale.as_ref().map(|locale| !hb.catalog().exists(locale)).unwrap_or(false) {
+        errors.push(hb.translate("profile-language-unknown", &[]));
+    }
+    if preferences.theme.as_ref().map(|theme| !hb.exists(theme)).unwrap_or(false) {
+        errors.push(hb.translate("settings-theme-unknown", &[]));
+    }
+
+    if errors.is_empty() {
+        preferences.save(&connection)?;
+        return Ok(HttpResponse::Found().header(actix_web::http::header::LOCATION, "/users/settings").finish());
+    }
+    let page = render_settings(&hb, &connection, &user, &preferences, errors)?;
+    Ok(HttpResponse::BadRequest().body(page))
+}
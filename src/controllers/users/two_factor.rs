@@ -0,0 +1,147 @@
+use crate::database::types::*;
+use crate::models::message::Message;
+use crate::models::two_factor::{RecoveryCode, TwoFactorError, TwoFactorSecret};
+use crate::models::user::{User, UserError};
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use crate::utils::totp;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct CodeForm {
+    code: String,
+}
+
+#[derive(Deserialize)]
+pub struct DisableForm {
+    password: String,
+    code: String,
+}
+
+fn two_factor_error_to_string(hb: &Renderer, error: &TwoFactorError) -> String {
+    match error {
+        TwoFactorError::InvalidCode => hb.translate("two-factor-code-invalid", &[]),
+        TwoFactorError::NotEnrolled => hb.translate("two-factor-not-enrolled", &[]),
+        TwoFactorError::LockedOut(until) => {
+            let until = until.format("%Y-%m-%d %H:%M UTC").to_string();
+            hb.translate("two-factor-locked-out", &[("until", until.as_str().into())])
+        }
+        _ => error.to_string(),
+    }
+}
+
+fn render_two_factor(
+    hb: &Renderer,
+    connection: &DatabaseConnection,
+    user: &User,
+    recovery_codes: Vec<String>,
+    errors: Vec<String>,
+) -> Result<String, RyobError> {
+    let secret = TwoFactorSecret::by_user(connection, user.id)?;
+    let enabled = secret.as_ref().map(|secret| secret.enabled_at.is_some()).unwrap_or(false);
+
+    // Only an unconfirmed secret is ever shown, once enabled it stays on the server
+    let enrollment = match &secret {
+        Some(secret) if !enabled => {
+            let uri = secret.otpauth_uri(&user.user_name);
+            Some(json!({ "uri": uri, "qr_code_svg": totp::qr_code_svg(&uri), "secret": secret.formatted_secret() }))
+        }
+        _ => None,
+    };
+    let remaining_recovery_codes = if enabled { RecoveryCode::remaining(connection, user.id)? } else { 0 };
+
+    let unread_message_count = Message::unread_count(connection, user.id, None)?;
+    let data = json!({
+        "user": user,
+        "unread_message_count": unread_message_count,
+        "enabled": enabled,
+        "required": user.two_factor_required(connection)?,
+        "enrollment": enrollment,
+        "remaining_recovery_codes": remaining_recovery_codes,
+        "recovery_codes": recovery_codes,
+        "errors": errors,
+    });
+    Ok(hb.render("pages/two_factor", &data)?)
+}
+
+fn redirect_to_settings() -> HttpResponse {
+    HttpResponse::Found().header(actix_web::http::header::LOCATION, "/users/two_factor").finish()
+}
+
+pub fn get(hb: Renderer, pool: web::Data<DatabasePool>, session: Session) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    let page = render_two_factor(&hb, &connection, &user, vec![], vec![])?;
+    Ok(HttpResponse::Ok().body(page))
+}
+
+pub fn enroll(pool: web::Data<DatabasePool>, session: Session) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    match TwoFactorSecret::begin_enrollment(&connection, user.id) {
+        Ok(_) | Err(TwoFactorError::AlreadyEnabled) => Ok(redirect_to_settings()),
+        Err(error) => Err(RyobError::from(error)),
+    }
+}
+
+pub fn confirm(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, form: web::Form<CodeForm>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    match TwoFactorSecret::confirm_enrollment(&connection, user.id, &form.code) {
+        Ok(recovery_codes) => {
+            let page = render_two_factor(&hb, &connection, &user, recovery_codes, vec![])?;
+            Ok(HttpResponse::Ok().body(page))
+        }
+        Err(error @ TwoFactorError::InvalidCode) | Err(error @ TwoFactorError::NotEnrolled) | Err(error @ TwoFactorError::LockedOut(_)) => {
+            let page = render_two_factor(&hb, &connection, &user, vec![], vec![two_factor_error_to_string(&hb, &error)])?;
+            Ok(HttpResponse::BadRequest().body(page))
+        }
+        Err(TwoFactorError::AlreadyEnabled) => Ok(redirect_to_settings()),
+        Err(error) => Err(RyobError::from(error)),
+    }
+}
+
+pub fn recovery_codes(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, form: web::Form<CodeForm>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    match TwoFactorSecret::verify(&connection, user.id, &form.code) {
+        Ok(()) => {
+            let recovery_codes = RecoveryCode::regenerate(&connection, user.id)?;
+            let page = render_two_factor(&hb, &connection, &user, recovery_codes, vec![])?;
+            Ok(HttpResponse::Ok().body(page))
+        }
+        Err(error @ TwoFactorError::InvalidCode) | Err(error @ TwoFactorError::NotEnrolled) | Err(error @ TwoFactorError::LockedOut(_)) => {
+            let page = render_two_factor(&hb, &connection, &user, vec![], vec![two_factor_error_to_string(&hb, &error)])?;
+            Ok(HttpResponse::BadRequest().body(page))
+        }
+        Err(error) => Err(RyobError::from(error)),
+    }
+}
+
+pub fn disable(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, form: web::Form<DisableForm>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+
+    let mut errors = vec![];
+    if user.two_factor_required(&connection)? {
+        errors.push(hb.translate("two-factor-required-cannot-disable", &[]));
+    } else if !bcrypt::verify(&form.password, &user.password_hash).map_err(UserError::UnknownHashError)? {
+        errors.push(hb.translate("two-factor-password-incorrect", &[]));
+    } else {
+        match TwoFactorSecret::verify(&connection, user.id, &form.code) {
+            Ok(()) => {
+                TwoFactorSecret::disable(&connection, user.id)?;
+                return Ok(redirect_to_settings());
+            }
+            Err(error @ TwoFactorError::InvalidCode) | Err(error @ TwoFactorError::NotEnrolled) | Err(error @ TwoFactorError::LockedOut(_)) => {
+                errors.push(two_factor_error_to_string(&hb, &error))
+            }
+            Err(error) => return Err(RyobError::from(error)),
+        }
+    }
+
+    let page = render_two_factor(&hb, &connection, &user, vec![], errors)?;
+    Ok(HttpResponse::BadRequest().body(page))
+}
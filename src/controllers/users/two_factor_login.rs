@@ -0,0 +1,109 @@
+use crate::database::types::*;
+use crate::models::ip_address::{IpEvent, IpRecord};
+use crate::models::two_factor::{TwoFactorError, TwoFactorSecret};
+use crate::models::user::{User, UserError};
+use crate::themes::Renderer;
+use crate::utils::client_ip::ClientIp;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use fluent_bundle::FluentValue;
+use log::info;
+use serde::Deserialize;
+
+// After the password has been checked, users with 2FA enabled are parked in the session under
+// these keys until they enter a code. Only then is User::to_session called. Incorrect codes are
+// counted by TwoFactorSecret::verify, not here.
+const PENDING_USER_KEY: &str = "two_factor_user";
+const PENDING_STARTED_AT_KEY: &str = "two_factor_started_at";
+
+const PENDING_LIFETIME_SECONDS: i64 = 5 * 60;
+
+#[derive(Deserialize)]
+pub struct TwoFactorLoginForm {
+    code: String,
+}
+
+pub fn begin(session: &Session, user: &User) -> Result<(), RyobError> {
+    session.remove("user");
+    session.set(PENDING_USER_KEY, user.id).map_err(UserError::UnknownActixError)?;
+    session
+        .set(PENDING_STARTED_AT_KEY, chrono::Utc::now().timestamp())
+        .map_err(UserError::UnknownActixError)?;
+    Ok(())
+}
+
+fn clear(session: &Session) {
+    session.remove(PENDING_USER_KEY);
+    session.remove(PENDING_STARTED_AT_KEY);
+}
+
+fn pending_user(session: &Session) -> Result<Option<Id<User>>, RyobError> {
+    let user = session.get::<Id<User>>(PENDING_USER_KEY).map_err(UserError::UnknownActixError)?;
+    let started_at = session.get::<i64>(PENDING_STARTED_AT_KEY).map_err(UserError::UnknownActixError)?.unwrap_or(0);
+    if user.is_some() && chrono::Utc::now().timestamp() - started_at > PENDING_LIFETIME_SECONDS {
+        clear(session);
+        return Ok(None);
+    }
+    Ok(user)
+}
+
+fn redirect_to_login() -> HttpResponse {
+    HttpResponse::Found().header(actix_web::http::header::LOCATION, "/users/login").finish()
+}
+
+pub fn post(
+    hb: Renderer,
+    pool: web::Data<DatabasePool>,
+    session: Session,
+    ip: ClientIp,
+    form: web::Form<TwoFactorLoginForm>,
+) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user_id = match pending_user(&session)? {
+        Some(user_id) => user_id,
+        None => return Ok(redirect_to_login()),
+    };
+
+    match TwoFactorSecret::verify(&connection, user_id, &form.code) {
+        Ok(()) => {
+            clear(&session);
+            let user = User::by_user_id(&connection, user_id)?;
+            IpRecord::record(&connection, user.id, None, IpEvent::Login, ip.0)?;
+            user.to_session(&session)?;
+            info!("User {:?} with username \"{}\" has completed two-factor login", user.id, user.user_name);
+            Ok(HttpResponse::Found().header(actix_web::http::header::LOCATION, "/").finish())
+        }
+        Err(TwoFactorError::InvalidCode) => {
+            let data = json!({ "errors": [hb.translate("two-factor-code-invalid", &[])] });
+            let page = hb.render("pages/two_factor_login", &data)?;
+            Ok(HttpResponse::BadRequest().body(page))
+        }
+        Err(TwoFactorError::LockedOut(until)) => {
+            clear(&session);
+            let until = until.format("%Y-%m-%d %H:%M UTC").to_string();
+            let data = json!({ "errors": [hb.translate("two-factor-locked-out", &[("until", FluentValue::from(until.as_str()))])] });
+            let page = hb.render("pages/login", &data)?;
+            Ok(HttpResponse::TooManyRequests().body(page))
+        }
+        // 2FA was turned off in the meantime, so the password alone is enough again
+        Err(TwoFactorError::NotEnrolled) => {
+            clear(&session);
+            let user = User::by_user_id(&connection, user_id)?;
+            IpRecord::record(&connection, user.id, None, IpEvent::Login, ip.0)?;
+            user.to_session(&session)?;
+            Ok(HttpResponse::Found().header(actix_web::http::header::LOCATION, "/").finish())
+        }
+        Err(error) => Err(RyobError::from(error)),
+    }
+}
+
+pub fn get(hb: Renderer, session: Session) -> Result<HttpResponse, RyobError> {
+    if pending_user(&session)?.is_none() {
+        return Ok(redirect_to_login());
+    }
+    let data = json!({});
+    let page = hb.render("pages/two_factor_login", &data)?;
+    Ok(HttpResponse::Ok().body(page))
+}
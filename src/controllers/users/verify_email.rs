@@ -0,0 +1,71 @@
+use crate::database::types::*;
+use crate::mailer;
+use crate::models::message::Message;
+use crate::models::user::User;
+use crate::models::user_preferences::UserPreferences;
+use crate::models::user_token::{TokenPurpose, UserToken, UserTokenError};
+use crate::themes::Renderer;
+use crate::utils::errors::RyobError;
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct VerifyQuery {
+    token: String,
+}
+
+pub fn send_verification(connection: &DatabaseConnection, hb: &Renderer, user: &User, email: &str) -> Result<(), RyobError> {
+    UserToken::revoke_all(connection, user.id, TokenPurpose::VerifyEmail)?;
+    let token = UserToken::issue(connection, user.id, TokenPurpose::VerifyEmail, Some(email))?;
+    let data = json!({
+        "user_name": user.user_name,
+        "link": mailer::absolute_url(&format!("/users/verify?token={}", token)),
+        "expires_in_hours": TokenPurpose::VerifyEmail.lifetime().num_hours(),
+        "preferences": UserPreferences::for_user(connection, user.id)?,
+    });
+    mailer::enqueue(connection, hb, hb.locale(), "verify_email", email, &data)
+}
+
+pub fn get(hb: Renderer, pool: web::Data<DatabasePool>, session: Session, query: web::Query<VerifyQuery>) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::from_session(&connection, &session)?;
+    let unread_message_count = match &user {
+        Some(user) => Message::unread_count(&connection, user.id, None)?,
+        None => 0,
+    };
+
+    let verified = match UserToken::consume(&connection, &query.token, TokenPurpose::VerifyEmail) {
+        Ok(token) => match token.email {
+            Some(email) => User::mark_email_verified(&connection, token.user_id, &email)?,
+            None => false,
+        },
+        Err(UserTokenError::InvalidToken) | Err(UserTokenError::ExpiredToken) | Err(UserTokenError::UsedToken) => false,
+        Err(error) => return Err(RyobError::from(error)),
+    };
+
+    // Re-read the user so the header reflects the verification straight away
+    let user = match user {
+        Some(user) => Some(User::by_user_id(&connection, user.id)?),
+        None => None,
+    };
+    let data = json!({ "user": user, "unread_message_count": unread_message_count, "verified": verified });
+    let page = hb.render("pages/verify_email", &data)?;
+    if verified {
+        Ok(HttpResponse::Ok().body(page))
+    } else {
+        Ok(HttpResponse::BadRequest().body(page))
+    }
+}
+
+pub fn post(hb: Renderer, pool: web::Data<DatabasePool>, session: Session) -> Result<HttpResponse, RyobError> {
+    let connection = pool.get()?;
+    let user = User::required_from_session(&connection, &session)?;
+    if let (Some(email), None) = (&user.email, &user.email_verified_at) {
+        send_verification(&connection, &hb, &user, email)?;
+    }
+    let unread_message_count = Message::unread_count(&connection, user.id, None)?;
+    let data = json!({ "user": user, "unread_message_count": unread_message_count, "resent": true });
+    let page = hb.render("pages/verify_email", &data)?;
+    Ok(HttpResponse::Ok().body(page))
+}
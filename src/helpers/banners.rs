@@ -0,0 +1,54 @@
+use crate::database::types::DatabasePool;
+use crate::models::banner::{Banner, BannerKind};
+use handlebars::{Context, Handlebars, Helper, HelperDef, RenderContext, RenderError, ScopedJson};
+use log::warn;
+use serde_json::Value;
+
+// (random_banner "banner") or (random_banner "cornerpet") picks one of the approved images of
+// that kind for each render, giving null when there are none. The result needs a block param:
+//
+//   {{#with (random_banner "banner") as |banner|}}<img src="{{banner.url}}" />{{/with}}
+pub struct RandomBanner {
+    pool: DatabasePool,
+}
+
+impl RandomBanner {
+    pub fn new(pool: DatabasePool) -> RandomBanner {
+        RandomBanner { pool }
+    }
+}
+
+impl HelperDef for RandomBanner {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg>,
+    ) -> Result<Option<ScopedJson<'reg, 'rc>>, RenderError> {
+        let kind = h
+            .param(0)
+            .and_then(|param| param.value().as_str())
+            .and_then(BannerKind::parse)
+            .ok_or_else(|| RenderError::new("random_banner expects \"banner\" or \"cornerpet\""))?;
+
+        // A page is still worth showing without its banner
+        let banner = match self
+            .pool
+            .get()
+            .map_err(|err| err.to_string())
+            .and_then(|connection| Banner::random(&connection, kind).map_err(|err| err.to_string()))
+        {
+            Ok(banner) => banner,
+            Err(err) => {
+                warn!("Failed to pick a {}: {}", kind.as_str(), err);
+                None
+            }
+        };
+
+        Ok(Some(ScopedJson::Derived(match banner {
+            Some(banner) => json!({ "id": banner.id, "url": banner.url() }),
+            None => Value::Null,
+        })))
+    }
+}
@@ -0,0 +1,86 @@
+use super::str_param;
+use crate::i18n::Catalog;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use chrono_tz::Tz;
+use handlebars::{html_escape, Context, Helper, HelperResult, Output, RenderError};
+use serde_json::Value;
+
+const DEFAULT_FORMAT: &str = "%Y-%m-%d %H:%M %Z";
+
+fn parse_time(h: &Helper) -> Result<DateTime<Utc>, RenderError> {
+    let value = str_param(h, 0)?;
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|err| RenderError::new(format!("{} can't read \"{}\" as a time: {}", h.name(), value, err)))
+}
+
+// A tz="Europe/London" hash parameter wins, then "timezone" at the top of the data, then UTC.
+// Names that aren't in the tz database are treated as UTC rather than failing the page.
+fn timezone(h: &Helper, ctx: &Context) -> Tz {
+    h.hash_get("tz")
+        .map(|tz| tz.value())
+        .or_else(|| ctx.data().get("timezone"))
+        .and_then(Value::as_str)
+        .and_then(|name| name.parse().ok())
+        .unwrap_or(Tz::UTC)
+}
+
+fn absolute(h: &Helper, ctx: &Context, time: DateTime<Utc>) -> String {
+    let format = h.hash_get("format").and_then(|format| format.value().as_str()).unwrap_or(DEFAULT_FORMAT);
+    timezone(h, ctx).from_utc_datetime(&time.naive_utc()).format(format).to_string()
+}
+
+// The messages are "time-{unit}-ago" and "time-in-{unit}", with the amount as $count
+fn ago(catalog: &Catalog, locale: &str, elapsed: Duration) -> String {
+    let (amount, unit) = match elapsed.num_seconds().abs() {
+        seconds if seconds < 60 => return catalog.translate(locale, "time-just-now", &[]),
+        seconds if seconds < 60 * 60 => (seconds / 60, "minutes"),
+        seconds if seconds < 24 * 60 * 60 => (seconds / (60 * 60), "hours"),
+        seconds if seconds < 30 * 24 * 60 * 60 => (seconds / (24 * 60 * 60), "days"),
+        seconds if seconds < 365 * 24 * 60 * 60 => (seconds / (30 * 24 * 60 * 60), "months"),
+        seconds => (seconds / (365 * 24 * 60 * 60), "years"),
+    };
+    let id = if elapsed < Duration::zero() {
+        format!("time-in-{}", unit)
+    } else {
+        format!("time-{}-ago", unit)
+    };
+    catalog.translate(locale, &id, &[("count", amount.into())])
+}
+
+// {{relative_time created_at}} gives a <time> element saying how long ago it was, e.g.
+// "3 hours ago" in the page's locale, with the full date as a tooltip. Takes the same tz and
+// format as format_time.
+pub fn relative_time(catalog: &Catalog, h: &Helper, ctx: &Context, out: &mut dyn Output) -> HelperResult {
+    let time = parse_time(h)?;
+    out.write(&format!(
+        "<time datetime=\"{}\" title=\"{}\">{}</time>",
+        time.to_rfc3339(),
+        html_escape(&absolute(h, ctx, time)),
+        html_escape(&ago(catalog, super::translate::locale(ctx), Utc::now().signed_duration_since(time)))
+    ))?;
+    Ok(())
+}
+
+// {{format_time created_at}} or {{format_time created_at format="%-d %B %Y" tz="Europe/Paris"}}
+pub fn format_time(h: &Helper, ctx: &Context) -> Result<Value, RenderError> {
+    Ok(Value::String(absolute(h, ctx, parse_time(h)?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn says_how_long_ago_in_the_locale() {
+        let catalog = Catalog::load(Path::new("locales")).unwrap();
+        assert_eq!(ago(&catalog, "en", Duration::seconds(30)), "just now");
+        assert_eq!(ago(&catalog, "en", Duration::minutes(1)), "1 minute ago");
+        assert_eq!(ago(&catalog, "en", Duration::hours(3)), "3 hours ago");
+        assert_eq!(ago(&catalog, "en", Duration::days(-2)), "in 2 days");
+        assert_eq!(ago(&catalog, "de", Duration::days(1)), "vor 1 Tag");
+        assert_eq!(ago(&catalog, "de", Duration::days(400)), "vor 1 Jahr");
+        assert_eq!(ago(&catalog, "de", Duration::days(-60)), "in 2 Monaten");
+    }
+}
@@ -0,0 +1,88 @@
+// Handlebars helpers registered for every template
+pub mod banners;
+pub mod dates;
+pub mod text;
+pub mod translate;
+pub mod urls;
+
+use crate::database::types::DatabasePool;
+use crate::i18n::Catalog;
+use handlebars::{Context, Handlebars, Helper, HelperDef, HelperResult, JsonRender, Output, RenderContext, RenderError, ScopedJson};
+use serde_json::Value;
+use std::sync::Arc;
+
+// Helpers that work out a value. Their output is escaped like any other {{value}}, which
+// handlebars doesn't do for helpers on its own, and they can be used as subexpressions.
+pub struct ValueHelper<F>(F);
+
+impl<F> HelperDef for ValueHelper<F>
+where
+    F: Fn(&Helper, &Context) -> Result<Value, RenderError> + Send + Sync,
+{
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars,
+        ctx: &'rc Context,
+        _: &mut RenderContext<'reg>,
+    ) -> Result<Option<ScopedJson<'reg, 'rc>>, RenderError> {
+        Ok(Some(ScopedJson::Derived((self.0)(h, ctx)?)))
+    }
+
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        r: &'reg Handlebars,
+        ctx: &'rc Context,
+        rc: &mut RenderContext<'reg>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let rendered = (self.0)(h, ctx)?.render();
+        if rc.is_disable_escape() {
+            out.write(&rendered)?;
+        } else {
+            out.write(&r.get_escape_fn()(&rendered))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn register(handlebars: &mut Handlebars, pool: DatabasePool, catalog: Arc<Catalog>) {
+    handlebars.register_helper("random_banner", Box::new(banners::RandomBanner::new(pool)));
+    let translate_catalog = catalog.clone();
+    handlebars.register_helper(
+        "t",
+        Box::new(ValueHelper(move |h: &Helper, ctx: &Context| translate::translate(&translate_catalog, h, ctx))),
+    );
+    let dates_catalog = catalog.clone();
+    handlebars.register_helper(
+        "relative_time",
+        Box::new(move |h: &Helper, _: &Handlebars, ctx: &Context, _: &mut RenderContext, out: &mut dyn Output| {
+            dates::relative_time(&dates_catalog, h, ctx, out)
+        }),
+    );
+    handlebars.register_helper("format_time", Box::new(ValueHelper(dates::format_time)));
+    handlebars.register_helper(
+        "pluralize",
+        Box::new(ValueHelper(move |h: &Helper, ctx: &Context| text::pluralize(&catalog, h, ctx))),
+    );
+    handlebars.register_helper("unescaped", Box::new(text::unescaped));
+    handlebars.register_helper("truncate", Box::new(ValueHelper(text::truncate)));
+    handlebars.register_helper("truncate_words", Box::new(ValueHelper(text::truncate_words)));
+    handlebars.register_helper("user_url", Box::new(ValueHelper(urls::user_url)));
+    handlebars.register_helper("topic_url", Box::new(ValueHelper(urls::topic_url)));
+    handlebars.register_helper("post_url", Box::new(ValueHelper(urls::post_url)));
+}
+
+// Positional parameters, with an error naming the helper when one is missing or the wrong type
+pub fn str_param<'a>(h: &'a Helper, index: usize) -> Result<&'a str, RenderError> {
+    h.param(index)
+        .and_then(|param| param.value().as_str())
+        .ok_or_else(|| RenderError::new(format!("{} expects a string as parameter {}", h.name(), index + 1)))
+}
+
+pub fn i64_param(h: &Helper, index: usize) -> Result<i64, RenderError> {
+    h.param(index)
+        .and_then(|param| param.value().as_i64())
+        .ok_or_else(|| RenderError::new(format!("{} expects a number as parameter {}", h.name(), index + 1)))
+}
@@ -0,0 +1,57 @@
+use super::{i64_param, str_param};
+use crate::i18n::Catalog;
+use handlebars::{Context, Handlebars, Helper, HelperResult, Output, RenderContext, RenderError, Renderable};
+use serde_json::Value;
+
+const ELLIPSIS: &str = "…";
+
+// {{pluralize post_count "topic-posts"}} gives "1 post" or "3 posts", looking the message up in
+// the page's locale with the count as $count, so it can pick the plural form of that language
+pub fn pluralize(catalog: &Catalog, h: &Helper, ctx: &Context) -> Result<Value, RenderError> {
+    let count = i64_param(h, 0)?;
+    let id = str_param(h, 1)?;
+    Ok(Value::String(catalog.translate(super::translate::locale(ctx), id, &[("count", count.into())])))
+}
+
+// {{#unescaped}}...{{/unescaped}} leaves everything inside unescaped, for the plain text parts of
+// emails. Triple braces do the same for values but can't call helpers like t.
+pub fn unescaped<'reg, 'rc>(h: &Helper<'reg, 'rc>, r: &'reg Handlebars, ctx: &Context, rc: &mut RenderContext<'reg>, out: &mut dyn Output) -> HelperResult {
+    let disable_escape = rc.is_disable_escape();
+    rc.set_disable_escape(true);
+    let rendered = match h.template() {
+        Some(template) => template.render(r, ctx, rc, out),
+        None => Ok(()),
+    };
+    rc.set_disable_escape(disable_escape);
+    rendered
+}
+
+// {{truncate content 200}} keeps at most that many characters, cutting at the last space if
+// there is one in the second half so words aren't split
+pub fn truncate(h: &Helper, _: &Context) -> Result<Value, RenderError> {
+    let text = str_param(h, 0)?;
+    let length = i64_param(h, 1)?.max(0) as usize;
+    match text.char_indices().nth(length) {
+        None => Ok(Value::String(text.to_owned())),
+        Some((end, _)) => {
+            let cut = &text[..end];
+            let cut = match cut.rfind(char::is_whitespace) {
+                Some(space) if space >= end / 2 => &cut[..space],
+                _ => cut,
+            };
+            Ok(Value::String(format!("{}{}", cut.trim_end(), ELLIPSIS)))
+        }
+    }
+}
+
+// {{truncate_words content 30}} keeps at most that many words
+pub fn truncate_words(h: &Helper, _: &Context) -> Result<Value, RenderError> {
+    let text = str_param(h, 0)?;
+    let length = i64_param(h, 1)?.max(0) as usize;
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.len() <= length {
+        Ok(Value::String(text.to_owned()))
+    } else {
+        Ok(Value::String(format!("{}{}", words[..length].join(" "), ELLIPSIS)))
+    }
+}
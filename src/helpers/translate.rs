@@ -0,0 +1,33 @@
+use super::str_param;
+use crate::i18n::{Catalog, DEFAULT_LOCALE};
+use fluent_bundle::FluentValue;
+use handlebars::{Context, Helper, RenderError};
+use serde_json::Value;
+
+// {{t "message-id"}} looks the message up in the locale of the page, given as "locale" at the top
+// of the data. Hash parameters become the message's arguments: {{t "topic-replies" count=3}}
+pub fn translate(catalog: &Catalog, h: &Helper, ctx: &Context) -> Result<Value, RenderError> {
+    let id = str_param(h, 0)?;
+    let args: Vec<(&str, FluentValue)> = h
+        .hash()
+        .iter()
+        .map(|(name, value)| {
+            let value = match value.value() {
+                Value::Number(number) => number
+                    .as_i64()
+                    .map(FluentValue::from)
+                    .unwrap_or_else(|| FluentValue::from(number.as_f64().unwrap_or(0.0))),
+                Value::String(string) => FluentValue::from(string.as_str()),
+                Value::Null => FluentValue::None,
+                other => FluentValue::from(other.to_string()),
+            };
+            (name.as_str(), value)
+        })
+        .collect();
+    Ok(Value::String(catalog.translate(locale(ctx), id, &args)))
+}
+
+// The locale of the page being rendered
+pub fn locale(ctx: &Context) -> &str {
+    ctx.data().get("locale").and_then(Value::as_str).unwrap_or(DEFAULT_LOCALE)
+}
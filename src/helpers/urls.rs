@@ -0,0 +1,22 @@
+use super::i64_param;
+use handlebars::{Context, Helper, RenderError};
+use serde_json::Value;
+
+// {{user_url id}}
+pub fn user_url(h: &Helper, _: &Context) -> Result<Value, RenderError> {
+    Ok(Value::String(format!("/users/{}", i64_param(h, 0)?)))
+}
+
+// {{topic_url id}}, or {{topic_url id page=2}} for a later page
+pub fn topic_url(h: &Helper, _: &Context) -> Result<Value, RenderError> {
+    let topic = i64_param(h, 0)?;
+    match h.hash_get("page").and_then(|page| page.value().as_i64()).filter(|page| *page > 1) {
+        Some(page) => Ok(Value::String(format!("/topics/{}?page={}", topic, page))),
+        None => Ok(Value::String(format!("/topics/{}", topic))),
+    }
+}
+
+// {{post_url id}} links to the post wherever it is in its topic, see topics::topic::post
+pub fn post_url(h: &Helper, _: &Context) -> Result<Value, RenderError> {
+    Ok(Value::String(format!("/posts/{}", i64_param(h, 0)?)))
+}
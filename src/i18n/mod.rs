@@ -0,0 +1,146 @@
+// Text shown to users comes from Fluent (https://projectfluent.org) catalogs, one directory per
+// locale under RYOB_LOCALES_PATH (default "./locales") holding any number of .ftl files:
+//
+//   locales/en/main.ftl
+//   locales/de/main.ftl
+//
+// Plurals are written as Fluent selectors on a number, which pick the right form with the CLDR
+// plural rules of each language:
+//
+//   topic-replies = { $count ->
+//       [one] { $count } reply
+//      *[other] { $count } replies
+//   }
+//
+// Messages missing from a locale fall back to the default locale, then to their id.
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use log::{info, warn};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use unic_langid::LanguageIdentifier;
+
+pub const DEFAULT_LOCALE: &str = "en";
+
+#[derive(Debug)]
+pub enum CatalogError {
+    Io(std::io::Error),
+    Parse(PathBuf, String),
+    NoDefaultLocale,
+}
+
+impl std::fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CatalogError::Io(err) => write!(f, "{}", err),
+            CatalogError::Parse(path, err) => write!(f, "{}: {}", path.display(), err),
+            CatalogError::NoDefaultLocale => write!(f, "There is no catalog for the default locale \"{}\"", DEFAULT_LOCALE),
+        }
+    }
+}
+
+impl From<std::io::Error> for CatalogError {
+    fn from(error: std::io::Error) -> CatalogError {
+        CatalogError::Io(error)
+    }
+}
+
+pub struct Catalog {
+    // The default locale comes first
+    locales: Vec<LanguageIdentifier>,
+    bundles: HashMap<String, FluentBundle<FluentResource>>,
+}
+
+fn sorted_entries(path: &Path) -> Result<Vec<PathBuf>, CatalogError> {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
+fn load_bundle(locale: &LanguageIdentifier, path: &Path) -> Result<FluentBundle<FluentResource>, CatalogError> {
+    let mut bundle = FluentBundle::new_concurrent(vec![locale.clone()]);
+    // Messages end up in HTML, where the invisible isolation marks around arguments get in the way
+    bundle.set_use_isolating(false);
+    for file in sorted_entries(path)?
+        .into_iter()
+        .filter(|file| file.extension().map(|extension| extension == "ftl").unwrap_or(false))
+    {
+        let source = std::fs::read_to_string(&file)?;
+        let resource = FluentResource::try_new(source).map_err(|(_, errors)| CatalogError::Parse(file.clone(), format!("{:?}", errors)))?;
+        bundle
+            .add_resource(resource)
+            .map_err(|errors| CatalogError::Parse(file.clone(), format!("{:?}", errors)))?;
+    }
+    Ok(bundle)
+}
+
+impl Catalog {
+    pub fn from_env() -> Result<Catalog, CatalogError> {
+        let path = std::env::var("RYOB_LOCALES_PATH").unwrap_or_else(|_| "./locales".to_owned());
+        Catalog::load(Path::new(&path))
+    }
+
+    pub fn load(path: &Path) -> Result<Catalog, CatalogError> {
+        let mut locales = vec![];
+        let mut bundles = HashMap::new();
+        for locale_path in sorted_entries(path)?.into_iter().filter(|entry| entry.is_dir()) {
+            let locale: LanguageIdentifier = match locale_path.file_name().and_then(|name| name.to_str()).and_then(|name| name.parse().ok()) {
+                Some(locale) => locale,
+                None => continue,
+            };
+            bundles.insert(locale.to_string(), load_bundle(&locale, &locale_path)?);
+            info!("Loaded locale \"{}\"", locale);
+            locales.push(locale);
+        }
+
+        let default_position = locales
+            .iter()
+            .position(|locale| *locale == DEFAULT_LOCALE)
+            .ok_or(CatalogError::NoDefaultLocale)?;
+        let default_locale = locales.remove(default_position);
+        locales.insert(0, default_locale);
+        Ok(Catalog { locales, bundles })
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.locales.iter().map(|locale| locale.to_string()).collect()
+    }
+
+    pub fn exists(&self, locale: &str) -> bool {
+        self.bundles.contains_key(locale)
+    }
+
+    // Picks the best of our locales for an Accept-Language header, e.g. "de-AT,de;q=0.9,en;q=0.5"
+    pub fn negotiate(&self, accept_language: &str) -> String {
+        let requested = fluent_langneg::accepted_languages::parse(accept_language);
+        let default_locale = &self.locales[0];
+        fluent_langneg::negotiate_languages(&requested, &self.locales, Some(default_locale), fluent_langneg::NegotiationStrategy::Lookup)
+            .first()
+            .map(|locale| locale.to_string())
+            .unwrap_or_else(|| DEFAULT_LOCALE.to_owned())
+    }
+
+    pub fn translate(&self, locale: &str, id: &str, args: &[(&str, FluentValue)]) -> String {
+        let mut fluent_args = FluentArgs::new();
+        for (name, value) in args {
+            fluent_args.set(*name, value.clone());
+        }
+
+        for bundle in self.bundles.get(locale).into_iter().chain(self.bundles.get(DEFAULT_LOCALE)) {
+            if let Some(pattern) = bundle.get_message(id).and_then(|message| message.value()) {
+                let mut errors = vec![];
+                let translated = bundle.format_pattern(pattern, Some(&fluent_args), &mut errors).into_owned();
+                if !errors.is_empty() {
+                    warn!("Problems translating \"{}\" into {}: {:?}", id, locale, errors);
+                }
+                return translated;
+            }
+        }
+
+        warn!("No translation of \"{}\" into {}", id, locale);
+        id.to_owned()
+    }
+}
@@ -0,0 +1,66 @@
+// The LISTEN side of live updates, with one connection per server process
+
+use super::{Broker, Feed, LiveEvent, CHANNEL};
+use crate::controllers::topics::topic::render_post;
+use crate::database::types::*;
+use crate::models::post::Post;
+use crate::themes::Themes;
+use crate::utils::errors::RyobError;
+use actix_web::web;
+use log::{error, info, warn};
+use postgres::fallible_iterator::FallibleIterator;
+use std::time::Duration;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+pub fn spawn(database_url: String, pool: DatabasePool, hb: web::Data<Themes>, broker: Broker) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || loop {
+        if let Err(err) = listen(&database_url, &pool, &hb, &broker) {
+            error!("Lost the connection listening for live updates: {}", err);
+        }
+        std::thread::sleep(RECONNECT_DELAY);
+    })
+}
+
+fn listen(database_url: &str, pool: &DatabasePool, hb: &Themes, broker: &Broker) -> Result<(), postgres::Error> {
+    let mut client = postgres::Client::connect(database_url, postgres::NoTls)?;
+    client.batch_execute(&format!("LISTEN {}", CHANNEL))?;
+    info!("Listening for live updates");
+
+    loop {
+        // Ends each time the interval passes without a notification
+        let mut notifications = client.notifications();
+        let mut iter = notifications.timeout_iter(HEARTBEAT_INTERVAL);
+        while let Some(notification) = iter.next()? {
+            match serde_json::from_str::<LiveEvent>(notification.payload()) {
+                Ok(event) => {
+                    if let Err(err) = publish(pool, hb, broker, event) {
+                        error!("Failed to publish a live update: {}", err);
+                    }
+                }
+                Err(err) => warn!("Ignoring malformed live update \"{}\": {}", notification.payload(), err),
+            }
+        }
+        broker.heartbeat();
+    }
+}
+
+fn publish(pool: &DatabasePool, hb: &Themes, broker: &Broker, event: LiveEvent) -> Result<(), RyobError> {
+    match event {
+        LiveEvent::TopicCreated { topic_id } => broker.send(&Feed::Topics, super::topic_event(topic_id)),
+        LiveEvent::PostCreated { topic_id, post_id } => {
+            // Rendered once for each theme, locale and timezone here rather than by every stream
+            let keys = broker.topic_keys(topic_id);
+            if !keys.is_empty() {
+                let connection = pool.get()?;
+                let (post, author) = Post::by_id(&connection, post_id)?;
+                let rendered = render_post(hb, &keys, &connection, post, author)?;
+                for (key, html) in keys.into_iter().zip(rendered) {
+                    broker.send(&Feed::Topic(topic_id, key), super::post_event(post_id, &html));
+                }
+            }
+        }
+    }
+    Ok(())
+}
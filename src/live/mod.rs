@@ -0,0 +1,117 @@
+// Live updates pushed to open pages as Server-Sent Events
+//
+// Models announce new topics and posts with NOTIFY on the ryob_live channel, so every server
+// process hears about them whichever one handled the request. Each process keeps a single LISTEN
+// connection (see listener.rs) and fans the events out to the streams it is serving.
+
+pub mod listener;
+
+use crate::database::types::*;
+use crate::models::post::Post;
+use crate::models::topic::Topic;
+use crate::themes::RenderKey;
+use crate::utils::id::Id;
+use actix_web::web::Bytes;
+use diesel::result::Error as DieselError;
+use futures::sync::mpsc;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+pub const CHANNEL: &str = "ryob_live";
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LiveEvent {
+    TopicCreated { topic_id: Id<Topic> },
+    PostCreated { topic_id: Id<Topic>, post_id: Id<Post> },
+}
+
+// Inside a transaction the notification is only sent once it commits
+pub fn notify(connection: &DatabaseConnection, event: &LiveEvent) -> Result<(), DieselError> {
+    use diesel::prelude::*;
+    use diesel::sql_types::Text;
+    let payload = serde_json::to_string(event).expect("Live events always serialize");
+    diesel::sql_query("SELECT pg_notify($1, $2)")
+        .bind::<Text, _>(CHANNEL)
+        .bind::<Text, _>(payload)
+        .execute(connection)?;
+    Ok(())
+}
+
+// Posts are pushed as HTML, so the streams showing a topic are told apart by how they render it
+#[derive(PartialEq)]
+pub enum Feed {
+    Topics,
+    Topic(Id<Topic>, RenderKey),
+}
+
+struct Subscriber {
+    feed: Feed,
+    sender: mpsc::UnboundedSender<Bytes>,
+}
+
+#[derive(Clone, Default)]
+pub struct Broker {
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+}
+
+impl Broker {
+    pub fn subscribe(&self, feed: Feed) -> mpsc::UnboundedReceiver<Bytes> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.subscribers
+            .lock()
+            .expect("Live subscribers lock poisoned")
+            .push(Subscriber { feed, sender });
+        receiver
+    }
+
+    // The distinct ways the topic is being rendered by its streams
+    pub fn topic_keys(&self, topic: Id<Topic>) -> Vec<RenderKey> {
+        let mut keys: Vec<RenderKey> = vec![];
+        for subscriber in self.subscribers.lock().expect("Live subscribers lock poisoned").iter() {
+            if let Feed::Topic(id, key) = &subscriber.feed {
+                if *id == topic && !keys.contains(key) {
+                    keys.push(key.clone());
+                }
+            }
+        }
+        keys
+    }
+
+    // Streams whose client has gone away fail to send and are dropped here
+    pub fn send(&self, feed: &Feed, message: Bytes) {
+        self.subscribers
+            .lock()
+            .expect("Live subscribers lock poisoned")
+            .retain(|subscriber| subscriber.feed != *feed || subscriber.sender.unbounded_send(message.clone()).is_ok());
+    }
+
+    // A comment line keeps idle connections open through proxies and finds closed ones
+    pub fn heartbeat(&self) {
+        let message = Bytes::from_static(b": heartbeat\n\n");
+        self.subscribers
+            .lock()
+            .expect("Live subscribers lock poisoned")
+            .retain(|subscriber| subscriber.sender.unbounded_send(message.clone()).is_ok());
+    }
+}
+
+// The id lets a reconnecting browser say where it left off with Last-Event-ID
+pub fn format_event(name: &str, id: i64, data: &str) -> Bytes {
+    let mut message = format!("event: {}\nid: {}\n", name, id);
+    for line in data.lines() {
+        message.push_str("data: ");
+        message.push_str(line);
+        message.push('\n');
+    }
+    message.push('\n');
+    Bytes::from(message)
+}
+
+pub fn topic_event(topic: Id<Topic>) -> Bytes {
+    format_event("topic", topic.value(), &json!({ "topic_id": topic }).to_string())
+}
+
+pub fn post_event(post: Id<Post>, html: &str) -> Bytes {
+    format_event("post", post.value(), html)
+}
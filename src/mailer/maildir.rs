@@ -0,0 +1,60 @@
+// Delivers emails into a local maildir (https://cr.yp.to/proto/maildir.html) instead of sending
+// them anywhere, which any mail client can open during development and tests can read back
+
+use super::{Email, Mailer, MailerError};
+use lettre::SendableEmail;
+use lettre_email::EmailBuilder;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub struct MaildirMailer {
+    from: String,
+    directory: PathBuf,
+    delivery_count: AtomicUsize,
+}
+
+impl MaildirMailer {
+    pub fn new<P: Into<PathBuf>>(from: String, directory: P) -> Result<MaildirMailer, MailerError> {
+        let directory = directory.into();
+        for subdirectory in &["tmp", "new", "cur"] {
+            std::fs::create_dir_all(directory.join(subdirectory))?;
+        }
+        Ok(MaildirMailer {
+            from,
+            directory,
+            delivery_count: AtomicUsize::new(0),
+        })
+    }
+
+    fn unique_name(&self) -> String {
+        let timestamp = chrono::Utc::now();
+        let delivery = self.delivery_count.fetch_add(1, Ordering::SeqCst);
+        format!(
+            "{}.M{}P{}Q{}.ryob",
+            timestamp.timestamp(),
+            timestamp.timestamp_subsec_micros(),
+            std::process::id(),
+            delivery
+        )
+    }
+}
+
+impl Mailer for MaildirMailer {
+    fn send(&self, email: &Email) -> Result<(), MailerError> {
+        let message: SendableEmail = EmailBuilder::new()
+            .from(self.from.as_str())
+            .to(email.to.as_str())
+            .subject(email.subject.as_str())
+            .alternative(email.html_body.as_str(), email.text_body.as_str())
+            .build()
+            .map_err(|err| MailerError::InvalidEmail(format!("{}", err)))?
+            .into();
+
+        // Writing into tmp and renaming into new means readers never see a partial message
+        let name = self.unique_name();
+        let temporary_path = self.directory.join("tmp").join(&name);
+        std::fs::write(&temporary_path, message.message_to_string()?)?;
+        std::fs::rename(&temporary_path, self.directory.join("new").join(&name))?;
+        Ok(())
+    }
+}
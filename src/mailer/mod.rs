@@ -0,0 +1,115 @@
+// Outbound email
+//
+// Emails are rendered from the `emails/subject/<name>`, `emails/text/<name>` and
+// `emails/html/<name>` templates, in the locale of the recipient's preferences in the data or else
+// the locale given. They are queued in the outgoing_emails table and delivered by a background
+// worker (see queue.rs) through whichever Mailer backend is configured:
+//
+//   RYOB_MAILER          "smtp" or "maildir" (default "maildir")
+//   RYOB_MAIL_FROM       the From address (default "ryob@localhost")
+//   RYOB_BASE_URL        prefixed to links in emails (default "http://127.0.0.1:8088")
+//   RYOB_MAILDIR         the maildir backend's directory (default "./mail")
+//   RYOB_SMTP_HOST       the SMTP server (required for "smtp")
+//   RYOB_SMTP_PORT       defaults to 465 for "tls", 587 for "starttls" and 25 for "none"
+//   RYOB_SMTP_SECURITY   "tls", "starttls" or "none" (default "tls")
+//   RYOB_SMTP_USER       optional SMTP credentials
+//   RYOB_SMTP_PASSWORD
+
+pub mod maildir;
+pub mod queue;
+pub mod smtp;
+
+use crate::database::types::*;
+use crate::models::outgoing_email::OutgoingEmail;
+use crate::themes::Themes;
+use crate::utils::errors::RyobError;
+use actix_web::http::StatusCode;
+use serde::Serialize;
+
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+}
+
+#[derive(Debug)]
+pub enum MailerError {
+    InvalidConfiguration(String),
+    InvalidEmail(String),
+    Transport(String),
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for MailerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MailerError::InvalidConfiguration(err) => write!(f, "Invalid mailer configuration: {}", err),
+            MailerError::InvalidEmail(err) => write!(f, "Invalid email: {}", err),
+            MailerError::Transport(err) => write!(f, "{}", err),
+            MailerError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<std::io::Error> for MailerError {
+    fn from(error: std::io::Error) -> MailerError {
+        MailerError::Io(error)
+    }
+}
+
+impl From<MailerError> for RyobError {
+    fn from(error: MailerError) -> RyobError {
+        RyobError::from_display(StatusCode::INTERNAL_SERVER_ERROR, error)
+    }
+}
+
+pub trait Mailer: Send + Sync {
+    fn send(&self, email: &Email) -> Result<(), MailerError>;
+}
+
+fn env_or(name: &str, default: &str) -> String {
+    std::env::var(name).unwrap_or_else(|_| default.to_owned())
+}
+
+pub fn from_env() -> Result<Box<dyn Mailer>, MailerError> {
+    let from = env_or("RYOB_MAIL_FROM", "ryob@localhost");
+    match env_or("RYOB_MAILER", "maildir").as_str() {
+        "maildir" => Ok(Box::new(maildir::MaildirMailer::new(from, env_or("RYOB_MAILDIR", "./mail"))?)),
+        "smtp" => {
+            let host = std::env::var("RYOB_SMTP_HOST").map_err(|_| MailerError::InvalidConfiguration("RYOB_SMTP_HOST is not set".to_owned()))?;
+            let security = env_or("RYOB_SMTP_SECURITY", "tls").parse::<smtp::SmtpSecurity>()?;
+            let port = match std::env::var("RYOB_SMTP_PORT") {
+                Ok(port) => port
+                    .parse::<u16>()
+                    .map_err(|_| MailerError::InvalidConfiguration(format!("Bad RYOB_SMTP_PORT \"{}\"", port)))?,
+                Err(_) => security.default_port(),
+            };
+            let credentials = match (std::env::var("RYOB_SMTP_USER"), std::env::var("RYOB_SMTP_PASSWORD")) {
+                (Ok(user), Ok(password)) => Some((user, password)),
+                _ => None,
+            };
+            Ok(Box::new(smtp::SmtpMailer::new(from, &host, port, security, credentials)?))
+        }
+        other => Err(MailerError::InvalidConfiguration(format!("Unknown mailer \"{}\"", other))),
+    }
+}
+
+pub fn absolute_url(path: &str) -> String {
+    format!("{}{}", env_or("RYOB_BASE_URL", "http://127.0.0.1:8088").trim_end_matches('/'), path)
+}
+
+pub fn render<T: Serialize>(hb: &Themes, locale: &str, template: &str, to: &str, data: &T) -> Result<Email, RyobError> {
+    Ok(Email {
+        to: to.to_owned(),
+        subject: hb.render_in(&format!("emails/subject/{}", template), data, locale)?.trim().to_owned(),
+        text_body: hb.render_in(&format!("emails/text/{}", template), data, locale)?,
+        html_body: hb.render_in(&format!("emails/html/{}", template), data, locale)?,
+    })
+}
+
+pub fn enqueue<T: Serialize>(connection: &DatabaseConnection, hb: &Themes, locale: &str, template: &str, to: &str, data: &T) -> Result<(), RyobError> {
+    let email = render(hb, locale, template, to, data)?;
+    OutgoingEmail::enqueue(connection, &email)?;
+    Ok(())
+}
@@ -0,0 +1,40 @@
+// Background delivery of the outgoing_emails queue
+
+use super::Mailer;
+use crate::database::types::*;
+use crate::models::outgoing_email::{OutgoingEmail, OutgoingEmailError};
+use crate::utils::queue::{self, DeliveryQueue};
+use std::sync::Arc;
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+struct MailQueue {
+    mailer: Arc<dyn Mailer>,
+}
+
+impl DeliveryQueue for MailQueue {
+    type Item = OutgoingEmail;
+    type Error = OutgoingEmailError;
+
+    const NAME: &'static str = "queued email";
+
+    fn claim_due(&self, connection: &DatabaseConnection, limit: i64) -> Result<Vec<OutgoingEmail>, OutgoingEmailError> {
+        OutgoingEmail::claim_due(connection, limit)
+    }
+
+    fn deliver(&self, connection: &DatabaseConnection, email: &OutgoingEmail) -> Result<(), OutgoingEmailError> {
+        match self.mailer.send(&email.to_email()) {
+            Ok(()) => email.mark_sent(connection),
+            Err(err) => email.mark_failed(connection, &format!("{}", err)),
+        }
+    }
+
+    fn describe(&self, email: &OutgoingEmail) -> String {
+        format!("email {:?}", email.id)
+    }
+}
+
+pub fn spawn_worker(pool: DatabasePool, mailer: Arc<dyn Mailer>) -> std::thread::JoinHandle<()> {
+    queue::spawn_worker(pool, POLL_INTERVAL, move || MailQueue { mailer })
+}
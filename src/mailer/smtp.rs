@@ -0,0 +1,81 @@
+use super::{Email, Mailer, MailerError};
+use lettre::smtp::authentication::Credentials;
+use lettre::{ClientSecurity, ClientTlsParameters, SmtpClient, Transport};
+use lettre_email::EmailBuilder;
+
+#[derive(Clone, Copy)]
+pub enum SmtpSecurity {
+    Tls,
+    StartTls,
+    None,
+}
+
+impl SmtpSecurity {
+    pub fn default_port(self) -> u16 {
+        match self {
+            SmtpSecurity::Tls => lettre::smtp::SUBMISSIONS_PORT,
+            SmtpSecurity::StartTls => lettre::smtp::SUBMISSION_PORT,
+            SmtpSecurity::None => lettre::smtp::SMTP_PORT,
+        }
+    }
+}
+
+impl std::str::FromStr for SmtpSecurity {
+    type Err = MailerError;
+
+    fn from_str(value: &str) -> Result<SmtpSecurity, MailerError> {
+        match value {
+            "tls" => Ok(SmtpSecurity::Tls),
+            "starttls" => Ok(SmtpSecurity::StartTls),
+            "none" => Ok(SmtpSecurity::None),
+            other => Err(MailerError::InvalidConfiguration(format!("Unknown SMTP security \"{}\"", other))),
+        }
+    }
+}
+
+pub struct SmtpMailer {
+    from: String,
+    client: SmtpClient,
+}
+
+impl SmtpMailer {
+    pub fn new(from: String, host: &str, port: u16, security: SmtpSecurity, credentials: Option<(String, String)>) -> Result<SmtpMailer, MailerError> {
+        let tls_parameters = || -> Result<ClientTlsParameters, MailerError> {
+            let connector = native_tls::TlsConnector::new().map_err(|err| MailerError::InvalidConfiguration(format!("{}", err)))?;
+            Ok(ClientTlsParameters::new(host.to_owned(), connector))
+        };
+
+        let client_security = match security {
+            SmtpSecurity::Tls => ClientSecurity::Wrapper(tls_parameters()?),
+            SmtpSecurity::StartTls => ClientSecurity::Required(tls_parameters()?),
+            SmtpSecurity::None => ClientSecurity::None,
+        };
+
+        let mut client = SmtpClient::new((host, port), client_security).map_err(|err| MailerError::InvalidConfiguration(format!("{}", err)))?;
+        if let Some((user, password)) = credentials {
+            client = client.credentials(Credentials::new(user, password));
+        }
+
+        Ok(SmtpMailer { from, client })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&self, email: &Email) -> Result<(), MailerError> {
+        let message = EmailBuilder::new()
+            .from(self.from.as_str())
+            .to(email.to.as_str())
+            .subject(email.subject.as_str())
+            .alternative(email.html_body.as_str(), email.text_body.as_str())
+            .build()
+            .map_err(|err| MailerError::InvalidEmail(format!("{}", err)))?;
+
+        // Connections are not reused, so each send gets a fresh transport
+        self.client
+            .clone()
+            .transport()
+            .send(message.into())
+            .map_err(|err| MailerError::Transport(format!("{}", err)))?;
+        Ok(())
+    }
+}
@@ -8,37 +8,265 @@ extern crate serde_json;
 
 pub mod controllers;
 pub mod database;
+pub mod helpers;
+pub mod i18n;
+pub mod live;
+pub mod mailer;
 pub mod models;
+pub mod retention;
 pub mod schema;
+pub mod spam;
+pub mod storage;
+pub mod themes;
 pub mod utils;
+pub mod webhooks;
 
 use actix_session::CookieSession;
-use actix_web::{web, App, HttpServer};
+use actix_web::cookie::SameSite;
+use actix_web::dev::{Service, ServiceRequest};
+use actix_web::http::header;
+use actix_web::{web, App, HttpResponse, HttpServer};
 use database::types::{DatabaseManager, DatabasePool};
+use futures::future::{self, Either};
+use std::sync::Arc;
+use utils::errors::ApiError;
 
 fn main() -> std::io::Result<()> {
     env_logger::init();
-    let manager = DatabaseManager::new(std::env::var("DATABASE_URL").expect("DATABASE_URL"));
+    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL");
+    let manager = DatabaseManager::new(database_url.clone());
     let pool = DatabasePool::builder().build(manager).expect("Failed to create connection pool");
-    let mut handlebars = handlebars::Handlebars::new();
-    handlebars
-        .register_templates_directory(".html", "./static/templates")
-        .expect("Failed to load templates");
+    let catalog = i18n::Catalog::from_env().expect("Failed to load locales");
+    let themes = themes::Themes::from_env(&pool, Arc::new(catalog)).expect("Failed to load themes");
+    let connection = pool.get().expect("Failed to connect to database");
+    if let Some(name) = models::setting::Setting::get(&connection, models::setting::DEFAULT_THEME).expect("Failed to read default theme") {
+        themes.set_default_theme(&name);
+    }
+    drop(connection);
 
-    let handlebars_data = web::Data::new(handlebars);
+    let mailer = mailer::from_env().expect("Failed to configure mailer");
+    mailer::queue::spawn_worker(pool.clone(), Arc::from(mailer));
+    webhooks::queue::spawn_worker(pool.clone());
+    retention::spawn_worker(pool.clone());
+
+    let trusted_proxies = web::Data::new(utils::client_ip::TrustedProxies::from_env().expect("Failed to read RYOB_TRUSTED_PROXIES"));
+    let themes_data = web::Data::new(themes);
+    let storage_data = web::Data::new(storage::from_env().expect("Failed to configure storage"));
+    let spam_checks = web::Data::new(spam::Pipeline::with_heuristics());
+    let broker = live::Broker::default();
+    live::listener::spawn(database_url, pool.clone(), themes_data.clone(), broker.clone());
+    if themes_data.is_dev_mode() {
+        themes::watcher::spawn(themes_data.clone());
+    }
 
     HttpServer::new(move || {
+        let template_errors = themes_data.clone();
+        let ban_pool = pool.clone();
+        let ban_proxies = trusted_proxies.clone();
         App::new()
-            .wrap(CookieSession::signed(&[0; 32]).secure(false))
+            .wrap(
+                CookieSession::signed(&utils::signing::derive_key("session"))
+                    .secure(false)
+                    .same_site(SameSite::Strict),
+            )
+            .wrap_fn(move |request, service| match cross_site_response(&request) {
+                Some(response) => Either::A(future::ok(request.into_response(response))),
+                None => Either::B(service.call(request)),
+            })
+            .wrap_fn(move |request, service| match template_errors.template_error() {
+                Some(error) => Either::A(future::ok(request.into_response(themes::template_error_page(&error)))),
+                None => Either::B(service.call(request)),
+            })
+            .wrap_fn(move |request, service| match ip_ban_response(&ban_pool, &ban_proxies, &request) {
+                Some(response) => Either::A(future::ok(request.into_response(response))),
+                None => Either::B(service.call(request)),
+            })
             .data(pool.clone())
-            .register_data(handlebars_data.clone())
+            .data(broker.clone())
+            .data(web::JsonConfig::default().error_handler(|error, _| ApiError::bad_request(vec![format!("{}", error)]).into()))
+            .register_data(themes_data.clone())
+            .register_data(storage_data.clone())
+            .register_data(trusted_proxies.clone())
+            .register_data(spam_checks.clone())
             .service(actix_files::Files::new("/styles", "static/styles/"))
+            .service(actix_files::Files::new("/scripts", "static/scripts/"))
+            .configure(|config| themes_data.configure(config))
+            .route("/attachments/{attachment_id}", web::get().to(crate::controllers::attachments::get))
+            .route(
+                "/attachments/{attachment_id}/thumbnails/{size}",
+                web::get().to(crate::controllers::attachments::thumbnail),
+            )
+            .route("/banners/new", web::get().to(crate::controllers::banners::new))
+            .route("/banners/new", web::post().to_async(crate::controllers::banners::create))
+            .route("/banners/{banner_id}", web::get().to(crate::controllers::banners::get))
             .route("/", web::get().to(crate::controllers::index::get))
+            .route("/feed.atom", web::get().to(crate::controllers::feeds::topics))
+            .route("/feed.rss", web::get().to(crate::controllers::feeds::topics_rss))
+            .route("/live", web::get().to(crate::controllers::live::topics))
+            .route("/topics/{topic_id}", web::get().to(crate::controllers::topics::topic::get))
+            .route("/topics/{topic_id}/feed.atom", web::get().to(crate::controllers::feeds::topic))
+            .route("/topics/{topic_id}/feed.rss", web::get().to(crate::controllers::feeds::topic_rss))
+            .route("/topics/{topic_id}/live", web::get().to(crate::controllers::live::topic))
+            .route("/topics/{topic_id}/report", web::get().to(crate::controllers::reports::new_topic))
+            .route("/topics/{topic_id}/report", web::post().to(crate::controllers::reports::create_topic))
+            .route("/posts/{post_id}", web::get().to(crate::controllers::topics::topic::post))
+            .route("/posts/{post_id}/report", web::get().to(crate::controllers::reports::new_post))
+            .route("/posts/{post_id}/report", web::post().to(crate::controllers::reports::create_post))
             .route("/users/register", web::get().to(crate::controllers::users::register::get))
             .route("/users/register", web::post().to(crate::controllers::users::register::post))
             .route("/users/login", web::get().to(crate::controllers::users::login::get))
             .route("/users/login", web::post().to(crate::controllers::users::login::post))
+            .route("/users/login/two_factor", web::get().to(crate::controllers::users::two_factor_login::get))
+            .route("/users/login/two_factor", web::post().to(crate::controllers::users::two_factor_login::post))
+            .route("/users/two_factor", web::get().to(crate::controllers::users::two_factor::get))
+            .route("/users/two_factor/enroll", web::post().to(crate::controllers::users::two_factor::enroll))
+            .route("/users/two_factor/confirm", web::post().to(crate::controllers::users::two_factor::confirm))
+            .route(
+                "/users/two_factor/recovery_codes",
+                web::post().to(crate::controllers::users::two_factor::recovery_codes),
+            )
+            .route("/users/two_factor/disable", web::post().to(crate::controllers::users::two_factor::disable))
+            .route("/users/tokens", web::get().to(crate::controllers::users::api_tokens::get))
+            .route("/users/tokens", web::post().to(crate::controllers::users::api_tokens::post))
+            .route("/users/tokens/{token_id}/revoke", web::post().to(crate::controllers::users::api_tokens::revoke))
+            .route("/users/profile", web::get().to(crate::controllers::users::profile::get))
+            .route("/users/profile/avatar", web::post().to_async(crate::controllers::users::profile::avatar))
+            .route(
+                "/users/profile/avatar/remove",
+                web::post().to(crate::controllers::users::profile::remove_avatar),
+            )
+            .route("/users/profile/signature", web::post().to(crate::controllers::users::profile::signature))
+            .route("/users/settings", web::get().to(crate::controllers::users::settings::get))
+            .route("/users/settings", web::post().to(crate::controllers::users::settings::post))
+            .route("/users/{user_id}/avatar", web::get().to(crate::controllers::users::profile::get_avatar))
+            .route("/users/verify", web::get().to(crate::controllers::users::verify_email::get))
+            .route("/users/verify", web::post().to(crate::controllers::users::verify_email::post))
+            .route("/users/password/forgot", web::get().to(crate::controllers::users::forgot_password::get))
+            .route("/users/password/forgot", web::post().to(crate::controllers::users::forgot_password::post))
+            .route("/users/password/reset", web::get().to(crate::controllers::users::reset_password::get))
+            .route("/users/password/reset", web::post().to(crate::controllers::users::reset_password::post))
+            .route("/messages", web::get().to(crate::controllers::messages::inbox::get))
+            .route("/messages/sent", web::get().to(crate::controllers::messages::outbox::get))
+            .route("/messages/new", web::get().to(crate::controllers::messages::compose::get))
+            .route("/messages/new", web::post().to(crate::controllers::messages::compose::post))
+            .route("/messages/blocks", web::get().to(crate::controllers::messages::blocks::get))
+            .route("/messages/blocks", web::post().to(crate::controllers::messages::blocks::post))
+            .route(
+                "/messages/blocks/{user_id}/remove",
+                web::post().to(crate::controllers::messages::blocks::remove),
+            )
+            .route("/messages/{conversation_id}", web::get().to(crate::controllers::messages::conversation::get))
+            .route("/messages/{conversation_id}", web::post().to(crate::controllers::messages::conversation::post))
+            .route("/messages/{conversation_id}/leave", web::post().to(crate::controllers::messages::leave::post))
+            .route("/moderation/ips", web::get().to(crate::controllers::moderation::ips::get))
+            .route("/moderation/pending", web::get().to(crate::controllers::moderation::pending::list))
+            .route(
+                "/moderation/pending/{post_id}/approve",
+                web::post().to(crate::controllers::moderation::pending::approve),
+            )
+            .route(
+                "/moderation/pending/{post_id}/reject",
+                web::post().to(crate::controllers::moderation::pending::reject),
+            )
+            .route("/moderation/reports", web::get().to(crate::controllers::moderation::reports::list))
+            .route(
+                "/moderation/reports/{report_id}/resolve",
+                web::post().to(crate::controllers::moderation::reports::resolve),
+            )
+            .route(
+                "/moderation/reports/{report_id}/dismiss",
+                web::post().to(crate::controllers::moderation::reports::dismiss),
+            )
+            .configure(crate::controllers::api::v1::configure)
+            .route("/admin", web::get().to(crate::controllers::admin::index::get))
+            .route("/admin/settings", web::post().to(crate::controllers::admin::settings::post))
+            .route("/admin/settings/theme", web::post().to(crate::controllers::admin::settings::theme))
+            .route(
+                "/admin/settings/ip_retention",
+                web::post().to(crate::controllers::admin::settings::ip_retention),
+            )
+            .route("/admin/settings/probation", web::post().to(crate::controllers::admin::settings::probation))
+            .route("/admin/roles", web::post().to(crate::controllers::admin::roles::post))
+            .route("/admin/audit", web::get().to(crate::controllers::admin::audit::list))
+            .route("/admin/audit/export.csv", web::get().to(crate::controllers::admin::audit::export_csv))
+            .route("/admin/audit/export.json", web::get().to(crate::controllers::admin::audit::export_json))
+            .route("/admin/banners", web::get().to(crate::controllers::admin::banners::list))
+            .route("/admin/banners", web::post().to_async(crate::controllers::admin::banners::create))
+            .route("/admin/banners/{banner_id}/status", web::post().to(crate::controllers::admin::banners::status))
+            .route("/admin/banners/{banner_id}/weight", web::post().to(crate::controllers::admin::banners::weight))
+            .route("/admin/banners/{banner_id}/delete", web::post().to(crate::controllers::admin::banners::delete))
+            .route("/admin/filters", web::get().to(crate::controllers::admin::filters::list))
+            .route("/admin/filters", web::post().to(crate::controllers::admin::filters::create))
+            .route("/admin/filters/test", web::post().to(crate::controllers::admin::filters::test))
+            .route("/admin/filters/{filter_id}/delete", web::post().to(crate::controllers::admin::filters::delete))
+            .route("/admin/ip_bans", web::get().to(crate::controllers::admin::ip_bans::list))
+            .route("/admin/ip_bans", web::post().to(crate::controllers::admin::ip_bans::create))
+            .route("/admin/ip_bans/{ip_ban_id}/delete", web::post().to(crate::controllers::admin::ip_bans::delete))
+            .route("/admin/spam", web::get().to(crate::controllers::admin::spam::list))
+            .route("/admin/spam/thresholds", web::post().to(crate::controllers::admin::spam::thresholds))
+            .route("/admin/users", web::get().to(crate::controllers::admin::users::find))
+            .route("/admin/users/{user_id}", web::get().to(crate::controllers::admin::users::get))
+            .route("/admin/users/{user_id}/bans", web::post().to(crate::controllers::admin::users::ban))
+            .route(
+                "/admin/users/{user_id}/bans/{ban_id}/lift",
+                web::post().to(crate::controllers::admin::users::lift),
+            )
+            .route("/admin/webhooks", web::get().to(crate::controllers::admin::webhooks::list))
+            .route("/admin/webhooks", web::post().to(crate::controllers::admin::webhooks::create))
+            .route("/admin/webhooks/{webhook_id}", web::get().to(crate::controllers::admin::webhooks::get))
+            .route("/admin/webhooks/{webhook_id}/ping", web::post().to(crate::controllers::admin::webhooks::ping))
+            .route(
+                "/admin/webhooks/{webhook_id}/delete",
+                web::post().to(crate::controllers::admin::webhooks::delete),
+            )
     })
     .bind("127.0.0.1:8088")?
     .run()
 }
+
+// Static files served without looking at the database, which would cost a query for every stylesheet
+const STATIC_PATHS: [&str; 3] = ["/styles/", "/scripts/", "/themes/"];
+
+// Requests from banned networks get nothing but the reason. If the bans can't be read the
+// request goes ahead rather than taking the whole site down.
+fn ip_ban_response(pool: &DatabasePool, proxies: &utils::client_ip::TrustedProxies, request: &ServiceRequest) -> Option<HttpResponse> {
+    if STATIC_PATHS.iter().any(|prefix| request.path().starts_with(prefix)) {
+        return None;
+    }
+    let ip = proxies.resolve(request.peer_addr(), request.headers())?;
+    let ban = pool
+        .get()
+        .map_err(|err| models::ip_ban::IpBanError::Unknown(format!("{}", err)))
+        .and_then(|connection| models::ip_ban::IpBan::matching(&connection, ip));
+    match ban {
+        Ok(ban) => ban.map(|ban| HttpResponse::Forbidden().content_type("text/plain; charset=utf-8").body(format!("{}", ban))),
+        Err(err) => {
+            log::error!("Failed to check IP bans for {}: {}", ip, err);
+            None
+        }
+    }
+}
+
+// Browsers say which page a form was sent from, so state-changing requests made from other sites
+// can be turned away before they reach a handler, on top of the session cookie not being sent with
+// them. Clients that send neither header, like scripts using API tokens, aren't affected.
+fn cross_site_response(request: &ServiceRequest) -> Option<HttpResponse> {
+    if request.method().is_safe() {
+        return None;
+    }
+    let source = request.headers().get(header::ORIGIN).or_else(|| request.headers().get(header::REFERER))?;
+    let source_host = source
+        .to_str()
+        .ok()
+        .and_then(|source| source.split_once("://"))
+        .map(|(_, rest)| rest.split('/').next().unwrap_or(rest));
+    let host = request.connection_info().host().to_owned();
+    if source_host.is_some_and(|source_host| source_host.eq_ignore_ascii_case(&host)) {
+        return None;
+    }
+    Some(
+        HttpResponse::Forbidden()
+            .content_type("text/plain; charset=utf-8")
+            .body("Requests from other sites are not accepted"),
+    )
+}
@@ -0,0 +1,194 @@
+use crate::database::types::*;
+use crate::models::user::User;
+use crate::schema::api_tokens;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use crate::utils::signing;
+use chrono;
+use diesel::result::Error as DieselError;
+use diesel::{Insertable, Queryable};
+use log::info;
+use serde::Serialize;
+
+// Tokens are handed out once as "ryob_<id>_<secret>" and only a hash of the secret is kept.
+// Unlike emailed links they aren't signed, so rotating RYOB_SECRET_KEY doesn't break scripts.
+const TOKEN_PREFIX: &str = "ryob_";
+
+// Only throttles how often last_used_at is written, a busy bot would otherwise write on every call
+const LAST_USED_PRECISION_SECONDS: i64 = 60;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scope {
+    // Create, edit and delete the user's own topics and posts
+    Write,
+    // Use the user's moderator privileges on other people's content
+    Moderate,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 2] = [Scope::Write, Scope::Moderate];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::Write => "write",
+            Scope::Moderate => "moderate",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Scope> {
+        Scope::ALL.iter().copied().find(|scope| scope.as_str() == value)
+    }
+}
+
+#[derive(Queryable, Serialize)]
+pub struct ApiToken {
+    pub id: Id<ApiToken>,
+    pub user_id: Id<User>,
+    pub name: String,
+    pub scopes: Vec<String>,
+    #[serde(skip_serializing)]
+    pub secret_hash: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub revoked_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Insertable)]
+#[table_name = "api_tokens"]
+pub struct NewApiToken {
+    pub user_id: Id<User>,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub secret_hash: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug)]
+pub enum ApiTokenError {
+    InvalidToken,
+    NoSuchToken,
+    UnknownDatabaseError(DieselError),
+    Unknown(String),
+}
+
+impl std::fmt::Display for ApiTokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ApiTokenError::InvalidToken => write!(f, "Invalid API token"),
+            ApiTokenError::NoSuchToken => write!(f, "No such API token"),
+            ApiTokenError::UnknownDatabaseError(err) => write!(f, "{}", err),
+            ApiTokenError::Unknown(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<ApiTokenError> for RyobError {
+    fn from(error: ApiTokenError) -> RyobError {
+        match error {
+            ApiTokenError::InvalidToken => RyobError::from_display(actix_web::http::StatusCode::UNAUTHORIZED, error),
+            ApiTokenError::NoSuchToken => RyobError::from_display(actix_web::http::StatusCode::NOT_FOUND, error),
+            _ => RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error),
+        }
+    }
+}
+
+impl ApiToken {
+    // Returns the token itself, which can't be recovered later
+    pub fn create(connection: &DatabaseConnection, user: Id<User>, name: &str, scopes: &[Scope]) -> Result<(ApiToken, String), ApiTokenError> {
+        let secret = signing::random_string(32);
+        let new_token = NewApiToken {
+            user_id: user,
+            name: name.to_owned(),
+            scopes: scopes.iter().map(|scope| scope.as_str().to_owned()).collect(),
+            secret_hash: signing::hash(&secret),
+            created_at: chrono::Utc::now(),
+        };
+
+        let token: ApiToken = {
+            use diesel::prelude::*;
+            diesel::insert_into(api_tokens::table)
+                .values(&new_token)
+                .get_result(connection)
+                .map_err(ApiTokenError::UnknownDatabaseError)?
+        };
+
+        info!("User {:?} has created API token {:?} named \"{}\"", user, token.id, name);
+
+        let value = format!("{}{}_{}", TOKEN_PREFIX, token.id.value(), secret);
+        Ok((token, value))
+    }
+
+    pub fn authenticate(connection: &DatabaseConnection, value: &str) -> Result<ApiToken, ApiTokenError> {
+        let (token_id, secret) = value
+            .strip_prefix(TOKEN_PREFIX)
+            .and_then(|rest| rest.split_once('_'))
+            .ok_or(ApiTokenError::InvalidToken)?;
+        let token_id = token_id.parse::<i64>().map_err(|_| ApiTokenError::InvalidToken)?;
+
+        let token: ApiToken = {
+            use crate::schema::api_tokens::dsl::*;
+            use diesel::prelude::*;
+            api_tokens
+                .filter(id.eq(token_id))
+                .filter(revoked_at.is_null())
+                .first::<ApiToken>(connection)
+                .optional()
+                .map_err(ApiTokenError::UnknownDatabaseError)?
+                .ok_or(ApiTokenError::InvalidToken)?
+        };
+
+        if token.secret_hash != signing::hash(secret) {
+            return Err(ApiTokenError::InvalidToken);
+        }
+
+        let now = chrono::Utc::now();
+        if token
+            .last_used_at
+            .map(|used| now - used > chrono::Duration::seconds(LAST_USED_PRECISION_SECONDS))
+            .unwrap_or(true)
+        {
+            use crate::schema::api_tokens::dsl::*;
+            use diesel::prelude::*;
+            diesel::update(api_tokens.filter(id.eq(token.id)))
+                .set(last_used_at.eq(Some(now)))
+                .execute(connection)
+                .map_err(ApiTokenError::UnknownDatabaseError)?;
+        }
+
+        Ok(token)
+    }
+
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.iter().any(|granted| granted == scope.as_str())
+    }
+
+    pub fn for_user(connection: &DatabaseConnection, user: Id<User>) -> Result<Vec<ApiToken>, ApiTokenError> {
+        use crate::schema::api_tokens::dsl::*;
+        use diesel::prelude::*;
+        api_tokens
+            .filter(user_id.eq(user))
+            .filter(revoked_at.is_null())
+            .order(created_at.desc())
+            .load::<ApiToken>(connection)
+            .map_err(ApiTokenError::UnknownDatabaseError)
+    }
+
+    pub fn revoke(connection: &DatabaseConnection, user: Id<User>, token: Id<ApiToken>) -> Result<(), ApiTokenError> {
+        let updated = {
+            use crate::schema::api_tokens::dsl::*;
+            use diesel::prelude::*;
+            diesel::update(api_tokens.filter(id.eq(token)).filter(user_id.eq(user)).filter(revoked_at.is_null()))
+                .set(revoked_at.eq(Some(chrono::Utc::now())))
+                .execute(connection)
+                .map_err(ApiTokenError::UnknownDatabaseError)?
+        };
+
+        if updated == 0 {
+            return Err(ApiTokenError::NoSuchToken);
+        }
+
+        info!("User {:?} has revoked API token {:?}", user, token);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,350 @@
+use crate::database::types::*;
+use crate::models::post::Post;
+use crate::models::user::User;
+use crate::schema::{attachments, stored_files, thumbnails};
+use crate::storage::{Storage, StorageError};
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use crate::utils::uploads::UploadError;
+use crate::utils::{images, sniff};
+use chrono;
+use diesel::result::Error as DieselError;
+use diesel::{Insertable, Queryable};
+use log::info;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+pub const MAX_ATTACHMENT_SIZE: usize = 8 * 1024 * 1024;
+pub const MAX_ATTACHMENTS_PER_POST: i64 = 10;
+const MAX_FILE_NAME_SIZE: usize = 200;
+
+pub const ALLOWED_CONTENT_TYPES: [&str; 6] = [
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "application/pdf",
+    "text/plain; charset=utf-8",
+];
+
+// The contents of an upload, shared by every attachment with the same bytes
+#[derive(Clone, Queryable, Serialize)]
+pub struct StoredFile {
+    pub id: Id<StoredFile>,
+    pub content_hash: String,
+    pub content_type: String,
+    pub size: i64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Insertable)]
+#[table_name = "stored_files"]
+pub struct NewStoredFile {
+    pub content_hash: String,
+    pub content_type: String,
+    pub size: i64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Queryable, Serialize)]
+pub struct Attachment {
+    pub id: Id<Attachment>,
+    pub post_id: Id<Post>,
+    pub file_id: Id<StoredFile>,
+    pub file_name: String,
+    pub created_by: Id<User>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Insertable)]
+#[table_name = "attachments"]
+pub struct NewAttachment {
+    pub post_id: Id<Post>,
+    pub file_id: Id<StoredFile>,
+    pub file_name: String,
+    pub created_by: Id<User>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+// Scaled down copies of an image attachment, one for each of images::THUMBNAIL_SIZES
+#[derive(Queryable, Insertable, Serialize)]
+#[table_name = "thumbnails"]
+pub struct Thumbnail {
+    pub attachment_id: Id<Attachment>,
+    pub size: String,
+    pub file_id: Id<StoredFile>,
+    pub width: i32,
+    pub height: i32,
+}
+
+#[derive(Debug)]
+pub enum AttachmentError {
+    TooLarge,
+    TooManyAttachments,
+    UnsupportedType,
+    NoFile,
+    NoSuchAttachment,
+    NoSuchThumbnail,
+    InvalidImage(String),
+    Upload(String),
+    Storage(StorageError),
+    UnknownDatabaseError(DieselError),
+    Unknown(String),
+}
+
+impl std::fmt::Display for AttachmentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AttachmentError::TooLarge => write!(f, "Attachments must be at most {} MiB", MAX_ATTACHMENT_SIZE / 1024 / 1024),
+            AttachmentError::TooManyAttachments => write!(f, "Posts can have at most {} attachments", MAX_ATTACHMENTS_PER_POST),
+            AttachmentError::UnsupportedType => write!(f, "Only PNG, JPEG, GIF and WebP images, PDFs and plain text can be attached"),
+            AttachmentError::NoFile => write!(f, "No file was uploaded"),
+            AttachmentError::NoSuchAttachment => write!(f, "No such attachment"),
+            AttachmentError::NoSuchThumbnail => write!(f, "No such thumbnail"),
+            AttachmentError::InvalidImage(err) => write!(f, "The image could not be read: {}", err),
+            AttachmentError::Upload(err) => write!(f, "{}", err),
+            AttachmentError::Storage(err) => write!(f, "{}", err),
+            AttachmentError::UnknownDatabaseError(err) => write!(f, "{}", err),
+            AttachmentError::Unknown(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<DieselError> for AttachmentError {
+    fn from(error: DieselError) -> AttachmentError {
+        AttachmentError::UnknownDatabaseError(error)
+    }
+}
+
+impl From<StorageError> for AttachmentError {
+    fn from(error: StorageError) -> AttachmentError {
+        AttachmentError::Storage(error)
+    }
+}
+
+impl From<image::ImageError> for AttachmentError {
+    fn from(error: image::ImageError) -> AttachmentError {
+        AttachmentError::InvalidImage(format!("{}", error))
+    }
+}
+
+impl From<UploadError> for AttachmentError {
+    fn from(error: UploadError) -> AttachmentError {
+        match error {
+            UploadError::TooLarge(_) => AttachmentError::TooLarge,
+            UploadError::TooManyFiles(_) => AttachmentError::TooManyAttachments,
+            UploadError::Invalid(err) => AttachmentError::Upload(format!("{}", err)),
+        }
+    }
+}
+
+impl From<AttachmentError> for RyobError {
+    fn from(error: AttachmentError) -> RyobError {
+        match error {
+            AttachmentError::TooLarge => RyobError::from_display(actix_web::http::StatusCode::PAYLOAD_TOO_LARGE, error),
+            AttachmentError::TooManyAttachments => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            AttachmentError::UnsupportedType => RyobError::from_display(actix_web::http::StatusCode::UNSUPPORTED_MEDIA_TYPE, error),
+            AttachmentError::NoFile => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            AttachmentError::NoSuchAttachment => RyobError::from_display(actix_web::http::StatusCode::NOT_FOUND, error),
+            AttachmentError::NoSuchThumbnail => RyobError::from_display(actix_web::http::StatusCode::NOT_FOUND, error),
+            AttachmentError::InvalidImage(_) => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            AttachmentError::Upload(_) => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            _ => RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error),
+        }
+    }
+}
+
+// Keeps only the last path component and drops characters that could break out of a header
+pub fn sanitize_file_name(file_name: &str) -> String {
+    let base_name = file_name.rsplit(['/', '\\']).next().unwrap_or("");
+    let cleaned: String = base_name.chars().filter(|c| !c.is_control() && *c != '"').take(MAX_FILE_NAME_SIZE).collect();
+    let cleaned = cleaned.trim().trim_start_matches('.');
+    if cleaned.is_empty() {
+        "attachment".to_owned()
+    } else {
+        cleaned.to_owned()
+    }
+}
+
+impl StoredFile {
+    // The file is written before its row so a row never points at missing contents
+    pub fn store(connection: &DatabaseConnection, storage: &dyn Storage, contents: &[u8], content_type: &str) -> Result<StoredFile, AttachmentError> {
+        let hash = format!("{:x}", Sha256::digest(contents));
+        storage.put(&hash, contents)?;
+
+        let new_file = NewStoredFile {
+            content_hash: hash.clone(),
+            content_type: content_type.to_owned(),
+            size: contents.len() as i64,
+            created_at: chrono::Utc::now(),
+        };
+
+        use diesel::prelude::*;
+        diesel::insert_into(stored_files::table)
+            .values(&new_file)
+            .on_conflict(stored_files::content_hash)
+            .do_nothing()
+            .execute(connection)?;
+        Ok(stored_files::table
+            .filter(stored_files::content_hash.eq(&hash))
+            .first::<StoredFile>(connection)?)
+    }
+
+    // Avatars are always re-encoded, so only the cropped image is kept and none of the upload
+    pub fn store_avatar(connection: &DatabaseConnection, storage: &dyn Storage, contents: &[u8]) -> Result<StoredFile, AttachmentError> {
+        if contents.is_empty() {
+            return Err(AttachmentError::NoFile);
+        }
+        let (image, _) = images::decode(contents)?;
+        let (content_type, avatar) = images::avatar(&image)?;
+        StoredFile::store(connection, storage, &avatar, content_type)
+    }
+
+    pub fn by_id(connection: &DatabaseConnection, file: Id<StoredFile>) -> Result<StoredFile, AttachmentError> {
+        use diesel::prelude::*;
+        Ok(stored_files::table.find(file).first::<StoredFile>(connection)?)
+    }
+}
+
+impl Attachment {
+    pub fn create(
+        connection: &DatabaseConnection,
+        storage: &dyn Storage,
+        creator: Id<User>,
+        post: Id<Post>,
+        file_name: &str,
+        contents: &[u8],
+    ) -> Result<(Attachment, StoredFile), AttachmentError> {
+        if contents.is_empty() {
+            return Err(AttachmentError::NoFile);
+        }
+        if contents.len() > MAX_ATTACHMENT_SIZE {
+            return Err(AttachmentError::TooLarge);
+        }
+        if Attachment::count_for_post(connection, post)? >= MAX_ATTACHMENTS_PER_POST {
+            return Err(AttachmentError::TooManyAttachments);
+        }
+        let content_type = sniff::content_type(contents)
+            .filter(|content_type| ALLOWED_CONTENT_TYPES.contains(content_type))
+            .ok_or(AttachmentError::UnsupportedType)?;
+
+        // Images are decoded up front, both to refuse ones that aren't really images and to
+        // publish them without their metadata
+        let (content_type, contents, image_thumbnails) = if sniff::is_image(content_type) {
+            let (image, orientation) = images::decode(contents)?;
+            let (content_type, stripped) = images::strip_metadata(content_type, contents, &image, orientation)?;
+            (content_type, stripped, images::thumbnails(&image)?)
+        } else {
+            (content_type, contents.to_vec(), vec![])
+        };
+
+        let file = StoredFile::store(connection, storage, &contents, content_type)?;
+        let new_attachment = NewAttachment {
+            post_id: post,
+            file_id: file.id,
+            file_name: sanitize_file_name(file_name),
+            created_by: creator,
+            created_at: chrono::Utc::now(),
+        };
+
+        let attachment: Attachment = {
+            use diesel::prelude::*;
+            diesel::insert_into(attachments::table).values(&new_attachment).get_result(connection)?
+        };
+
+        for thumbnail in image_thumbnails {
+            let thumbnail_file = StoredFile::store(connection, storage, &thumbnail.contents, thumbnail.content_type)?;
+            let new_thumbnail = Thumbnail {
+                attachment_id: attachment.id,
+                size: thumbnail.size.to_owned(),
+                file_id: thumbnail_file.id,
+                width: thumbnail.width as i32,
+                height: thumbnail.height as i32,
+            };
+            use diesel::prelude::*;
+            diesel::insert_into(thumbnails::table).values(&new_thumbnail).execute(connection)?;
+        }
+
+        info!("User {:?} has attached {:?} to post {:?}", creator, attachment.id, post);
+
+        Ok((attachment, file))
+    }
+
+    pub fn by_id(connection: &DatabaseConnection, attachment: Id<Attachment>) -> Result<(Attachment, StoredFile), AttachmentError> {
+        use crate::schema::attachments::dsl::*;
+        use crate::schema::stored_files::dsl::stored_files;
+        use diesel::prelude::*;
+        attachments
+            .filter(id.eq(attachment))
+            .inner_join(stored_files)
+            .first(connection)
+            .optional()?
+            .ok_or(AttachmentError::NoSuchAttachment)
+    }
+
+    pub fn for_posts(connection: &DatabaseConnection, post_ids: &[Id<Post>]) -> Result<Vec<(Attachment, StoredFile)>, AttachmentError> {
+        use crate::schema::attachments::dsl::*;
+        use crate::schema::stored_files::dsl::stored_files;
+        use diesel::prelude::*;
+        Ok(attachments
+            .filter(post_id.eq_any(post_ids))
+            .order(id.asc())
+            .inner_join(stored_files)
+            .load(connection)?)
+    }
+
+    pub fn count_for_post(connection: &DatabaseConnection, post: Id<Post>) -> Result<i64, AttachmentError> {
+        use crate::schema::attachments::dsl::*;
+        use diesel::prelude::*;
+        Ok(attachments.filter(post_id.eq(post)).count().get_result(connection)?)
+    }
+
+    // Only the attachment goes, the stored file may be shared with other attachments
+    pub fn delete(connection: &DatabaseConnection, attachment: Id<Attachment>) -> Result<(), AttachmentError> {
+        let deleted = {
+            use crate::schema::attachments::dsl::*;
+            use diesel::prelude::*;
+            diesel::delete(attachments.filter(id.eq(attachment))).execute(connection)?
+        };
+
+        if deleted == 0 {
+            return Err(AttachmentError::NoSuchAttachment);
+        }
+
+        info!("Attachment {:?} has been deleted", attachment);
+
+        Ok(())
+    }
+
+    pub fn url(&self) -> String {
+        format!("/attachments/{}", self.id.value())
+    }
+}
+
+impl Thumbnail {
+    pub fn by_attachment(connection: &DatabaseConnection, attachment: Id<Attachment>, target_size: &str) -> Result<(Thumbnail, StoredFile), AttachmentError> {
+        use crate::schema::stored_files::dsl::stored_files;
+        use crate::schema::thumbnails::dsl::*;
+        use diesel::prelude::*;
+        thumbnails
+            .filter(attachment_id.eq(attachment))
+            .filter(size.eq(target_size))
+            .inner_join(stored_files)
+            .first(connection)
+            .optional()?
+            .ok_or(AttachmentError::NoSuchThumbnail)
+    }
+
+    pub fn for_attachments(connection: &DatabaseConnection, attachment_ids: &[Id<Attachment>], target_size: &str) -> Result<Vec<Thumbnail>, AttachmentError> {
+        use crate::schema::thumbnails::dsl::*;
+        use diesel::prelude::*;
+        Ok(thumbnails
+            .filter(attachment_id.eq_any(attachment_ids))
+            .filter(size.eq(target_size))
+            .load(connection)?)
+    }
+
+    pub fn url(&self) -> String {
+        format!("/attachments/{}/thumbnails/{}", self.attachment_id.value(), self.size)
+    }
+}
@@ -0,0 +1,199 @@
+use crate::database::types::*;
+use crate::models::user::User;
+use crate::schema::audit_log;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use chrono;
+use diesel::result::Error as DieselError;
+use diesel::{Insertable, Queryable};
+use log::info;
+use serde::Serialize;
+
+// Everything staff do with privileges members don't have. Members changing their own content
+// aren't logged.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AuditAction {
+    TopicEdit,
+    TopicDelete,
+    PostEdit,
+    PostDelete,
+    PostApprove,
+    PostReject,
+    AttachmentDelete,
+    RoleChange,
+    UserBan,
+    UserUnban,
+    IpBanCreate,
+    IpBanDelete,
+    FilterCreate,
+    FilterDelete,
+    ReportResolve,
+    ReportDismiss,
+}
+
+impl AuditAction {
+    pub const ALL: [AuditAction; 16] = [
+        AuditAction::TopicEdit,
+        AuditAction::TopicDelete,
+        AuditAction::PostEdit,
+        AuditAction::PostDelete,
+        AuditAction::PostApprove,
+        AuditAction::PostReject,
+        AuditAction::AttachmentDelete,
+        AuditAction::RoleChange,
+        AuditAction::UserBan,
+        AuditAction::UserUnban,
+        AuditAction::IpBanCreate,
+        AuditAction::IpBanDelete,
+        AuditAction::FilterCreate,
+        AuditAction::FilterDelete,
+        AuditAction::ReportResolve,
+        AuditAction::ReportDismiss,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuditAction::TopicEdit => "topic.edit",
+            AuditAction::TopicDelete => "topic.delete",
+            AuditAction::PostEdit => "post.edit",
+            AuditAction::PostDelete => "post.delete",
+            AuditAction::PostApprove => "post.approve",
+            AuditAction::PostReject => "post.reject",
+            AuditAction::AttachmentDelete => "attachment.delete",
+            AuditAction::RoleChange => "user.role",
+            AuditAction::UserBan => "user.ban",
+            AuditAction::UserUnban => "user.unban",
+            AuditAction::IpBanCreate => "ip_ban.create",
+            AuditAction::IpBanDelete => "ip_ban.delete",
+            AuditAction::FilterCreate => "filter.create",
+            AuditAction::FilterDelete => "filter.delete",
+            AuditAction::ReportResolve => "report.resolve",
+            AuditAction::ReportDismiss => "report.dismiss",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<AuditAction> {
+        AuditAction::ALL.iter().copied().find(|action| action.as_str() == value)
+    }
+
+    // What kind of thing the target id refers to
+    pub fn target_type(self) -> &'static str {
+        let name = self.as_str();
+        &name[..name.find('.').unwrap_or(name.len())]
+    }
+}
+
+// Before and after are JSON snapshots of the target, missing for targets that were created or removed
+#[derive(Clone, Queryable, Serialize)]
+pub struct AuditEntry {
+    pub id: Id<AuditEntry>,
+    pub actor_id: Id<User>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: i64,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub reason: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Insertable)]
+#[table_name = "audit_log"]
+pub struct NewAuditEntry {
+    pub actor_id: Id<User>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: i64,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub reason: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Default)]
+pub struct AuditFilter {
+    pub action: Option<AuditAction>,
+    pub actor: Option<Id<User>>,
+    pub target_type: Option<String>,
+    pub target_id: Option<i64>,
+}
+
+#[derive(Debug)]
+pub enum AuditLogError {
+    UnknownDatabaseError(DieselError),
+    Unknown(String),
+}
+
+impl std::fmt::Display for AuditLogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AuditLogError::UnknownDatabaseError(err) => write!(f, "{}", err),
+            AuditLogError::Unknown(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<DieselError> for AuditLogError {
+    fn from(error: DieselError) -> AuditLogError {
+        AuditLogError::UnknownDatabaseError(error)
+    }
+}
+
+impl From<AuditLogError> for RyobError {
+    fn from(error: AuditLogError) -> RyobError {
+        RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error)
+    }
+}
+
+impl AuditEntry {
+    // Callers should record in the same transaction as the action, so neither happens without the other
+    pub fn record(
+        connection: &DatabaseConnection,
+        actor: Id<User>,
+        action: AuditAction,
+        target_id: i64,
+        before: Option<serde_json::Value>,
+        after: Option<serde_json::Value>,
+        reason: &str,
+    ) -> Result<AuditEntry, AuditLogError> {
+        let new_entry = NewAuditEntry {
+            actor_id: actor,
+            action: action.as_str().to_owned(),
+            target_type: action.target_type().to_owned(),
+            target_id,
+            before: before.map(|value| value.to_string()),
+            after: after.map(|value| value.to_string()),
+            reason: reason.trim().to_owned(),
+            created_at: chrono::Utc::now(),
+        };
+        let entry: AuditEntry = {
+            use diesel::prelude::*;
+            diesel::insert_into(audit_log::table).values(&new_entry).get_result(connection)?
+        };
+
+        info!("User {:?} has done {} on {} {}", actor, action.as_str(), entry.target_type, target_id);
+
+        Ok(entry)
+    }
+
+    // Newest first
+    pub fn search(connection: &DatabaseConnection, filter: &AuditFilter, offset: i64, limit: i64) -> Result<Vec<(AuditEntry, User)>, AuditLogError> {
+        use crate::schema::audit_log::dsl::*;
+        use crate::schema::users::dsl::users;
+        use diesel::prelude::*;
+        let mut query = audit_log.inner_join(users).into_boxed();
+        if let Some(filter_action) = filter.action {
+            query = query.filter(action.eq(filter_action.as_str()));
+        }
+        if let Some(filter_actor) = filter.actor {
+            query = query.filter(actor_id.eq(filter_actor));
+        }
+        if let Some(filter_target_type) = &filter.target_type {
+            query = query.filter(target_type.eq(filter_target_type.clone()));
+        }
+        if let Some(filter_target_id) = filter.target_id {
+            query = query.filter(target_id.eq(filter_target_id));
+        }
+        Ok(query.order(id.desc()).offset(offset).limit(limit).load(connection)?)
+    }
+}
@@ -0,0 +1,217 @@
+use crate::database::types::*;
+use crate::models::user::User;
+use crate::schema::bans;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use chrono;
+use diesel::result::Error as DieselError;
+use diesel::{Insertable, Queryable};
+use log::info;
+use serde::Serialize;
+
+pub const MAX_REASON_SIZE: usize = 1000;
+
+// Global bans keep the user from logging in at all, posting bans leave them able to read
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BanScope {
+    Global,
+    Posting,
+}
+
+impl BanScope {
+    pub const ALL: [BanScope; 2] = [BanScope::Global, BanScope::Posting];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BanScope::Global => "global",
+            BanScope::Posting => "posting",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<BanScope> {
+        BanScope::ALL.iter().copied().find(|scope| scope.as_str() == value)
+    }
+}
+
+// Bans without an expiry are permanent. Expired and lifted bans are kept as the user's history.
+#[derive(Clone, Debug, Queryable, Serialize)]
+pub struct Ban {
+    pub id: Id<Ban>,
+    pub user_id: Id<User>,
+    pub scope: String,
+    pub reason: String,
+    pub banned_by: Id<User>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub lifted_by: Option<Id<User>>,
+    pub lifted_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Insertable)]
+#[table_name = "bans"]
+pub struct NewBan {
+    pub user_id: Id<User>,
+    pub scope: String,
+    pub reason: String,
+    pub banned_by: Id<User>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug)]
+pub enum BanError {
+    ReasonEmpty,
+    ReasonTooLong,
+    AlreadyExpired,
+    AlreadyLifted,
+    NoSuchBan,
+    UnknownDatabaseError(DieselError),
+    Unknown(String),
+}
+
+impl std::fmt::Display for BanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BanError::ReasonEmpty => write!(f, "Please give a reason for the ban, it is shown to the banned user"),
+            BanError::ReasonTooLong => write!(f, "Reasons must be at most {} characters long", MAX_REASON_SIZE),
+            BanError::AlreadyExpired => write!(f, "Bans have to end in the future"),
+            BanError::AlreadyLifted => write!(f, "This ban is no longer in effect"),
+            BanError::NoSuchBan => write!(f, "No such ban"),
+            BanError::UnknownDatabaseError(err) => write!(f, "{}", err),
+            BanError::Unknown(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<DieselError> for BanError {
+    fn from(error: DieselError) -> BanError {
+        BanError::UnknownDatabaseError(error)
+    }
+}
+
+impl From<BanError> for RyobError {
+    fn from(error: BanError) -> RyobError {
+        match error {
+            BanError::ReasonEmpty => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            BanError::ReasonTooLong => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            BanError::AlreadyExpired => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            BanError::AlreadyLifted => RyobError::from_display(actix_web::http::StatusCode::CONFLICT, error),
+            BanError::NoSuchBan => RyobError::from_display(actix_web::http::StatusCode::NOT_FOUND, error),
+            _ => RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error),
+        }
+    }
+}
+
+impl Ban {
+    pub fn create(
+        connection: &DatabaseConnection,
+        target: Id<User>,
+        banned_by: Id<User>,
+        scope: BanScope,
+        reason: &str,
+        expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<Ban, BanError> {
+        let reason = reason.trim();
+        if reason.is_empty() {
+            return Err(BanError::ReasonEmpty);
+        }
+        if reason.chars().count() > MAX_REASON_SIZE {
+            return Err(BanError::ReasonTooLong);
+        }
+        let now = chrono::Utc::now();
+        if expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Err(BanError::AlreadyExpired);
+        }
+
+        let new_ban = NewBan {
+            user_id: target,
+            scope: scope.as_str().to_owned(),
+            reason: reason.to_owned(),
+            banned_by,
+            created_at: now,
+            expires_at,
+        };
+        let ban: Ban = {
+            use diesel::prelude::*;
+            diesel::insert_into(bans::table).values(&new_ban).get_result(connection)?
+        };
+
+        info!("User {:?} has banned user {:?} ({}) until {:?}", banned_by, target, scope.as_str(), expires_at);
+
+        Ok(ban)
+    }
+
+    pub fn scope(&self) -> BanScope {
+        BanScope::parse(&self.scope).unwrap_or(BanScope::Global)
+    }
+
+    // Bans run out on their own, there is nothing to clean up when they expire
+    pub fn is_active(&self) -> bool {
+        self.lifted_at.is_none() && self.expires_at.is_none_or(|expires_at| expires_at > chrono::Utc::now())
+    }
+
+    pub fn by_id(connection: &DatabaseConnection, ban: Id<Ban>) -> Result<Ban, BanError> {
+        use crate::schema::bans::dsl::*;
+        use diesel::prelude::*;
+        bans.filter(id.eq(ban)).first(connection).optional()?.ok_or(BanError::NoSuchBan)
+    }
+
+    // Newest first
+    pub fn history(connection: &DatabaseConnection, target: Id<User>) -> Result<Vec<Ban>, BanError> {
+        use crate::schema::bans::dsl::*;
+        use diesel::prelude::*;
+        Ok(bans.filter(user_id.eq(target)).order(id.desc()).load(connection)?)
+    }
+
+    // The ban keeping the user from doing what needs the given scope, if any. Global bans cover
+    // posting too. When there are several, the one that lasts longest is the one worth telling about.
+    pub fn blocking(connection: &DatabaseConnection, target: Id<User>, needed: BanScope) -> Result<Option<Ban>, BanError> {
+        let scopes: Vec<&str> = match needed {
+            BanScope::Global => vec![BanScope::Global.as_str()],
+            BanScope::Posting => vec![BanScope::Global.as_str(), BanScope::Posting.as_str()],
+        };
+        let active: Vec<Ban> = {
+            use crate::schema::bans::dsl::*;
+            use diesel::prelude::*;
+            bans.filter(user_id.eq(target))
+                .filter(scope.eq_any(scopes))
+                .filter(lifted_at.is_null())
+                .filter(expires_at.is_null().or(expires_at.gt(chrono::Utc::now())))
+                .load(connection)?
+        };
+        Ok(active.into_iter().max_by_key(|ban| (ban.expires_at.is_none(), ban.expires_at)))
+    }
+
+    pub fn lift(connection: &DatabaseConnection, ban: Id<Ban>, lifter: Id<User>) -> Result<Ban, BanError> {
+        let target = Ban::by_id(connection, ban)?;
+        if !target.is_active() {
+            return Err(BanError::AlreadyLifted);
+        }
+
+        let lifted: Ban = {
+            use crate::schema::bans::dsl::*;
+            use diesel::prelude::*;
+            diesel::update(bans.filter(id.eq(ban)))
+                .set((lifted_by.eq(Some(lifter)), lifted_at.eq(Some(chrono::Utc::now()))))
+                .get_result(connection)?
+        };
+
+        info!("User {:?} has lifted ban {:?} on user {:?}", lifter, ban, lifted.user_id);
+
+        Ok(lifted)
+    }
+}
+
+impl std::fmt::Display for Ban {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.scope() {
+            BanScope::Global => write!(f, "You have been banned")?,
+            BanScope::Posting => write!(f, "You have been banned from posting")?,
+        }
+        match self.expires_at {
+            Some(expires_at) => write!(f, " until {}", expires_at.format("%Y-%m-%d %H:%M UTC"))?,
+            None => write!(f, " permanently")?,
+        }
+        write!(f, ". Reason: {}", self.reason)
+    }
+}
@@ -0,0 +1,309 @@
+use crate::database::types::*;
+use crate::models::attachment::{AttachmentError, StoredFile};
+use crate::models::user::User;
+use crate::schema::banners;
+use crate::storage::Storage;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use crate::utils::uploads::UploadError;
+use crate::utils::{images, sniff};
+use chrono;
+use diesel::result::Error as DieselError;
+use diesel::{Insertable, Queryable};
+use log::info;
+use rand::seq::SliceRandom;
+use serde::Serialize;
+
+pub const MAX_BANNER_SIZE: usize = 2 * 1024 * 1024;
+pub const MAX_WEIGHT: i32 = 100;
+
+// Banners run across the top of every page, with a cornerpet either side
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BannerKind {
+    Banner,
+    Cornerpet,
+}
+
+impl BannerKind {
+    pub const ALL: [BannerKind; 2] = [BannerKind::Banner, BannerKind::Cornerpet];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BannerKind::Banner => "banner",
+            BannerKind::Cornerpet => "cornerpet",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<BannerKind> {
+        BannerKind::ALL.iter().copied().find(|kind| kind.as_str() == value)
+    }
+}
+
+// Banners submitted by users wait for an admin to approve them, only approved ones are shown
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BannerStatus {
+    Pending,
+    Approved,
+    Disabled,
+}
+
+impl BannerStatus {
+    pub const ALL: [BannerStatus; 3] = [BannerStatus::Pending, BannerStatus::Approved, BannerStatus::Disabled];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BannerStatus::Pending => "pending",
+            BannerStatus::Approved => "approved",
+            BannerStatus::Disabled => "disabled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<BannerStatus> {
+        BannerStatus::ALL.iter().copied().find(|status| status.as_str() == value)
+    }
+}
+
+#[derive(Clone, Queryable, Serialize)]
+pub struct Banner {
+    pub id: Id<Banner>,
+    pub kind: String,
+    pub file_id: Id<StoredFile>,
+    // How often the banner is picked compared to the others of its kind
+    pub weight: i32,
+    pub status: String,
+    pub submitted_by: Id<User>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub reviewed_by: Option<Id<User>>,
+    pub reviewed_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Insertable)]
+#[table_name = "banners"]
+pub struct NewBanner {
+    pub kind: String,
+    pub file_id: Id<StoredFile>,
+    pub weight: i32,
+    pub status: String,
+    pub submitted_by: Id<User>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub reviewed_by: Option<Id<User>>,
+    pub reviewed_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug)]
+pub enum BannerError {
+    TooLarge,
+    UnsupportedType,
+    NoFile,
+    InvalidWeight,
+    NoSuchBanner,
+    InvalidImage(String),
+    Upload(UploadError),
+    File(AttachmentError),
+    UnknownDatabaseError(DieselError),
+    Unknown(String),
+}
+
+impl std::fmt::Display for BannerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BannerError::TooLarge => write!(f, "Banners must be at most {} MiB", MAX_BANNER_SIZE / 1024 / 1024),
+            BannerError::UnsupportedType => write!(f, "Banners must be PNG, JPEG, GIF or WebP images"),
+            BannerError::NoFile => write!(f, "No file was uploaded"),
+            BannerError::InvalidWeight => write!(f, "Weight must be between 1 and {}", MAX_WEIGHT),
+            BannerError::NoSuchBanner => write!(f, "No such banner"),
+            BannerError::InvalidImage(err) => write!(f, "The image could not be read: {}", err),
+            BannerError::Upload(err) => write!(f, "{}", err),
+            BannerError::File(err) => write!(f, "{}", err),
+            BannerError::UnknownDatabaseError(err) => write!(f, "{}", err),
+            BannerError::Unknown(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<DieselError> for BannerError {
+    fn from(error: DieselError) -> BannerError {
+        BannerError::UnknownDatabaseError(error)
+    }
+}
+
+impl From<UploadError> for BannerError {
+    fn from(error: UploadError) -> BannerError {
+        BannerError::Upload(error)
+    }
+}
+
+impl From<AttachmentError> for BannerError {
+    fn from(error: AttachmentError) -> BannerError {
+        BannerError::File(error)
+    }
+}
+
+impl From<image::ImageError> for BannerError {
+    fn from(error: image::ImageError) -> BannerError {
+        BannerError::InvalidImage(format!("{}", error))
+    }
+}
+
+impl From<BannerError> for RyobError {
+    fn from(error: BannerError) -> RyobError {
+        match error {
+            BannerError::TooLarge => RyobError::from_display(actix_web::http::StatusCode::PAYLOAD_TOO_LARGE, error),
+            BannerError::UnsupportedType => RyobError::from_display(actix_web::http::StatusCode::UNSUPPORTED_MEDIA_TYPE, error),
+            BannerError::NoFile => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            BannerError::InvalidWeight => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            BannerError::NoSuchBanner => RyobError::from_display(actix_web::http::StatusCode::NOT_FOUND, error),
+            BannerError::InvalidImage(_) => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            BannerError::Upload(err) => RyobError::from(err),
+            BannerError::File(err) => RyobError::from(err),
+            _ => RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error),
+        }
+    }
+}
+
+impl Banner {
+    // Banners uploaded by admins skip the queue, reviewer is then the admin who uploaded it
+    pub fn create(
+        connection: &DatabaseConnection,
+        storage: &dyn Storage,
+        submitter: Id<User>,
+        reviewer: Option<Id<User>>,
+        kind: BannerKind,
+        weight: i32,
+        contents: &[u8],
+    ) -> Result<Banner, BannerError> {
+        if contents.is_empty() {
+            return Err(BannerError::NoFile);
+        }
+        if contents.len() > MAX_BANNER_SIZE {
+            return Err(BannerError::TooLarge);
+        }
+        if !(1..=MAX_WEIGHT).contains(&weight) {
+            return Err(BannerError::InvalidWeight);
+        }
+        let content_type = sniff::content_type(contents)
+            .filter(|content_type| sniff::is_image(content_type))
+            .ok_or(BannerError::UnsupportedType)?;
+        let (image, orientation) = images::decode(contents)?;
+        let (content_type, stripped) = images::strip_metadata(content_type, contents, &image, orientation)?;
+        let file = StoredFile::store(connection, storage, &stripped, content_type)?;
+
+        let status = if reviewer.is_some() { BannerStatus::Approved } else { BannerStatus::Pending };
+        let new_banner = NewBanner {
+            kind: kind.as_str().to_owned(),
+            file_id: file.id,
+            weight,
+            status: status.as_str().to_owned(),
+            submitted_by: submitter,
+            created_at: chrono::Utc::now(),
+            reviewed_by: reviewer,
+            reviewed_at: reviewer.map(|_| chrono::Utc::now()),
+        };
+
+        let banner: Banner = {
+            use diesel::prelude::*;
+            diesel::insert_into(banners::table).values(&new_banner).get_result(connection)?
+        };
+
+        info!("User {:?} has submitted {} {:?} as {}", submitter, kind.as_str(), banner.id, status.as_str());
+
+        Ok(banner)
+    }
+
+    pub fn by_id(connection: &DatabaseConnection, banner: Id<Banner>) -> Result<(Banner, StoredFile), BannerError> {
+        use crate::schema::banners::dsl::*;
+        use crate::schema::stored_files::dsl::stored_files;
+        use diesel::prelude::*;
+        banners
+            .filter(id.eq(banner))
+            .inner_join(stored_files)
+            .first(connection)
+            .optional()?
+            .ok_or(BannerError::NoSuchBanner)
+    }
+
+    pub fn all(connection: &DatabaseConnection) -> Result<Vec<(Banner, User)>, BannerError> {
+        use crate::schema::banners::dsl::*;
+        use crate::schema::users::dsl::users;
+        use diesel::prelude::*;
+        Ok(banners.order(created_at.desc()).inner_join(users).load(connection)?)
+    }
+
+    // Picks one of the approved banners of a kind, the chance of each in proportion to its weight
+    pub fn random(connection: &DatabaseConnection, target_kind: BannerKind) -> Result<Option<Banner>, BannerError> {
+        let approved: Vec<Banner> = {
+            use crate::schema::banners::dsl::*;
+            use diesel::prelude::*;
+            banners
+                .filter(kind.eq(target_kind.as_str()))
+                .filter(status.eq(BannerStatus::Approved.as_str()))
+                .load(connection)?
+        };
+        Ok(approved.choose_weighted(&mut rand::thread_rng(), |banner| banner.weight).ok().cloned())
+    }
+
+    pub fn set_status(connection: &DatabaseConnection, banner: Id<Banner>, reviewer: Id<User>, new_status: BannerStatus) -> Result<(), BannerError> {
+        let updated = {
+            use crate::schema::banners::dsl::*;
+            use diesel::prelude::*;
+            diesel::update(banners.filter(id.eq(banner)))
+                .set((
+                    status.eq(new_status.as_str()),
+                    reviewed_by.eq(Some(reviewer)),
+                    reviewed_at.eq(Some(chrono::Utc::now())),
+                ))
+                .execute(connection)?
+        };
+
+        if updated == 0 {
+            return Err(BannerError::NoSuchBanner);
+        }
+
+        info!("User {:?} has set banner {:?} to {}", reviewer, banner, new_status.as_str());
+
+        Ok(())
+    }
+
+    pub fn set_weight(connection: &DatabaseConnection, banner: Id<Banner>, new_weight: i32) -> Result<(), BannerError> {
+        if !(1..=MAX_WEIGHT).contains(&new_weight) {
+            return Err(BannerError::InvalidWeight);
+        }
+
+        let updated = {
+            use crate::schema::banners::dsl::*;
+            use diesel::prelude::*;
+            diesel::update(banners.filter(id.eq(banner))).set(weight.eq(new_weight)).execute(connection)?
+        };
+
+        if updated == 0 {
+            return Err(BannerError::NoSuchBanner);
+        }
+
+        Ok(())
+    }
+
+    // The stored file is left behind in case it is shared
+    pub fn delete(connection: &DatabaseConnection, banner: Id<Banner>) -> Result<(), BannerError> {
+        let deleted = {
+            use crate::schema::banners::dsl::*;
+            use diesel::prelude::*;
+            diesel::delete(banners.filter(id.eq(banner))).execute(connection)?
+        };
+
+        if deleted == 0 {
+            return Err(BannerError::NoSuchBanner);
+        }
+
+        info!("Banner {:?} has been deleted", banner);
+
+        Ok(())
+    }
+
+    pub fn status(&self) -> BannerStatus {
+        BannerStatus::parse(&self.status).unwrap_or(BannerStatus::Pending)
+    }
+
+    pub fn url(&self) -> String {
+        format!("/banners/{}", self.id.value())
+    }
+}
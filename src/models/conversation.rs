@@ -0,0 +1,261 @@
+use crate::database::types::*;
+use crate::models::message::NewMessage;
+use crate::models::user::User;
+use crate::models::user_block::UserBlock;
+use crate::schema::{conversation_participants, conversations, messages};
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use chrono;
+use diesel::result::Error as DieselError;
+use diesel::{Insertable, Queryable};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+pub const MAX_PARTICIPANTS: usize = 10;
+
+#[derive(Queryable, Serialize, Deserialize)]
+pub struct Conversation {
+    pub id: Id<Conversation>,
+    pub subject: String,
+    pub created_by: Id<User>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Insertable)]
+#[table_name = "conversations"]
+pub struct NewConversation {
+    pub subject: String,
+    pub created_by: Id<User>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Queryable, Insertable, Serialize)]
+#[table_name = "conversation_participants"]
+pub struct Participant {
+    pub conversation_id: Id<Conversation>,
+    pub user_id: Id<User>,
+    pub joined_at: chrono::DateTime<chrono::Utc>,
+    pub last_read_at: chrono::DateTime<chrono::Utc>,
+    pub left_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug)]
+pub enum ConversationError {
+    NoSuchConversation,
+    NotAParticipant,
+    NoRecipients,
+    TooManyParticipants,
+    BlockedByRecipient(String),
+    UnknownDatabaseError(DieselError),
+    Unknown(String),
+}
+
+impl std::fmt::Display for ConversationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ConversationError::NoSuchConversation => write!(f, "No such conversation"),
+            ConversationError::NotAParticipant => write!(f, "Not a participant in this conversation"),
+            ConversationError::NoRecipients => write!(f, "A conversation needs at least one other participant"),
+            ConversationError::TooManyParticipants => write!(f, "A conversation can have at most {} participants", MAX_PARTICIPANTS),
+            ConversationError::BlockedByRecipient(name) => write!(f, "{} is not accepting messages from you", name),
+            ConversationError::UnknownDatabaseError(err) => write!(f, "{}", err),
+            ConversationError::Unknown(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<DieselError> for ConversationError {
+    fn from(error: DieselError) -> ConversationError {
+        match error {
+            DieselError::NotFound => ConversationError::NoSuchConversation,
+            _ => ConversationError::UnknownDatabaseError(error),
+        }
+    }
+}
+
+impl From<ConversationError> for RyobError {
+    fn from(error: ConversationError) -> RyobError {
+        match error {
+            ConversationError::NoSuchConversation => RyobError::from_display(actix_web::http::StatusCode::NOT_FOUND, error),
+            ConversationError::NotAParticipant => RyobError::from_display(actix_web::http::StatusCode::FORBIDDEN, error),
+            ConversationError::BlockedByRecipient(_) => RyobError::from_display(actix_web::http::StatusCode::FORBIDDEN, error),
+            ConversationError::NoRecipients => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            ConversationError::TooManyParticipants => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            _ => RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error),
+        }
+    }
+}
+
+impl Conversation {
+    pub fn start(
+        connection: &DatabaseConnection,
+        creator: Id<User>,
+        recipients: &[User],
+        subject: &str,
+        content: &str,
+    ) -> Result<Conversation, ConversationError> {
+        let mut recipients: Vec<&User> = recipients.iter().filter(|recipient| recipient.id != creator).collect();
+        recipients.sort_by_key(|recipient| recipient.id.value());
+        recipients.dedup_by_key(|recipient| recipient.id);
+        if recipients.is_empty() {
+            return Err(ConversationError::NoRecipients);
+        }
+        if recipients.len() + 1 > MAX_PARTICIPANTS {
+            return Err(ConversationError::TooManyParticipants);
+        }
+
+        for recipient in &recipients {
+            let blocked = UserBlock::is_blocked(connection, recipient.id, creator).map_err(|err| ConversationError::Unknown(format!("{}", err)))?;
+            if blocked {
+                return Err(ConversationError::BlockedByRecipient(recipient.user_name.clone()));
+            }
+        }
+
+        let conversation = {
+            use diesel::prelude::*;
+            connection.transaction::<Conversation, ConversationError, _>(|| {
+                let timestamp = chrono::Utc::now();
+                let new_conversation = NewConversation {
+                    subject: subject.to_owned(),
+                    created_by: creator,
+                    created_at: timestamp,
+                    updated_at: timestamp,
+                };
+
+                let conversation: Conversation = diesel::insert_into(conversations::table).values(&new_conversation).get_result(connection)?;
+
+                let participants: Vec<Participant> = std::iter::once(creator)
+                    .chain(recipients.iter().map(|recipient| recipient.id))
+                    .map(|participant_id| Participant {
+                        conversation_id: conversation.id,
+                        user_id: participant_id,
+                        joined_at: timestamp,
+                        // Recipients start out having read nothing so the opening message counts as unread
+                        last_read_at: if participant_id == creator {
+                            timestamp
+                        } else {
+                            timestamp - chrono::Duration::seconds(1)
+                        },
+                        left_at: None,
+                    })
+                    .collect();
+
+                diesel::insert_into(conversation_participants::table)
+                    .values(&participants)
+                    .execute(connection)?;
+
+                let first_message = NewMessage {
+                    conversation_id: conversation.id,
+                    sent_by: creator,
+                    sent_at: timestamp,
+                    content: content.to_owned(),
+                };
+
+                diesel::insert_into(messages::table).values(&first_message).execute(connection)?;
+
+                Ok(conversation)
+            })?
+        };
+
+        info!(
+            "User {:?} has started conversation {:?} with {} recipient(s)",
+            creator,
+            conversation.id,
+            recipients.len()
+        );
+
+        Ok(conversation)
+    }
+
+    pub fn by_id(connection: &DatabaseConnection, target_id: Id<Conversation>) -> Result<Conversation, ConversationError> {
+        Ok({
+            use crate::schema::conversations::dsl::*;
+            use diesel::prelude::*;
+            conversations.filter(id.eq(target_id)).first::<Conversation>(connection)?
+        })
+    }
+
+    // Fetches the participation of a user who has not left the conversation
+    pub fn participation(connection: &DatabaseConnection, conversation: Id<Conversation>, user: Id<User>) -> Result<Participant, ConversationError> {
+        use crate::schema::conversation_participants::dsl::*;
+        use diesel::prelude::*;
+        conversation_participants
+            .filter(conversation_id.eq(conversation))
+            .filter(user_id.eq(user))
+            .filter(left_at.is_null())
+            .first::<Participant>(connection)
+            .optional()?
+            .ok_or(ConversationError::NotAParticipant)
+    }
+
+    pub fn participants(connection: &DatabaseConnection, conversation: Id<Conversation>) -> Result<Vec<(Participant, User)>, ConversationError> {
+        Ok({
+            use crate::schema::conversation_participants::dsl::*;
+            use crate::schema::users;
+            use diesel::prelude::*;
+            conversation_participants
+                .filter(conversation_id.eq(conversation))
+                .filter(left_at.is_null())
+                .inner_join(users::table)
+                .order(users::user_name.asc())
+                .load(connection)?
+        })
+    }
+
+    pub fn for_user_by_date(
+        connection: &DatabaseConnection,
+        user: Id<User>,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<(Conversation, Participant)>, ConversationError> {
+        Ok({
+            use crate::schema::conversation_participants::dsl::*;
+            use crate::schema::conversations;
+            use diesel::prelude::*;
+            conversations::table
+                .inner_join(conversation_participants)
+                .filter(user_id.eq(user))
+                .filter(left_at.is_null())
+                .order(conversations::updated_at.desc())
+                .limit(limit)
+                .offset(offset)
+                .load(connection)?
+        })
+    }
+
+    pub fn mark_read(connection: &DatabaseConnection, conversation: Id<Conversation>, user: Id<User>) -> Result<(), ConversationError> {
+        use crate::schema::conversation_participants::dsl::*;
+        use diesel::prelude::*;
+        diesel::update(conversation_participants.filter(conversation_id.eq(conversation)).filter(user_id.eq(user)))
+            .set(last_read_at.eq(chrono::Utc::now()))
+            .execute(connection)?;
+        Ok(())
+    }
+
+    pub fn leave(connection: &DatabaseConnection, conversation: Id<Conversation>, user: Id<User>) -> Result<(), ConversationError> {
+        Conversation::participation(connection, conversation, user)?;
+
+        {
+            use crate::schema::conversation_participants::dsl::*;
+            use diesel::prelude::*;
+            diesel::update(conversation_participants.filter(conversation_id.eq(conversation)).filter(user_id.eq(user)))
+                .set(left_at.eq(Some(chrono::Utc::now())))
+                .execute(connection)?;
+        }
+
+        info!("User {:?} has left conversation {:?}", user, conversation);
+
+        Ok(())
+    }
+
+    pub fn touch(connection: &DatabaseConnection, conversation: Id<Conversation>, timestamp: chrono::DateTime<chrono::Utc>) -> Result<(), ConversationError> {
+        use crate::schema::conversations::dsl::*;
+        use diesel::prelude::*;
+        diesel::update(conversations.filter(id.eq(conversation)))
+            .set(updated_at.eq(timestamp))
+            .execute(connection)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+use crate::database::types::*;
+use crate::models::post::Post;
+use crate::models::user::User;
+use crate::schema::ip_addresses;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use chrono;
+use diesel::result::Error as DieselError;
+use diesel::{Insertable, Queryable};
+use ipnet::IpNet;
+use log::info;
+use serde::Serialize;
+use std::net::IpAddr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IpEvent {
+    Register,
+    Login,
+    Post,
+}
+
+impl IpEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IpEvent::Register => "register",
+            IpEvent::Login => "login",
+            IpEvent::Post => "post",
+        }
+    }
+}
+
+// Where a user was when they registered, logged in or posted. Once older than the retention
+// setting the address is cut down to its subnet and marked as anonymized.
+#[derive(Clone, Queryable, Serialize)]
+pub struct IpRecord {
+    pub id: Id<IpRecord>,
+    pub user_id: Id<User>,
+    pub post_id: Option<Id<Post>>,
+    pub event: String,
+    pub ip: String,
+    pub anonymized: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Insertable)]
+#[table_name = "ip_addresses"]
+pub struct NewIpRecord {
+    pub user_id: Id<User>,
+    pub post_id: Option<Id<Post>>,
+    pub event: String,
+    pub ip: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug)]
+pub enum IpRecordError {
+    UnknownDatabaseError(DieselError),
+    Unknown(String),
+}
+
+impl std::fmt::Display for IpRecordError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            IpRecordError::UnknownDatabaseError(err) => write!(f, "{}", err),
+            IpRecordError::Unknown(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<DieselError> for IpRecordError {
+    fn from(error: DieselError) -> IpRecordError {
+        IpRecordError::UnknownDatabaseError(error)
+    }
+}
+
+impl From<IpRecordError> for RyobError {
+    fn from(error: IpRecordError) -> RyobError {
+        RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error)
+    }
+}
+
+impl IpRecord {
+    // Requests whose address couldn't be worked out aren't recorded
+    pub fn record(connection: &DatabaseConnection, user: Id<User>, post: Option<Id<Post>>, event: IpEvent, ip: Option<IpAddr>) -> Result<(), IpRecordError> {
+        let ip = match ip {
+            Some(ip) => ip,
+            None => return Ok(()),
+        };
+        let new_record = NewIpRecord {
+            user_id: user,
+            post_id: post,
+            event: event.as_str().to_owned(),
+            ip: ip.to_string(),
+            created_at: chrono::Utc::now(),
+        };
+        {
+            use diesel::prelude::*;
+            diesel::insert_into(ip_addresses::table).values(&new_record).execute(connection)?;
+        }
+        Ok(())
+    }
+
+    // How many times the event has come from this exact address since the given time
+    pub fn count_since(
+        connection: &DatabaseConnection,
+        address: IpAddr,
+        event_kind: IpEvent,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<i64, IpRecordError> {
+        use crate::schema::ip_addresses::dsl::*;
+        use diesel::prelude::*;
+        Ok(ip_addresses
+            .filter(ip.eq(address.to_string()))
+            .filter(event.eq(event_kind.as_str()))
+            .filter(created_at.gt(since))
+            .count()
+            .get_result(connection)?)
+    }
+
+    // Newest first
+    pub fn for_user(connection: &DatabaseConnection, user: Id<User>, limit: i64) -> Result<Vec<IpRecord>, IpRecordError> {
+        use crate::schema::ip_addresses::dsl::*;
+        use diesel::prelude::*;
+        Ok(ip_addresses.filter(user_id.eq(user)).order(id.desc()).limit(limit).load(connection)?)
+    }
+
+    // Newest first. Anonymized addresses are the start of their subnet, so they still turn up
+    // when looking up the subnet.
+    pub fn in_network(connection: &DatabaseConnection, network: IpNet, limit: i64) -> Result<Vec<(IpRecord, User)>, IpRecordError> {
+        use crate::schema::ip_addresses::dsl::*;
+        use crate::schema::users::dsl::users;
+        use diesel::dsl::sql;
+        use diesel::prelude::*;
+        use diesel::sql_types::Bool;
+        // IpNet only ever prints as digits, dots, colons and a prefix length, so it can go straight into the query
+        Ok(ip_addresses
+            .filter(sql::<Bool>(&format!("ip::inet <<= '{}'::inet", network)))
+            .inner_join(users)
+            .order(id.desc())
+            .limit(limit)
+            .load(connection)?)
+    }
+
+    // Cuts addresses recorded before the cutoff down to their subnet, as in utils::client_ip::subnet
+    pub fn anonymize_before(connection: &DatabaseConnection, cutoff: chrono::DateTime<chrono::Utc>) -> Result<usize, IpRecordError> {
+        use diesel::prelude::*;
+        use diesel::sql_types::Timestamptz;
+        let anonymized = diesel::sql_query(
+            "UPDATE ip_addresses SET ip = host(network(set_masklen(ip::inet, CASE WHEN family(ip::inet) = 4 THEN 24 ELSE 48 END))), anonymized = TRUE \
+             WHERE NOT anonymized AND created_at < $1",
+        )
+        .bind::<Timestamptz, _>(cutoff)
+        .execute(connection)?;
+
+        if anonymized > 0 {
+            info!("{} IP address(es) recorded before {} have been anonymized", anonymized, cutoff);
+        }
+
+        Ok(anonymized)
+    }
+}
@@ -0,0 +1,275 @@
+use crate::database::types::*;
+use crate::models::ban::MAX_REASON_SIZE;
+use crate::models::user::User;
+use crate::schema::ip_bans;
+use crate::utils::client_ip;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use chrono;
+use diesel::result::Error as DieselError;
+use diesel::{Insertable, Queryable};
+use log::info;
+use serde::Serialize;
+use std::net::IpAddr;
+
+// Bans a single address or a whole CIDR range from the site, logged in or not
+#[derive(Clone, Debug, Queryable, Serialize)]
+pub struct IpBan {
+    pub id: Id<IpBan>,
+    pub network: String,
+    pub reason: String,
+    pub created_by: Id<User>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Insertable)]
+#[table_name = "ip_bans"]
+pub struct NewIpBan {
+    pub network: String,
+    pub reason: String,
+    pub created_by: Id<User>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug)]
+pub enum IpBanError {
+    InvalidNetwork(String),
+    ReasonEmpty,
+    ReasonTooLong,
+    AlreadyExpired,
+    NoSuchBan,
+    UnknownDatabaseError(DieselError),
+    Unknown(String),
+}
+
+impl std::fmt::Display for IpBanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            IpBanError::InvalidNetwork(network) => write!(f, "\"{}\" is not an IP address or CIDR range", network),
+            IpBanError::ReasonEmpty => write!(f, "Please give a reason for the ban, it is shown to everyone it blocks"),
+            IpBanError::ReasonTooLong => write!(f, "Reasons must be at most {} characters long", MAX_REASON_SIZE),
+            IpBanError::AlreadyExpired => write!(f, "Bans have to end in the future"),
+            IpBanError::NoSuchBan => write!(f, "No such ban"),
+            IpBanError::UnknownDatabaseError(err) => write!(f, "{}", err),
+            IpBanError::Unknown(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<DieselError> for IpBanError {
+    fn from(error: DieselError) -> IpBanError {
+        IpBanError::UnknownDatabaseError(error)
+    }
+}
+
+impl From<IpBanError> for RyobError {
+    fn from(error: IpBanError) -> RyobError {
+        match error {
+            IpBanError::InvalidNetwork(_) => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            IpBanError::ReasonEmpty => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            IpBanError::ReasonTooLong => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            IpBanError::AlreadyExpired => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            IpBanError::NoSuchBan => RyobError::from_display(actix_web::http::StatusCode::NOT_FOUND, error),
+            _ => RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error),
+        }
+    }
+}
+
+impl IpBan {
+    pub fn create(
+        connection: &DatabaseConnection,
+        network: &str,
+        created_by: Id<User>,
+        reason: &str,
+        expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<IpBan, IpBanError> {
+        let network = network.trim();
+        let parsed = client_ip::parse_network(network).ok_or_else(|| IpBanError::InvalidNetwork(network.to_owned()))?;
+        let reason = reason.trim();
+        if reason.is_empty() {
+            return Err(IpBanError::ReasonEmpty);
+        }
+        if reason.chars().count() > MAX_REASON_SIZE {
+            return Err(IpBanError::ReasonTooLong);
+        }
+        let now = chrono::Utc::now();
+        if expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Err(IpBanError::AlreadyExpired);
+        }
+
+        let new_ban = NewIpBan {
+            network: parsed.to_string(),
+            reason: reason.to_owned(),
+            created_by,
+            created_at: now,
+            expires_at,
+        };
+        let ban: IpBan = {
+            use diesel::prelude::*;
+            diesel::insert_into(ip_bans::table).values(&new_ban).get_result(connection)?
+        };
+
+        info!("User {:?} has banned {} until {:?}", created_by, ban.network, expires_at);
+
+        Ok(ban)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.expires_at.is_none_or(|expires_at| expires_at > chrono::Utc::now())
+    }
+
+    pub fn covers(&self, ip: IpAddr) -> bool {
+        client_ip::parse_network(&self.network).is_some_and(|network| network.contains(&ip))
+    }
+
+    pub fn by_id(connection: &DatabaseConnection, ban: Id<IpBan>) -> Result<IpBan, IpBanError> {
+        use crate::schema::ip_bans::dsl::*;
+        use diesel::prelude::*;
+        ip_bans.filter(id.eq(ban)).first(connection).optional()?.ok_or(IpBanError::NoSuchBan)
+    }
+
+    // Newest first, expired ones included
+    pub fn all(connection: &DatabaseConnection) -> Result<Vec<(IpBan, User)>, IpBanError> {
+        use crate::schema::ip_bans::dsl::*;
+        use crate::schema::users::dsl::users;
+        use diesel::prelude::*;
+        Ok(ip_bans.inner_join(users).order(id.desc()).load(connection)?)
+    }
+
+    // The active ban covering the address, if any. When there are several, the one that lasts longest.
+    pub fn matching(connection: &DatabaseConnection, ip: IpAddr) -> Result<Option<IpBan>, IpBanError> {
+        let active: Vec<IpBan> = {
+            use crate::schema::ip_bans::dsl::*;
+            use diesel::prelude::*;
+            ip_bans.filter(expires_at.is_null().or(expires_at.gt(chrono::Utc::now()))).load(connection)?
+        };
+        Ok(longest_covering(active, ip))
+    }
+
+    pub fn delete(connection: &DatabaseConnection, ban: Id<IpBan>) -> Result<IpBan, IpBanError> {
+        let deleted: IpBan = {
+            use crate::schema::ip_bans::dsl::*;
+            use diesel::prelude::*;
+            diesel::delete(ip_bans.filter(id.eq(ban)))
+                .get_result(connection)
+                .optional()?
+                .ok_or(IpBanError::NoSuchBan)?
+        };
+
+        info!("The ban on {} has been removed", deleted.network);
+
+        Ok(deleted)
+    }
+}
+
+fn longest_covering(bans: Vec<IpBan>, ip: IpAddr) -> Option<IpBan> {
+    bans.into_iter()
+        .filter(|ban| ban.covers(ip))
+        .max_by_key(|ban| (ban.expires_at.is_none(), ban.expires_at))
+}
+
+impl std::fmt::Display for IpBan {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Your network has been banned")?;
+        match self.expires_at {
+            Some(expires_at) => write!(f, " until {}", expires_at.format("%Y-%m-%d %H:%M UTC"))?,
+            None => write!(f, " permanently")?,
+        }
+        write!(f, ". Reason: {}", self.reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ban(network: &str, expires_in_days: Option<i64>) -> IpBan {
+        let now = chrono::Utc::now();
+        IpBan {
+            id: Id::new(1),
+            network: network.to_owned(),
+            reason: "test".to_owned(),
+            created_by: Id::new(1),
+            created_at: now,
+            expires_at: expires_in_days.map(|days| now + chrono::Duration::days(days)),
+        }
+    }
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn covers_single_addresses() {
+        let ban = ban("192.0.2.7/32", None);
+        assert!(ban.covers(ip("192.0.2.7")));
+        assert!(!ban.covers(ip("192.0.2.8")));
+    }
+
+    #[test]
+    fn covers_ipv4_ranges() {
+        let ban = ban("198.51.100.0/24", None);
+        assert!(ban.covers(ip("198.51.100.0")));
+        assert!(ban.covers(ip("198.51.100.255")));
+        assert!(!ban.covers(ip("198.51.101.1")));
+        assert!(!ban.covers(ip("198.51.99.255")));
+    }
+
+    #[test]
+    fn covers_ipv6_ranges() {
+        let ban = ban("2001:db8:1234::/48", None);
+        assert!(ban.covers(ip("2001:db8:1234:ffff::1")));
+        assert!(!ban.covers(ip("2001:db8:1235::1")));
+    }
+
+    #[test]
+    fn keeps_address_families_apart() {
+        assert!(!ban("2001:db8::/32", None).covers(ip("192.0.2.1")));
+        assert!(!ban("0.0.0.0/0", None).covers(ip("::1")));
+    }
+
+    #[test]
+    fn ignores_unparseable_networks() {
+        assert!(!ban("not a network", None).covers(ip("192.0.2.1")));
+    }
+
+    #[test]
+    fn prefers_the_longest_ban() {
+        let bans = vec![
+            ban("192.0.2.0/24", Some(1)),
+            ban("192.0.2.0/28", Some(30)),
+            ban("192.0.2.128/25", None),
+            ban("203.0.113.0/24", None),
+        ];
+        assert_eq!(
+            longest_covering(bans.clone(), ip("192.0.2.1")).map(|ban| ban.network),
+            Some("192.0.2.0/28".to_owned())
+        );
+        assert_eq!(
+            longest_covering(bans.clone(), ip("192.0.2.200")).map(|ban| ban.network),
+            Some("192.0.2.128/25".to_owned())
+        );
+        assert!(longest_covering(bans, ip("198.51.100.1")).is_none());
+    }
+
+    #[test]
+    fn matches_ipv4_mapped_clients() {
+        use crate::utils::client_ip::TrustedProxies;
+        use actix_web::http::header::{HeaderName, HeaderValue};
+        use actix_web::http::HeaderMap;
+
+        let bans = vec![ban("192.0.2.0/24", None)];
+        let direct = TrustedProxies::default().resolve("[::ffff:192.0.2.1]:443".parse().ok(), &HeaderMap::new());
+        assert_eq!(direct, Some(ip("192.0.2.1")));
+        assert!(longest_covering(bans.clone(), direct.unwrap()).is_some());
+
+        let proxies = TrustedProxies::parse("10.0.0.1").unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(HeaderName::from_static("x-forwarded-for"), HeaderValue::from_static("::ffff:192.0.2.9"));
+        let forwarded = proxies.resolve("[::ffff:10.0.0.1]:443".parse().ok(), &headers);
+        assert_eq!(forwarded, Some(ip("192.0.2.9")));
+        assert!(longest_covering(bans, forwarded.unwrap()).is_some());
+    }
+}
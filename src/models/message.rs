@@ -0,0 +1,174 @@
+use crate::database::types::*;
+use crate::models::conversation::{Conversation, ConversationError};
+use crate::models::user::User;
+use crate::models::user_block::UserBlock;
+use crate::schema::messages;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use crate::utils::markup;
+use chrono;
+use diesel::result::Error as DieselError;
+use diesel::{Insertable, Queryable};
+use log::info;
+use serde::Serialize;
+
+#[derive(Queryable, Serialize)]
+pub struct Message {
+    pub id: Id<Message>,
+    pub conversation_id: Id<Conversation>,
+    pub sent_by: Id<User>,
+    pub sent_at: chrono::DateTime<chrono::Utc>,
+    pub content: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "messages"]
+pub struct NewMessage {
+    pub conversation_id: Id<Conversation>,
+    pub sent_by: Id<User>,
+    pub sent_at: chrono::DateTime<chrono::Utc>,
+    pub content: String,
+}
+
+#[derive(Debug)]
+pub enum MessageError {
+    NotAParticipant,
+    BlockedByParticipant(String),
+    UnknownDatabaseError(DieselError),
+    Unknown(String),
+}
+
+impl std::fmt::Display for MessageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MessageError::NotAParticipant => write!(f, "Not a participant in this conversation"),
+            MessageError::BlockedByParticipant(name) => write!(f, "{} is not accepting messages from you", name),
+            MessageError::UnknownDatabaseError(err) => write!(f, "{}", err),
+            MessageError::Unknown(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<ConversationError> for MessageError {
+    fn from(error: ConversationError) -> MessageError {
+        match error {
+            ConversationError::NotAParticipant => MessageError::NotAParticipant,
+            ConversationError::UnknownDatabaseError(err) => MessageError::UnknownDatabaseError(err),
+            _ => MessageError::Unknown(format!("{}", error)),
+        }
+    }
+}
+
+impl From<MessageError> for RyobError {
+    fn from(error: MessageError) -> RyobError {
+        match error {
+            MessageError::NotAParticipant => RyobError::from_display(actix_web::http::StatusCode::FORBIDDEN, error),
+            MessageError::BlockedByParticipant(_) => RyobError::from_display(actix_web::http::StatusCode::FORBIDDEN, error),
+            _ => RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error),
+        }
+    }
+}
+
+impl Message {
+    pub fn send(connection: &DatabaseConnection, sender: Id<User>, conversation: Id<Conversation>, content: &str) -> Result<Message, MessageError> {
+        Conversation::participation(connection, conversation, sender)?;
+
+        for (participant, participant_user) in Conversation::participants(connection, conversation)? {
+            if participant.user_id == sender {
+                continue;
+            }
+            let blocked = UserBlock::is_blocked(connection, participant.user_id, sender).map_err(|err| MessageError::Unknown(format!("{}", err)))?;
+            if blocked {
+                return Err(MessageError::BlockedByParticipant(participant_user.user_name));
+            }
+        }
+
+        let timestamp = chrono::Utc::now();
+        let new_message = NewMessage {
+            conversation_id: conversation,
+            sent_by: sender,
+            sent_at: timestamp,
+            content: content.to_owned(),
+        };
+
+        let result: Message = {
+            use diesel::prelude::*;
+            diesel::insert_into(messages::table)
+                .values(&new_message)
+                .get_result(connection)
+                .map_err(MessageError::UnknownDatabaseError)?
+        };
+
+        Conversation::touch(connection, conversation, timestamp)?;
+        Conversation::mark_read(connection, conversation, sender)?;
+
+        info!("User {:?} has sent a message in conversation {:?}", sender, conversation);
+
+        Ok(result)
+    }
+
+    pub fn rendered_content(&self) -> String {
+        markup::render(&self.content)
+    }
+
+    pub fn in_conversation_by_date(
+        connection: &DatabaseConnection,
+        conversation: Id<Conversation>,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<(Message, User)>, MessageError> {
+        Ok({
+            use crate::schema::messages::dsl::*;
+            use crate::schema::users::dsl::users;
+            use diesel::prelude::*;
+            messages
+                .order(sent_at.desc())
+                .filter(conversation_id.eq(conversation))
+                .limit(limit)
+                .offset(offset)
+                .inner_join(users)
+                .load(connection)
+                .map_err(MessageError::UnknownDatabaseError)?
+        })
+    }
+
+    pub fn sent_by_date(connection: &DatabaseConnection, sender: Id<User>, offset: i64, limit: i64) -> Result<Vec<(Message, Conversation)>, MessageError> {
+        Ok({
+            use crate::schema::conversations::dsl::conversations;
+            use crate::schema::messages::dsl::*;
+            use diesel::prelude::*;
+            messages
+                .order(sent_at.desc())
+                .filter(sent_by.eq(sender))
+                .limit(limit)
+                .offset(offset)
+                .inner_join(conversations)
+                .load(connection)
+                .map_err(MessageError::UnknownDatabaseError)?
+        })
+    }
+
+    // Counts messages from other participants sent after the user last read the conversation,
+    // optionally restricted to a single conversation
+    pub fn unread_count(connection: &DatabaseConnection, user: Id<User>, conversation: Option<Id<Conversation>>) -> Result<i64, MessageError> {
+        Ok({
+            use crate::schema::conversation_participants;
+            use crate::schema::messages::dsl::*;
+            use diesel::prelude::*;
+            let mut query = messages
+                .inner_join(
+                    conversation_participants::table.on(conversation_participants::conversation_id
+                        .eq(conversation_id)
+                        .and(conversation_participants::user_id.eq(user))),
+                )
+                .filter(conversation_participants::left_at.is_null())
+                .filter(sent_at.gt(conversation_participants::last_read_at))
+                .filter(sent_by.ne(user))
+                .into_boxed();
+            if let Some(target_conversation) = conversation {
+                query = query.filter(conversation_id.eq(target_conversation));
+            }
+            query.count().get_result(connection).map_err(MessageError::UnknownDatabaseError)?
+        })
+    }
+}
@@ -1,3 +1,22 @@
 pub mod user;
 pub mod topic;
-pub mod post;
\ No newline at end of file
+pub mod post;
+pub mod conversation;
+pub mod message;
+pub mod user_block;
+pub mod outgoing_email;
+pub mod user_token;
+pub mod setting;
+pub mod two_factor;
+pub mod api_token;
+pub mod webhook;
+pub mod attachment;
+pub mod banner;
+pub mod user_preferences;
+pub mod report;
+pub mod audit_log;
+pub mod ban;
+pub mod ip_address;
+pub mod ip_ban;
+pub mod word_filter;
+pub mod spam_log;
\ No newline at end of file
@@ -0,0 +1,160 @@
+use crate::database::types::*;
+use crate::mailer::Email;
+use crate::schema::outgoing_emails;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use crate::utils::queue;
+use chrono;
+use diesel::result::Error as DieselError;
+use diesel::{Insertable, Queryable};
+use log::{info, warn};
+
+pub const MAX_ATTEMPTS: i32 = 8;
+
+#[derive(Queryable)]
+pub struct OutgoingEmail {
+    pub id: Id<OutgoingEmail>,
+    pub recipient: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub attempts: i32,
+    pub next_attempt_at: chrono::DateTime<chrono::Utc>,
+    pub last_error: Option<String>,
+    pub sent_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Insertable)]
+#[table_name = "outgoing_emails"]
+pub struct NewOutgoingEmail {
+    pub recipient: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub next_attempt_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug)]
+pub enum OutgoingEmailError {
+    UnknownDatabaseError(DieselError),
+    Unknown(String),
+}
+
+impl std::fmt::Display for OutgoingEmailError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            OutgoingEmailError::UnknownDatabaseError(err) => write!(f, "{}", err),
+            OutgoingEmailError::Unknown(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<DieselError> for OutgoingEmailError {
+    fn from(error: DieselError) -> OutgoingEmailError {
+        OutgoingEmailError::UnknownDatabaseError(error)
+    }
+}
+
+impl From<OutgoingEmailError> for RyobError {
+    fn from(error: OutgoingEmailError) -> RyobError {
+        RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error)
+    }
+}
+
+impl OutgoingEmail {
+    pub fn enqueue(connection: &DatabaseConnection, email: &Email) -> Result<OutgoingEmail, OutgoingEmailError> {
+        let timestamp = chrono::Utc::now();
+        let new_email = NewOutgoingEmail {
+            recipient: email.to.clone(),
+            subject: email.subject.clone(),
+            text_body: email.text_body.clone(),
+            html_body: email.html_body.clone(),
+            created_at: timestamp,
+            next_attempt_at: timestamp,
+        };
+
+        let result: OutgoingEmail = {
+            use diesel::prelude::*;
+            diesel::insert_into(outgoing_emails::table)
+                .values(&new_email)
+                .get_result(connection)
+                .map_err(OutgoingEmailError::UnknownDatabaseError)?
+        };
+
+        info!("Email {:?} has been queued", result.id);
+
+        Ok(result)
+    }
+
+    // Claims the returned rows as described in utils::queue, so several workers can drain the queue
+    // without sending anything twice
+    pub fn claim_due(connection: &DatabaseConnection, limit: i64) -> Result<Vec<OutgoingEmail>, OutgoingEmailError> {
+        use crate::schema::outgoing_emails::dsl::*;
+        use diesel::prelude::*;
+        connection.transaction::<_, OutgoingEmailError, _>(|| {
+            let due: Vec<OutgoingEmail> = outgoing_emails
+                .filter(sent_at.is_null())
+                .filter(attempts.lt(MAX_ATTEMPTS))
+                .filter(next_attempt_at.le(chrono::Utc::now()))
+                .order(next_attempt_at.asc())
+                .limit(limit)
+                .for_update()
+                .skip_locked()
+                .load(connection)?;
+            let claimed: Vec<Id<OutgoingEmail>> = due.iter().map(|email| email.id).collect();
+            diesel::update(outgoing_emails.filter(id.eq_any(claimed)))
+                .set(next_attempt_at.eq(queue::lease_expiry()))
+                .execute(connection)?;
+            Ok(due)
+        })
+    }
+
+    pub fn mark_sent(&self, connection: &DatabaseConnection) -> Result<(), OutgoingEmailError> {
+        {
+            use crate::schema::outgoing_emails::dsl::*;
+            use diesel::prelude::*;
+            diesel::update(outgoing_emails.filter(id.eq(self.id)))
+                .set((sent_at.eq(Some(chrono::Utc::now())), attempts.eq(self.attempts + 1)))
+                .execute(connection)
+                .map_err(OutgoingEmailError::UnknownDatabaseError)?;
+        }
+
+        info!("Email {:?} has been sent", self.id);
+
+        Ok(())
+    }
+
+    // Backs off exponentially: 1, 2, 4, ... minutes between attempts
+    pub fn mark_failed(&self, connection: &DatabaseConnection, error: &str) -> Result<(), OutgoingEmailError> {
+        let attempt = self.attempts + 1;
+        let retry_at = chrono::Utc::now() + chrono::Duration::minutes(1 << (attempt - 1).min(16));
+
+        {
+            use crate::schema::outgoing_emails::dsl::*;
+            use diesel::prelude::*;
+            diesel::update(outgoing_emails.filter(id.eq(self.id)))
+                .set((attempts.eq(attempt), next_attempt_at.eq(retry_at), last_error.eq(Some(error))))
+                .execute(connection)
+                .map_err(OutgoingEmailError::UnknownDatabaseError)?;
+        }
+
+        if attempt >= MAX_ATTEMPTS {
+            warn!("Email {:?} has been abandoned after {} attempts: {}", self.id, attempt, error);
+        } else {
+            warn!("Email {:?} failed to send on attempt {}, retrying at {}: {}", self.id, attempt, retry_at, error);
+        }
+
+        Ok(())
+    }
+
+    pub fn to_email(&self) -> Email {
+        Email {
+            to: self.recipient.clone(),
+            subject: self.subject.clone(),
+            text_body: self.text_body.clone(),
+            html_body: self.html_body.clone(),
+        }
+    }
+}
@@ -1,21 +1,29 @@
 use crate::database::types::*;
+use crate::live::{self, LiveEvent};
 use crate::models::topic::Topic;
-use crate::models::user::User;
-use crate::schema::posts;
+use crate::models::user::{User, UserError};
+use crate::models::webhook::{Webhook, WebhookEvent};
+use crate::models::word_filter::{FilterTarget, Filtered, WordFilter, WordFilterError};
+use crate::schema::{attachments, posts, topics};
 use crate::utils::errors::RyobError;
 use crate::utils::id::Id;
+use crate::utils::markup;
 use chrono;
 use diesel::result::Error as DieselError;
 use diesel::{Insertable, Queryable};
 use log::info;
+use serde::Serialize;
 
-#[derive(Queryable)]
+#[derive(Queryable, Serialize)]
 pub struct Post {
     pub id: Id<Post>,
     pub posted_in: Id<Topic>,
     pub created_by: Id<User>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub content: String,
+    // Held for a moderator to approve, and hidden from everyone else until then
+    pub pending: bool,
+    pub hold_reason: String,
 }
 
 #[derive(Insertable)]
@@ -25,10 +33,15 @@ pub struct NewPost {
     pub created_by: Id<User>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub content: String,
+    pub pending: bool,
+    pub hold_reason: String,
 }
 
 #[derive(Debug)]
 pub enum PostError {
+    NoSuchPost,
+    NotPending,
+    Rejected(String),
     UnknownDatabaseError(DieselError),
     Unknown(String),
 }
@@ -36,6 +49,9 @@ pub enum PostError {
 impl std::fmt::Display for PostError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
+            PostError::NoSuchPost => write!(f, "No such post"),
+            PostError::NotPending => write!(f, "That post is not waiting for approval"),
+            PostError::Rejected(message) => write!(f, "{}", message),
             PostError::UnknownDatabaseError(err) => write!(f, "{}", err),
             PostError::Unknown(err) => write!(f, "{}", err),
         }
@@ -44,21 +60,70 @@ impl std::fmt::Display for PostError {
 
 impl From<PostError> for RyobError {
     fn from(error: PostError) -> RyobError {
-        RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error)
+        match error {
+            PostError::NoSuchPost => RyobError::from_display(actix_web::http::StatusCode::NOT_FOUND, error),
+            PostError::NotPending => RyobError::from_display(actix_web::http::StatusCode::CONFLICT, error),
+            PostError::Rejected(_) => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            _ => RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error),
+        }
+    }
+}
+
+impl From<DieselError> for PostError {
+    fn from(error: DieselError) -> PostError {
+        PostError::UnknownDatabaseError(error)
+    }
+}
+
+impl From<WordFilterError> for PostError {
+    fn from(error: WordFilterError) -> PostError {
+        match error {
+            WordFilterError::Rejected(message) => PostError::Rejected(message),
+            WordFilterError::UnknownDatabaseError(err) => PostError::UnknownDatabaseError(err),
+            _ => PostError::Unknown(error.to_string()),
+        }
+    }
+}
+
+impl From<UserError> for PostError {
+    fn from(error: UserError) -> PostError {
+        match error {
+            UserError::UnknownDatabaseError(err) => PostError::UnknownDatabaseError(err),
+            _ => PostError::Unknown(error.to_string()),
+        }
     }
 }
 
 impl Post {
-    pub fn create(connection: &DatabaseConnection, creator: Id<User>, topic: Id<Topic>, content: &String) -> Result<Post, PostError> {
+    // The content goes through the word filters first, which may change it, reject it or hold it.
+    // Callers pass on any reason of their own for holding it, such as the spam checks'.
+    pub fn create(connection: &DatabaseConnection, creator: Id<User>, topic: Id<Topic>, content: &str, held: Option<String>) -> Result<Post, PostError> {
+        let mut filtered = WordFilter::apply(connection, FilterTarget::Content, content)?;
+        filtered.hold_reason = Post::hold_reason(connection, creator, filtered.hold_reason.or(held))?;
+        Post::insert(connection, creator, topic, filtered)
+    }
+
+    // Content nothing else held is still held while its author is on probation
+    pub fn hold_reason(connection: &DatabaseConnection, creator: Id<User>, reason: Option<String>) -> Result<Option<String>, PostError> {
+        match reason {
+            Some(reason) => Ok(Some(reason)),
+            None => Ok(User::by_user_id(connection, creator)?.probation_reason(connection)?),
+        }
+    }
+
+    // For content that has already been through the filters
+    pub fn insert(connection: &DatabaseConnection, creator: Id<User>, topic: Id<Topic>, filtered: Filtered) -> Result<Post, PostError> {
         let timestamp = chrono::Utc::now();
         let new_post = NewPost {
             posted_in: topic,
             created_by: creator,
             created_at: timestamp,
-            content: content.clone(),
+            content: filtered.text,
+            pending: filtered.hold_reason.is_some(),
+            hold_reason: filtered.hold_reason.unwrap_or_default(),
         };
 
-        let result = {
+        let result: Post = {
             use diesel::prelude::*;
             diesel::insert_into(posts::table)
                 .values(&new_post)
@@ -66,24 +131,330 @@ impl Post {
                 .map_err(PostError::UnknownDatabaseError)?
         };
 
+        if result.pending {
+            info!(
+                "User {:?} has created a post in topic {:?}, held for approval: {}",
+                creator, topic, result.hold_reason
+            );
+            return Ok(result);
+        }
+
+        Webhook::dispatch(connection, WebhookEvent::PostCreated, json!({ "post": result })).map_err(PostError::UnknownDatabaseError)?;
+        live::notify(
+            connection,
+            &LiveEvent::PostCreated {
+                topic_id: topic,
+                post_id: result.id,
+            },
+        )
+        .map_err(PostError::UnknownDatabaseError)?;
+
         info!("User {:?} has created a post in topic {:?}", creator, topic);
 
         Ok(result)
     }
 
-    pub fn in_topic_by_date(connection: &DatabaseConnection, topic: Id<Topic>, offset: i64, limit: i64) -> Result<Vec<(Post, User)>, PostError> {
-        Ok({
+    // Oldest first, with the topics they were posted in
+    pub fn pending(connection: &DatabaseConnection, limit: i64) -> Result<Vec<(Post, User, Topic)>, PostError> {
+        use crate::schema::posts::dsl::*;
+        use crate::schema::topics::dsl::topics;
+        use crate::schema::users::dsl::users;
+        use diesel::prelude::*;
+        posts
+            .filter(pending.eq(true))
+            .order(created_at.asc())
+            .limit(limit)
+            .inner_join(users)
+            .inner_join(topics)
+            .load(connection)
+            .map_err(PostError::UnknownDatabaseError)
+    }
+
+    // Publishes a held post, and its topic if it was held along with it, as if it had just been created
+    pub fn approve(connection: &DatabaseConnection, post: Id<Post>) -> Result<Post, PostError> {
+        use diesel::prelude::*;
+        let approved = connection.transaction::<_, PostError, _>(|| {
+            let approved: Post = diesel::update(posts::table.filter(posts::id.eq(post)).filter(posts::pending.eq(true)))
+                .set((posts::pending.eq(false), posts::hold_reason.eq("")))
+                .get_result(connection)
+                .optional()?
+                .ok_or(PostError::NotPending)?;
+            let topic: Option<Topic> = diesel::update(topics::table.filter(topics::id.eq(approved.posted_in)).filter(topics::pending.eq(true)))
+                .set(topics::pending.eq(false))
+                .get_result(connection)
+                .optional()?;
+
+            if let Some(topic) = topic {
+                Webhook::dispatch(connection, WebhookEvent::TopicCreated, json!({ "topic": topic }))?;
+                live::notify(connection, &LiveEvent::TopicCreated { topic_id: topic.id })?;
+            }
+            Webhook::dispatch(connection, WebhookEvent::PostCreated, json!({ "post": approved }))?;
+            live::notify(
+                connection,
+                &LiveEvent::PostCreated {
+                    topic_id: approved.posted_in,
+                    post_id: approved.id,
+                },
+            )?;
+            Ok(approved)
+        })?;
+
+        info!("Post {:?} has been approved", post);
+
+        Ok(approved)
+    }
+
+    // Throws a held post away, along with its topic if that is still held too. Nothing was ever
+    // published, so no webhooks are sent.
+    pub fn reject(connection: &DatabaseConnection, post: Id<Post>) -> Result<Post, PostError> {
+        use diesel::prelude::*;
+        let rejected: Post = connection
+            .transaction(|| {
+                let rejected: Option<Post> = posts::table
+                    .filter(posts::id.eq(post))
+                    .filter(posts::pending.eq(true))
+                    .first(connection)
+                    .optional()?;
+                let rejected = match rejected {
+                    Some(rejected) => rejected,
+                    None => return Ok(None),
+                };
+                let topic_pending = topics::table
+                    .select(topics::pending)
+                    .filter(topics::id.eq(rejected.posted_in))
+                    .first(connection)?;
+                if topic_pending {
+                    let topic_posts = posts::table.select(posts::id).filter(posts::posted_in.eq(rejected.posted_in));
+                    diesel::delete(attachments::table.filter(attachments::post_id.eq_any(topic_posts))).execute(connection)?;
+                    diesel::delete(posts::table.filter(posts::posted_in.eq(rejected.posted_in))).execute(connection)?;
+                    diesel::delete(topics::table.filter(topics::id.eq(rejected.posted_in))).execute(connection)?;
+                } else {
+                    diesel::delete(attachments::table.filter(attachments::post_id.eq(post))).execute(connection)?;
+                    diesel::delete(posts::table.filter(posts::id.eq(post))).execute(connection)?;
+                }
+                Ok(Some(rejected))
+            })
+            .map_err(PostError::UnknownDatabaseError)?
+            .ok_or(PostError::NotPending)?;
+
+        info!("Post {:?} has been rejected", post);
+
+        Ok(rejected)
+    }
+
+    pub fn rendered_content(&self) -> String {
+        markup::render(&self.content)
+    }
+
+    // Held posts are left out, except for the viewer's own
+    pub fn in_topic_by_date(
+        connection: &DatabaseConnection,
+        topic: Id<Topic>,
+        viewer: Option<Id<User>>,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<(Post, User)>, PostError> {
+        use crate::schema::posts::dsl::*;
+        use crate::schema::users::dsl::users;
+        use diesel::prelude::*;
+        let query = posts
+            .order(created_at.desc())
+            .filter(posted_in.eq(topic))
+            .limit(limit)
+            .offset(offset)
+            .inner_join(users)
+            .into_boxed();
+        let query = match viewer {
+            Some(viewer) => query.filter(pending.eq(false).or(created_by.eq(viewer))),
+            None => query.filter(pending.eq(false)),
+        };
+        query.load(connection).map_err(PostError::UnknownDatabaseError)
+    }
+
+    // Oldest first, for catching up a live stream that missed some
+    pub fn in_topic_after(connection: &DatabaseConnection, topic: Id<Topic>, after: Id<Post>, limit: i64) -> Result<Vec<(Post, User)>, PostError> {
+        use crate::schema::posts::dsl::*;
+        use crate::schema::users::dsl::users;
+        use diesel::prelude::*;
+        posts
+            .filter(posted_in.eq(topic))
+            .filter(id.gt(after))
+            .filter(pending.eq(false))
+            .order(id.asc())
+            .limit(limit)
+            .inner_join(users)
+            .load(connection)
+            .map_err(PostError::UnknownDatabaseError)
+    }
+
+    pub fn by_id(connection: &DatabaseConnection, post: Id<Post>) -> Result<(Post, User), PostError> {
+        use crate::schema::posts::dsl::*;
+        use crate::schema::users::dsl::users;
+        use diesel::prelude::*;
+        posts.filter(id.eq(post)).inner_join(users).first(connection).map_err(|err| match err {
+            DieselError::NotFound => PostError::NoSuchPost,
+            _ => PostError::UnknownDatabaseError(err),
+        })
+    }
+
+    // Posts that don't exist are left out
+    pub fn by_ids(connection: &DatabaseConnection, post_ids: &[Id<Post>]) -> Result<Vec<(Post, User)>, PostError> {
+        use crate::schema::posts::dsl::*;
+        use crate::schema::users::dsl::users;
+        use diesel::prelude::*;
+        posts
+            .filter(id.eq_any(post_ids))
+            .inner_join(users)
+            .load(connection)
+            .map_err(PostError::UnknownDatabaseError)
+    }
+
+    // Held posts included, for telling how fast someone is posting
+    pub fn count_by_user_since(connection: &DatabaseConnection, user: Id<User>, since: chrono::DateTime<chrono::Utc>) -> Result<i64, PostError> {
+        use crate::schema::posts::dsl::*;
+        use diesel::prelude::*;
+        posts
+            .filter(created_by.eq(user))
+            .filter(created_at.gt(since))
+            .count()
+            .get_result(connection)
+            .map_err(PostError::UnknownDatabaseError)
+    }
+
+    // Like by_id, but as if held posts did not exist for anyone but their author
+    pub fn visible_by_id(connection: &DatabaseConnection, post: Id<Post>, viewer: Option<Id<User>>) -> Result<(Post, User), PostError> {
+        match Post::by_id(connection, post)? {
+            (post, _) if post.pending && Some(post.created_by) != viewer => Err(PostError::NoSuchPost),
+            result => Ok(result),
+        }
+    }
+
+    // How many posts in the same topic come before this one on the newest first pages, as the
+    // viewer sees them
+    pub fn newer_count(connection: &DatabaseConnection, post: &Post, viewer: Option<Id<User>>) -> Result<i64, PostError> {
+        use crate::schema::posts::dsl::*;
+        use diesel::prelude::*;
+        let query = posts.filter(posted_in.eq(post.posted_in)).filter(created_at.gt(post.created_at)).into_boxed();
+        let query = match viewer {
+            Some(viewer) => query.filter(pending.eq(false).or(created_by.eq(viewer))),
+            None => query.filter(pending.eq(false)),
+        };
+        query.count().get_result(connection).map_err(PostError::UnknownDatabaseError)
+    }
+
+    // Edits go through the word filters the same as new posts. One that is held takes the post off
+    // the board until a moderator approves it again.
+    pub fn set_content(connection: &DatabaseConnection, post: Id<Post>, new_content: &str) -> Result<(), PostError> {
+        let filtered = WordFilter::apply(connection, FilterTarget::Content, new_content)?;
+        let edited: Post = {
+            use crate::schema::posts::dsl::*;
+            use diesel::prelude::*;
+            let query = diesel::update(posts.filter(id.eq(post)));
+            match &filtered.hold_reason {
+                Some(reason) => query
+                    .set((content.eq(&filtered.text), pending.eq(true), hold_reason.eq(reason)))
+                    .get_result(connection),
+                None => query.set(content.eq(&filtered.text)).get_result(connection),
+            }
+            .optional()
+            .map_err(PostError::UnknownDatabaseError)?
+            .ok_or(PostError::NoSuchPost)?
+        };
+
+        // Held posts were never published, so nobody outside needs to hear about them
+        if !edited.pending {
+            Webhook::dispatch(connection, WebhookEvent::PostEdited, json!({ "post": edited })).map_err(PostError::UnknownDatabaseError)?;
+        }
+
+        match filtered.hold_reason {
+            Some(reason) => info!("Post {:?} has been edited, held for approval: {}", post, reason),
+            None => info!("Post {:?} has been edited", post),
+        }
+
+        Ok(())
+    }
+
+    pub fn delete(connection: &DatabaseConnection, post: Id<Post>) -> Result<(), PostError> {
+        let deleted: Post = {
             use crate::schema::posts::dsl::*;
-            use crate::schema::users::dsl::users;
             use diesel::prelude::*;
-            posts
-                .order(created_at.desc())
-                .filter(posted_in.eq(topic))
-                .limit(limit)
-                .offset(offset)
-                .inner_join(users)
-                .load(connection)
+            connection
+                .transaction(|| {
+                    diesel::delete(attachments::table.filter(attachments::post_id.eq(post))).execute(connection)?;
+                    diesel::delete(posts.filter(id.eq(post))).get_result(connection).optional()
+                })
                 .map_err(PostError::UnknownDatabaseError)?
-        })
+                .ok_or(PostError::NoSuchPost)?
+        };
+
+        if !deleted.pending {
+            Webhook::dispatch(connection, WebhookEvent::PostDeleted, json!({ "post": deleted })).map_err(PostError::UnknownDatabaseError)?;
+        }
+
+        info!("Post {:?} has been deleted", post);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::topic::TopicError;
+    use crate::models::word_filter::FilterRule;
+    use diesel::Connection;
+
+    // Runs against the database in DATABASE_URL, with everything rolled back afterwards
+    fn with_database(test: impl FnOnce(&DatabaseConnection) -> Result<(), PostError>) {
+        let url = match std::env::var("DATABASE_URL") {
+            Ok(url) => url,
+            Err(_) => return,
+        };
+        let connection = DatabaseConnection::establish(&url).expect("The test database is reachable");
+        connection.test_transaction(|| test(&connection));
+    }
+
+    fn filter(connection: &DatabaseConnection, creator: Id<User>, pattern: &str, action: &str) {
+        let rule = FilterRule {
+            pattern: pattern.to_owned(),
+            is_regex: false,
+            action: action.to_owned(),
+            replacement: String::new(),
+            message: String::new(),
+        };
+        WordFilter::create(connection, creator, &rule, true, false).expect("The filter is valid");
+    }
+
+    #[test]
+    fn edits_go_through_the_word_filters() {
+        with_database(|connection| {
+            let author = User::register(
+                connection,
+                &"filter_test_author".to_owned(),
+                &"Passw0rd long pass".to_owned(),
+                "filter_test@example.com",
+            )?;
+            let (topic, post) =
+                Topic::create(connection, author.id, "A topic", "Nothing wrong with this", None).map_err(|err| PostError::Unknown(err.to_string()))?;
+            filter(connection, author.id, "forbidden", "reject");
+            filter(connection, author.id, "dubious", "hold");
+
+            assert!(matches!(
+                Post::set_content(connection, post.id, "Now it is forbidden"),
+                Err(PostError::Rejected(_))
+            ));
+            assert!(matches!(
+                Topic::set_title(connection, topic.id, "A forbidden topic"),
+                Err(TopicError::Rejected(_))
+            ));
+            let (unchanged, _) = Post::by_id(connection, post.id)?;
+            assert_eq!(unchanged.content, "Nothing wrong with this");
+
+            Post::set_content(connection, post.id, "Now it is dubious")?;
+            let (held, _) = Post::by_id(connection, post.id)?;
+            assert!(held.pending);
+            assert!(!held.hold_reason.is_empty());
+            Ok(())
+        });
     }
 }
@@ -0,0 +1,228 @@
+use crate::database::types::*;
+use crate::models::post::Post;
+use crate::models::topic::Topic;
+use crate::models::user::User;
+use crate::models::webhook::{Webhook, WebhookEvent};
+use crate::schema::reports;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use chrono;
+use diesel::result::Error as DieselError;
+use diesel::{Insertable, Queryable};
+use log::info;
+use serde::Serialize;
+
+pub const MAX_REASON_SIZE: usize = 1000;
+
+// Reports stay open until a moderator either acts on them or decides there is nothing to do
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReportStatus {
+    Open,
+    Resolved,
+    Dismissed,
+}
+
+impl ReportStatus {
+    pub const ALL: [ReportStatus; 3] = [ReportStatus::Open, ReportStatus::Resolved, ReportStatus::Dismissed];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReportStatus::Open => "open",
+            ReportStatus::Resolved => "resolved",
+            ReportStatus::Dismissed => "dismissed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<ReportStatus> {
+        ReportStatus::ALL.iter().copied().find(|status| status.as_str() == value)
+    }
+}
+
+// A report is about a post, or about the topic itself when there is no post
+#[derive(Clone, Queryable, Serialize)]
+pub struct Report {
+    pub id: Id<Report>,
+    pub topic_id: Id<Topic>,
+    pub post_id: Option<Id<Post>>,
+    pub reported_by: Id<User>,
+    pub reason: String,
+    pub status: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub handled_by: Option<Id<User>>,
+    pub handled_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Insertable)]
+#[table_name = "reports"]
+pub struct NewReport {
+    pub topic_id: Id<Topic>,
+    pub post_id: Option<Id<Post>>,
+    pub reported_by: Id<User>,
+    pub reason: String,
+    pub status: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug)]
+pub enum ReportError {
+    ReasonEmpty,
+    ReasonTooLong,
+    AlreadyReported,
+    AlreadyHandled,
+    NoSuchReport,
+    UnknownDatabaseError(DieselError),
+    Unknown(String),
+}
+
+impl std::fmt::Display for ReportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ReportError::ReasonEmpty => write!(f, "Please give a reason for the report"),
+            ReportError::ReasonTooLong => write!(f, "Reasons must be at most {} characters long", MAX_REASON_SIZE),
+            ReportError::AlreadyReported => write!(f, "You have already reported this, a moderator will look at it soon"),
+            ReportError::AlreadyHandled => write!(f, "This report has already been handled"),
+            ReportError::NoSuchReport => write!(f, "No such report"),
+            ReportError::UnknownDatabaseError(err) => write!(f, "{}", err),
+            ReportError::Unknown(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<DieselError> for ReportError {
+    fn from(error: DieselError) -> ReportError {
+        ReportError::UnknownDatabaseError(error)
+    }
+}
+
+impl From<ReportError> for RyobError {
+    fn from(error: ReportError) -> RyobError {
+        match error {
+            ReportError::ReasonEmpty => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            ReportError::ReasonTooLong => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            ReportError::AlreadyReported => RyobError::from_display(actix_web::http::StatusCode::CONFLICT, error),
+            ReportError::AlreadyHandled => RyobError::from_display(actix_web::http::StatusCode::CONFLICT, error),
+            ReportError::NoSuchReport => RyobError::from_display(actix_web::http::StatusCode::NOT_FOUND, error),
+            _ => RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error),
+        }
+    }
+}
+
+impl Report {
+    // Each user can have one open report on a post or topic at a time
+    pub fn file(connection: &DatabaseConnection, reporter: Id<User>, topic: Id<Topic>, post: Option<Id<Post>>, reason: &str) -> Result<Report, ReportError> {
+        let reason = reason.trim();
+        if reason.is_empty() {
+            return Err(ReportError::ReasonEmpty);
+        }
+        if reason.chars().count() > MAX_REASON_SIZE {
+            return Err(ReportError::ReasonTooLong);
+        }
+
+        let already_reported = Report::open_for_target(connection, topic, post)?
+            .iter()
+            .any(|report| report.reported_by == reporter);
+        if already_reported {
+            return Err(ReportError::AlreadyReported);
+        }
+
+        let new_report = NewReport {
+            topic_id: topic,
+            post_id: post,
+            reported_by: reporter,
+            reason: reason.to_owned(),
+            status: ReportStatus::Open.as_str().to_owned(),
+            created_at: chrono::Utc::now(),
+        };
+        let report: Report = {
+            use diesel::prelude::*;
+            diesel::insert_into(reports::table).values(&new_report).get_result(connection)?
+        };
+
+        Webhook::dispatch(connection, WebhookEvent::ReportFiled, json!({ "report": report }))?;
+
+        info!("User {:?} has reported topic {:?}, post {:?}", reporter, topic, post);
+
+        Ok(report)
+    }
+
+    pub fn status(&self) -> ReportStatus {
+        ReportStatus::parse(&self.status).unwrap_or(ReportStatus::Open)
+    }
+
+    pub fn by_id(connection: &DatabaseConnection, report: Id<Report>) -> Result<Report, ReportError> {
+        use crate::schema::reports::dsl::*;
+        use diesel::prelude::*;
+        reports.filter(id.eq(report)).first(connection).optional()?.ok_or(ReportError::NoSuchReport)
+    }
+
+    // Oldest first, so the queue is worked through in the order reports came in
+    pub fn open(connection: &DatabaseConnection, limit: i64) -> Result<Vec<(Report, User)>, ReportError> {
+        use crate::schema::reports::dsl::*;
+        use crate::schema::users::dsl::users;
+        use diesel::prelude::*;
+        Ok(reports
+            .filter(status.eq(ReportStatus::Open.as_str()))
+            .order(created_at.asc())
+            .limit(limit)
+            .inner_join(users)
+            .load(connection)?)
+    }
+
+    pub fn recently_handled(connection: &DatabaseConnection, limit: i64) -> Result<Vec<(Report, User)>, ReportError> {
+        use crate::schema::reports::dsl::*;
+        use crate::schema::users::dsl::users;
+        use diesel::prelude::*;
+        Ok(reports
+            .filter(status.ne(ReportStatus::Open.as_str()))
+            .order(handled_at.desc())
+            .limit(limit)
+            .inner_join(users)
+            .load(connection)?)
+    }
+
+    fn open_for_target(connection: &DatabaseConnection, topic: Id<Topic>, post: Option<Id<Post>>) -> Result<Vec<Report>, ReportError> {
+        use crate::schema::reports::dsl::*;
+        use diesel::prelude::*;
+        let query = reports.filter(status.eq(ReportStatus::Open.as_str())).filter(topic_id.eq(topic)).into_boxed();
+        let query = match post {
+            Some(post) => query.filter(post_id.eq(post)),
+            None => query.filter(post_id.is_null()),
+        };
+        Ok(query.load(connection)?)
+    }
+
+    // Handling a report handles every open report about the same post or topic along with it
+    pub fn handle(connection: &DatabaseConnection, report: Id<Report>, handler: Id<User>, new_status: ReportStatus) -> Result<usize, ReportError> {
+        let target = Report::by_id(connection, report)?;
+        if target.status() != ReportStatus::Open {
+            return Err(ReportError::AlreadyHandled);
+        }
+
+        let handled_ids: Vec<Id<Report>> = Report::open_for_target(connection, target.topic_id, target.post_id)?
+            .iter()
+            .map(|report| report.id)
+            .collect();
+        let handled = {
+            use crate::schema::reports::dsl::*;
+            use diesel::prelude::*;
+            diesel::update(reports.filter(id.eq_any(&handled_ids)).filter(status.eq(ReportStatus::Open.as_str())))
+                .set((
+                    status.eq(new_status.as_str()),
+                    handled_by.eq(Some(handler)),
+                    handled_at.eq(Some(chrono::Utc::now())),
+                ))
+                .execute(connection)?
+        };
+
+        info!(
+            "User {:?} has {} {} report(s) on topic {:?}, post {:?}",
+            handler,
+            new_status.as_str(),
+            handled,
+            target.topic_id,
+            target.post_id
+        );
+
+        Ok(handled)
+    }
+}
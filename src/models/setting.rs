@@ -0,0 +1,96 @@
+use crate::database::types::*;
+use crate::schema::settings;
+use crate::utils::errors::RyobError;
+use diesel::result::Error as DieselError;
+use diesel::{Insertable, Queryable};
+use log::info;
+
+// Site-wide settings changed from the admin panel, stored as text and read with a default for
+// when they have never been set
+pub const REQUIRE_TWO_FACTOR_FOR_MODERATORS: &str = "require_two_factor_for_moderators";
+pub const DEFAULT_THEME: &str = "default_theme";
+// Days before recorded IP addresses are anonymized, 0 keeps them
+pub const IP_RETENTION_DAYS: &str = "ip_retention_days";
+// Members' posts are held for approval until their account is this many days old and has this
+// many approved posts, 0 for either leaves it out
+pub const PROBATION_DAYS: &str = "probation_days";
+pub const PROBATION_POSTS: &str = "probation_posts";
+// Spam scores at which submissions are held for approval or turned away, 0 turns either off
+pub const SPAM_HOLD_SCORE: &str = "spam_hold_score";
+pub const SPAM_REJECT_SCORE: &str = "spam_reject_score";
+
+#[derive(Queryable, Insertable)]
+#[table_name = "settings"]
+pub struct Setting {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug)]
+pub enum SettingError {
+    UnknownDatabaseError(DieselError),
+    Unknown(String),
+}
+
+impl std::fmt::Display for SettingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SettingError::UnknownDatabaseError(err) => write!(f, "{}", err),
+            SettingError::Unknown(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<SettingError> for RyobError {
+    fn from(error: SettingError) -> RyobError {
+        RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error)
+    }
+}
+
+impl Setting {
+    pub fn get(connection: &DatabaseConnection, target_key: &str) -> Result<Option<String>, SettingError> {
+        use crate::schema::settings::dsl::*;
+        use diesel::prelude::*;
+        settings
+            .filter(key.eq(target_key))
+            .select(value)
+            .first::<String>(connection)
+            .optional()
+            .map_err(SettingError::UnknownDatabaseError)
+    }
+
+    pub fn get_bool(connection: &DatabaseConnection, target_key: &str, default: bool) -> Result<bool, SettingError> {
+        Ok(Setting::get(connection, target_key)?.map(|stored| stored == "true").unwrap_or(default))
+    }
+
+    pub fn get_int(connection: &DatabaseConnection, target_key: &str, default: i64) -> Result<i64, SettingError> {
+        Ok(Setting::get(connection, target_key)?.and_then(|stored| stored.parse().ok()).unwrap_or(default))
+    }
+
+    pub fn set(connection: &DatabaseConnection, target_key: &str, new_value: &str) -> Result<(), SettingError> {
+        let setting = Setting {
+            key: target_key.to_owned(),
+            value: new_value.to_owned(),
+        };
+
+        {
+            use crate::schema::settings::dsl::*;
+            use diesel::prelude::*;
+            diesel::insert_into(settings)
+                .values(&setting)
+                .on_conflict(key)
+                .do_update()
+                .set(value.eq(new_value))
+                .execute(connection)
+                .map_err(SettingError::UnknownDatabaseError)?;
+        }
+
+        info!("Setting \"{}\" has been set to \"{}\"", target_key, new_value);
+
+        Ok(())
+    }
+
+    pub fn set_bool(connection: &DatabaseConnection, target_key: &str, new_value: bool) -> Result<(), SettingError> {
+        Setting::set(connection, target_key, if new_value { "true" } else { "false" })
+    }
+}
@@ -0,0 +1,158 @@
+use crate::database::types::*;
+use crate::models::post::Post;
+use crate::models::user::User;
+use crate::schema::spam_log;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use chrono;
+use diesel::result::Error as DieselError;
+use diesel::{Insertable, Queryable};
+use log::info;
+use serde::Serialize;
+
+// What was submitted to the spam checks
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpamKind {
+    Register,
+    Topic,
+    Post,
+}
+
+impl SpamKind {
+    pub const ALL: [SpamKind; 3] = [SpamKind::Register, SpamKind::Topic, SpamKind::Post];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SpamKind::Register => "register",
+            SpamKind::Topic => "topic",
+            SpamKind::Post => "post",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<SpamKind> {
+        SpamKind::ALL.iter().copied().find(|kind| kind.as_str() == value)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpamDecision {
+    Allow,
+    Hold,
+    Reject,
+}
+
+impl SpamDecision {
+    pub const ALL: [SpamDecision; 3] = [SpamDecision::Allow, SpamDecision::Hold, SpamDecision::Reject];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SpamDecision::Allow => "allow",
+            SpamDecision::Hold => "hold",
+            SpamDecision::Reject => "reject",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<SpamDecision> {
+        SpamDecision::ALL.iter().copied().find(|decision| decision.as_str() == value)
+    }
+}
+
+// Signals is a JSON array of what each check found. Rejected submissions have no user or post.
+#[derive(Clone, Queryable, Serialize)]
+pub struct SpamLogEntry {
+    pub id: Id<SpamLogEntry>,
+    pub kind: String,
+    pub user_id: Option<Id<User>>,
+    pub post_id: Option<Id<Post>>,
+    pub content_hash: Option<String>,
+    pub score: i32,
+    pub decision: String,
+    pub signals: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Insertable)]
+#[table_name = "spam_log"]
+pub struct NewSpamLogEntry {
+    pub kind: String,
+    pub user_id: Option<Id<User>>,
+    pub post_id: Option<Id<Post>>,
+    pub content_hash: Option<String>,
+    pub score: i32,
+    pub decision: String,
+    pub signals: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug)]
+pub enum SpamLogError {
+    UnknownDatabaseError(DieselError),
+    Unknown(String),
+}
+
+impl std::fmt::Display for SpamLogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SpamLogError::UnknownDatabaseError(err) => write!(f, "{}", err),
+            SpamLogError::Unknown(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<DieselError> for SpamLogError {
+    fn from(error: DieselError) -> SpamLogError {
+        SpamLogError::UnknownDatabaseError(error)
+    }
+}
+
+impl From<SpamLogError> for RyobError {
+    fn from(error: SpamLogError) -> RyobError {
+        RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error)
+    }
+}
+
+impl SpamLogEntry {
+    pub fn record(connection: &DatabaseConnection, new_entry: &NewSpamLogEntry) -> Result<SpamLogEntry, SpamLogError> {
+        let entry: SpamLogEntry = {
+            use diesel::prelude::*;
+            diesel::insert_into(spam_log::table).values(new_entry).get_result(connection)?
+        };
+
+        if entry.decision != SpamDecision::Allow.as_str() {
+            info!(
+                "The spam checks decided to {} a {} scoring {}: {}",
+                entry.decision, entry.kind, entry.score, entry.signals
+            );
+        }
+
+        Ok(entry)
+    }
+
+    // Newest first, optionally only those with one decision
+    pub fn recent(
+        connection: &DatabaseConnection,
+        only: Option<SpamDecision>,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<(SpamLogEntry, Option<User>)>, SpamLogError> {
+        use crate::schema::spam_log::dsl::*;
+        use crate::schema::users::dsl::users;
+        use diesel::prelude::*;
+        let mut query = spam_log.left_join(users).into_boxed();
+        if let Some(only) = only {
+            query = query.filter(decision.eq(only.as_str()));
+        }
+        Ok(query.order(id.desc()).offset(offset).limit(limit).load(connection)?)
+    }
+
+    // How many times the same content has been submitted since the given time
+    pub fn hash_count_since(connection: &DatabaseConnection, hash: &str, since: chrono::DateTime<chrono::Utc>) -> Result<i64, SpamLogError> {
+        use crate::schema::spam_log::dsl::*;
+        use diesel::prelude::*;
+        Ok(spam_log
+            .filter(content_hash.eq(hash))
+            .filter(created_at.gt(since))
+            .count()
+            .get_result(connection)?)
+    }
+}
@@ -1,5 +1,9 @@
 use crate::database::types::*;
+use crate::live::{self, LiveEvent};
+use crate::models::post::{Post, PostError};
 use crate::models::user::User;
+use crate::models::webhook::{Webhook, WebhookEvent};
+use crate::models::word_filter::{FilterTarget, Filtered, WordFilter, WordFilterError};
 use crate::schema::topics;
 use crate::utils::errors::RyobError;
 use crate::utils::id::Id;
@@ -15,6 +19,8 @@ pub struct Topic {
     pub title: String,
     pub created_by: Id<User>,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    // Held along with its first post until a moderator approves that
+    pub pending: bool,
 }
 
 #[derive(Insertable)]
@@ -23,10 +29,13 @@ pub struct NewTopic {
     pub title: String,
     pub created_by: Id<User>,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    pub pending: bool,
 }
 
 #[derive(Debug)]
 pub enum TopicError {
+    NoSuchTopic,
+    Rejected(String),
     UnknownDatabaseError(DieselError),
     Unknown(String),
 }
@@ -34,6 +43,8 @@ pub enum TopicError {
 impl std::fmt::Display for TopicError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
+            TopicError::NoSuchTopic => write!(f, "No such topic"),
+            TopicError::Rejected(message) => write!(f, "{}", message),
             TopicError::UnknownDatabaseError(err) => write!(f, "{}", err),
             TopicError::Unknown(err) => write!(f, "{}", err),
         }
@@ -42,45 +53,191 @@ impl std::fmt::Display for TopicError {
 
 impl From<TopicError> for RyobError {
     fn from(error: TopicError) -> RyobError {
-        RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error)
+        match error {
+            TopicError::NoSuchTopic => RyobError::from_display(actix_web::http::StatusCode::NOT_FOUND, error),
+            TopicError::Rejected(_) => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            _ => RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error),
+        }
+    }
+}
+
+impl From<DieselError> for TopicError {
+    fn from(error: DieselError) -> TopicError {
+        TopicError::UnknownDatabaseError(error)
+    }
+}
+
+impl From<WordFilterError> for TopicError {
+    fn from(error: WordFilterError) -> TopicError {
+        match error {
+            WordFilterError::Rejected(message) => TopicError::Rejected(message),
+            WordFilterError::UnknownDatabaseError(err) => TopicError::UnknownDatabaseError(err),
+            _ => TopicError::Unknown(error.to_string()),
+        }
+    }
+}
+
+impl From<PostError> for TopicError {
+    fn from(error: PostError) -> TopicError {
+        match error {
+            PostError::Rejected(message) => TopicError::Rejected(message),
+            PostError::UnknownDatabaseError(err) => TopicError::UnknownDatabaseError(err),
+            _ => TopicError::Unknown(error.to_string()),
+        }
     }
 }
 
 impl Topic {
-    pub fn create(connection: &DatabaseConnection, creator: Id<User>, title: &String) -> Result<Topic, TopicError> {
-        let timestamp = chrono::Utc::now();
+    // A topic is created together with its first post. Both go through the word filters, and if
+    // either is held then so is the other. Callers pass on any reason of their own for holding it.
+    pub fn create(connection: &DatabaseConnection, creator: Id<User>, title: &str, content: &str, held: Option<String>) -> Result<(Topic, Post), TopicError> {
+        use diesel::prelude::*;
+        let (result, post) = connection.transaction::<_, TopicError, _>(|| {
+            let filtered_title = WordFilter::apply(connection, FilterTarget::Content, title)?;
+            let filtered_content = WordFilter::apply(connection, FilterTarget::Content, content)?;
+            let hold_reason = Post::hold_reason(connection, creator, filtered_title.hold_reason.or(filtered_content.hold_reason).or(held))?;
 
-        let new_topic = NewTopic {
-            title: title.clone(),
-            created_by: creator,
-            created_at: timestamp,
-        };
+            let new_topic = NewTopic {
+                title: filtered_title.text,
+                created_by: creator,
+                created_at: chrono::Utc::now(),
+                pending: hold_reason.is_some(),
+            };
+            let result: Topic = diesel::insert_into(topics::table).values(&new_topic).get_result(connection)?;
+            let post = Post::insert(
+                connection,
+                creator,
+                result.id,
+                Filtered {
+                    text: filtered_content.text,
+                    hold_reason,
+                },
+            )?;
 
-        let result = {
-            use diesel::prelude::*;
-            diesel::insert_into(topics::table)
-                .values(&new_topic)
-                .get_result(connection)
-                .map_err(TopicError::UnknownDatabaseError)?
-        };
+            if !result.pending {
+                Webhook::dispatch(connection, WebhookEvent::TopicCreated, json!({ "topic": result }))?;
+                live::notify(connection, &LiveEvent::TopicCreated { topic_id: result.id })?;
+            }
+            Ok((result, post))
+        })?;
+
+        info!("User {:?} has created a topic titled \"{}\"", creator, result.title);
 
-        info!("User {:?} has created a topic titled \"{}\"", creator, title);
+        Ok((result, post))
+    }
 
-        Ok(result)
+    // Held topics are left out, except for the viewer's own
+    pub fn by_date(connection: &DatabaseConnection, viewer: Option<Id<User>>, offset: i64, limit: i64) -> Result<Vec<(Topic, User)>, TopicError> {
+        use crate::schema::topics::dsl::*;
+        use crate::schema::users::dsl::users;
+        use diesel::prelude::*;
+        let query = topics.order(created_at.desc()).limit(limit).offset(offset).inner_join(users).into_boxed();
+        let query = match viewer {
+            Some(viewer) => query.filter(pending.eq(false).or(created_by.eq(viewer))),
+            None => query.filter(pending.eq(false)),
+        };
+        query.load(connection).map_err(TopicError::UnknownDatabaseError)
     }
 
-    pub fn by_date(connection: &DatabaseConnection, offset: i64, limit: i64) -> Result<Vec<(Topic, User)>, TopicError> {
-        Ok({
-            use crate::schema::topics::dsl::*;
-            use crate::schema::users::dsl::users;
-            use diesel::prelude::*;
-            topics
-                .order(created_at.desc())
-                .limit(limit)
-                .offset(offset)
-                .inner_join(users)
-                .load(connection)
-                .map_err(TopicError::UnknownDatabaseError)?
+    // Oldest first, for catching up a live stream that missed some
+    pub fn ids_after(connection: &DatabaseConnection, after: Id<Topic>, limit: i64) -> Result<Vec<Id<Topic>>, TopicError> {
+        use crate::schema::topics::dsl::*;
+        use diesel::prelude::*;
+        topics
+            .select(id)
+            .filter(id.gt(after))
+            .filter(pending.eq(false))
+            .order(id.asc())
+            .limit(limit)
+            .load(connection)
+            .map_err(TopicError::UnknownDatabaseError)
+    }
+
+    pub fn by_id(connection: &DatabaseConnection, topic: Id<Topic>) -> Result<(Topic, User), TopicError> {
+        use crate::schema::topics::dsl::*;
+        use crate::schema::users::dsl::users;
+        use diesel::prelude::*;
+        topics.filter(id.eq(topic)).inner_join(users).first(connection).map_err(|err| match err {
+            DieselError::NotFound => TopicError::NoSuchTopic,
+            _ => TopicError::UnknownDatabaseError(err),
         })
     }
+
+    // Topics that don't exist are left out
+    pub fn by_ids(connection: &DatabaseConnection, topic_ids: &[Id<Topic>]) -> Result<Vec<(Topic, User)>, TopicError> {
+        use crate::schema::topics::dsl::*;
+        use crate::schema::users::dsl::users;
+        use diesel::prelude::*;
+        topics
+            .filter(id.eq_any(topic_ids))
+            .inner_join(users)
+            .load(connection)
+            .map_err(TopicError::UnknownDatabaseError)
+    }
+
+    // Like by_id, but as if held topics did not exist for anyone but their author
+    pub fn visible_by_id(connection: &DatabaseConnection, topic: Id<Topic>, viewer: Option<Id<User>>) -> Result<(Topic, User), TopicError> {
+        match Topic::by_id(connection, topic)? {
+            (topic, _) if topic.pending && Some(topic.created_by) != viewer => Err(TopicError::NoSuchTopic),
+            result => Ok(result),
+        }
+    }
+
+    // Titles go through the word filters like post content. A held title takes the topic off the
+    // board along with its first post, which is what moderators approve it by.
+    pub fn set_title(connection: &DatabaseConnection, topic: Id<Topic>, new_title: &str) -> Result<(), TopicError> {
+        use diesel::prelude::*;
+        let filtered = WordFilter::apply(connection, FilterTarget::Content, new_title)?;
+        connection.transaction::<_, TopicError, _>(|| {
+            let updated = match &filtered.hold_reason {
+                Some(_) => diesel::update(topics::table.filter(topics::id.eq(topic)))
+                    .set((topics::title.eq(&filtered.text), topics::pending.eq(true)))
+                    .execute(connection)?,
+                None => diesel::update(topics::table.filter(topics::id.eq(topic)))
+                    .set(topics::title.eq(&filtered.text))
+                    .execute(connection)?,
+            };
+            if updated == 0 {
+                return Err(TopicError::NoSuchTopic);
+            }
+            if let Some(reason) = &filtered.hold_reason {
+                let first_post = crate::schema::posts::table
+                    .select(crate::schema::posts::id)
+                    .filter(crate::schema::posts::posted_in.eq(topic))
+                    .order(crate::schema::posts::id.asc())
+                    .first::<Id<Post>>(connection)?;
+                diesel::update(crate::schema::posts::table.filter(crate::schema::posts::id.eq(first_post)))
+                    .set((crate::schema::posts::pending.eq(true), crate::schema::posts::hold_reason.eq(reason)))
+                    .execute(connection)?;
+            }
+            Ok(())
+        })?;
+
+        match filtered.hold_reason {
+            Some(reason) => info!("Topic {:?} has been retitled \"{}\", held for approval: {}", topic, filtered.text, reason),
+            None => info!("Topic {:?} has been retitled \"{}\"", topic, filtered.text),
+        }
+
+        Ok(())
+    }
+
+    // Takes the topic's posts with it
+    pub fn delete(connection: &DatabaseConnection, topic: Id<Topic>) -> Result<(), TopicError> {
+        use diesel::prelude::*;
+        connection
+            .transaction(|| {
+                let topic_posts = crate::schema::posts::table
+                    .select(crate::schema::posts::id)
+                    .filter(crate::schema::posts::posted_in.eq(topic));
+                diesel::delete(crate::schema::attachments::table.filter(crate::schema::attachments::post_id.eq_any(topic_posts))).execute(connection)?;
+                diesel::delete(crate::schema::posts::table.filter(crate::schema::posts::posted_in.eq(topic))).execute(connection)?;
+                diesel::delete(topics::table.filter(topics::id.eq(topic))).execute(connection)
+            })
+            .map_err(TopicError::UnknownDatabaseError)
+            .and_then(|deleted| if deleted == 0 { Err(TopicError::NoSuchTopic) } else { Ok(()) })?;
+
+        info!("Topic {:?} has been deleted", topic);
+
+        Ok(())
+    }
 }
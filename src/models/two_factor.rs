@@ -0,0 +1,313 @@
+use crate::database::types::*;
+use crate::models::user::User;
+use crate::schema::{two_factor_recovery_codes, two_factor_secrets};
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use crate::utils::{signing, totp};
+use chrono;
+use diesel::result::Error as DieselError;
+use diesel::{Insertable, Queryable};
+use log::{info, warn};
+use serde::Serialize;
+
+pub const RECOVERY_CODE_COUNT: usize = 10;
+// Incorrect codes in a row before codes stop being checked for a while
+pub const MAX_FAILED_ATTEMPTS: i32 = 5;
+pub const LOCKOUT_MINUTES: i64 = 15;
+
+// A secret without enabled_at is an enrollment that hasn't been confirmed with a code yet and
+// isn't asked for at login
+#[derive(Queryable, Insertable, Serialize)]
+#[table_name = "two_factor_secrets"]
+pub struct TwoFactorSecret {
+    pub user_id: Id<User>,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub enabled_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(skip_serializing)]
+    pub last_used_step: Option<i64>,
+    #[serde(skip_serializing)]
+    pub failed_attempts: i32,
+    pub locked_until: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+// Recovery codes are only shown once when generated, the database keeps a hash of each
+#[derive(Queryable)]
+pub struct RecoveryCode {
+    pub id: Id<RecoveryCode>,
+    pub user_id: Id<User>,
+    pub code_hash: String,
+    pub used_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Insertable)]
+#[table_name = "two_factor_recovery_codes"]
+pub struct NewRecoveryCode {
+    pub user_id: Id<User>,
+    pub code_hash: String,
+}
+
+#[derive(Debug)]
+pub enum TwoFactorError {
+    AlreadyEnabled,
+    NotEnrolled,
+    InvalidCode,
+    LockedOut(chrono::DateTime<chrono::Utc>),
+    UnknownDatabaseError(DieselError),
+    Unknown(String),
+}
+
+impl std::fmt::Display for TwoFactorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TwoFactorError::AlreadyEnabled => write!(f, "Two-factor authentication is already enabled"),
+            TwoFactorError::NotEnrolled => write!(f, "Two-factor authentication has not been set up"),
+            TwoFactorError::InvalidCode => write!(f, "That code is not valid"),
+            TwoFactorError::LockedOut(until) => write!(f, "Too many incorrect codes, please try again after {}", until.format("%Y-%m-%d %H:%M UTC")),
+            TwoFactorError::UnknownDatabaseError(err) => write!(f, "{}", err),
+            TwoFactorError::Unknown(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<DieselError> for TwoFactorError {
+    fn from(error: DieselError) -> TwoFactorError {
+        TwoFactorError::UnknownDatabaseError(error)
+    }
+}
+
+impl From<TwoFactorError> for RyobError {
+    fn from(error: TwoFactorError) -> RyobError {
+        match error {
+            TwoFactorError::AlreadyEnabled => RyobError::from_display(actix_web::http::StatusCode::CONFLICT, error),
+            TwoFactorError::NotEnrolled => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            TwoFactorError::InvalidCode => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            TwoFactorError::LockedOut(_) => RyobError::from_display(actix_web::http::StatusCode::TOO_MANY_REQUESTS, error),
+            _ => RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error),
+        }
+    }
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars().filter(|c| c.is_ascii_alphanumeric()).map(|c| c.to_ascii_uppercase()).collect()
+}
+
+impl TwoFactorSecret {
+    pub fn by_user(connection: &DatabaseConnection, user: Id<User>) -> Result<Option<TwoFactorSecret>, TwoFactorError> {
+        use crate::schema::two_factor_secrets::dsl::*;
+        use diesel::prelude::*;
+        Ok(two_factor_secrets.filter(user_id.eq(user)).first::<TwoFactorSecret>(connection).optional()?)
+    }
+
+    pub fn is_enabled(connection: &DatabaseConnection, user: Id<User>) -> Result<bool, TwoFactorError> {
+        Ok(TwoFactorSecret::by_user(connection, user)?
+            .map(|secret| secret.enabled_at.is_some())
+            .unwrap_or(false))
+    }
+
+    // Starting over replaces any earlier unconfirmed secret
+    pub fn begin_enrollment(connection: &DatabaseConnection, user: Id<User>) -> Result<TwoFactorSecret, TwoFactorError> {
+        if TwoFactorSecret::is_enabled(connection, user)? {
+            return Err(TwoFactorError::AlreadyEnabled);
+        }
+
+        let new_secret = TwoFactorSecret {
+            user_id: user,
+            secret: totp::generate_secret(),
+            created_at: chrono::Utc::now(),
+            enabled_at: None,
+            last_used_step: None,
+            failed_attempts: 0,
+            locked_until: None,
+        };
+
+        use crate::schema::two_factor_secrets::dsl::*;
+        use diesel::prelude::*;
+        Ok(diesel::insert_into(two_factor_secrets)
+            .values(&new_secret)
+            .on_conflict(user_id)
+            .do_update()
+            .set((secret.eq(&new_secret.secret), created_at.eq(new_secret.created_at)))
+            .get_result(connection)?)
+    }
+
+    pub fn otpauth_uri(&self, user_name: &str) -> String {
+        totp::otpauth_uri("Ryob", user_name, &self.secret)
+    }
+
+    pub fn formatted_secret(&self) -> String {
+        let characters: Vec<char> = self.secret.chars().collect();
+        characters
+            .chunks(4)
+            .map(|chunk| chunk.iter().collect::<String>())
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    // Returns the recovery codes for the user to write down
+    pub fn confirm_enrollment(connection: &DatabaseConnection, user: Id<User>, code: &str) -> Result<Vec<String>, TwoFactorError> {
+        use diesel::Connection;
+        connection.transaction(|| {
+            let pending = TwoFactorSecret::by_user(connection, user)?.ok_or(TwoFactorError::NotEnrolled)?;
+            if pending.enabled_at.is_some() {
+                return Err(TwoFactorError::AlreadyEnabled);
+            }
+            let step = totp::verify(&pending.secret, code).ok_or(TwoFactorError::InvalidCode)?;
+
+            {
+                use crate::schema::two_factor_secrets::dsl::*;
+                use diesel::prelude::*;
+                diesel::update(two_factor_secrets.filter(user_id.eq(user)))
+                    .set((enabled_at.eq(Some(chrono::Utc::now())), last_used_step.eq(Some(step))))
+                    .execute(connection)?;
+            }
+
+            info!("User {:?} has enabled two-factor authentication", user);
+
+            RecoveryCode::regenerate(connection, user)
+        })
+    }
+
+    // Accepts either a current code from the authenticator or an unused recovery code. Incorrect codes
+    // are counted against the user, so guessing can't carry on by starting a new session.
+    pub fn verify(connection: &DatabaseConnection, user: Id<User>, code: &str) -> Result<(), TwoFactorError> {
+        let enabled = match TwoFactorSecret::by_user(connection, user)? {
+            Some(enabled) if enabled.enabled_at.is_some() => enabled,
+            _ => return Err(TwoFactorError::NotEnrolled),
+        };
+        if let Some(until) = enabled.locked_until.filter(|until| *until > chrono::Utc::now()) {
+            return Err(TwoFactorError::LockedOut(until));
+        }
+
+        let accepted = match totp::verify(&enabled.secret, code) {
+            // Guarding on the last used step stops a code being replayed within its window
+            Some(step) => {
+                use crate::schema::two_factor_secrets::dsl::*;
+                use diesel::prelude::*;
+                diesel::update(
+                    two_factor_secrets
+                        .filter(user_id.eq(user))
+                        .filter(last_used_step.is_null().or(last_used_step.lt(step))),
+                )
+                .set(last_used_step.eq(Some(step)))
+                .execute(connection)?
+                    > 0
+            }
+            None => RecoveryCode::consume(connection, user, code)?,
+        };
+
+        if accepted {
+            use crate::schema::two_factor_secrets::dsl::*;
+            use diesel::prelude::*;
+            diesel::update(two_factor_secrets.filter(user_id.eq(user)))
+                .set(failed_attempts.eq(0))
+                .execute(connection)?;
+            Ok(())
+        } else {
+            Err(TwoFactorSecret::record_failure(connection, user)?)
+        }
+    }
+
+    // Counts in the database so concurrent guesses are all counted, and locks the user out once there
+    // have been too many in a row
+    fn record_failure(connection: &DatabaseConnection, user: Id<User>) -> Result<TwoFactorError, TwoFactorError> {
+        use crate::schema::two_factor_secrets::dsl::*;
+        use diesel::prelude::*;
+        let failures: i32 = diesel::update(two_factor_secrets.filter(user_id.eq(user)))
+            .set(failed_attempts.eq(failed_attempts + 1))
+            .returning(failed_attempts)
+            .get_result(connection)?;
+        if failures < MAX_FAILED_ATTEMPTS {
+            return Ok(TwoFactorError::InvalidCode);
+        }
+
+        let until = chrono::Utc::now() + chrono::Duration::minutes(LOCKOUT_MINUTES);
+        diesel::update(two_factor_secrets.filter(user_id.eq(user)))
+            .set((failed_attempts.eq(0), locked_until.eq(Some(until))))
+            .execute(connection)?;
+
+        warn!(
+            "User {:?} has been locked out of two-factor login until {} after {} incorrect codes",
+            user, until, failures
+        );
+
+        Ok(TwoFactorError::LockedOut(until))
+    }
+
+    pub fn disable(connection: &DatabaseConnection, user: Id<User>) -> Result<(), TwoFactorError> {
+        use diesel::prelude::*;
+        connection.transaction::<_, TwoFactorError, _>(|| {
+            diesel::delete(two_factor_recovery_codes::table.filter(two_factor_recovery_codes::user_id.eq(user))).execute(connection)?;
+            diesel::delete(two_factor_secrets::table.filter(two_factor_secrets::user_id.eq(user))).execute(connection)?;
+            Ok(())
+        })?;
+
+        info!("User {:?} has disabled two-factor authentication", user);
+
+        Ok(())
+    }
+}
+
+impl RecoveryCode {
+    // Replaces all of the user's recovery codes and returns the new ones formatted as XXXXX-XXXXX
+    pub fn regenerate(connection: &DatabaseConnection, user: Id<User>) -> Result<Vec<String>, TwoFactorError> {
+        let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+            .map(|_| {
+                let bytes: [u8; 7] = rand::random();
+                let encoded = base32::encode(base32::Alphabet::RFC4648 { padding: false }, &bytes);
+                format!("{}-{}", &encoded[0..5], &encoded[5..10])
+            })
+            .collect();
+
+        let new_codes: Vec<NewRecoveryCode> = codes
+            .iter()
+            .map(|code| NewRecoveryCode {
+                user_id: user,
+                code_hash: signing::hash(&normalize_recovery_code(code)),
+            })
+            .collect();
+
+        {
+            use crate::schema::two_factor_recovery_codes::dsl::*;
+            use diesel::prelude::*;
+            diesel::delete(two_factor_recovery_codes.filter(user_id.eq(user))).execute(connection)?;
+            diesel::insert_into(two_factor_recovery_codes).values(&new_codes).execute(connection)?;
+        }
+
+        info!("User {:?} has been issued new recovery codes", user);
+
+        Ok(codes)
+    }
+
+    pub fn consume(connection: &DatabaseConnection, user: Id<User>, code: &str) -> Result<bool, TwoFactorError> {
+        let updated = {
+            use crate::schema::two_factor_recovery_codes::dsl::*;
+            use diesel::prelude::*;
+            diesel::update(
+                two_factor_recovery_codes
+                    .filter(user_id.eq(user))
+                    .filter(code_hash.eq(signing::hash(&normalize_recovery_code(code))))
+                    .filter(used_at.is_null()),
+            )
+            .set(used_at.eq(Some(chrono::Utc::now())))
+            .execute(connection)?
+        };
+
+        if updated > 0 {
+            info!("User {:?} has used a recovery code", user);
+        }
+
+        Ok(updated > 0)
+    }
+
+    pub fn remaining(connection: &DatabaseConnection, user: Id<User>) -> Result<i64, TwoFactorError> {
+        use crate::schema::two_factor_recovery_codes::dsl::*;
+        use diesel::prelude::*;
+        Ok(two_factor_recovery_codes
+            .filter(user_id.eq(user))
+            .filter(used_at.is_null())
+            .count()
+            .get_result(connection)?)
+    }
+}
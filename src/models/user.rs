@@ -1,7 +1,14 @@
 use crate::database::types::*;
+use crate::models::attachment::StoredFile;
+use crate::models::ban::{Ban, BanScope};
+use crate::models::setting::{self, Setting};
+use crate::models::two_factor::TwoFactorSecret;
+use crate::models::webhook::{Webhook, WebhookEvent};
+use crate::models::word_filter::{FilterTarget, WordFilter, WordFilterError};
 use crate::schema::users;
 use crate::utils::errors::RyobError;
 use crate::utils::id::Id;
+use crate::utils::markup::{self, MarkupOptions};
 use actix_session::Session;
 use actix_web::error::Error as ActixError;
 use bcrypt::BcryptError;
@@ -11,11 +18,52 @@ use log::info;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
+// Signatures are shown under every post, so they are kept much shorter than posts and may hold
+// only one image
+pub const MAX_SIGNATURE_SIZE: usize = 300;
+pub const MAX_SIGNATURE_LINES: usize = 4;
+const MAX_SIGNATURE_IMAGES: usize = 1;
+
 #[derive(Queryable, Serialize, Deserialize)]
 pub struct User {
     pub id: Id<User>,
     pub user_name: String,
     pub password_hash: String,
+    pub email: Option<String>,
+    pub email_verified_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub session_generation: i32,
+    pub role: String,
+    pub avatar_file_id: Option<Id<StoredFile>>,
+    pub signature: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub spam_held: bool,
+}
+
+// Roles are ordered so that each includes the privileges of the ones before it
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Role {
+    Member,
+    Moderator,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Member => "member",
+            Role::Moderator => "moderator",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Role> {
+        match value {
+            "member" => Some(Role::Member),
+            "moderator" => Some(Role::Moderator),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Insertable)]
@@ -23,13 +71,20 @@ pub struct User {
 pub struct NewUser {
     pub user_name: String,
     pub password_hash: String,
+    pub email: Option<String>,
 }
 
 #[derive(Debug)]
 pub enum UserError {
     NameAlreadyInUse,
+    NameRejected(String),
+    EmailAlreadyInUse,
     BadLogin,
     NoSuchUser,
+    NotLoggedIn,
+    Forbidden,
+    TwoFactorRequired,
+    Banned(Box<Ban>),
     UnknownHashError(BcryptError),
     UnknownDatabaseError(DieselError),
     UnknownActixError(ActixError),
@@ -40,8 +95,14 @@ impl fmt::Display for UserError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             UserError::NameAlreadyInUse => write!(f, "Name already in use"),
+            UserError::NameRejected(message) => write!(f, "{}", message),
+            UserError::EmailAlreadyInUse => write!(f, "Email address already in use"),
             UserError::BadLogin => write!(f, "Bad login"),
             UserError::NoSuchUser => write!(f, "No such user"),
+            UserError::NotLoggedIn => write!(f, "Not logged in"),
+            UserError::Forbidden => write!(f, "You are not allowed to do that"),
+            UserError::TwoFactorRequired => write!(f, "Your role requires two-factor authentication to be enabled"),
+            UserError::Banned(ban) => write!(f, "{}", ban),
             UserError::UnknownActixError(err) => write!(f, "{}", err),
             UserError::UnknownDatabaseError(err) => write!(f, "{}", err),
             UserError::UnknownHashError(err) => write!(f, "{}", err),
@@ -54,20 +115,32 @@ impl From<UserError> for RyobError {
     fn from(error: UserError) -> RyobError {
         match error {
             UserError::NameAlreadyInUse => RyobError::from_display(actix_web::http::StatusCode::CONFLICT, error),
+            UserError::NameRejected(_) => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            UserError::EmailAlreadyInUse => RyobError::from_display(actix_web::http::StatusCode::CONFLICT, error),
             UserError::BadLogin => RyobError::from_display(actix_web::http::StatusCode::UNAUTHORIZED, error),
             UserError::NoSuchUser => RyobError::from_display(actix_web::http::StatusCode::NOT_FOUND, error),
+            UserError::NotLoggedIn => RyobError::from_display(actix_web::http::StatusCode::UNAUTHORIZED, error),
+            UserError::Forbidden => RyobError::from_display(actix_web::http::StatusCode::FORBIDDEN, error),
+            UserError::TwoFactorRequired => RyobError::from_display(actix_web::http::StatusCode::FORBIDDEN, error),
+            UserError::Banned(_) => RyobError::from_display(actix_web::http::StatusCode::FORBIDDEN, error),
             _ => RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error),
         }
     }
 }
 
 impl User {
-    pub fn register(connection: &DatabaseConnection, user_name: &String, password: &String) -> Result<User, UserError> {
+    pub fn register(connection: &DatabaseConnection, user_name: &String, password: &String, email: &str) -> Result<User, UserError> {
+        WordFilter::apply(connection, FilterTarget::UserName, user_name).map_err(|err| match err {
+            WordFilterError::Rejected(message) => UserError::NameRejected(message),
+            WordFilterError::UnknownDatabaseError(err) => UserError::UnknownDatabaseError(err),
+            _ => UserError::Unknown(err.to_string()),
+        })?;
         let hash = bcrypt::hash(password, 10).map_err(UserError::UnknownHashError)?;
 
         let new_user = NewUser {
             user_name: user_name.clone(),
             password_hash: hash,
+            email: Some(email.to_owned()),
         };
 
         let user: User = {
@@ -79,6 +152,7 @@ impl User {
                         let constraint_name_option = (*error_info).constraint_name();
                         match constraint_name_option {
                             Some("users_user_name_key") => UserError::NameAlreadyInUse,
+                            Some("users_email_key") => UserError::EmailAlreadyInUse,
                             _ => UserError::UnknownDatabaseError(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, error_info)),
                         }
                     }
@@ -86,6 +160,13 @@ impl User {
                 })?
         };
 
+        Webhook::dispatch(
+            connection,
+            WebhookEvent::UserRegistered,
+            json!({ "user": { "id": user.id, "user_name": user.user_name } }),
+        )
+        .map_err(UserError::UnknownDatabaseError)?;
+
         info!("User {:?} has been registered with username \"{}\"", user.id, user_name);
 
         Ok(user)
@@ -95,6 +176,7 @@ impl User {
         let user = User::by_user_name(connection, user_name)?;
         let user_verified = bcrypt::verify(password, &user.password_hash).map_err(UserError::UnknownHashError)?;
         if user_verified {
+            user.check_ban(connection, BanScope::Global)?;
             info!("User {:?} with username \"{}\" has logged in", user.id, user_name);
             Ok(user)
         } else {
@@ -113,6 +195,253 @@ impl User {
         })
     }
 
+    pub fn by_user_names(connection: &DatabaseConnection, target_user_names: &[String]) -> Result<Vec<User>, UserError> {
+        Ok({
+            use crate::schema::users::dsl::*;
+            use diesel::prelude::*;
+            users
+                .filter(user_name.eq_any(target_user_names))
+                .load::<User>(connection)
+                .map_err(UserError::UnknownDatabaseError)?
+        })
+    }
+
+    pub fn by_name(connection: &DatabaseConnection, offset: i64, limit: i64) -> Result<Vec<User>, UserError> {
+        Ok({
+            use crate::schema::users::dsl::*;
+            use diesel::prelude::*;
+            users
+                .order(user_name.asc())
+                .limit(limit)
+                .offset(offset)
+                .load::<User>(connection)
+                .map_err(UserError::UnknownDatabaseError)?
+        })
+    }
+
+    pub fn staff(connection: &DatabaseConnection) -> Result<Vec<User>, UserError> {
+        Ok({
+            use crate::schema::users::dsl::*;
+            use diesel::prelude::*;
+            users
+                .filter(role.ne(Role::Member.as_str()))
+                .order(user_name.asc())
+                .load::<User>(connection)
+                .map_err(UserError::UnknownDatabaseError)?
+        })
+    }
+
+    pub fn by_email(connection: &DatabaseConnection, target_email: &str) -> Result<Option<User>, UserError> {
+        Ok({
+            use crate::schema::users::dsl::*;
+            use diesel::prelude::*;
+            users
+                .filter(email.eq(target_email))
+                .first::<User>(connection)
+                .optional()
+                .map_err(UserError::UnknownDatabaseError)?
+        })
+    }
+
+    // Only marks the address as verified if it is still the one the verification was sent to
+    pub fn mark_email_verified(connection: &DatabaseConnection, target_user_id: Id<User>, verified_email: &str) -> Result<bool, UserError> {
+        let updated = {
+            use crate::schema::users::dsl::*;
+            use diesel::prelude::*;
+            diesel::update(users.filter(id.eq(target_user_id)).filter(email.eq(verified_email)))
+                .set(email_verified_at.eq(Some(chrono::Utc::now())))
+                .execute(connection)
+                .map_err(UserError::UnknownDatabaseError)?
+        };
+
+        if updated > 0 {
+            info!("User {:?} has verified their email address", target_user_id);
+        }
+
+        Ok(updated > 0)
+    }
+
+    // Bumping the session generation logs the user out everywhere
+    pub fn set_password(connection: &DatabaseConnection, target_user_id: Id<User>, password: &str) -> Result<(), UserError> {
+        let hash = bcrypt::hash(password, 10).map_err(UserError::UnknownHashError)?;
+
+        {
+            use crate::schema::users::dsl::*;
+            use diesel::prelude::*;
+            diesel::update(users.filter(id.eq(target_user_id)))
+                .set((password_hash.eq(hash), session_generation.eq(session_generation + 1)))
+                .execute(connection)
+                .map_err(UserError::UnknownDatabaseError)?;
+        }
+
+        info!("User {:?} has changed their password", target_user_id);
+
+        Ok(())
+    }
+
+    pub fn set_role(connection: &DatabaseConnection, target_user_id: Id<User>, new_role: Role) -> Result<(), UserError> {
+        {
+            use crate::schema::users::dsl::*;
+            use diesel::prelude::*;
+            diesel::update(users.filter(id.eq(target_user_id)))
+                .set(role.eq(new_role.as_str()))
+                .execute(connection)
+                .map_err(UserError::UnknownDatabaseError)?;
+        }
+
+        info!("User {:?} has been given the {} role", target_user_id, new_role.as_str());
+
+        Ok(())
+    }
+
+    // None goes back to the generated identicon
+    pub fn set_avatar(connection: &DatabaseConnection, target_user_id: Id<User>, file: Option<Id<StoredFile>>) -> Result<(), UserError> {
+        {
+            use crate::schema::users::dsl::*;
+            use diesel::prelude::*;
+            diesel::update(users.filter(id.eq(target_user_id)))
+                .set(avatar_file_id.eq(file))
+                .execute(connection)
+                .map_err(UserError::UnknownDatabaseError)?;
+        }
+
+        info!("User {:?} has changed their avatar", target_user_id);
+
+        Ok(())
+    }
+
+    pub fn set_signature(connection: &DatabaseConnection, target_user_id: Id<User>, new_signature: &str) -> Result<(), UserError> {
+        use crate::schema::users::dsl::*;
+        use diesel::prelude::*;
+        diesel::update(users.filter(id.eq(target_user_id)))
+            .set(signature.eq(new_signature))
+            .execute(connection)
+            .map_err(UserError::UnknownDatabaseError)?;
+        Ok(())
+    }
+
+    // For accounts the spam checks were unsure about when they registered, whose posts are then
+    // held until one is approved
+    pub fn set_spam_held(connection: &DatabaseConnection, target_user_id: Id<User>) -> Result<(), UserError> {
+        {
+            use crate::schema::users::dsl::*;
+            use diesel::prelude::*;
+            diesel::update(users.filter(id.eq(target_user_id)))
+                .set(spam_held.eq(true))
+                .execute(connection)
+                .map_err(UserError::UnknownDatabaseError)?;
+        }
+
+        info!("User {:?} has been held by the spam checks", target_user_id);
+
+        Ok(())
+    }
+
+    // The stored file is part of the address so that browsers fetch a new avatar straight away
+    pub fn avatar_url(&self) -> String {
+        match self.avatar_file_id {
+            Some(file) => format!("/users/{}/avatar?v={}", self.id.value(), file.value()),
+            None => format!("/users/{}/avatar", self.id.value()),
+        }
+    }
+
+    pub fn rendered_signature(&self) -> String {
+        let options = MarkupOptions {
+            allow_images: true,
+            max_images: MAX_SIGNATURE_IMAGES,
+        };
+        markup::render_with(&self.signature, &options)
+    }
+
+    pub fn role(&self) -> Role {
+        Role::parse(&self.role).unwrap_or(Role::Member)
+    }
+
+    // Whether the user holds a role that the admins have said must be protected by 2FA
+    pub fn two_factor_required(&self, connection: &DatabaseConnection) -> Result<bool, UserError> {
+        if self.role() < Role::Moderator {
+            return Ok(false);
+        }
+        Setting::get_bool(connection, setting::REQUIRE_TWO_FACTOR_FOR_MODERATORS, false).map_err(|err| UserError::Unknown(err.to_string()))
+    }
+
+    pub fn missing_required_two_factor(&self, connection: &DatabaseConnection) -> Result<bool, UserError> {
+        if !self.two_factor_required(connection)? {
+            return Ok(false);
+        }
+        let enabled = TwoFactorSecret::is_enabled(connection, self.id).map_err(|err| UserError::Unknown(err.to_string()))?;
+        Ok(!enabled)
+    }
+
+    pub fn has_role(&self, connection: &DatabaseConnection, minimum_role: Role) -> Result<bool, UserError> {
+        Ok(self.role() >= minimum_role && !self.missing_required_two_factor(connection)?)
+    }
+
+    // Authors can change their own content, moderators anyone's
+    pub fn may_edit(&self, connection: &DatabaseConnection, author: Id<User>) -> Result<bool, UserError> {
+        Ok(self.id == author || self.has_role(connection, Role::Moderator)?)
+    }
+
+    // Every way of putting content in front of other users checks this first
+    pub fn check_may_post(&self, connection: &DatabaseConnection) -> Result<(), UserError> {
+        self.check_ban(connection, BanScope::Posting)
+    }
+
+    // Why this account's posts are held for approval, if it is newer or has had fewer posts
+    // approved than the admins require. Staff are never held.
+    pub fn probation_reason(&self, connection: &DatabaseConnection) -> Result<Option<String>, UserError> {
+        if self.role() >= Role::Moderator {
+            return Ok(None);
+        }
+        let min_days = Setting::get_int(connection, setting::PROBATION_DAYS, 0).map_err(|err| UserError::Unknown(err.to_string()))?;
+        let min_posts = Setting::get_int(connection, setting::PROBATION_POSTS, 0).map_err(|err| UserError::Unknown(err.to_string()))?;
+        if min_days > 0 && self.created_at > chrono::Utc::now() - chrono::Duration::days(min_days) {
+            return Ok(Some(format!("Accounts are held until they are {} days old", min_days)));
+        }
+        if min_posts == 0 && !self.spam_held {
+            return Ok(None);
+        }
+        let approved: i64 = {
+            use crate::schema::posts::dsl::*;
+            use diesel::prelude::*;
+            posts
+                .filter(created_by.eq(self.id))
+                .filter(pending.eq(false))
+                .count()
+                .get_result(connection)
+                .map_err(UserError::UnknownDatabaseError)?
+        };
+        if approved < min_posts {
+            return Ok(Some(format!(
+                "Accounts are held until they have {} approved posts, this one has {}",
+                min_posts, approved
+            )));
+        }
+        if self.spam_held && approved == 0 {
+            return Ok(Some("The spam checks were unsure about this account when it registered".to_owned()));
+        }
+        Ok(None)
+    }
+
+    fn check_ban(&self, connection: &DatabaseConnection, needed: BanScope) -> Result<(), UserError> {
+        match Ban::blocking(connection, self.id, needed).map_err(|err| UserError::Unknown(err.to_string()))? {
+            Some(ban) => Err(UserError::Banned(Box::new(ban))),
+            None => Ok(()),
+        }
+    }
+
+    // The privileges of a role that requires 2FA are withheld until it has been enabled
+    pub fn required_with_role(connection: &DatabaseConnection, session: &Session, minimum_role: Role) -> Result<User, UserError> {
+        let user = User::required_from_session(connection, session)?;
+        if user.role() < minimum_role {
+            Err(UserError::Forbidden)
+        } else if user.missing_required_two_factor(connection)? {
+            Err(UserError::TwoFactorRequired)
+        } else {
+            Ok(user)
+        }
+    }
+
     pub fn by_user_id(connection: &DatabaseConnection, target_user_id: Id<User>) -> Result<User, UserError> {
         Ok({
             use crate::schema::users::dsl::*;
@@ -128,15 +457,35 @@ impl User {
         let user_id_maybe = User::id_from_session(session)?;
         match user_id_maybe {
             None => Ok(None),
-            Some(id) => Ok(Some(User::by_user_id(connection, id)?)),
+            Some(id) => {
+                let user = User::by_user_id(connection, id)?;
+                let generation = session.get::<i32>("session_generation").map_err(UserError::UnknownActixError)?.unwrap_or(0);
+                // Banned users are logged out, and told why when they try to log in again
+                let banned = Ban::blocking(connection, user.id, BanScope::Global)
+                    .map_err(|err| UserError::Unknown(err.to_string()))?
+                    .is_some();
+                if generation == user.session_generation && !banned {
+                    Ok(Some(user))
+                } else {
+                    session.clear();
+                    Ok(None)
+                }
+            }
         }
     }
 
+    pub fn required_from_session(connection: &DatabaseConnection, session: &Session) -> Result<User, UserError> {
+        User::from_session(connection, session)?.ok_or(UserError::NotLoggedIn)
+    }
+
     pub fn id_from_session(session: &Session) -> Result<Option<Id<User>>, UserError> {
         Ok(session.get::<Id<User>>("user").map_err(UserError::UnknownActixError)?)
     }
 
     pub fn to_session(&self, session: &Session) -> Result<(), UserError> {
+        session
+            .set("session_generation", self.session_generation)
+            .map_err(UserError::UnknownActixError)?;
         Ok(session.set("user", &self.id).map_err(UserError::UnknownActixError)?)
     }
 }
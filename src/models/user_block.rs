@@ -0,0 +1,110 @@
+use crate::database::types::*;
+use crate::models::user::User;
+use crate::schema::user_blocks;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use chrono;
+use diesel::result::Error as DieselError;
+use diesel::{Insertable, Queryable};
+use log::info;
+use serde::Serialize;
+
+#[derive(Queryable, Insertable, Serialize)]
+#[table_name = "user_blocks"]
+pub struct UserBlock {
+    pub blocker: Id<User>,
+    pub blocked: Id<User>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug)]
+pub enum UserBlockError {
+    CannotBlockSelf,
+    UnknownDatabaseError(DieselError),
+    Unknown(String),
+}
+
+impl std::fmt::Display for UserBlockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            UserBlockError::CannotBlockSelf => write!(f, "You cannot block yourself"),
+            UserBlockError::UnknownDatabaseError(err) => write!(f, "{}", err),
+            UserBlockError::Unknown(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<UserBlockError> for RyobError {
+    fn from(error: UserBlockError) -> RyobError {
+        match error {
+            UserBlockError::CannotBlockSelf => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            _ => RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error),
+        }
+    }
+}
+
+impl UserBlock {
+    pub fn block(connection: &DatabaseConnection, blocker: Id<User>, blocked: Id<User>) -> Result<(), UserBlockError> {
+        if blocker == blocked {
+            return Err(UserBlockError::CannotBlockSelf);
+        }
+
+        let new_block = UserBlock {
+            blocker,
+            blocked,
+            created_at: chrono::Utc::now(),
+        };
+
+        {
+            use diesel::prelude::*;
+            diesel::insert_into(user_blocks::table)
+                .values(&new_block)
+                .on_conflict_do_nothing()
+                .execute(connection)
+                .map_err(UserBlockError::UnknownDatabaseError)?;
+        }
+
+        info!("User {:?} has blocked user {:?}", blocker, blocked);
+
+        Ok(())
+    }
+
+    pub fn unblock(connection: &DatabaseConnection, target_blocker: Id<User>, target_blocked: Id<User>) -> Result<(), UserBlockError> {
+        {
+            use crate::schema::user_blocks::dsl::*;
+            use diesel::prelude::*;
+            diesel::delete(user_blocks.filter(blocker.eq(target_blocker)).filter(blocked.eq(target_blocked)))
+                .execute(connection)
+                .map_err(UserBlockError::UnknownDatabaseError)?;
+        }
+
+        info!("User {:?} has unblocked user {:?}", target_blocker, target_blocked);
+
+        Ok(())
+    }
+
+    pub fn is_blocked(connection: &DatabaseConnection, target_blocker: Id<User>, target_blocked: Id<User>) -> Result<bool, UserBlockError> {
+        Ok({
+            use crate::schema::user_blocks::dsl::*;
+            use diesel::dsl::exists;
+            use diesel::prelude::*;
+            diesel::select(exists(user_blocks.filter(blocker.eq(target_blocker)).filter(blocked.eq(target_blocked))))
+                .get_result(connection)
+                .map_err(UserBlockError::UnknownDatabaseError)?
+        })
+    }
+
+    pub fn blocked_by(connection: &DatabaseConnection, target_blocker: Id<User>) -> Result<Vec<(UserBlock, User)>, UserBlockError> {
+        Ok({
+            use crate::schema::user_blocks::dsl::*;
+            use crate::schema::users;
+            use diesel::prelude::*;
+            user_blocks
+                .filter(blocker.eq(target_blocker))
+                .inner_join(users::table.on(users::id.eq(blocked)))
+                .order(users::user_name.asc())
+                .load(connection)
+                .map_err(UserBlockError::UnknownDatabaseError)?
+        })
+    }
+}
@@ -0,0 +1,136 @@
+use crate::database::types::*;
+use crate::models::user::User;
+use crate::schema::user_preferences;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use diesel::result::Error as DieselError;
+use diesel::{AsChangeset, Insertable, Queryable};
+use log::info;
+use serde::Serialize;
+
+pub const DEFAULT_TOPICS_PER_PAGE: i64 = 25;
+pub const DEFAULT_POSTS_PER_PAGE: i64 = 50;
+pub const MIN_PER_PAGE: i32 = 5;
+pub const MAX_PER_PAGE: i32 = 100;
+
+// Settings users choose for themselves. Users who have never saved any have no row and get the
+// defaults, and every None here means the board's default.
+#[derive(Clone, Queryable, Insertable, AsChangeset, Serialize)]
+#[table_name = "user_preferences"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct UserPreferences {
+    pub user_id: Id<User>,
+    pub timezone: Option<String>,
+    pub locale: Option<String>,
+    pub theme: Option<String>,
+    pub topics_per_page: Option<i32>,
+    pub posts_per_page: Option<i32>,
+    pub email_notifications: bool,
+}
+
+#[derive(Debug)]
+pub enum UserPreferencesError {
+    InvalidTimezone,
+    InvalidPerPage,
+    UnknownDatabaseError(DieselError),
+    Unknown(String),
+}
+
+impl std::fmt::Display for UserPreferencesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            UserPreferencesError::InvalidTimezone => write!(f, "Unknown timezone"),
+            UserPreferencesError::InvalidPerPage => write!(f, "Pages must show between {} and {} items", MIN_PER_PAGE, MAX_PER_PAGE),
+            UserPreferencesError::UnknownDatabaseError(err) => write!(f, "{}", err),
+            UserPreferencesError::Unknown(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<UserPreferencesError> for RyobError {
+    fn from(error: UserPreferencesError) -> RyobError {
+        match error {
+            UserPreferencesError::InvalidTimezone => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            UserPreferencesError::InvalidPerPage => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            _ => RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error),
+        }
+    }
+}
+
+fn validate_per_page(per_page: Option<i32>) -> Result<(), UserPreferencesError> {
+    match per_page {
+        Some(per_page) if !(MIN_PER_PAGE..=MAX_PER_PAGE).contains(&per_page) => Err(UserPreferencesError::InvalidPerPage),
+        _ => Ok(()),
+    }
+}
+
+impl UserPreferences {
+    pub fn defaults(user: Id<User>) -> UserPreferences {
+        UserPreferences {
+            user_id: user,
+            timezone: None,
+            locale: None,
+            theme: None,
+            topics_per_page: None,
+            posts_per_page: None,
+            email_notifications: true,
+        }
+    }
+
+    pub fn for_user(connection: &DatabaseConnection, user: Id<User>) -> Result<UserPreferences, UserPreferencesError> {
+        let stored = {
+            use crate::schema::user_preferences::dsl::*;
+            use diesel::prelude::*;
+            user_preferences
+                .filter(user_id.eq(user))
+                .first::<UserPreferences>(connection)
+                .optional()
+                .map_err(UserPreferencesError::UnknownDatabaseError)?
+        };
+        Ok(stored.unwrap_or_else(|| UserPreferences::defaults(user)))
+    }
+
+    // Themes and locales are checked by the caller, which knows which ones are installed
+    pub fn validate(&self) -> Vec<UserPreferencesError> {
+        let mut errors = vec![];
+        if let Some(name) = &self.timezone {
+            if name.parse::<chrono_tz::Tz>().is_err() {
+                errors.push(UserPreferencesError::InvalidTimezone);
+            }
+        }
+        if validate_per_page(self.topics_per_page).is_err() || validate_per_page(self.posts_per_page).is_err() {
+            errors.push(UserPreferencesError::InvalidPerPage);
+        }
+        errors
+    }
+
+    pub fn save(&self, connection: &DatabaseConnection) -> Result<(), UserPreferencesError> {
+        if let Some(error) = self.validate().into_iter().next() {
+            return Err(error);
+        }
+
+        {
+            use crate::schema::user_preferences::dsl::*;
+            use diesel::prelude::*;
+            diesel::insert_into(user_preferences)
+                .values(self)
+                .on_conflict(user_id)
+                .do_update()
+                .set(self)
+                .execute(connection)
+                .map_err(UserPreferencesError::UnknownDatabaseError)?;
+        }
+
+        info!("User {:?} has saved their preferences", self.user_id);
+
+        Ok(())
+    }
+
+    pub fn topics_per_page(&self) -> i64 {
+        self.topics_per_page.map(i64::from).unwrap_or(DEFAULT_TOPICS_PER_PAGE)
+    }
+
+    pub fn posts_per_page(&self) -> i64 {
+        self.posts_per_page.map(i64::from).unwrap_or(DEFAULT_POSTS_PER_PAGE)
+    }
+}
@@ -0,0 +1,182 @@
+use crate::database::types::*;
+use crate::models::user::User;
+use crate::schema::user_tokens;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use crate::utils::signing;
+use chrono;
+use diesel::result::Error as DieselError;
+use diesel::{Insertable, Queryable};
+use log::info;
+
+// Tokens are handed out as signing::sign("<id>.<nonce>"). Only a hash of the nonce is stored, so
+// a leaked database row can't be turned back into a working link.
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TokenPurpose {
+    VerifyEmail,
+    ResetPassword,
+}
+
+impl TokenPurpose {
+    fn as_str(self) -> &'static str {
+        match self {
+            TokenPurpose::VerifyEmail => "verify_email",
+            TokenPurpose::ResetPassword => "reset_password",
+        }
+    }
+
+    pub fn lifetime(self) -> chrono::Duration {
+        match self {
+            TokenPurpose::VerifyEmail => chrono::Duration::hours(48),
+            TokenPurpose::ResetPassword => chrono::Duration::hours(1),
+        }
+    }
+}
+
+#[derive(Queryable)]
+pub struct UserToken {
+    pub id: Id<UserToken>,
+    pub user_id: Id<User>,
+    pub purpose: String,
+    pub nonce_hash: String,
+    pub email: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    pub used_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Insertable)]
+#[table_name = "user_tokens"]
+pub struct NewUserToken {
+    pub user_id: Id<User>,
+    pub purpose: String,
+    pub nonce_hash: String,
+    pub email: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug)]
+pub enum UserTokenError {
+    InvalidToken,
+    ExpiredToken,
+    UsedToken,
+    UnknownDatabaseError(DieselError),
+    Unknown(String),
+}
+
+impl std::fmt::Display for UserTokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            UserTokenError::InvalidToken => write!(f, "This link is not valid"),
+            UserTokenError::ExpiredToken => write!(f, "This link has expired"),
+            UserTokenError::UsedToken => write!(f, "This link has already been used"),
+            UserTokenError::UnknownDatabaseError(err) => write!(f, "{}", err),
+            UserTokenError::Unknown(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<UserTokenError> for RyobError {
+    fn from(error: UserTokenError) -> RyobError {
+        match error {
+            UserTokenError::InvalidToken => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            UserTokenError::ExpiredToken => RyobError::from_display(actix_web::http::StatusCode::GONE, error),
+            UserTokenError::UsedToken => RyobError::from_display(actix_web::http::StatusCode::GONE, error),
+            _ => RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error),
+        }
+    }
+}
+
+impl UserToken {
+    pub fn issue(connection: &DatabaseConnection, user: Id<User>, purpose: TokenPurpose, email: Option<&str>) -> Result<String, UserTokenError> {
+        let nonce = signing::random_string(24);
+        let timestamp = chrono::Utc::now();
+        let new_token = NewUserToken {
+            user_id: user,
+            purpose: purpose.as_str().to_owned(),
+            nonce_hash: signing::hash(&nonce),
+            email: email.map(str::to_owned),
+            created_at: timestamp,
+            expires_at: timestamp + purpose.lifetime(),
+        };
+
+        let token: UserToken = {
+            use diesel::prelude::*;
+            diesel::insert_into(user_tokens::table)
+                .values(&new_token)
+                .get_result(connection)
+                .map_err(UserTokenError::UnknownDatabaseError)?
+        };
+
+        info!("A {} token has been issued for user {:?}", purpose.as_str(), user);
+
+        Ok(signing::sign(&format!("{}.{}", token.id.value(), nonce)))
+    }
+
+    // Checks a token without using it up, e.g. before showing the form it authorizes
+    pub fn check(connection: &DatabaseConnection, signed_token: &str, purpose: TokenPurpose) -> Result<UserToken, UserTokenError> {
+        let payload = signing::verify(signed_token).ok_or(UserTokenError::InvalidToken)?;
+        let (token_id, nonce) = payload.split_once('.').ok_or(UserTokenError::InvalidToken)?;
+        let token_id = token_id.parse::<i64>().map_err(|_| UserTokenError::InvalidToken)?;
+
+        let token: UserToken = {
+            use crate::schema::user_tokens::dsl::*;
+            use diesel::prelude::*;
+            user_tokens
+                .filter(id.eq(token_id))
+                .first::<UserToken>(connection)
+                .optional()
+                .map_err(UserTokenError::UnknownDatabaseError)?
+                .ok_or(UserTokenError::InvalidToken)?
+        };
+
+        if token.purpose != purpose.as_str() || token.nonce_hash != signing::hash(nonce) {
+            Err(UserTokenError::InvalidToken)
+        } else if token.used_at.is_some() {
+            Err(UserTokenError::UsedToken)
+        } else if token.expires_at < chrono::Utc::now() {
+            Err(UserTokenError::ExpiredToken)
+        } else {
+            Ok(token)
+        }
+    }
+
+    pub fn consume(connection: &DatabaseConnection, signed_token: &str, purpose: TokenPurpose) -> Result<UserToken, UserTokenError> {
+        let token = UserToken::check(connection, signed_token, purpose)?;
+
+        // Guarding on used_at makes the check-and-set atomic when two requests race
+        let updated = {
+            use crate::schema::user_tokens::dsl::*;
+            use diesel::prelude::*;
+            diesel::update(user_tokens.filter(id.eq(token.id)).filter(used_at.is_null()))
+                .set(used_at.eq(Some(chrono::Utc::now())))
+                .execute(connection)
+                .map_err(UserTokenError::UnknownDatabaseError)?
+        };
+
+        if updated == 0 {
+            return Err(UserTokenError::UsedToken);
+        }
+
+        info!("A {} token has been used by user {:?}", purpose.as_str(), token.user_id);
+
+        Ok(token)
+    }
+
+    pub fn revoke_all(connection: &DatabaseConnection, user: Id<User>, target_purpose: TokenPurpose) -> Result<(), UserTokenError> {
+        use crate::schema::user_tokens::dsl::*;
+        use diesel::prelude::*;
+        diesel::update(
+            user_tokens
+                .filter(user_id.eq(user))
+                .filter(purpose.eq(target_purpose.as_str()))
+                .filter(used_at.is_null()),
+        )
+        .set(used_at.eq(Some(chrono::Utc::now())))
+        .execute(connection)
+        .map_err(UserTokenError::UnknownDatabaseError)?;
+        Ok(())
+    }
+}
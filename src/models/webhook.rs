@@ -0,0 +1,342 @@
+use crate::database::types::*;
+use crate::models::user::User;
+use crate::schema::{webhook_deliveries, webhooks};
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use crate::utils::queue;
+use crate::utils::signing;
+use chrono;
+use diesel::result::Error as DieselError;
+use diesel::{Insertable, Queryable};
+use log::{info, warn};
+use serde::Serialize;
+
+pub const MAX_ATTEMPTS: i32 = 8;
+const MAX_URL_SIZE: usize = 2000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WebhookEvent {
+    UserRegistered,
+    TopicCreated,
+    PostCreated,
+    PostEdited,
+    PostDeleted,
+    ReportFiled,
+    // Only sent on request from the admin panel, to check an endpoint is reachable
+    Ping,
+}
+
+impl WebhookEvent {
+    // The events an endpoint can subscribe to
+    pub const SUBSCRIBABLE: [WebhookEvent; 6] = [
+        WebhookEvent::UserRegistered,
+        WebhookEvent::TopicCreated,
+        WebhookEvent::PostCreated,
+        WebhookEvent::PostEdited,
+        WebhookEvent::PostDeleted,
+        WebhookEvent::ReportFiled,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WebhookEvent::UserRegistered => "user.registered",
+            WebhookEvent::TopicCreated => "topic.created",
+            WebhookEvent::PostCreated => "post.created",
+            WebhookEvent::PostEdited => "post.edited",
+            WebhookEvent::PostDeleted => "post.deleted",
+            WebhookEvent::ReportFiled => "report.filed",
+            WebhookEvent::Ping => "ping",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<WebhookEvent> {
+        WebhookEvent::SUBSCRIBABLE.iter().copied().find(|event| event.as_str() == value)
+    }
+}
+
+#[derive(Clone, Queryable, Serialize)]
+pub struct Webhook {
+    pub id: Id<Webhook>,
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<String>,
+    pub created_by: Id<User>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub deleted_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Insertable)]
+#[table_name = "webhooks"]
+pub struct NewWebhook {
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<String>,
+    pub created_by: Id<User>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+// Each delivery is both an entry in the queue and, once attempted, in the delivery log
+#[derive(Queryable, Serialize)]
+pub struct WebhookDelivery {
+    pub id: Id<WebhookDelivery>,
+    pub webhook_id: Id<Webhook>,
+    pub event: String,
+    pub payload: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub attempts: i32,
+    pub next_attempt_at: chrono::DateTime<chrono::Utc>,
+    pub last_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Insertable)]
+#[table_name = "webhook_deliveries"]
+pub struct NewWebhookDelivery {
+    pub webhook_id: Id<Webhook>,
+    pub event: String,
+    pub payload: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub next_attempt_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug)]
+pub enum WebhookError {
+    InvalidUrl,
+    NoEvents,
+    NoSuchWebhook,
+    UnknownDatabaseError(DieselError),
+    Unknown(String),
+}
+
+impl std::fmt::Display for WebhookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            WebhookError::InvalidUrl => write!(f, "The URL must be an http or https address"),
+            WebhookError::NoEvents => write!(f, "At least one event must be chosen"),
+            WebhookError::NoSuchWebhook => write!(f, "No such webhook"),
+            WebhookError::UnknownDatabaseError(err) => write!(f, "{}", err),
+            WebhookError::Unknown(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<DieselError> for WebhookError {
+    fn from(error: DieselError) -> WebhookError {
+        WebhookError::UnknownDatabaseError(error)
+    }
+}
+
+impl From<WebhookError> for RyobError {
+    fn from(error: WebhookError) -> RyobError {
+        match error {
+            WebhookError::InvalidUrl => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            WebhookError::NoEvents => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            WebhookError::NoSuchWebhook => RyobError::from_display(actix_web::http::StatusCode::NOT_FOUND, error),
+            _ => RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error),
+        }
+    }
+}
+
+impl Webhook {
+    pub fn create(connection: &DatabaseConnection, creator: Id<User>, target_url: &str, subscribed: &[WebhookEvent]) -> Result<Webhook, WebhookError> {
+        let valid_url = (target_url.starts_with("http://") || target_url.starts_with("https://"))
+            && target_url.len() <= MAX_URL_SIZE
+            && !target_url.chars().any(|c| c.is_whitespace() || c.is_control());
+        if !valid_url {
+            return Err(WebhookError::InvalidUrl);
+        }
+        if subscribed.is_empty() {
+            return Err(WebhookError::NoEvents);
+        }
+
+        let new_webhook = NewWebhook {
+            url: target_url.to_owned(),
+            secret: signing::random_string(24),
+            events: subscribed.iter().map(|event| event.as_str().to_owned()).collect(),
+            created_by: creator,
+            created_at: chrono::Utc::now(),
+        };
+
+        let webhook: Webhook = {
+            use diesel::prelude::*;
+            diesel::insert_into(webhooks::table).values(&new_webhook).get_result(connection)?
+        };
+
+        info!("User {:?} has created webhook {:?} for {}", creator, webhook.id, target_url);
+
+        Ok(webhook)
+    }
+
+    pub fn by_id(connection: &DatabaseConnection, webhook: Id<Webhook>) -> Result<Webhook, WebhookError> {
+        use crate::schema::webhooks::dsl::*;
+        use diesel::prelude::*;
+        webhooks
+            .filter(id.eq(webhook))
+            .filter(deleted_at.is_null())
+            .first::<Webhook>(connection)
+            .optional()?
+            .ok_or(WebhookError::NoSuchWebhook)
+    }
+
+    pub fn active(connection: &DatabaseConnection) -> Result<Vec<Webhook>, WebhookError> {
+        use crate::schema::webhooks::dsl::*;
+        use diesel::prelude::*;
+        Ok(webhooks.filter(deleted_at.is_null()).order(created_at.asc()).load::<Webhook>(connection)?)
+    }
+
+    // Kept rather than removed so the delivery log still has something to point at
+    pub fn delete(connection: &DatabaseConnection, webhook: Id<Webhook>) -> Result<(), WebhookError> {
+        {
+            use crate::schema::webhooks::dsl::*;
+            use diesel::prelude::*;
+            diesel::update(webhooks.filter(id.eq(webhook)))
+                .set(deleted_at.eq(Some(chrono::Utc::now())))
+                .execute(connection)?;
+        }
+
+        info!("Webhook {:?} has been deleted", webhook);
+
+        Ok(())
+    }
+
+    // Queues the event for every endpoint subscribed to it. Called from inside the model
+    // functions that cause the events, so a rolled back change never announces anything.
+    pub fn dispatch(connection: &DatabaseConnection, event: WebhookEvent, data: serde_json::Value) -> Result<(), DieselError> {
+        use diesel::prelude::*;
+        let subscribed: Vec<Id<Webhook>> = webhooks::table
+            .filter(webhooks::deleted_at.is_null())
+            .filter(webhooks::events.contains(vec![event.as_str()]))
+            .select(webhooks::id)
+            .load(connection)?;
+        if subscribed.is_empty() {
+            return Ok(());
+        }
+
+        let timestamp = chrono::Utc::now();
+        let payload = json!({ "event": event.as_str(), "created_at": timestamp, "data": data }).to_string();
+        let deliveries: Vec<NewWebhookDelivery> = subscribed
+            .into_iter()
+            .map(|webhook| NewWebhookDelivery {
+                webhook_id: webhook,
+                event: event.as_str().to_owned(),
+                payload: payload.clone(),
+                created_at: timestamp,
+                next_attempt_at: timestamp,
+            })
+            .collect();
+        diesel::insert_into(webhook_deliveries::table).values(&deliveries).execute(connection)?;
+        Ok(())
+    }
+
+    pub fn ping(&self, connection: &DatabaseConnection) -> Result<(), WebhookError> {
+        let timestamp = chrono::Utc::now();
+        let delivery = NewWebhookDelivery {
+            webhook_id: self.id,
+            event: WebhookEvent::Ping.as_str().to_owned(),
+            payload: json!({ "event": WebhookEvent::Ping.as_str(), "created_at": timestamp, "data": {} }).to_string(),
+            created_at: timestamp,
+            next_attempt_at: timestamp,
+        };
+
+        use diesel::prelude::*;
+        diesel::insert_into(webhook_deliveries::table).values(&delivery).execute(connection)?;
+        Ok(())
+    }
+}
+
+impl WebhookDelivery {
+    // Claims the returned deliveries as described in utils::queue, so several workers can drain the
+    // queue without sending anything twice
+    pub fn claim_due(connection: &DatabaseConnection, limit: i64) -> Result<Vec<(WebhookDelivery, Webhook)>, WebhookError> {
+        use diesel::prelude::*;
+        let due: Vec<WebhookDelivery> = connection.transaction::<_, WebhookError, _>(|| {
+            let due: Vec<WebhookDelivery> = webhook_deliveries::table
+                .filter(webhook_deliveries::delivered_at.is_null())
+                .filter(webhook_deliveries::attempts.lt(MAX_ATTEMPTS))
+                .filter(webhook_deliveries::next_attempt_at.le(chrono::Utc::now()))
+                .filter(webhook_deliveries::webhook_id.eq_any(webhooks::table.filter(webhooks::deleted_at.is_null()).select(webhooks::id)))
+                .order(webhook_deliveries::next_attempt_at.asc())
+                .limit(limit)
+                .for_update()
+                .skip_locked()
+                .load(connection)?;
+            let claimed: Vec<Id<WebhookDelivery>> = due.iter().map(|delivery| delivery.id).collect();
+            diesel::update(webhook_deliveries::table.filter(webhook_deliveries::id.eq_any(claimed)))
+                .set(webhook_deliveries::next_attempt_at.eq(queue::lease_expiry()))
+                .execute(connection)?;
+            Ok(due)
+        })?;
+
+        let webhook_ids: Vec<Id<Webhook>> = due.iter().map(|delivery| delivery.webhook_id).collect();
+        let endpoints: Vec<Webhook> = webhooks::table.filter(webhooks::id.eq_any(webhook_ids)).load(connection)?;
+
+        Ok(due
+            .into_iter()
+            .filter_map(|delivery| {
+                let webhook = endpoints.iter().find(|webhook| webhook.id == delivery.webhook_id)?;
+                Some((delivery, webhook.clone()))
+            })
+            .collect())
+    }
+
+    pub fn for_webhook(connection: &DatabaseConnection, webhook: Id<Webhook>, offset: i64, limit: i64) -> Result<Vec<WebhookDelivery>, WebhookError> {
+        use crate::schema::webhook_deliveries::dsl::*;
+        use diesel::prelude::*;
+        Ok(webhook_deliveries
+            .filter(webhook_id.eq(webhook))
+            .order(created_at.desc())
+            .limit(limit)
+            .offset(offset)
+            .load::<WebhookDelivery>(connection)?)
+    }
+
+    pub fn mark_delivered(&self, connection: &DatabaseConnection, status: u16) -> Result<(), WebhookError> {
+        {
+            use crate::schema::webhook_deliveries::dsl::*;
+            use diesel::prelude::*;
+            diesel::update(webhook_deliveries.filter(id.eq(self.id)))
+                .set((
+                    delivered_at.eq(Some(chrono::Utc::now())),
+                    attempts.eq(self.attempts + 1),
+                    last_status.eq(Some(i32::from(status))),
+                    last_error.eq(None::<String>),
+                ))
+                .execute(connection)?;
+        }
+
+        info!("Webhook delivery {:?} has been delivered", self.id);
+
+        Ok(())
+    }
+
+    // Backs off exponentially: 1, 2, 4, ... minutes between attempts
+    pub fn mark_failed(&self, connection: &DatabaseConnection, status: Option<u16>, error: &str) -> Result<(), WebhookError> {
+        let attempt = self.attempts + 1;
+        let retry_at = chrono::Utc::now() + chrono::Duration::minutes(1 << (attempt - 1).min(16));
+
+        {
+            use crate::schema::webhook_deliveries::dsl::*;
+            use diesel::prelude::*;
+            diesel::update(webhook_deliveries.filter(id.eq(self.id)))
+                .set((
+                    attempts.eq(attempt),
+                    next_attempt_at.eq(retry_at),
+                    last_status.eq(status.map(i32::from)),
+                    last_error.eq(Some(error)),
+                ))
+                .execute(connection)?;
+        }
+
+        if attempt >= MAX_ATTEMPTS {
+            warn!("Webhook delivery {:?} has been abandoned after {} attempts: {}", self.id, attempt, error);
+        } else {
+            warn!(
+                "Webhook delivery {:?} failed on attempt {}, retrying at {}: {}",
+                self.id, attempt, retry_at, error
+            );
+        }
+
+        Ok(())
+    }
+}
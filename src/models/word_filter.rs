@@ -0,0 +1,361 @@
+use crate::database::types::*;
+use crate::models::user::User;
+use crate::schema::word_filters;
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use chrono;
+use diesel::result::Error as DieselError;
+use diesel::{Insertable, Queryable};
+use log::{info, warn};
+use regex::{NoExpand, Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+pub const MAX_PATTERN_SIZE: usize = 500;
+pub const MAX_MESSAGE_SIZE: usize = 500;
+// Keeps a single pattern from taking up a lot of memory once compiled
+const MAX_COMPILED_SIZE: usize = 1 << 20;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FilterAction {
+    Replace,
+    Reject,
+    Hold,
+}
+
+impl FilterAction {
+    pub const ALL: [FilterAction; 3] = [FilterAction::Replace, FilterAction::Reject, FilterAction::Hold];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FilterAction::Replace => "replace",
+            FilterAction::Reject => "reject",
+            FilterAction::Hold => "hold",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<FilterAction> {
+        FilterAction::ALL.iter().copied().find(|action| action.as_str() == value)
+    }
+}
+
+// Post content and topic titles, or the names people register with
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FilterTarget {
+    Content,
+    UserName,
+}
+
+// What a filter does, whether or not it has been saved. Literal patterns match regardless of case.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FilterRule {
+    pub pattern: String,
+    pub is_regex: bool,
+    pub action: String,
+    #[serde(default)]
+    pub replacement: String,
+    #[serde(default)]
+    pub message: String,
+}
+
+#[derive(Clone, Queryable, Serialize)]
+pub struct WordFilter {
+    pub id: Id<WordFilter>,
+    pub pattern: String,
+    pub is_regex: bool,
+    pub action: String,
+    pub replacement: String,
+    pub message: String,
+    pub apply_to_content: bool,
+    pub apply_to_user_names: bool,
+    pub created_by: Id<User>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Insertable)]
+#[table_name = "word_filters"]
+pub struct NewWordFilter {
+    pub pattern: String,
+    pub is_regex: bool,
+    pub action: String,
+    pub replacement: String,
+    pub message: String,
+    pub apply_to_content: bool,
+    pub apply_to_user_names: bool,
+    pub created_by: Id<User>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+// Text that got through the filters, with any replacements made, and why it is being held if it is
+#[derive(Debug, Serialize)]
+pub struct Filtered {
+    pub text: String,
+    pub hold_reason: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum WordFilterError {
+    PatternEmpty,
+    PatternTooLong,
+    InvalidPattern(String),
+    UnknownAction(String),
+    MessageTooLong,
+    NoTarget,
+    ReplaceOnUserName,
+    Rejected(String),
+    NoSuchFilter,
+    UnknownDatabaseError(DieselError),
+    Unknown(String),
+}
+
+impl std::fmt::Display for WordFilterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            WordFilterError::PatternEmpty => write!(f, "Please give a pattern to match"),
+            WordFilterError::PatternTooLong => write!(f, "Patterns must be at most {} characters long", MAX_PATTERN_SIZE),
+            WordFilterError::InvalidPattern(err) => write!(f, "The pattern is not a valid regular expression: {}", err),
+            WordFilterError::UnknownAction(action) => write!(f, "Unknown action \"{}\"", action),
+            WordFilterError::MessageTooLong => write!(f, "Messages and replacements must be at most {} characters long", MAX_MESSAGE_SIZE),
+            WordFilterError::NoTarget => write!(f, "Filters have to apply to posts, user names or both"),
+            WordFilterError::ReplaceOnUserName => write!(f, "User names are never rewritten, so replace filters can only apply to posts"),
+            WordFilterError::Rejected(message) => write!(f, "{}", message),
+            WordFilterError::NoSuchFilter => write!(f, "No such filter"),
+            WordFilterError::UnknownDatabaseError(err) => write!(f, "{}", err),
+            WordFilterError::Unknown(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<DieselError> for WordFilterError {
+    fn from(error: DieselError) -> WordFilterError {
+        WordFilterError::UnknownDatabaseError(error)
+    }
+}
+
+impl From<WordFilterError> for RyobError {
+    fn from(error: WordFilterError) -> RyobError {
+        match error {
+            WordFilterError::UnknownDatabaseError(_) | WordFilterError::Unknown(_) => {
+                RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error)
+            }
+            WordFilterError::NoSuchFilter => RyobError::from_display(actix_web::http::StatusCode::NOT_FOUND, error),
+            _ => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+        }
+    }
+}
+
+impl FilterRule {
+    pub fn action(&self) -> Result<FilterAction, WordFilterError> {
+        FilterAction::parse(&self.action).ok_or_else(|| WordFilterError::UnknownAction(self.action.clone()))
+    }
+
+    pub fn validate(&self) -> Result<(), WordFilterError> {
+        if self.pattern.trim().is_empty() {
+            return Err(WordFilterError::PatternEmpty);
+        }
+        if self.pattern.chars().count() > MAX_PATTERN_SIZE {
+            return Err(WordFilterError::PatternTooLong);
+        }
+        if self.replacement.chars().count() > MAX_MESSAGE_SIZE || self.message.chars().count() > MAX_MESSAGE_SIZE {
+            return Err(WordFilterError::MessageTooLong);
+        }
+        self.action()?;
+        self.compile()?;
+        Ok(())
+    }
+
+    fn compile(&self) -> Result<Regex, WordFilterError> {
+        let pattern = if self.is_regex {
+            self.pattern.clone()
+        } else {
+            regex::escape(self.pattern.trim())
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.is_regex)
+            .size_limit(MAX_COMPILED_SIZE)
+            .build()
+            .map_err(|err| WordFilterError::InvalidPattern(err.to_string()))
+    }
+
+    // Regex replacements can use the groups they captured, as in $1
+    fn apply(&self, target: FilterTarget, filtered: &mut Filtered) -> Result<(), WordFilterError> {
+        let pattern = self.compile()?;
+        let action = self.action()?;
+        // A name is never rewritten behind its owner's back, and there is nothing to hold it for,
+        // so the other filters turn away any name they match
+        if target == FilterTarget::UserName {
+            if action == FilterAction::Replace {
+                return Err(WordFilterError::ReplaceOnUserName);
+            }
+            if pattern.is_match(&filtered.text) {
+                return Err(WordFilterError::Rejected(self.rejection_message("That user name is not allowed")));
+            }
+            return Ok(());
+        }
+        match action {
+            FilterAction::Replace if self.is_regex => filtered.text = pattern.replace_all(&filtered.text, self.replacement.as_str()).into_owned(),
+            FilterAction::Replace => filtered.text = pattern.replace_all(&filtered.text, NoExpand(&self.replacement)).into_owned(),
+            FilterAction::Reject if pattern.is_match(&filtered.text) => {
+                return Err(WordFilterError::Rejected(
+                    self.rejection_message("That contains text which is not allowed here"),
+                ))
+            }
+            FilterAction::Hold if filtered.hold_reason.is_none() && pattern.is_match(&filtered.text) => {
+                filtered.hold_reason = Some(format!("Matched the filter \"{}\"", self.pattern))
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn rejection_message(&self, default: &str) -> String {
+        if self.message.trim().is_empty() {
+            default.to_owned()
+        } else {
+            self.message.trim().to_owned()
+        }
+    }
+
+    // Runs the rule on its own, for trying it out before saving it
+    pub fn preview(&self, target: FilterTarget, text: &str) -> Result<Filtered, WordFilterError> {
+        self.validate()?;
+        let mut filtered = Filtered {
+            text: text.to_owned(),
+            hold_reason: None,
+        };
+        self.apply(target, &mut filtered)?;
+        Ok(filtered)
+    }
+}
+
+impl WordFilter {
+    pub fn create(
+        connection: &DatabaseConnection,
+        creator: Id<User>,
+        rule: &FilterRule,
+        apply_to_content: bool,
+        apply_to_user_names: bool,
+    ) -> Result<WordFilter, WordFilterError> {
+        rule.validate()?;
+        if !apply_to_content && !apply_to_user_names {
+            return Err(WordFilterError::NoTarget);
+        }
+        if apply_to_user_names && rule.action()? == FilterAction::Replace {
+            return Err(WordFilterError::ReplaceOnUserName);
+        }
+
+        let new_filter = NewWordFilter {
+            pattern: rule.pattern.clone(),
+            is_regex: rule.is_regex,
+            action: rule.action.clone(),
+            replacement: rule.replacement.clone(),
+            message: rule.message.trim().to_owned(),
+            apply_to_content,
+            apply_to_user_names,
+            created_by: creator,
+            created_at: chrono::Utc::now(),
+        };
+        let filter: WordFilter = {
+            use diesel::prelude::*;
+            diesel::insert_into(word_filters::table).values(&new_filter).get_result(connection)?
+        };
+
+        info!("User {:?} has added a {} filter for \"{}\"", creator, filter.action, filter.pattern);
+
+        Ok(filter)
+    }
+
+    pub fn rule(&self) -> FilterRule {
+        FilterRule {
+            pattern: self.pattern.clone(),
+            is_regex: self.is_regex,
+            action: self.action.clone(),
+            replacement: self.replacement.clone(),
+            message: self.message.clone(),
+        }
+    }
+
+    // In the order they were added, which is also the order they are applied in
+    pub fn all(connection: &DatabaseConnection) -> Result<Vec<WordFilter>, WordFilterError> {
+        use crate::schema::word_filters::dsl::*;
+        use diesel::prelude::*;
+        Ok(word_filters.order(id.asc()).load(connection)?)
+    }
+
+    pub fn delete(connection: &DatabaseConnection, filter: Id<WordFilter>) -> Result<WordFilter, WordFilterError> {
+        let deleted: WordFilter = {
+            use crate::schema::word_filters::dsl::*;
+            use diesel::prelude::*;
+            diesel::delete(word_filters.filter(id.eq(filter)))
+                .get_result(connection)
+                .optional()?
+                .ok_or(WordFilterError::NoSuchFilter)?
+        };
+
+        info!("The {} filter for \"{}\" has been removed", deleted.action, deleted.pattern);
+
+        Ok(deleted)
+    }
+
+    // Every filter for the target in turn, each working on what the ones before it left. Stops at
+    // the first rejection.
+    pub fn apply(connection: &DatabaseConnection, target: FilterTarget, text: &str) -> Result<Filtered, WordFilterError> {
+        let mut filtered = Filtered {
+            text: text.to_owned(),
+            hold_reason: None,
+        };
+        for filter in WordFilter::all(connection)? {
+            let applies = match target {
+                FilterTarget::Content => filter.apply_to_content,
+                FilterTarget::UserName => filter.apply_to_user_names,
+            };
+            if !applies {
+                continue;
+            }
+            match filter.rule().apply(target, &mut filtered) {
+                Err(WordFilterError::InvalidPattern(err)) | Err(WordFilterError::UnknownAction(err)) => {
+                    warn!("Skipping broken filter {:?}: {}", filter.id, err)
+                }
+                // Saved before they were refused
+                Err(WordFilterError::ReplaceOnUserName) => warn!("Skipping replace filter {:?} on user names", filter.id),
+                result => result?,
+            }
+        }
+        Ok(filtered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, action: FilterAction) -> FilterRule {
+        FilterRule {
+            pattern: pattern.to_owned(),
+            is_regex: false,
+            action: action.as_str().to_owned(),
+            replacement: "***".to_owned(),
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn replaces_in_content() {
+        let filtered = rule("darn", FilterAction::Replace)
+            .preview(FilterTarget::Content, "Darn it, darn it all")
+            .unwrap();
+        assert_eq!(filtered.text, "*** it, *** it all");
+    }
+
+    #[test]
+    fn never_rewrites_user_names() {
+        assert!(matches!(
+            rule("darn", FilterAction::Replace).preview(FilterTarget::UserName, "darnit"),
+            Err(WordFilterError::ReplaceOnUserName)
+        ));
+        assert!(matches!(
+            rule("darn", FilterAction::Hold).preview(FilterTarget::UserName, "darnit"),
+            Err(WordFilterError::Rejected(_))
+        ));
+        assert!(rule("darn", FilterAction::Reject).preview(FilterTarget::UserName, "someone").is_ok());
+    }
+}
@@ -0,0 +1,30 @@
+// Background anonymizing of recorded IP addresses once they are older than the retention setting
+
+use crate::database::types::*;
+use crate::models::ip_address::{IpRecord, IpRecordError};
+use crate::models::setting::{self, Setting};
+use log::error;
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+pub fn anonymize_expired(connection: &DatabaseConnection) -> Result<usize, IpRecordError> {
+    let days = Setting::get_int(connection, setting::IP_RETENTION_DAYS, 0).map_err(|err| IpRecordError::Unknown(err.to_string()))?;
+    if days <= 0 {
+        return Ok(0);
+    }
+    IpRecord::anonymize_before(connection, chrono::Utc::now() - chrono::Duration::days(days))
+}
+
+pub fn spawn_worker(pool: DatabasePool) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || loop {
+        let anonymized = pool
+            .get()
+            .map_err(|err| IpRecordError::Unknown(format!("{}", err)))
+            .and_then(|connection| anonymize_expired(&connection));
+        if let Err(err) = anonymized {
+            error!("Failed to anonymize old IP addresses: {}", err);
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    })
+}
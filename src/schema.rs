@@ -1,3 +1,137 @@
+table! {
+    api_tokens (id) {
+        id -> Int8,
+        user_id -> Int8,
+        name -> Text,
+        scopes -> Array<Text>,
+        secret_hash -> Text,
+        created_at -> Timestamptz,
+        last_used_at -> Nullable<Timestamptz>,
+        revoked_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    attachments (id) {
+        id -> Int8,
+        post_id -> Int8,
+        file_id -> Int8,
+        file_name -> Text,
+        created_by -> Int8,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    audit_log (id) {
+        id -> Int8,
+        actor_id -> Int8,
+        action -> Text,
+        target_type -> Text,
+        target_id -> Int8,
+        before -> Nullable<Text>,
+        after -> Nullable<Text>,
+        reason -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    bans (id) {
+        id -> Int8,
+        user_id -> Int8,
+        scope -> Text,
+        reason -> Text,
+        banned_by -> Int8,
+        created_at -> Timestamptz,
+        expires_at -> Nullable<Timestamptz>,
+        lifted_by -> Nullable<Int8>,
+        lifted_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    banners (id) {
+        id -> Int8,
+        kind -> Text,
+        file_id -> Int8,
+        weight -> Int4,
+        status -> Text,
+        submitted_by -> Int8,
+        created_at -> Timestamptz,
+        reviewed_by -> Nullable<Int8>,
+        reviewed_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    conversation_participants (conversation_id, user_id) {
+        conversation_id -> Int8,
+        user_id -> Int8,
+        joined_at -> Timestamptz,
+        last_read_at -> Timestamptz,
+        left_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    conversations (id) {
+        id -> Int8,
+        subject -> Text,
+        created_by -> Int8,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+table! {
+    ip_addresses (id) {
+        id -> Int8,
+        user_id -> Int8,
+        post_id -> Nullable<Int8>,
+        event -> Text,
+        ip -> Text,
+        anonymized -> Bool,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    ip_bans (id) {
+        id -> Int8,
+        network -> Text,
+        reason -> Text,
+        created_by -> Int8,
+        created_at -> Timestamptz,
+        expires_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    messages (id) {
+        id -> Int8,
+        conversation_id -> Int8,
+        sent_by -> Int8,
+        sent_at -> Timestamptz,
+        content -> Text,
+    }
+}
+
+table! {
+    outgoing_emails (id) {
+        id -> Int8,
+        recipient -> Text,
+        subject -> Text,
+        text_body -> Text,
+        html_body -> Text,
+        created_at -> Timestamptz,
+        attempts -> Int4,
+        next_attempt_at -> Timestamptz,
+        last_error -> Nullable<Text>,
+        sent_at -> Nullable<Timestamptz>,
+    }
+}
+
 table! {
     posts (id) {
         id -> Int8,
@@ -5,6 +139,63 @@ table! {
         created_by -> Int8,
         created_at -> Timestamptz,
         content -> Text,
+        pending -> Bool,
+        hold_reason -> Text,
+    }
+}
+
+table! {
+    reports (id) {
+        id -> Int8,
+        topic_id -> Int8,
+        post_id -> Nullable<Int8>,
+        reported_by -> Int8,
+        reason -> Text,
+        status -> Text,
+        created_at -> Timestamptz,
+        handled_by -> Nullable<Int8>,
+        handled_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    settings (key) {
+        key -> Text,
+        value -> Text,
+    }
+}
+
+table! {
+    spam_log (id) {
+        id -> Int8,
+        kind -> Text,
+        user_id -> Nullable<Int8>,
+        post_id -> Nullable<Int8>,
+        content_hash -> Nullable<Text>,
+        score -> Int4,
+        decision -> Text,
+        signals -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    stored_files (id) {
+        id -> Int8,
+        content_hash -> Text,
+        content_type -> Text,
+        size -> Int8,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    thumbnails (attachment_id, size) {
+        attachment_id -> Int8,
+        size -> Text,
+        file_id -> Int8,
+        width -> Int4,
+        height -> Int4,
     }
 }
 
@@ -14,6 +205,61 @@ table! {
         title -> Text,
         created_by -> Int8,
         created_at -> Timestamptz,
+        pending -> Bool,
+    }
+}
+
+table! {
+    two_factor_recovery_codes (id) {
+        id -> Int8,
+        user_id -> Int8,
+        code_hash -> Text,
+        used_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    two_factor_secrets (user_id) {
+        user_id -> Int8,
+        secret -> Text,
+        created_at -> Timestamptz,
+        enabled_at -> Nullable<Timestamptz>,
+        last_used_step -> Nullable<Int8>,
+        failed_attempts -> Int4,
+        locked_until -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    user_blocks (blocker, blocked) {
+        blocker -> Int8,
+        blocked -> Int8,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    user_preferences (user_id) {
+        user_id -> Int8,
+        timezone -> Nullable<Text>,
+        locale -> Nullable<Text>,
+        theme -> Nullable<Text>,
+        topics_per_page -> Nullable<Int4>,
+        posts_per_page -> Nullable<Int4>,
+        email_notifications -> Bool,
+    }
+}
+
+table! {
+    user_tokens (id) {
+        id -> Int8,
+        user_id -> Int8,
+        purpose -> Text,
+        nonce_hash -> Text,
+        email -> Nullable<Text>,
+        created_at -> Timestamptz,
+        expires_at -> Timestamptz,
+        used_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -22,15 +268,120 @@ table! {
         id -> Int8,
         user_name -> Text,
         password_hash -> Text,
+        email -> Nullable<Text>,
+        email_verified_at -> Nullable<Timestamptz>,
+        session_generation -> Int4,
+        role -> Text,
+        avatar_file_id -> Nullable<Int8>,
+        signature -> Text,
+        created_at -> Timestamptz,
+        spam_held -> Bool,
+    }
+}
+
+table! {
+    webhook_deliveries (id) {
+        id -> Int8,
+        webhook_id -> Int8,
+        event -> Text,
+        payload -> Text,
+        created_at -> Timestamptz,
+        attempts -> Int4,
+        next_attempt_at -> Timestamptz,
+        last_status -> Nullable<Int4>,
+        last_error -> Nullable<Text>,
+        delivered_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    webhooks (id) {
+        id -> Int8,
+        url -> Text,
+        secret -> Text,
+        events -> Array<Text>,
+        created_by -> Int8,
+        created_at -> Timestamptz,
+        deleted_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    word_filters (id) {
+        id -> Int8,
+        pattern -> Text,
+        is_regex -> Bool,
+        action -> Text,
+        replacement -> Text,
+        message -> Text,
+        apply_to_content -> Bool,
+        apply_to_user_names -> Bool,
+        created_by -> Int8,
+        created_at -> Timestamptz,
     }
 }
 
+joinable!(api_tokens -> users (user_id));
+joinable!(attachments -> posts (post_id));
+joinable!(attachments -> stored_files (file_id));
+joinable!(attachments -> users (created_by));
+joinable!(audit_log -> users (actor_id));
+joinable!(bans -> users (user_id));
+joinable!(banners -> stored_files (file_id));
+joinable!(banners -> users (submitted_by));
+joinable!(conversation_participants -> conversations (conversation_id));
+joinable!(conversation_participants -> users (user_id));
+joinable!(conversations -> users (created_by));
+joinable!(ip_addresses -> posts (post_id));
+joinable!(ip_addresses -> users (user_id));
+joinable!(ip_bans -> users (created_by));
+joinable!(messages -> conversations (conversation_id));
+joinable!(messages -> users (sent_by));
 joinable!(posts -> topics (posted_in));
 joinable!(posts -> users (created_by));
+joinable!(reports -> posts (post_id));
+joinable!(reports -> topics (topic_id));
+joinable!(reports -> users (reported_by));
+joinable!(spam_log -> posts (post_id));
+joinable!(spam_log -> users (user_id));
+joinable!(thumbnails -> attachments (attachment_id));
+joinable!(thumbnails -> stored_files (file_id));
 joinable!(topics -> users (created_by));
+joinable!(two_factor_recovery_codes -> users (user_id));
+joinable!(two_factor_secrets -> users (user_id));
+joinable!(user_preferences -> users (user_id));
+joinable!(user_tokens -> users (user_id));
+joinable!(users -> stored_files (avatar_file_id));
+joinable!(webhook_deliveries -> webhooks (webhook_id));
+joinable!(webhooks -> users (created_by));
+joinable!(word_filters -> users (created_by));
 
 allow_tables_to_appear_in_same_query!(
+    api_tokens,
+    attachments,
+    audit_log,
+    bans,
+    banners,
+    conversation_participants,
+    conversations,
+    ip_addresses,
+    ip_bans,
+    messages,
+    outgoing_emails,
     posts,
+    reports,
+    settings,
+    spam_log,
+    stored_files,
+    thumbnails,
     topics,
+    two_factor_recovery_codes,
+    two_factor_secrets,
+    user_blocks,
+    user_preferences,
+    user_tokens,
     users,
+    webhook_deliveries,
+    webhooks,
+    word_filters,
 );
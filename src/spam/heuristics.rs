@@ -0,0 +1,252 @@
+// Checks that need nothing but the submission and the board's own records
+
+use super::{SpamCheck, SpamError, Submission};
+use crate::database::types::*;
+use crate::models::ip_address::{IpEvent, IpRecord};
+use crate::models::post::Post;
+use crate::models::spam_log::{SpamKind, SpamLogEntry};
+use regex::Regex;
+use std::sync::OnceLock;
+
+const REPEAT_WINDOW_HOURS: i64 = 24;
+const POSTING_WINDOW_MINUTES: i64 = 10;
+const REGISTRATION_WINDOW_HOURS: i64 = 1;
+// People take longer than this to fill in a form
+const MIN_FILL_SECONDS: i64 = 3;
+const MAX_FORM_AGE_HOURS: i64 = 24;
+
+static LINK_PATTERN: OnceLock<Regex> = OnceLock::new();
+
+pub fn all() -> Vec<Box<dyn SpamCheck>> {
+    vec![
+        Box::new(LinkDensity),
+        Box::new(RepeatedContent),
+        Box::new(PostingVelocity),
+        Box::new(Honeypot),
+        Box::new(TimeToSubmit),
+    ]
+}
+
+// Lots of links, or little but links
+pub struct LinkDensity;
+
+impl SpamCheck for LinkDensity {
+    fn name(&self) -> &'static str {
+        "link_density"
+    }
+
+    fn check(&self, _connection: &DatabaseConnection, submission: &Submission) -> Result<Option<(i32, String)>, SpamError> {
+        Ok(self.score(submission))
+    }
+}
+
+impl LinkDensity {
+    fn score(&self, submission: &Submission) -> Option<(i32, String)> {
+        if submission.kind == SpamKind::Register {
+            return None;
+        }
+        let pattern = LINK_PATTERN.get_or_init(|| Regex::new(r"(?i)\bhttps?://|\bwww\.|\[url[=\]]").expect("The link pattern is valid"));
+        let links = pattern.find_iter(submission.text).count();
+        if links == 0 {
+            return None;
+        }
+        let words = submission.text.split_whitespace().count().max(1);
+        // Every link past the second, and more for a link in every five words or fewer
+        let mut score = links.saturating_sub(2).min(5) as i32;
+        if links * 5 >= words {
+            score += 3;
+        }
+        if score > 0 {
+            Some((score, format!("{} links in {} words", links, words)))
+        } else {
+            None
+        }
+    }
+}
+
+// The same content submitted again, by anyone
+pub struct RepeatedContent;
+
+impl SpamCheck for RepeatedContent {
+    fn name(&self) -> &'static str {
+        "repeated_content"
+    }
+
+    fn check(&self, connection: &DatabaseConnection, submission: &Submission) -> Result<Option<(i32, String)>, SpamError> {
+        let hash = match submission.content_hash() {
+            Some(hash) => hash,
+            None => return Ok(None),
+        };
+        let since = chrono::Utc::now() - chrono::Duration::hours(REPEAT_WINDOW_HOURS);
+        let count = SpamLogEntry::hash_count_since(connection, &hash, since).map_err(|err| SpamError::Unknown(err.to_string()))?;
+        if count == 0 {
+            return Ok(None);
+        }
+        let score = (count * 3).min(9) as i32;
+        let times = if count == 1 { "once".to_owned() } else { format!("{} times", count) };
+        Ok(Some((score, format!("Submitted {} before in the last {} hours", times, REPEAT_WINDOW_HOURS))))
+    }
+}
+
+// Many posts from one account, or registrations from one address, in a short time
+pub struct PostingVelocity;
+
+impl SpamCheck for PostingVelocity {
+    fn name(&self) -> &'static str {
+        "posting_velocity"
+    }
+
+    fn check(&self, connection: &DatabaseConnection, submission: &Submission) -> Result<Option<(i32, String)>, SpamError> {
+        if submission.kind == SpamKind::Register {
+            let ip = match submission.ip {
+                Some(ip) => ip,
+                None => return Ok(None),
+            };
+            let since = chrono::Utc::now() - chrono::Duration::hours(REGISTRATION_WINDOW_HOURS);
+            let count = IpRecord::count_since(connection, ip, IpEvent::Register, since).map_err(|err| SpamError::Unknown(err.to_string()))?;
+            let score = match count {
+                0..=1 => return Ok(None),
+                2..=4 => 3,
+                _ => 8,
+            };
+            return Ok(Some((score, format!("{} registrations from this address in the last hour", count))));
+        }
+
+        let user = match submission.user {
+            Some(user) => user,
+            None => return Ok(None),
+        };
+        let since = chrono::Utc::now() - chrono::Duration::minutes(POSTING_WINDOW_MINUTES);
+        let count = Post::count_by_user_since(connection, user.id, since).map_err(|err| SpamError::Unknown(err.to_string()))?;
+        let score = match count {
+            0..=3 => return Ok(None),
+            4..=5 => 2,
+            6..=9 => 5,
+            _ => 8,
+        };
+        Ok(Some((score, format!("{} posts in the last {} minutes", count, POSTING_WINDOW_MINUTES))))
+    }
+}
+
+// A form field hidden from people, which only bots fill in
+pub struct Honeypot;
+
+impl SpamCheck for Honeypot {
+    fn name(&self) -> &'static str {
+        "honeypot"
+    }
+
+    fn check(&self, _connection: &DatabaseConnection, submission: &Submission) -> Result<Option<(i32, String)>, SpamError> {
+        Ok(self.score(submission))
+    }
+}
+
+impl Honeypot {
+    fn score(&self, submission: &Submission) -> Option<(i32, String)> {
+        match &submission.form {
+            Some(form) if !form.honeypot.trim().is_empty() => Some((10, "Filled in the hidden field".to_owned())),
+            _ => None,
+        }
+    }
+}
+
+// Forms sent back faster than anyone could fill them in, or without the token they were shown with
+pub struct TimeToSubmit;
+
+impl SpamCheck for TimeToSubmit {
+    fn name(&self) -> &'static str {
+        "time_to_submit"
+    }
+
+    fn check(&self, _connection: &DatabaseConnection, submission: &Submission) -> Result<Option<(i32, String)>, SpamError> {
+        Ok(self.score(submission))
+    }
+}
+
+impl TimeToSubmit {
+    fn score(&self, submission: &Submission) -> Option<(i32, String)> {
+        let form = match &submission.form {
+            Some(form) => form,
+            None => return None,
+        };
+        match super::form_age(form.form_token) {
+            None => Some((4, "The form token was missing or invalid".to_owned())),
+            Some(age) if age < chrono::Duration::seconds(MIN_FILL_SECONDS) => Some((
+                6,
+                format!("Sent {:.1} seconds after the form was shown", age.num_milliseconds() as f64 / 1000.0),
+            )),
+            Some(age) if age > chrono::Duration::hours(MAX_FORM_AGE_HOURS) => Some((2, format!("Sent {} hours after the form was shown", age.num_hours()))),
+            Some(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spam::FormFields;
+    use crate::utils::signing;
+
+    fn post(text: &str) -> Submission<'_> {
+        Submission {
+            kind: SpamKind::Post,
+            user: None,
+            ip: None,
+            text,
+            form: None,
+        }
+    }
+
+    fn registration<'a>(honeypot: &'a str, form_token: &'a str) -> Submission<'a> {
+        Submission {
+            kind: SpamKind::Register,
+            user: None,
+            ip: None,
+            text: "someone",
+            form: Some(FormFields { honeypot, form_token }),
+        }
+    }
+
+    // A token for a form shown the given number of seconds ago
+    fn shown_ago(seconds: i64) -> String {
+        signing::sign(&(chrono::Utc::now() - chrono::Duration::seconds(seconds)).timestamp_millis().to_string())
+    }
+
+    #[test]
+    fn scores_link_density() {
+        assert!(LinkDensity.score(&post("No links in here at all")).is_none());
+        assert!(LinkDensity
+            .score(&post("See https://example.com for the rest of what I had to say about this"))
+            .is_none());
+        assert_eq!(LinkDensity.score(&post("https://example.com")).map(|(score, _)| score), Some(3));
+        let many = "https://a.example https://b.example www.c.example [url=http://d.example]d[/url] and a few more words to go with them here";
+        assert_eq!(LinkDensity.score(&post(many)).map(|(score, _)| score), Some(6));
+    }
+
+    #[test]
+    fn ignores_links_in_user_names() {
+        assert!(LinkDensity
+            .score(&Submission {
+                text: "www.example.com",
+                ..registration("", "")
+            })
+            .is_none());
+    }
+
+    #[test]
+    fn catches_filled_in_honeypots() {
+        assert_eq!(Honeypot.score(&registration("http://spam.example", "")).map(|(score, _)| score), Some(10));
+        assert!(Honeypot.score(&registration("  ", "")).is_none());
+        assert!(Honeypot.score(&post("Forms without a honeypot")).is_none());
+    }
+
+    #[test]
+    fn times_form_submissions() {
+        assert_eq!(TimeToSubmit.score(&registration("", "")).map(|(score, _)| score), Some(4));
+        assert_eq!(TimeToSubmit.score(&registration("", "forged")).map(|(score, _)| score), Some(4));
+        assert_eq!(TimeToSubmit.score(&registration("", &shown_ago(1))).map(|(score, _)| score), Some(6));
+        assert!(TimeToSubmit.score(&registration("", &shown_ago(60))).is_none());
+        assert_eq!(TimeToSubmit.score(&registration("", &shown_ago(25 * 3600))).map(|(score, _)| score), Some(2));
+        assert!(TimeToSubmit.score(&post("Not sent from a form")).is_none());
+    }
+}
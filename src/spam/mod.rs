@@ -0,0 +1,220 @@
+// Spam checks for registrations and new topics and posts
+//
+// Each check in the pipeline looks at a submission and may add to its score, noting what it saw.
+// A total at or above the hold score is held for a moderator and one at or above the reject score
+// is turned away; admins set both at /admin/spam, where 0 turns either off. Every decision is
+// logged to spam_log with the signals behind it, so the scores can be tuned against what actually
+// got through. Staff are never checked.
+//
+// The built-in checks in heuristics.rs need nothing from outside the board. Others, such as asking
+// an outside service, can be added by implementing SpamCheck and passing them to Pipeline::new.
+
+pub mod heuristics;
+
+use crate::database::types::*;
+use crate::models::post::Post;
+use crate::models::setting::{self, Setting};
+use crate::models::spam_log::{NewSpamLogEntry, SpamDecision, SpamKind, SpamLogEntry};
+use crate::models::user::{Role, User};
+use crate::utils::errors::RyobError;
+use crate::utils::id::Id;
+use crate::utils::signing;
+use chrono::TimeZone;
+use serde::Serialize;
+use std::net::IpAddr;
+
+pub const DEFAULT_HOLD_SCORE: i64 = 5;
+pub const DEFAULT_REJECT_SCORE: i64 = 10;
+// Shorter content, e.g. "Thanks!", is repeated innocently too often to be worth hashing
+const MIN_HASHED_SIZE: usize = 20;
+
+// Fields only the HTML forms carry: one hidden from people that bots fill in, and a signed
+// timestamp of when the form was shown
+pub struct FormFields<'a> {
+    pub honeypot: &'a str,
+    pub form_token: &'a str,
+}
+
+pub struct Submission<'a> {
+    pub kind: SpamKind,
+    // The author, who doesn't exist yet when registering
+    pub user: Option<&'a User>,
+    pub ip: Option<IpAddr>,
+    // The user name when registering, the title and content for topics
+    pub text: &'a str,
+    pub form: Option<FormFields<'a>>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Signal {
+    pub check: &'static str,
+    pub score: i32,
+    pub detail: String,
+}
+
+pub struct Verdict {
+    pub kind: SpamKind,
+    pub score: i32,
+    pub decision: SpamDecision,
+    pub signals: Vec<Signal>,
+    content_hash: Option<String>,
+    // Staff submissions aren't checked, so there is nothing to log
+    exempt: bool,
+}
+
+#[derive(Debug)]
+pub enum SpamError {
+    Rejected,
+    UnknownDatabaseError(diesel::result::Error),
+    Unknown(String),
+}
+
+impl std::fmt::Display for SpamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SpamError::Rejected => write!(
+                f,
+                "That looks like spam, so it has not been accepted. If it isn't, please wait a while and try again"
+            ),
+            SpamError::UnknownDatabaseError(err) => write!(f, "{}", err),
+            SpamError::Unknown(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<diesel::result::Error> for SpamError {
+    fn from(error: diesel::result::Error) -> SpamError {
+        SpamError::UnknownDatabaseError(error)
+    }
+}
+
+impl From<SpamError> for RyobError {
+    fn from(error: SpamError) -> RyobError {
+        match error {
+            SpamError::Rejected => RyobError::from_display(actix_web::http::StatusCode::FORBIDDEN, error),
+            _ => RyobError::from_display(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, error),
+        }
+    }
+}
+
+pub trait SpamCheck: Send + Sync {
+    // Identifies the check in the log
+    fn name(&self) -> &'static str;
+
+    // The score to add and why, or None when the check has nothing to say about the submission
+    fn check(&self, connection: &DatabaseConnection, submission: &Submission) -> Result<Option<(i32, String)>, SpamError>;
+}
+
+impl<'a> Submission<'a> {
+    // Ignores case and spacing, so trivially varied copies still match
+    pub fn content_hash(&self) -> Option<String> {
+        if self.kind == SpamKind::Register {
+            return None;
+        }
+        let normalized = self.text.split_whitespace().collect::<Vec<&str>>().join(" ").to_lowercase();
+        if normalized.chars().count() < MIN_HASHED_SIZE {
+            return None;
+        }
+        Some(signing::hash(&normalized))
+    }
+}
+
+impl Verdict {
+    pub fn hold_reason(&self) -> Option<String> {
+        if self.decision != SpamDecision::Hold {
+            return None;
+        }
+        let details: Vec<&str> = self.signals.iter().map(|signal| signal.detail.as_str()).collect();
+        Some(format!("Spam score {}: {}", self.score, details.join("; ")))
+    }
+
+    // Logs the decision along with whatever it let through
+    pub fn record(&self, connection: &DatabaseConnection, user: Option<Id<User>>, post: Option<Id<Post>>) -> Result<(), SpamError> {
+        if self.exempt {
+            return Ok(());
+        }
+        let new_entry = NewSpamLogEntry {
+            kind: self.kind.as_str().to_owned(),
+            user_id: user,
+            post_id: post,
+            content_hash: self.content_hash.clone(),
+            score: self.score,
+            decision: self.decision.as_str().to_owned(),
+            signals: json!(self.signals).to_string(),
+            created_at: chrono::Utc::now(),
+        };
+        SpamLogEntry::record(connection, &new_entry).map_err(|err| SpamError::Unknown(err.to_string()))?;
+        Ok(())
+    }
+
+    // Logs and turns away rejected submissions. The rest are logged by the caller once they have
+    // been created.
+    pub fn enforce(&self, connection: &DatabaseConnection, user: Option<Id<User>>) -> Result<(), SpamError> {
+        if self.decision != SpamDecision::Reject {
+            return Ok(());
+        }
+        self.record(connection, user, None)?;
+        Err(SpamError::Rejected)
+    }
+}
+
+pub struct Pipeline {
+    checks: Vec<Box<dyn SpamCheck>>,
+}
+
+impl Pipeline {
+    pub fn new(checks: Vec<Box<dyn SpamCheck>>) -> Pipeline {
+        Pipeline { checks }
+    }
+
+    pub fn with_heuristics() -> Pipeline {
+        Pipeline::new(heuristics::all())
+    }
+
+    pub fn run(&self, connection: &DatabaseConnection, submission: &Submission) -> Result<Verdict, SpamError> {
+        let mut verdict = Verdict {
+            kind: submission.kind,
+            score: 0,
+            decision: SpamDecision::Allow,
+            signals: vec![],
+            content_hash: submission.content_hash(),
+            exempt: submission.user.is_some_and(|user| user.role() >= Role::Moderator),
+        };
+        if verdict.exempt {
+            return Ok(verdict);
+        }
+
+        for check in &self.checks {
+            if let Some((score, detail)) = check.check(connection, submission)? {
+                verdict.score += score;
+                verdict.signals.push(Signal {
+                    check: check.name(),
+                    score,
+                    detail,
+                });
+            }
+        }
+
+        let hold_score = Setting::get_int(connection, setting::SPAM_HOLD_SCORE, DEFAULT_HOLD_SCORE).map_err(|err| SpamError::Unknown(err.to_string()))?;
+        let reject_score = Setting::get_int(connection, setting::SPAM_REJECT_SCORE, DEFAULT_REJECT_SCORE).map_err(|err| SpamError::Unknown(err.to_string()))?;
+        let score = i64::from(verdict.score);
+        verdict.decision = if reject_score > 0 && score >= reject_score {
+            SpamDecision::Reject
+        } else if hold_score > 0 && score >= hold_score {
+            SpamDecision::Hold
+        } else {
+            SpamDecision::Allow
+        };
+        Ok(verdict)
+    }
+}
+
+// Handed out with each form so the time it took to fill in can be told when it comes back
+pub fn form_token() -> String {
+    signing::sign(&chrono::Utc::now().timestamp_millis().to_string())
+}
+
+pub fn form_age(token: &str) -> Option<chrono::Duration> {
+    let shown_at = signing::verify(token)?.parse::<i64>().ok()?;
+    Some(chrono::Utc::now() - chrono::Utc.timestamp_millis(shown_at))
+}
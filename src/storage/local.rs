@@ -0,0 +1,65 @@
+// Keeps files in a local directory, fanned out into subdirectories by the first characters of the
+// key so no single directory grows too large
+
+use super::{Storage, StorageError};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub struct LocalStorage {
+    directory: PathBuf,
+    write_count: AtomicUsize,
+}
+
+impl LocalStorage {
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Result<LocalStorage, StorageError> {
+        let directory = directory.into();
+        std::fs::create_dir_all(directory.join("tmp"))?;
+        Ok(LocalStorage {
+            directory,
+            write_count: AtomicUsize::new(0),
+        })
+    }
+
+    // Keys never come from users, but refusing anything path-like keeps it that way
+    fn path(&self, key: &str) -> Result<PathBuf, StorageError> {
+        if key.len() < 4 || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(StorageError::InvalidKey(key.to_owned()));
+        }
+        Ok(self.directory.join(&key[0..2]).join(&key[2..4]).join(key))
+    }
+}
+
+impl Storage for LocalStorage {
+    // Written to tmp/ and renamed into place so a reader never sees half a file
+    fn put(&self, key: &str, contents: &[u8]) -> Result<(), StorageError> {
+        let path = self.path(key)?;
+        if path.exists() {
+            return Ok(());
+        }
+        let write = self.write_count.fetch_add(1, Ordering::SeqCst);
+        let temporary = self.directory.join("tmp").join(format!("{}.{}.{}", key, std::process::id(), write));
+        {
+            let mut file = std::fs::File::create(&temporary)?;
+            file.write_all(contents)?;
+            file.sync_all()?;
+        }
+        std::fs::create_dir_all(path.parent().expect("Storage paths always have a parent"))?;
+        std::fs::rename(&temporary, &path)?;
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        std::fs::read(self.path(key)?).map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => StorageError::NotFound(key.to_owned()),
+            _ => StorageError::Io(err),
+        })
+    }
+
+    fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match std::fs::remove_file(self.path(key)?) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(StorageError::Io(err)),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+// Storage for uploaded files, addressed by key (the hex SHA-256 of the contents, so identical
+// uploads share one stored copy). The backend is chosen when the server starts:
+//
+//   RYOB_STORAGE        "local" (default "local")
+//   RYOB_STORAGE_PATH   the local backend's directory (default "./uploads")
+
+pub mod local;
+
+use crate::utils::errors::RyobError;
+use actix_web::http::StatusCode;
+
+#[derive(Debug)]
+pub enum StorageError {
+    InvalidConfiguration(String),
+    InvalidKey(String),
+    NotFound(String),
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            StorageError::InvalidConfiguration(err) => write!(f, "Invalid storage configuration: {}", err),
+            StorageError::InvalidKey(key) => write!(f, "Invalid storage key \"{}\"", key),
+            StorageError::NotFound(key) => write!(f, "Nothing is stored under \"{}\"", key),
+            StorageError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<std::io::Error> for StorageError {
+    fn from(error: std::io::Error) -> StorageError {
+        StorageError::Io(error)
+    }
+}
+
+impl From<StorageError> for RyobError {
+    fn from(error: StorageError) -> RyobError {
+        match error {
+            StorageError::NotFound(_) => RyobError::from_display(StatusCode::NOT_FOUND, error),
+            _ => RyobError::from_display(StatusCode::INTERNAL_SERVER_ERROR, error),
+        }
+    }
+}
+
+// Putting the same key twice must be harmless, callers rely on it when deduplicating
+pub trait Storage: Send + Sync {
+    fn put(&self, key: &str, contents: &[u8]) -> Result<(), StorageError>;
+    fn get(&self, key: &str) -> Result<Vec<u8>, StorageError>;
+    fn delete(&self, key: &str) -> Result<(), StorageError>;
+}
+
+fn env_or(name: &str, default: &str) -> String {
+    std::env::var(name).unwrap_or_else(|_| default.to_owned())
+}
+
+pub fn from_env() -> Result<Box<dyn Storage>, StorageError> {
+    match env_or("RYOB_STORAGE", "local").as_str() {
+        "local" => Ok(Box::new(local::LocalStorage::new(env_or("RYOB_STORAGE_PATH", "./uploads"))?)),
+        other => Err(StorageError::InvalidConfiguration(format!("Unknown storage \"{}\"", other))),
+    }
+}
@@ -0,0 +1,450 @@
+// Themes change how the board looks. Each is a directory under RYOB_THEMES_PATH (default
+// "./themes") holding any of:
+//
+//   <name>/styles/theme.css   loaded after main.css, and anything else under styles/ it refers to
+//   <name>/templates/...      used in place of the template at the same path in static/templates
+//
+// Themes are found when the server starts. Every theme is layered over the base templates, so it
+// only needs the templates it changes and the rest fall back to the base ones.
+//
+// With RYOB_DEV_MODE=1 the templates are watched and registered again whenever they change. A
+// template that doesn't compile is reported in the browser instead of stopping the server, and
+// the last templates that did compile keep being used until it is fixed.
+
+pub mod watcher;
+
+use crate::database::types::DatabasePool;
+use crate::helpers;
+use crate::i18n::{Catalog, DEFAULT_LOCALE};
+use crate::models::attachment::{AttachmentError, MAX_ATTACHMENTS_PER_POST, MAX_ATTACHMENT_SIZE};
+use crate::models::ban::{Ban, BanScope};
+use crate::models::user::User;
+use crate::models::user_preferences::{UserPreferences, DEFAULT_POSTS_PER_PAGE, DEFAULT_TOPICS_PER_PAGE};
+use crate::utils::errors::RyobError;
+use crate::utils::uploads::UploadError;
+use actix_session::UserSession;
+use actix_web::dev::Payload;
+use actix_web::http::{header, StatusCode};
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse};
+use fluent_bundle::FluentValue;
+use handlebars::{Handlebars, RenderError, TemplateFileError};
+use log::{error, info};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+// The base templates and styles on their own
+pub const BASE_THEME: &str = "default";
+
+const BASE_TEMPLATES_PATH: &str = "./static/templates";
+const TEMPLATE_EXTENSIONS: [&str; 3] = [".html", ".txt", ".xml"];
+
+#[derive(Debug)]
+pub enum ThemeError {
+    Io(std::io::Error),
+    Template(Box<TemplateFileError>),
+}
+
+impl std::fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ThemeError::Io(err) => write!(f, "{}", err),
+            ThemeError::Template(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<std::io::Error> for ThemeError {
+    fn from(error: std::io::Error) -> ThemeError {
+        ThemeError::Io(error)
+    }
+}
+
+impl From<TemplateFileError> for ThemeError {
+    fn from(error: TemplateFileError) -> ThemeError {
+        ThemeError::Template(Box::new(error))
+    }
+}
+
+pub struct Theme {
+    pub name: String,
+    pub path: Option<PathBuf>,
+    pub stylesheet: Option<String>,
+}
+
+pub struct Themes {
+    themes: Vec<Theme>,
+    registries: RwLock<HashMap<String, Handlebars>>,
+    default_theme: RwLock<String>,
+    pool: DatabasePool,
+    catalog: Arc<Catalog>,
+    dev_mode: bool,
+    // Why the templates last failed to load, only ever set in dev mode
+    template_error: RwLock<Option<String>>,
+}
+
+// Names end up in URLs and in the users table, so only simple ones are picked up
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name != BASE_THEME && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+fn register_templates(registry: &mut Handlebars, path: &Path) -> Result<(), ThemeError> {
+    for extension in TEMPLATE_EXTENSIONS.iter() {
+        registry.register_templates_directory(extension, path)?;
+    }
+    Ok(())
+}
+
+fn build_registries(themes: &[Theme], pool: &DatabasePool, catalog: &Arc<Catalog>) -> Result<HashMap<String, Handlebars>, ThemeError> {
+    let mut registries = HashMap::new();
+    for theme in themes {
+        let mut registry = Handlebars::new();
+        register_templates(&mut registry, Path::new(BASE_TEMPLATES_PATH))?;
+        if let Some(templates_path) = theme.templates_path() {
+            register_templates(&mut registry, &templates_path)?;
+        }
+        helpers::register(&mut registry, pool.clone(), catalog.clone());
+        registries.insert(theme.name.clone(), registry);
+    }
+    Ok(registries)
+}
+
+impl Theme {
+    fn templates_path(&self) -> Option<PathBuf> {
+        self.path.as_ref().map(|path| path.join("templates")).filter(|path| path.is_dir())
+    }
+}
+
+impl Themes {
+    pub fn from_env(pool: &DatabasePool, catalog: Arc<Catalog>) -> Result<Themes, ThemeError> {
+        let path = std::env::var("RYOB_THEMES_PATH").unwrap_or_else(|_| "./themes".to_owned());
+        let dev_mode = std::env::var("RYOB_DEV_MODE").map(|value| value == "1" || value == "true").unwrap_or(false);
+        Themes::load(Path::new(&path), pool, catalog, dev_mode)
+    }
+
+    pub fn load(path: &Path, pool: &DatabasePool, catalog: Arc<Catalog>, dev_mode: bool) -> Result<Themes, ThemeError> {
+        let mut themes = vec![Theme {
+            name: BASE_THEME.to_owned(),
+            path: None,
+            stylesheet: None,
+        }];
+
+        // A missing themes directory just means there is only the base theme
+        if path.is_dir() {
+            let mut entries: Vec<PathBuf> = std::fs::read_dir(path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<_, _>>()?;
+            entries.sort();
+            for theme_path in entries.into_iter().filter(|entry| entry.is_dir()) {
+                let name = match theme_path.file_name().and_then(|name| name.to_str()) {
+                    Some(name) if is_valid_name(name) => name.to_owned(),
+                    _ => continue,
+                };
+                let stylesheet = if theme_path.join("styles").join("theme.css").is_file() {
+                    Some(format!("/themes/{}/styles/theme.css", name))
+                } else {
+                    None
+                };
+                themes.push(Theme {
+                    name,
+                    path: Some(theme_path),
+                    stylesheet,
+                });
+            }
+        }
+
+        let (registries, template_error) = match build_registries(&themes, pool, &catalog) {
+            Ok(registries) => (registries, None),
+            // Dev mode starts anyway so the error can be shown and fixed without a restart
+            Err(ThemeError::Template(err)) if dev_mode => {
+                error!("Failed to load templates: {}", err);
+                (HashMap::new(), Some(format!("{}", err)))
+            }
+            Err(err) => return Err(err),
+        };
+        for theme in &themes {
+            info!("Loaded theme \"{}\"", theme.name);
+        }
+
+        Ok(Themes {
+            themes,
+            registries: RwLock::new(registries),
+            default_theme: RwLock::new(BASE_THEME.to_owned()),
+            pool: pool.clone(),
+            catalog,
+            dev_mode,
+            template_error: RwLock::new(template_error),
+        })
+    }
+
+    pub fn is_dev_mode(&self) -> bool {
+        self.dev_mode
+    }
+
+    // The base templates followed by the templates of each theme that has any
+    pub fn template_paths(&self) -> Vec<PathBuf> {
+        let mut paths = vec![PathBuf::from(BASE_TEMPLATES_PATH)];
+        paths.extend(self.themes.iter().filter_map(Theme::templates_path));
+        paths
+    }
+
+    // Registers every template again, keeping the current ones if any fail to compile
+    pub fn reload(&self) {
+        match build_registries(&self.themes, &self.pool, &self.catalog) {
+            Ok(registries) => {
+                *self.registries.write().unwrap() = registries;
+                *self.template_error.write().unwrap() = None;
+                info!("Reloaded templates");
+            }
+            Err(err) => {
+                error!("Failed to reload templates: {}", err);
+                *self.template_error.write().unwrap() = Some(format!("{}", err));
+            }
+        }
+    }
+
+    pub fn template_error(&self) -> Option<String> {
+        self.template_error.read().unwrap().clone()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.themes.iter().map(|theme| theme.name.as_str()).collect()
+    }
+
+    // For theme pickers, with the theme given marked as selected
+    pub fn options(&self, selected: &str) -> Vec<Value> {
+        self.themes
+            .iter()
+            .map(|theme| json!({ "name": theme.name, "selected": theme.name == selected }))
+            .collect()
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.themes.iter().any(|theme| theme.name == name)
+    }
+
+    pub fn default_theme(&self) -> String {
+        self.default_theme.read().unwrap().clone()
+    }
+
+    // Unknown themes are ignored, the default stays as it was
+    pub fn set_default_theme(&self, name: &str) {
+        if self.exists(name) {
+            *self.default_theme.write().unwrap() = name.to_owned();
+        }
+    }
+
+    // Serves each theme's styles directory under /themes/<name>/styles
+    pub fn configure(&self, config: &mut web::ServiceConfig) {
+        for theme in &self.themes {
+            if let Some(path) = &theme.path {
+                let styles_path = path.join("styles");
+                if styles_path.is_dir() {
+                    config.service(actix_files::Files::new(&format!("/themes/{}/styles", theme.name), styles_path));
+                }
+            }
+        }
+    }
+
+    pub fn catalog(&self) -> &Catalog {
+        &self.catalog
+    }
+
+    // Renders with the theme in the user's preferences in the data, or the board's default. The
+    // theme is added to the data as "theme" so the wrapper can load its stylesheet.
+    pub fn render<T: Serialize>(&self, template: &str, data: &T) -> Result<String, RenderError> {
+        self.render_in(template, data, DEFAULT_LOCALE)
+    }
+
+    // As render, in the locale in the preferences or else the locale given. The locale and the
+    // user's timezone are added to the data as "locale" and "timezone" for the helpers.
+    pub fn render_in<T: Serialize>(&self, template: &str, data: &T, locale: &str) -> Result<String, RenderError> {
+        let mut context = serde_json::to_value(data).map_err(|err| RenderError::new(format!("{}", err)))?;
+        let timezone = context.pointer("/preferences/timezone").cloned().unwrap_or(Value::Null);
+        let locale = context
+            .pointer("/preferences/locale")
+            .and_then(Value::as_str)
+            .filter(|user_locale| self.catalog.exists(user_locale))
+            .unwrap_or(locale)
+            .to_owned();
+        let default_theme = self.default_theme();
+        let name = context
+            .pointer("/preferences/theme")
+            .and_then(Value::as_str)
+            .filter(|name| self.exists(name))
+            .unwrap_or(&default_theme)
+            .to_owned();
+        let theme = self.themes.iter().find(|theme| theme.name == name).expect("The theme exists");
+
+        if let Value::Object(map) = &mut context {
+            map.insert("theme".to_owned(), json!({ "name": theme.name, "stylesheet": theme.stylesheet }));
+            map.insert("locale".to_owned(), Value::String(locale));
+            map.insert("timezone".to_owned(), timezone);
+        }
+        match self.registries.read().unwrap().get(&name) {
+            Some(registry) => registry.render(template, &context),
+            None => Err(RenderError::new("The templates have not been loaded")),
+        }
+    }
+
+    // As render, for a viewer known only by the parts of their preferences that change the output
+    pub fn render_for<T: Serialize>(&self, key: &RenderKey, template: &str, data: &T) -> Result<String, RenderError> {
+        let mut context = serde_json::to_value(data).map_err(|err| RenderError::new(format!("{}", err)))?;
+        if let Value::Object(map) = &mut context {
+            map.insert(
+                "preferences".to_owned(),
+                json!({ "theme": key.theme, "locale": key.locale, "timezone": key.timezone }),
+            );
+        }
+        self.render_in(template, &context, &key.locale)
+    }
+}
+
+// Everything besides the data that a rendered fragment depends on, so viewers with the same key
+// can share it
+#[derive(Clone, PartialEq)]
+pub struct RenderKey {
+    pub theme: Option<String>,
+    pub locale: String,
+    pub timezone: Option<String>,
+}
+
+// What handlers render pages with. The logged in user's preferences are loaded once for the
+// request and added to the data as "preferences", next to "user". Pages are in the locale the
+// user picked, or else the one the browser asks for. Everything else about the themes is
+// available through it as well.
+pub struct Renderer {
+    themes: web::Data<Themes>,
+    locale: String,
+    preferences: Option<UserPreferences>,
+}
+
+impl Renderer {
+    pub fn render<T: Serialize>(&self, template: &str, data: &T) -> Result<String, RenderError> {
+        let mut context = serde_json::to_value(data).map_err(|err| RenderError::new(format!("{}", err)))?;
+        if let (Value::Object(map), Some(preferences)) = (&mut context, &self.preferences) {
+            map.entry("preferences").or_insert_with(|| json!(preferences));
+        }
+        self.themes.render_in(template, &context, &self.locale)
+    }
+
+    // For messages worked out in Rust, like validation errors
+    pub fn translate(&self, id: &str, args: &[(&str, FluentValue)]) -> String {
+        self.themes.catalog.translate(&self.locale, id, args)
+    }
+
+    pub fn translate_ban(&self, ban: &Ban) -> String {
+        let id = match ban.scope() {
+            BanScope::Global => "banned",
+            BanScope::Posting => "banned-from-posting",
+        };
+        let reason = FluentValue::from(ban.reason.as_str());
+        match ban.expires_at {
+            Some(expires_at) => {
+                let until = expires_at.format("%Y-%m-%d %H:%M UTC").to_string();
+                self.translate(id, &[("until", FluentValue::from(until.as_str())), ("reason", reason)])
+            }
+            None => self.translate(&format!("{}-permanently", id), &[("reason", reason)]),
+        }
+    }
+
+    // Only the errors caused by what was sent, anything else is left as it is
+    pub fn translate_upload_error(&self, error: &UploadError) -> String {
+        match error {
+            UploadError::TooLarge(max_size) => self.translate("upload-too-large", &[("size", (max_size / 1024 / 1024).into())]),
+            UploadError::TooManyFiles(max_count) => self.translate("upload-too-many", &[("count", (*max_count).into())]),
+            UploadError::Invalid(_) => error.to_string(),
+        }
+    }
+
+    pub fn translate_attachment_error(&self, error: &AttachmentError) -> String {
+        match error {
+            AttachmentError::TooLarge => self.translate("attachment-too-large", &[("size", (MAX_ATTACHMENT_SIZE / 1024 / 1024).into())]),
+            AttachmentError::TooManyAttachments => self.translate("attachment-too-many", &[("count", MAX_ATTACHMENTS_PER_POST.into())]),
+            AttachmentError::UnsupportedType => self.translate("attachment-unsupported-type", &[]),
+            AttachmentError::NoFile => self.translate("upload-no-file", &[]),
+            AttachmentError::InvalidImage(err) => self.translate("upload-invalid-image", &[("error", err.as_str().into())]),
+            _ => error.to_string(),
+        }
+    }
+
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    pub fn key(&self) -> RenderKey {
+        RenderKey {
+            theme: self.preferences.as_ref().and_then(|preferences| preferences.theme.clone()),
+            locale: self.locale.clone(),
+            timezone: self.preferences.as_ref().and_then(|preferences| preferences.timezone.clone()),
+        }
+    }
+
+    // The defaults for visitors who aren't logged in
+    pub fn preferences(&self) -> Option<&UserPreferences> {
+        self.preferences.as_ref()
+    }
+
+    pub fn topics_per_page(&self) -> i64 {
+        self.preferences
+            .as_ref()
+            .map(UserPreferences::topics_per_page)
+            .unwrap_or(DEFAULT_TOPICS_PER_PAGE)
+    }
+
+    pub fn posts_per_page(&self) -> i64 {
+        self.preferences.as_ref().map(UserPreferences::posts_per_page).unwrap_or(DEFAULT_POSTS_PER_PAGE)
+    }
+}
+
+impl std::ops::Deref for Renderer {
+    type Target = Themes;
+
+    fn deref(&self) -> &Themes {
+        &self.themes
+    }
+}
+
+fn load_preferences(req: &HttpRequest) -> Result<Option<UserPreferences>, RyobError> {
+    let pool = req
+        .get_app_data::<DatabasePool>()
+        .ok_or_else(|| RyobError::from_display(StatusCode::INTERNAL_SERVER_ERROR, "No database pool"))?;
+    let connection = pool.get()?;
+    match User::from_session(&connection, &req.clone().get_session())? {
+        Some(user) => Ok(Some(UserPreferences::for_user(&connection, user.id)?)),
+        None => Ok(None),
+    }
+}
+
+impl FromRequest for Renderer {
+    type Error = RyobError;
+    type Future = Result<Renderer, RyobError>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let themes = req
+            .get_app_data::<Themes>()
+            .ok_or_else(|| RyobError::from_display(StatusCode::INTERNAL_SERVER_ERROR, "No themes"))?;
+        let preferences = load_preferences(req)?;
+        let chosen_locale = preferences
+            .as_ref()
+            .and_then(|preferences| preferences.locale.clone())
+            .filter(|locale| themes.catalog.exists(locale));
+        let locale = match (chosen_locale, req.headers().get(header::ACCEPT_LANGUAGE).and_then(|value| value.to_str().ok())) {
+            (Some(locale), _) => locale,
+            (None, Some(accept_language)) => themes.catalog.negotiate(accept_language),
+            (None, None) => DEFAULT_LOCALE.to_owned(),
+        };
+        Ok(Renderer { themes, locale, preferences })
+    }
+}
+
+// Shown in place of every page while the templates are broken in dev mode
+pub fn template_error_page(error: &str) -> HttpResponse {
+    HttpResponse::InternalServerError().content_type("text/html; charset=utf-8").body(format!(
+        "<!DOCTYPE html>\n<html>\n<head><title>Template error</title></head>\n<body>\n<h1>Template error</h1>\n<pre>{}</pre>\n</body>\n</html>\n",
+        handlebars::html_escape(error)
+    ))
+}
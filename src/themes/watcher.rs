@@ -0,0 +1,51 @@
+// Reloads the templates when they change in dev mode. Editors tend to write a file in several
+// steps, so changes are collected until things have been quiet for a moment.
+
+use super::Themes;
+use actix_web::web;
+use log::{error, info};
+use notify::{RecursiveMode, Watcher};
+use std::sync::mpsc;
+use std::time::Duration;
+
+const SETTLE_DELAY: Duration = Duration::from_millis(200);
+
+pub fn spawn(hb: web::Data<Themes>) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        if let Err(err) = watch(&hb) {
+            error!("Stopped watching templates: {}", err);
+        }
+    })
+}
+
+fn watch(hb: &Themes) -> notify::Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    for path in hb.template_paths() {
+        watcher.watch(&path, RecursiveMode::Recursive)?;
+        info!("Watching {} for template changes", path.display());
+    }
+
+    // Ends when the watcher goes away and takes the sender with it
+    while let Ok(event) = receiver.recv() {
+        let mut changed = is_change(event);
+        while let Ok(event) = receiver.recv_timeout(SETTLE_DELAY) {
+            changed |= is_change(event);
+        }
+        if changed {
+            hb.reload();
+        }
+    }
+    Ok(())
+}
+
+// Reading the templates while loading them is reported too, so only writes count
+fn is_change(event: notify::Result<notify::Event>) -> bool {
+    match event {
+        Ok(event) => event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove(),
+        Err(err) => {
+            error!("Failed to watch templates: {}", err);
+            false
+        }
+    }
+}
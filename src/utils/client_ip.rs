@@ -0,0 +1,87 @@
+// Working out which address a request came from. Behind a reverse proxy the peer is the proxy,
+// so X-Forwarded-For is followed back through the proxies listed in RYOB_TRUSTED_PROXIES (comma
+// separated addresses or CIDR ranges). Without that list the header is ignored, as anyone can set it.
+
+use actix_web::dev::Payload;
+use actix_web::http::HeaderMap;
+use actix_web::{FromRequest, HttpRequest};
+use ipnet::IpNet;
+use std::net::{IpAddr, SocketAddr};
+
+#[derive(Default)]
+pub struct TrustedProxies(Vec<IpNet>);
+
+impl TrustedProxies {
+    pub fn from_env() -> Result<TrustedProxies, String> {
+        match std::env::var("RYOB_TRUSTED_PROXIES") {
+            Ok(value) => TrustedProxies::parse(&value),
+            Err(_) => Ok(TrustedProxies::default()),
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<TrustedProxies, String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| parse_network(entry).ok_or_else(|| format!("\"{}\" is not an address or CIDR range", entry)))
+            .collect::<Result<Vec<IpNet>, String>>()
+            .map(TrustedProxies)
+    }
+
+    fn contains(&self, ip: &IpAddr) -> bool {
+        self.0.iter().any(|network| network.contains(ip))
+    }
+
+    // The rightmost forwarded address that isn't one of our proxies is the client. Anything to
+    // the left of it was added by the client and can't be trusted. IPv4 clients of a dual stack
+    // listener show up as ::ffff:a.b.c.d, which is turned back into a.b.c.d so they match IPv4
+    // bans and proxies.
+    pub fn resolve(&self, peer: Option<SocketAddr>, headers: &HeaderMap) -> Option<IpAddr> {
+        let peer = peer.map(|peer| peer.ip().to_canonical())?;
+        if !self.contains(&peer) {
+            return Some(peer);
+        }
+        let forwarded: Vec<IpAddr> = headers
+            .get_all("x-forwarded-for")
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|entry| entry.trim().parse::<IpAddr>().ok())
+            .map(|ip| ip.to_canonical())
+            .collect();
+        let client = forwarded.iter().rev().find(|ip| !self.contains(ip)).or_else(|| forwarded.first());
+        Some(client.copied().unwrap_or(peer))
+    }
+}
+
+// Plain addresses are taken as a range holding just that address
+pub fn parse_network(value: &str) -> Option<IpNet> {
+    value
+        .parse::<IpNet>()
+        .ok()
+        .map(|network| network.trunc())
+        .or_else(|| value.parse::<IpAddr>().ok().map(IpNet::from))
+}
+
+// The range that addresses from the same household or provider usually share
+pub fn subnet(ip: IpAddr) -> IpNet {
+    let prefix = if ip.is_ipv4() { 24 } else { 48 };
+    IpNet::new(ip, prefix).map(|network| network.trunc()).unwrap_or_else(|_| IpNet::from(ip))
+}
+
+// The client's address, if it could be worked out
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl FromRequest for ClientIp {
+    type Error = actix_web::Error;
+    type Future = Result<ClientIp, actix_web::Error>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let ip = match req.get_app_data::<TrustedProxies>() {
+            Some(proxies) => proxies.resolve(req.peer_addr(), req.headers()),
+            None => TrustedProxies::default().resolve(req.peer_addr(), req.headers()),
+        };
+        Ok(ClientIp(ip))
+    }
+}
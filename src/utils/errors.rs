@@ -29,8 +29,57 @@ impl From<r2d2::Error> for RyobError {
     }
 }
 
+impl From<diesel::result::Error> for RyobError {
+    fn from(error: diesel::result::Error) -> RyobError {
+        RyobError::from_display(StatusCode::INTERNAL_SERVER_ERROR, error)
+    }
+}
+
 impl From<handlebars::RenderError> for RyobError {
     fn from(error: handlebars::RenderError) -> RyobError {
         RyobError::from_display(StatusCode::INTERNAL_SERVER_ERROR, error)
     }
 }
+
+// The JSON API reports the same errors with the same status codes, but with a body saying what
+// went wrong. Details of server errors stay in the log.
+#[derive(Debug)]
+pub struct ApiError(pub StatusCode, pub Vec<String>);
+
+impl ApiError {
+    pub fn bad_request(errors: Vec<String>) -> ApiError {
+        ApiError(StatusCode::BAD_REQUEST, errors)
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.1.join(", "))
+    }
+}
+
+impl ResponseError for ApiError {
+    fn error_response(&self) -> HttpResponse {
+        if self.0.is_server_error() {
+            log::error!("{}", self);
+            HttpResponse::build(self.0).json(json!({ "errors": [self.0.canonical_reason().unwrap_or("Internal Server Error")] }))
+        } else {
+            HttpResponse::build(self.0).json(json!({ "errors": self.1 }))
+        }
+    }
+
+    // The default would replace the JSON body with the Display text
+    fn render_response(&self) -> HttpResponse {
+        self.error_response()
+    }
+}
+
+impl<T> From<T> for ApiError
+where
+    RyobError: From<T>,
+{
+    fn from(error: T) -> ApiError {
+        let RyobError(code, message) = RyobError::from(error);
+        ApiError(code, vec![message])
+    }
+}
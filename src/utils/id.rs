@@ -3,7 +3,7 @@
 use diesel::deserialize::FromSqlRow;
 use diesel::expression::AsExpression;
 use diesel::serialize::ToSql;
-use diesel::sql_types::BigInt;
+use diesel::sql_types::{BigInt, Nullable};
 use diesel::Queryable;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
@@ -11,7 +11,7 @@ use std::fmt::{Debug, Display, Formatter};
 use std::io::Write;
 use std::marker::PhantomData;
 
-#[derive(PartialEq, PartialOrd, Serialize, Deserialize)]
+#[derive(Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct Id<T>(i64, #[serde(skip)] PhantomData<T>);
 
@@ -19,16 +19,40 @@ impl<T> Id<T> {
     pub fn new(id: i64) -> Id<T> {
         Id(id, PhantomData)
     }
+
+    pub fn value(self) -> i64 {
+        self.0
+    }
 }
 
 impl<T> Clone for Id<T> {
     fn clone(&self) -> Self {
-        Id::new(self.0)
+        *self
     }
 }
 
 impl<T> Copy for Id<T> {}
 
+impl<T> PartialEq for Id<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T> Eq for Id<T> {}
+
+impl<T> PartialOrd for Id<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.0.partial_cmp(&other.0)
+    }
+}
+
+impl<T> std::hash::Hash for Id<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
 impl<T> Display for Id<T> {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         write!(f, "<ID: {}>", self.0)
@@ -61,6 +85,27 @@ where
     }
 }
 
+// Lets optional ids be inserted into nullable columns
+impl<T> AsExpression<Nullable<BigInt>> for Id<T>
+where
+    i64: AsExpression<Nullable<BigInt>>,
+{
+    type Expression = <i64 as AsExpression<Nullable<BigInt>>>::Expression;
+    fn as_expression(self) -> Self::Expression {
+        <i64 as AsExpression<Nullable<BigInt>>>::as_expression(self.0)
+    }
+}
+
+impl<T> AsExpression<Nullable<BigInt>> for &Id<T>
+where
+    i64: AsExpression<Nullable<BigInt>>,
+{
+    type Expression = <i64 as AsExpression<Nullable<BigInt>>>::Expression;
+    fn as_expression(self) -> Self::Expression {
+        <i64 as AsExpression<Nullable<BigInt>>>::as_expression(self.0)
+    }
+}
+
 impl<T, DB> ToSql<BigInt, DB> for Id<T>
 where
     DB: diesel::backend::Backend,
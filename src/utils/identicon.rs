@@ -0,0 +1,52 @@
+// The avatar shown for users who haven't uploaded one: a 5x5 grid, mirrored left to right, in a
+// colour picked from a hash of the user's id so that it is the same wherever it is shown
+
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+
+const GRID_SIZE: usize = 5;
+const CELL_SIZE: usize = 16;
+const BACKGROUND: &str = "#f0f0f0";
+
+pub fn svg(seed: i64) -> String {
+    let hash = Sha256::digest(seed.to_be_bytes());
+    let hue = u16::from_be_bytes([hash[0], hash[1]]) % 360;
+    let side = GRID_SIZE * CELL_SIZE;
+
+    let mut output = String::new();
+    write!(
+        output,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{side}\" height=\"{side}\" viewBox=\"0 0 {side} {side}\">\
+         <rect width=\"{side}\" height=\"{side}\" fill=\"{}\"/><g fill=\"hsl({}, 55%, 50%)\">",
+        BACKGROUND,
+        hue,
+        side = side
+    )
+    .unwrap();
+
+    // Only the left half and middle column are drawn from the hash, the right half mirrors them
+    let columns = GRID_SIZE.div_ceil(2);
+    for row in 0..GRID_SIZE {
+        for column in 0..columns {
+            let bit = row * columns + column;
+            if hash[2 + bit / 8] & (1 << (bit % 8)) == 0 {
+                continue;
+            }
+            let mirrored = GRID_SIZE - 1 - column;
+            for x in if mirrored == column { vec![column] } else { vec![column, mirrored] } {
+                write!(
+                    output,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>",
+                    x * CELL_SIZE,
+                    row * CELL_SIZE,
+                    CELL_SIZE,
+                    CELL_SIZE
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    output.push_str("</g></svg>");
+    output
+}
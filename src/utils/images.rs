@@ -0,0 +1,295 @@
+// Processing of uploaded images: removing metadata that shouldn't be published (EXIF holds camera
+// details and often GPS coordinates) and making the thumbnails shown in threads and avatars
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits};
+use std::io::Cursor;
+
+// Images this large are refused before decoding, so a small file that expands into gigabytes of
+// pixels (a decompression bomb) costs nothing
+const MAX_DIMENSION: u32 = 10000;
+const MAX_DECODE_ALLOCATION: u64 = 256 * 1024 * 1024;
+
+const JPEG_QUALITY: u8 = 85;
+
+// Each thumbnail fits within a square of this many pixels, and is never scaled up
+pub const THUMBNAIL_SIZES: [(&str, u32); 3] = [("small", 160), ("medium", 480), ("large", 1200)];
+
+// Avatars are cropped to a square this many pixels wide, or smaller if the upload is
+pub const AVATAR_SIZE: u32 = 256;
+
+pub struct Thumbnail {
+    pub size: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub content_type: &'static str,
+    pub contents: Vec<u8>,
+}
+
+fn limits() -> Limits {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOCATION);
+    limits
+}
+
+// Animated GIFs decode to their first frame. The image comes back the right way up, with any
+// EXIF orientation applied, along with that orientation.
+pub fn decode(contents: &[u8]) -> Result<(DynamicImage, Orientation), image::ImageError> {
+    let mut reader = ImageReader::new(Cursor::new(contents)).with_guessed_format()?;
+    reader.limits(limits());
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok((image, orientation))
+}
+
+fn encode(image: &DynamicImage) -> Result<(&'static str, Vec<u8>), image::ImageError> {
+    let mut contents = vec![];
+    if image.color().has_alpha() {
+        image.write_to(Cursor::new(&mut contents), ImageFormat::Png)?;
+        Ok(("image/png", contents))
+    } else {
+        JpegEncoder::new_with_quality(&mut contents, JPEG_QUALITY).encode_image(&image.to_rgb8())?;
+        Ok(("image/jpeg", contents))
+    }
+}
+
+pub fn thumbnails(image: &DynamicImage) -> Result<Vec<Thumbnail>, image::ImageError> {
+    THUMBNAIL_SIZES
+        .iter()
+        .map(|(size, max_dimension)| {
+            let resized = if image.width() > *max_dimension || image.height() > *max_dimension {
+                image.thumbnail(*max_dimension, *max_dimension)
+            } else {
+                image.clone()
+            };
+            let (content_type, contents) = encode(&resized)?;
+            Ok(Thumbnail {
+                size,
+                width: resized.width(),
+                height: resized.height(),
+                content_type,
+                contents,
+            })
+        })
+        .collect()
+}
+
+// Crops the middle square out of the image. Re-encoding also leaves behind any metadata.
+pub fn avatar(image: &DynamicImage) -> Result<(&'static str, Vec<u8>), image::ImageError> {
+    let side = image.width().min(image.height()).min(AVATAR_SIZE);
+    encode(&image.resize_to_fill(side, side, FilterType::Lanczos3))
+}
+
+// Returns the image without its metadata. Only an image that relied on EXIF to be shown the right
+// way up is re-encoded, the rest have their metadata cut out and the image data left untouched.
+pub fn strip_metadata(
+    content_type: &'static str,
+    contents: &[u8],
+    image: &DynamicImage,
+    orientation: Orientation,
+) -> Result<(&'static str, Vec<u8>), image::ImageError> {
+    match content_type {
+        _ if orientation != Orientation::NoTransforms => encode(image),
+        "image/jpeg" => Ok((content_type, strip_jpeg(contents))),
+        "image/png" => Ok((content_type, strip_png(contents))),
+        "image/webp" => Ok((content_type, strip_webp(contents))),
+        // GIFs carry no EXIF
+        _ => Ok((content_type, contents.to_vec())),
+    }
+}
+
+// Drops the APP1 (EXIF and XMP) and APP13 (IPTC) segments and comments, keeping colour profiles
+fn strip_jpeg(contents: &[u8]) -> Vec<u8> {
+    let mut stripped = contents[0..2].to_vec();
+    let mut position = 2;
+    while position + 4 <= contents.len() && contents[position] == 0xff {
+        let marker = contents[position + 1];
+        // Everything from the start of scan on is image data
+        if marker == 0xda {
+            break;
+        }
+        let length = u16::from_be_bytes([contents[position + 2], contents[position + 3]]) as usize;
+        let end = (position + 2 + length).min(contents.len());
+        if marker != 0xe1 && marker != 0xed && marker != 0xfe {
+            stripped.extend_from_slice(&contents[position..end]);
+        }
+        position = end;
+    }
+    stripped.extend_from_slice(&contents[position.min(contents.len())..]);
+    stripped
+}
+
+fn strip_png(contents: &[u8]) -> Vec<u8> {
+    let mut stripped = contents[0..8].to_vec();
+    let mut position = 8;
+    while position + 12 <= contents.len() {
+        let length = u32::from_be_bytes([contents[position], contents[position + 1], contents[position + 2], contents[position + 3]]) as usize;
+        let end = (position + 12 + length).min(contents.len());
+        let chunk_type = &contents[position + 4..position + 8];
+        if !matches!(chunk_type, b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt" | b"tIME") {
+            stripped.extend_from_slice(&contents[position..end]);
+        }
+        position = end;
+    }
+    stripped
+}
+
+fn strip_webp(contents: &[u8]) -> Vec<u8> {
+    let mut chunks = vec![];
+    let mut position = 12;
+    while position + 8 <= contents.len() {
+        let length = u32::from_le_bytes([contents[position + 4], contents[position + 5], contents[position + 6], contents[position + 7]]) as usize;
+        // Chunks are padded to an even length
+        let end = (position + 8 + length + length % 2).min(contents.len());
+        let mut chunk = contents[position..end].to_vec();
+        match &chunk[0..4] {
+            b"EXIF" | b"XMP " => {}
+            b"VP8X" => {
+                // The extended header has flags announcing the metadata chunks
+                if chunk.len() > 8 {
+                    chunk[8] &= !(0x08 | 0x04);
+                }
+                chunks.push(chunk);
+            }
+            _ => chunks.push(chunk),
+        }
+        position = end;
+    }
+    let body: Vec<u8> = chunks.concat();
+    let mut stripped = b"RIFF".to_vec();
+    stripped.extend_from_slice(&((body.len() + 4) as u32).to_le_bytes());
+    stripped.extend_from_slice(b"WEBP");
+    stripped.extend_from_slice(&body);
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> DynamicImage {
+        DynamicImage::ImageRgb8(image::RgbImage::from_fn(8, 8, |x, y| image::Rgb([(x * 32) as u8, (y * 32) as u8, 128])))
+    }
+
+    fn jpeg_segment(marker: u8, data: &[u8]) -> Vec<u8> {
+        let mut segment = vec![0xff, marker];
+        segment.extend_from_slice(&((data.len() + 2) as u16).to_be_bytes());
+        segment.extend_from_slice(data);
+        segment
+    }
+
+    // The CRC isn't checked when stripping, and the chunks given one here are all removed
+    fn png_chunk(chunk_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(chunk_type);
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(&[0; 4]);
+        chunk
+    }
+
+    fn webp_chunk(fourcc: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = fourcc.to_vec();
+        chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    fn webp(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body = chunks.concat();
+        let mut contents = b"RIFF".to_vec();
+        contents.extend_from_slice(&((body.len() + 4) as u32).to_le_bytes());
+        contents.extend_from_slice(b"WEBP");
+        contents.extend_from_slice(&body);
+        contents
+    }
+
+    #[test]
+    fn strips_jpeg_metadata() {
+        let (_, original) = encode(&sample()).unwrap();
+        let mut tagged = original[0..2].to_vec();
+        tagged.extend(jpeg_segment(0xe1, b"Exif\0\0GPS here"));
+        tagged.extend(jpeg_segment(0xed, b"Photoshop 3.0\0IPTC"));
+        tagged.extend(jpeg_segment(0xfe, b"a comment"));
+        tagged.extend_from_slice(&original[2..]);
+
+        let stripped = strip_jpeg(&tagged);
+        assert_eq!(stripped, original);
+        assert!(decode(&stripped).is_ok());
+    }
+
+    #[test]
+    fn keeps_jpeg_colour_profiles() {
+        let (_, original) = encode(&sample()).unwrap();
+        let profile = jpeg_segment(0xe2, b"ICC_PROFILE\0\x01\x01");
+        let mut tagged = original[0..2].to_vec();
+        tagged.extend_from_slice(&profile);
+        tagged.extend(jpeg_segment(0xe1, b"Exif\0\0"));
+        tagged.extend_from_slice(&original[2..]);
+
+        let stripped = strip_jpeg(&tagged);
+        assert_eq!(&stripped[2..2 + profile.len()], &profile[..]);
+        assert_eq!(stripped.len(), original.len() + profile.len());
+    }
+
+    #[test]
+    fn survives_truncated_jpeg() {
+        let stripped = strip_jpeg(b"\xff\xd8\xff\xe1\xff\xff Exif");
+        assert_eq!(stripped, b"\xff\xd8");
+    }
+
+    #[test]
+    fn strips_png_metadata() {
+        let mut original = vec![];
+        sample().write_to(Cursor::new(&mut original), ImageFormat::Png).unwrap();
+        // The signature and IHDR chunk come first
+        let header = 8 + 12 + 13;
+        let mut tagged = original[0..header].to_vec();
+        for chunk_type in [b"eXIf", b"tEXt", b"zTXt", b"iTXt", b"tIME"] {
+            tagged.extend(png_chunk(chunk_type, b"metadata"));
+        }
+        tagged.extend_from_slice(&original[header..]);
+
+        let stripped = strip_png(&tagged);
+        assert_eq!(stripped, original);
+        assert!(decode(&stripped).is_ok());
+    }
+
+    #[test]
+    fn strips_webp_metadata() {
+        let image = webp_chunk(b"VP8L", b"odd");
+        let tagged = webp(&[
+            webp_chunk(b"VP8X", &[0x0c | 0x10, 0, 0, 0, 7, 0, 0, 7, 0, 0]),
+            webp_chunk(b"EXIF", b"Exif\0\0GPS"),
+            image.clone(),
+            webp_chunk(b"XMP ", b"<x:xmpmeta/>"),
+        ]);
+
+        let stripped = strip_webp(&tagged);
+        // Only the alpha flag is left in the extended header
+        assert_eq!(stripped, webp(&[webp_chunk(b"VP8X", &[0x10, 0, 0, 0, 7, 0, 0, 7, 0, 0]), image]));
+    }
+
+    #[test]
+    fn leaves_gifs_alone() {
+        let contents = b"GIF89a\x01\0\x01\0";
+        let (content_type, stripped) = strip_metadata("image/gif", contents, &sample(), Orientation::NoTransforms).unwrap();
+        assert_eq!(content_type, "image/gif");
+        assert_eq!(stripped, contents);
+    }
+
+    #[test]
+    fn re_encodes_rotated_images() {
+        let (content_type, stripped) = strip_metadata("image/png", b"not used", &sample(), Orientation::Rotate90).unwrap();
+        assert_eq!(content_type, "image/jpeg");
+        assert_eq!(decode(&stripped).unwrap().0.width(), 8);
+    }
+}
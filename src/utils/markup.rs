@@ -0,0 +1,313 @@
+// A small BBCode-style markup renderer shared by everything users write (posts, messages)
+//
+// Text is always HTML-escaped; only the tags below produce markup, and anything that
+// fails to parse is emitted literally.
+//
+//   [b] [i] [u] [s] [quote]   simple formatting, closed by the matching [/tag]
+//   [url=http://...]text[/url] and [url]http://...[/url]
+//   [img]http://...[/img]      rendered as a link when images are not allowed
+//   [code]...[/code]           contents are not interpreted
+
+pub struct MarkupOptions {
+    pub allow_images: bool,
+    pub max_images: usize,
+}
+
+impl Default for MarkupOptions {
+    fn default() -> MarkupOptions {
+        MarkupOptions {
+            allow_images: true,
+            max_images: 16,
+        }
+    }
+}
+
+pub fn render(source: &str) -> String {
+    render_with(source, &MarkupOptions::default())
+}
+
+pub fn render_with(source: &str, options: &MarkupOptions) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut open_tags: Vec<&'static str> = vec![];
+    let mut image_count = 0;
+    let mut rest = source;
+
+    while let Some(start) = rest.find('[') {
+        escape_text_into(&rest[..start], &mut output);
+        rest = &rest[start..];
+
+        let (tag, tag_length) = match parse_tag(rest) {
+            Some(parsed) => parsed,
+            None => {
+                output.push('[');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+
+        match tag {
+            Tag::Close(name) => {
+                if let Some(position) = open_tags.iter().rposition(|open| *open == name) {
+                    while open_tags.len() > position {
+                        let open = open_tags.pop().unwrap();
+                        output.push_str(closing_html(open));
+                    }
+                } else {
+                    escape_text_into(&rest[..tag_length], &mut output);
+                }
+                rest = &rest[tag_length..];
+            }
+            Tag::Open(name, None) if is_simple_tag(name) => {
+                output.push_str(opening_html(name));
+                open_tags.push(name);
+                rest = &rest[tag_length..];
+            }
+            Tag::Open("url", Some(target)) if is_safe_url(target) => {
+                output.push_str("<a href=\"");
+                escape_attribute_into(target, &mut output);
+                output.push_str("\" rel=\"nofollow noopener\">");
+                open_tags.push("url");
+                rest = &rest[tag_length..];
+            }
+            Tag::Open(name, None) if is_raw_tag(name) => {
+                let body = &rest[tag_length..];
+                let closing = format!("[/{}]", name);
+                match find_case_insensitive(body, &closing) {
+                    Some(end) => {
+                        let content = &body[..end];
+                        render_raw_tag(name, content, options, &mut image_count, &mut output);
+                        rest = &body[end + closing.len()..];
+                    }
+                    None => {
+                        escape_text_into(&rest[..tag_length], &mut output);
+                        rest = body;
+                    }
+                }
+            }
+            _ => {
+                escape_text_into(&rest[..tag_length], &mut output);
+                rest = &rest[tag_length..];
+            }
+        }
+    }
+
+    escape_text_into(rest, &mut output);
+    while let Some(open) = open_tags.pop() {
+        output.push_str(closing_html(open));
+    }
+    output
+}
+
+pub fn escape_html(source: &str) -> String {
+    let mut output = String::with_capacity(source.len());
+    escape_attribute_into(source, &mut output);
+    output
+}
+
+enum Tag<'a> {
+    Open(&'static str, Option<&'a str>),
+    Close(&'static str),
+}
+
+const KNOWN_TAGS: [&str; 8] = ["b", "i", "u", "s", "quote", "url", "img", "code"];
+
+fn parse_tag(source: &str) -> Option<(Tag<'_>, usize)> {
+    let end = source.find(']')?;
+    let inner = &source[1..end];
+    if inner.contains('[') || inner.contains('\n') {
+        return None;
+    }
+
+    let (closing, inner) = if let Some(stripped) = inner.strip_prefix('/') {
+        (true, stripped)
+    } else {
+        (false, inner)
+    };
+    let (name, argument) = match inner.find('=') {
+        Some(position) if !closing => (&inner[..position], Some(inner[position + 1..].trim_matches('"'))),
+        _ => (inner, None),
+    };
+
+    let name = KNOWN_TAGS.iter().find(|known| known.eq_ignore_ascii_case(name))?;
+    let tag = if closing { Tag::Close(name) } else { Tag::Open(name, argument) };
+    Some((tag, end + 1))
+}
+
+fn is_simple_tag(name: &str) -> bool {
+    matches!(name, "b" | "i" | "u" | "s" | "quote")
+}
+
+fn is_raw_tag(name: &str) -> bool {
+    matches!(name, "url" | "img" | "code")
+}
+
+fn opening_html(name: &str) -> &'static str {
+    match name {
+        "b" => "<strong>",
+        "i" => "<em>",
+        "u" => "<u>",
+        "s" => "<s>",
+        "quote" => "<blockquote class=\"ryob-markup-quote\">",
+        _ => "",
+    }
+}
+
+fn closing_html(name: &str) -> &'static str {
+    match name {
+        "b" => "</strong>",
+        "i" => "</em>",
+        "u" => "</u>",
+        "s" => "</s>",
+        "quote" => "</blockquote>",
+        "url" => "</a>",
+        _ => "",
+    }
+}
+
+fn render_raw_tag(name: &str, content: &str, options: &MarkupOptions, image_count: &mut usize, output: &mut String) {
+    let target = content.trim();
+    match name {
+        "code" => {
+            output.push_str("<pre class=\"ryob-markup-code\"><code>");
+            escape_attribute_into(content.trim_matches('\n'), output);
+            output.push_str("</code></pre>");
+        }
+        "img" if is_safe_url(target) && options.allow_images && *image_count < options.max_images => {
+            *image_count += 1;
+            output.push_str("<img class=\"ryob-markup-image\" alt=\"\" src=\"");
+            escape_attribute_into(target, output);
+            output.push_str("\" />");
+        }
+        "url" | "img" if is_safe_url(target) => {
+            output.push_str("<a href=\"");
+            escape_attribute_into(target, output);
+            output.push_str("\" rel=\"nofollow noopener\">");
+            escape_attribute_into(target, output);
+            output.push_str("</a>");
+        }
+        _ => {
+            output.push('[');
+            output.push_str(name);
+            output.push(']');
+            escape_text_into(content, output);
+            output.push_str("[/");
+            output.push_str(name);
+            output.push(']');
+        }
+    }
+}
+
+fn is_safe_url(url: &str) -> bool {
+    let lowercase = url.to_ascii_lowercase();
+    let has_safe_scheme = lowercase.starts_with("http://") || lowercase.starts_with("https://");
+    let is_local = url.starts_with('/') && !url.starts_with("//");
+    (has_safe_scheme || is_local) && !url.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+fn find_case_insensitive(haystack: &str, needle: &str) -> Option<usize> {
+    haystack.to_ascii_lowercase().find(&needle.to_ascii_lowercase())
+}
+
+fn escape_attribute_into(source: &str, output: &mut String) {
+    for c in source.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#x27;"),
+            _ => output.push(c),
+        }
+    }
+}
+
+fn escape_text_into(source: &str, output: &mut String) {
+    for (index, line) in source.split('\n').enumerate() {
+        if index > 0 {
+            output.push_str("<br />\n");
+        }
+        escape_attribute_into(line.trim_end_matches('\r'), output);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_text() {
+        assert_eq!(
+            render("<script>alert('x')</script> & \"y\""),
+            "&lt;script&gt;alert(&#x27;x&#x27;)&lt;/script&gt; &amp; &quot;y&quot;"
+        );
+        assert_eq!(escape_html("<a href=\"x\">"), "&lt;a href=&quot;x&quot;&gt;");
+    }
+
+    #[test]
+    fn converts_newlines() {
+        assert_eq!(render("one\r\ntwo\nthree"), "one<br />\ntwo<br />\nthree");
+    }
+
+    #[test]
+    fn renders_and_closes_simple_tags() {
+        assert_eq!(render("[b]bold[/b] [I]it[/i]"), "<strong>bold</strong> <em>it</em>");
+        assert_eq!(render("[b][i]unclosed"), "<strong><em>unclosed</em></strong>");
+        assert_eq!(render("[b][i]x[/b]"), "<strong><em>x</em></strong>");
+        assert_eq!(render("[/b]stray"), "[/b]stray");
+        assert_eq!(render("[unknown]x[/unknown] [b"), "[unknown]x[/unknown] [b");
+    }
+
+    #[test]
+    fn allows_only_safe_urls() {
+        assert_eq!(
+            render("[url=https://example.com/?a=1&b=2]x[/url]"),
+            "<a href=\"https://example.com/?a=1&amp;b=2\" rel=\"nofollow noopener\">x</a>"
+        );
+        assert_eq!(render("[url]/topics/1[/url]"), "<a href=\"/topics/1\" rel=\"nofollow noopener\">/topics/1</a>");
+        assert_eq!(render("[url=javascript:alert(1)]x[/url]"), "[url=javascript:alert(1)]x[/url]");
+        assert_eq!(render("[url]JavaScript:alert(1)[/url]"), "[url]JavaScript:alert(1)[/url]");
+        assert_eq!(render("[url]//evil.example[/url]"), "[url]//evil.example[/url]");
+        assert_eq!(render("[url]data:text/html,x[/url]"), "[url]data:text/html,x[/url]");
+        assert_eq!(
+            render("[url=\"https://a.example/\" onclick=\"x\"]y[/url]"),
+            "[url=&quot;https://a.example/&quot; onclick=&quot;x&quot;]y[/url]"
+        );
+    }
+
+    #[test]
+    fn escapes_quotes_in_urls() {
+        assert_eq!(
+            render("[img]https://a.example/\"onerror=\"x[/img]"),
+            "<img class=\"ryob-markup-image\" alt=\"\" src=\"https://a.example/&quot;onerror=&quot;x\" />"
+        );
+    }
+
+    #[test]
+    fn limits_images() {
+        let options = MarkupOptions {
+            allow_images: true,
+            max_images: 1,
+        };
+        assert_eq!(
+            render_with("[img]/a.png[/img][img]/b.png[/img]", &options),
+            "<img class=\"ryob-markup-image\" alt=\"\" src=\"/a.png\" /><a href=\"/b.png\" rel=\"nofollow noopener\">/b.png</a>"
+        );
+        let options = MarkupOptions {
+            allow_images: false,
+            max_images: 16,
+        };
+        assert_eq!(
+            render_with("[img]/a.png[/img]", &options),
+            "<a href=\"/a.png\" rel=\"nofollow noopener\">/a.png</a>"
+        );
+    }
+
+    #[test]
+    fn leaves_code_uninterpreted() {
+        assert_eq!(
+            render("[code]\n[b]<x>[/b]\n[/code]"),
+            "<pre class=\"ryob-markup-code\"><code>[b]&lt;x&gt;[/b]</code></pre>"
+        );
+        assert_eq!(render("[code]never closed"), "[code]never closed");
+    }
+}
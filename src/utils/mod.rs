@@ -1,2 +1,12 @@
+pub mod client_ip;
 pub mod errors;
 pub mod id;
+pub mod identicon;
+pub mod images;
+pub mod markup;
+pub mod pagination;
+pub mod queue;
+pub mod signing;
+pub mod sniff;
+pub mod totp;
+pub mod uploads;
@@ -0,0 +1,78 @@
+// Page-number based pagination for listing controllers
+
+use serde::{Deserialize, Serialize};
+
+// Far past the end of any listing, and small enough that offsets can't overflow
+const MAX_PAGE: i64 = 1_000_000;
+
+#[derive(Deserialize, Default)]
+pub struct PageQuery {
+    page: Option<i64>,
+}
+
+impl PageQuery {
+    pub fn page(&self) -> i64 {
+        self.page.unwrap_or(1).clamp(1, MAX_PAGE)
+    }
+
+    pub fn offset(&self, per_page: i64) -> i64 {
+        (self.page() - 1).saturating_mul(per_page)
+    }
+}
+
+#[derive(Serialize)]
+pub struct Pagination {
+    pub page: i64,
+    pub per_page: i64,
+    pub previous_page: Option<i64>,
+    pub next_page: Option<i64>,
+}
+
+impl Pagination {
+    // Listings fetch one more row than they display so we can tell whether a next page exists
+    pub fn from_results<T>(query: &PageQuery, per_page: i64, results: &mut Vec<T>) -> Pagination {
+        let page = query.page();
+        let has_next = results.len() as i64 > per_page;
+        results.truncate(per_page as usize);
+        Pagination {
+            page,
+            per_page,
+            previous_page: if page > 1 { Some(page - 1) } else { None },
+            next_page: if has_next { Some(page + 1) } else { None },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(page: Option<i64>) -> PageQuery {
+        PageQuery { page }
+    }
+
+    #[test]
+    fn starts_at_the_first_page() {
+        assert_eq!(query(None).offset(50), 0);
+        assert_eq!(query(Some(0)).offset(50), 0);
+        assert_eq!(query(Some(-3)).offset(50), 0);
+        assert_eq!(query(Some(3)).offset(50), 100);
+    }
+
+    #[test]
+    fn clamps_huge_pages() {
+        let huge = query(Some(i64::MAX));
+        assert_eq!(huge.page(), MAX_PAGE);
+        assert_eq!(huge.offset(50), (MAX_PAGE - 1) * 50);
+        assert_eq!(huge.offset(i64::MAX), i64::MAX);
+    }
+
+    #[test]
+    fn links_neighbouring_pages() {
+        let mut results: Vec<i32> = (0..51).collect();
+        let pagination = Pagination::from_results(&query(Some(i64::MAX)), 50, &mut results);
+        assert_eq!(results.len(), 50);
+        assert_eq!(pagination.previous_page, Some(MAX_PAGE - 1));
+        assert_eq!(pagination.next_page, Some(MAX_PAGE + 1));
+    }
+}
@@ -0,0 +1,69 @@
+// The delivery loop shared by the background queues (outgoing mail and webhooks)
+//
+// Due items are claimed in a short transaction of their own that moves their next attempt past a
+// lease, then sent with no transaction open, and each is marked on its own once it has been sent.
+// A failure part way through a batch can't undo the record of something that already went out,
+// and items claimed by a worker that dies are picked up again once the lease runs out.
+
+use crate::database::types::*;
+use log::error;
+use std::fmt::Display;
+use std::time::Duration;
+
+pub const BATCH_SIZE: i64 = 20;
+// Long enough for a whole batch even if every item runs into its timeout
+const CLAIM_LEASE_MINUTES: i64 = 10;
+
+pub trait DeliveryQueue {
+    type Item;
+    type Error: Display;
+
+    // What is being delivered, for the logs
+    const NAME: &'static str;
+
+    // Returns the due items after moving their next attempt to lease_expiry
+    fn claim_due(&self, connection: &DatabaseConnection, limit: i64) -> Result<Vec<Self::Item>, Self::Error>;
+
+    // Sends the item and records how that went
+    fn deliver(&self, connection: &DatabaseConnection, item: &Self::Item) -> Result<(), Self::Error>;
+
+    fn describe(&self, item: &Self::Item) -> String;
+}
+
+pub fn lease_expiry() -> chrono::DateTime<chrono::Utc> {
+    chrono::Utc::now() + chrono::Duration::minutes(CLAIM_LEASE_MINUTES)
+}
+
+pub fn deliver_due<Q: DeliveryQueue>(queue: &Q, connection: &DatabaseConnection) -> Result<usize, Q::Error> {
+    let due = queue.claim_due(connection, BATCH_SIZE)?;
+    for item in &due {
+        if let Err(err) = queue.deliver(connection, item) {
+            error!("Failed to record the delivery of {}: {}", queue.describe(item), err);
+        }
+    }
+    Ok(due.len())
+}
+
+// The queue is made on the worker's thread, for things like HTTP agents that are best kept to one
+pub fn spawn_worker<Q, F>(pool: DatabasePool, poll_interval: Duration, make_queue: F) -> std::thread::JoinHandle<()>
+where
+    Q: DeliveryQueue,
+    F: FnOnce() -> Q + Send + 'static,
+{
+    std::thread::spawn(move || {
+        let queue = make_queue();
+        loop {
+            let delivered = match pool.get() {
+                Ok(connection) => deliver_due(&queue, &connection).map_err(|err| format!("{}", err)),
+                Err(err) => Err(format!("{}", err)),
+            };
+            match delivered {
+                // A full batch suggests there is more waiting, so go again straight away
+                Ok(count) if count as i64 == BATCH_SIZE => continue,
+                Ok(_) => {}
+                Err(err) => error!("Failed to deliver {}: {}", Q::NAME, err),
+            }
+            std::thread::sleep(poll_interval);
+        }
+    })
+}
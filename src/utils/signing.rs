@@ -0,0 +1,76 @@
+// HMAC-SHA256 signing of values handed out to users (e.g. in emailed links) so they can be
+// checked for tampering before touching the database
+//
+// The key is derived from RYOB_SECRET_KEY. Without it a random key is generated at startup,
+// which means anything signed, and everyone's session, becomes invalid when the server restarts.
+
+use hmac::{Hmac, Mac};
+use log::warn;
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+
+type HmacSha256 = Hmac<Sha256>;
+
+static SECRET_KEY: OnceLock<[u8; 32]> = OnceLock::new();
+
+fn secret_key() -> &'static [u8; 32] {
+    SECRET_KEY.get_or_init(|| match std::env::var("RYOB_SECRET_KEY") {
+        Ok(secret) => Sha256::digest(secret.as_bytes()).into(),
+        Err(_) => {
+            warn!("RYOB_SECRET_KEY is not set, signed values will not survive a restart");
+            rand::random()
+        }
+    })
+}
+
+fn signature(payload: &str) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(secret_key()).expect("HMAC accepts keys of any length");
+    mac.update(payload.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+pub fn encode(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+pub fn hash(value: &str) -> String {
+    encode(&Sha256::digest(value.as_bytes()))
+}
+
+pub fn random_string(byte_count: usize) -> String {
+    let bytes: Vec<u8> = (0..byte_count).map(|_| rand::random::<u8>()).collect();
+    encode(&bytes)
+}
+
+// Produces "<payload>.<signature>"
+pub fn sign(payload: &str) -> String {
+    format!("{}.{}", payload, encode(&signature(payload)))
+}
+
+// Returns the payload of a value produced by sign if its signature is intact
+pub fn verify(signed: &str) -> Option<&str> {
+    let (payload, encoded_signature) = signed.rsplit_once('.')?;
+    let provided = base64::decode_config(encoded_signature, base64::URL_SAFE_NO_PAD).ok()?;
+    let mut mac = HmacSha256::new_from_slice(secret_key()).expect("HMAC accepts keys of any length");
+    mac.update(payload.as_bytes());
+    mac.verify_slice(&provided).ok()?;
+    Some(payload)
+}
+
+// A key for something else that needs one, such as the session cookie, which changes along with
+// RYOB_SECRET_KEY. Hashed rather than signed, so nothing handed out by sign can be used as a key.
+pub fn derive_key(purpose: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"ryob derived key\0");
+    hasher.update(purpose.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(secret_key());
+    hasher.finalize().into()
+}
+
+// For signing with a key other than our own, e.g. a webhook's shared secret
+pub fn hmac_hex(key: &str, message: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+    mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,75 @@
+// Works out what an uploaded file is from its first bytes. Browsers and clients send whatever
+// type and extension they like, so neither is trusted.
+
+const SIGNATURES: [(&[u8], &str); 6] = [
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+];
+
+pub fn content_type(contents: &[u8]) -> Option<&'static str> {
+    if let Some((_, content_type)) = SIGNATURES.iter().find(|(signature, _)| contents.starts_with(signature)) {
+        return Some(content_type);
+    }
+    if contents.len() >= 12 && &contents[0..4] == b"RIFF" && &contents[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    // Anything else is only accepted as plain text, which can't hold control characters
+    let text = std::str::from_utf8(contents).ok()?;
+    if text.chars().all(|c| !c.is_control() || c == '\n' || c == '\r' || c == '\t') {
+        Some("text/plain; charset=utf-8")
+    } else {
+        None
+    }
+}
+
+pub fn is_image(content_type: &str) -> bool {
+    content_type.starts_with("image/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognises_signatures() {
+        assert_eq!(content_type(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Some("image/png"));
+        assert_eq!(content_type(b"\xff\xd8\xff\xe0\0\x10JFIF"), Some("image/jpeg"));
+        assert_eq!(content_type(b"GIF87a\x01\0"), Some("image/gif"));
+        assert_eq!(content_type(b"GIF89a\x01\0"), Some("image/gif"));
+        assert_eq!(content_type(b"%PDF-1.7\n"), Some("application/pdf"));
+        assert_eq!(content_type(b"PK\x03\x04\x14\0"), Some("application/zip"));
+        assert_eq!(content_type(b"RIFF\x24\0\0\0WEBPVP8 "), Some("image/webp"));
+    }
+
+    #[test]
+    fn needs_the_whole_webp_header() {
+        assert_eq!(content_type(b"RIFF\x24\0\0\0WAVEfmt "), None);
+        assert_eq!(content_type(b"RIFF\x24\0\0\0WEB"), None);
+    }
+
+    #[test]
+    fn accepts_plain_text() {
+        assert_eq!(content_type(b"Hello,\r\n\tworld"), Some("text/plain; charset=utf-8"));
+        assert_eq!(content_type("Grüße".as_bytes()), Some("text/plain; charset=utf-8"));
+        assert_eq!(content_type(b""), Some("text/plain; charset=utf-8"));
+    }
+
+    #[test]
+    fn rejects_binary() {
+        assert_eq!(content_type(b"text with a \0 in it"), None);
+        assert_eq!(content_type(b"\x1b[31mred"), None);
+        assert_eq!(content_type(b"\xff\xfe not utf-8"), None);
+    }
+
+    #[test]
+    fn only_images_are_images() {
+        assert!(is_image("image/png"));
+        assert!(is_image("image/webp"));
+        assert!(!is_image("application/pdf"));
+        assert!(!is_image("text/plain; charset=utf-8"));
+    }
+}
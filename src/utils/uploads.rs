@@ -0,0 +1,110 @@
+// Reading files out of multipart/form-data requests, for attachments, avatars and banners
+
+use crate::utils::errors::RyobError;
+use actix_multipart::{Field, Multipart, MultipartError};
+use futures::{Future, Stream};
+use std::collections::HashMap;
+
+pub struct Upload {
+    pub file_name: String,
+    pub contents: Vec<u8>,
+}
+
+// The ordinary form values sent alongside the files. A value sent more than once keeps the last.
+#[derive(Default)]
+pub struct UploadForm {
+    pub values: HashMap<String, String>,
+    pub uploads: Vec<Upload>,
+}
+
+enum Part {
+    Value(String, String),
+    File(Upload),
+}
+
+#[derive(Debug)]
+pub enum UploadError {
+    TooLarge(usize),
+    TooManyFiles(usize),
+    Invalid(MultipartError),
+}
+
+impl std::fmt::Display for UploadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            UploadError::TooLarge(max_size) => write!(f, "Files must be at most {} MiB", max_size / 1024 / 1024),
+            UploadError::TooManyFiles(max_count) => write!(f, "At most {} files can be uploaded at once", max_count),
+            UploadError::Invalid(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<MultipartError> for UploadError {
+    fn from(error: MultipartError) -> UploadError {
+        UploadError::Invalid(error)
+    }
+}
+
+impl From<UploadError> for RyobError {
+    fn from(error: UploadError) -> RyobError {
+        match error {
+            UploadError::TooLarge(_) => RyobError::from_display(actix_web::http::StatusCode::PAYLOAD_TOO_LARGE, error),
+            UploadError::TooManyFiles(_) => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+            UploadError::Invalid(_) => RyobError::from_display(actix_web::http::StatusCode::BAD_REQUEST, error),
+        }
+    }
+}
+
+// Fields without a file name are ordinary form values
+fn read_field(field: Field, max_size: usize) -> impl Future<Item = Option<Part>, Error = UploadError> {
+    let name = field.content_disposition().and_then(|disposition| disposition.get_name().map(str::to_owned));
+    let file_name = field
+        .content_disposition()
+        .and_then(|disposition| disposition.get_filename().map(str::to_owned));
+    field
+        .map_err(UploadError::from)
+        .fold(Vec::new(), move |mut contents, chunk| {
+            // Checked as the upload arrives so an oversized one is never held in memory
+            if contents.len() + chunk.len() > max_size {
+                return Err(UploadError::TooLarge(max_size));
+            }
+            contents.extend_from_slice(&chunk);
+            Ok(contents)
+        })
+        .map(move |contents| match (file_name, name) {
+            (Some(file_name), _) => Some(Part::File(Upload { file_name, contents })),
+            (None, Some(name)) => Some(Part::Value(name, String::from_utf8_lossy(&contents).into_owned())),
+            (None, None) => None,
+        })
+}
+
+fn read_parts(multipart: Multipart, max_size: usize) -> impl Stream<Item = Part, Error = UploadError> {
+    multipart
+        .map_err(UploadError::from)
+        .and_then(move |field| read_field(field, max_size))
+        .filter_map(|part| part)
+}
+
+// Stops after one more file than allowed, which is enough for the caller to know there are too many
+pub fn read_uploads(multipart: Multipart, max_size: usize, max_count: usize) -> impl Future<Item = Vec<Upload>, Error = UploadError> {
+    read_parts(multipart, max_size)
+        .filter_map(|part| match part {
+            Part::File(upload) => Some(upload),
+            Part::Value(..) => None,
+        })
+        .take(max_count as u64 + 1)
+        .collect()
+}
+
+pub fn read_form(multipart: Multipart, max_size: usize, max_count: usize) -> impl Future<Item = UploadForm, Error = UploadError> {
+    read_parts(multipart, max_size).fold(UploadForm::default(), move |mut form, part| {
+        match part {
+            Part::Value(name, value) => {
+                form.values.insert(name, value);
+            }
+            Part::File(_) if form.uploads.len() == max_count => return Err(UploadError::TooManyFiles(max_count)),
+            Part::File(upload) => form.uploads.push(upload),
+        }
+        Ok(form)
+    })
+}
@@ -0,0 +1,60 @@
+// Delivery of board events to the HTTP endpoints registered by admins
+//
+// Each request carries the event as a JSON body with these headers:
+//   X-Ryob-Event      the event name, e.g. post.created
+//   X-Ryob-Delivery   the delivery id, which stays the same across retries
+//   X-Ryob-Timestamp  seconds since the epoch when the request was sent
+//   X-Ryob-Signature  "sha256=" followed by the hex HMAC-SHA256 of "<timestamp>.<body>" keyed
+//                     with the webhook's secret
+// Receivers should check the signature and reject old timestamps to stop replays.
+
+pub mod queue;
+
+use crate::models::webhook::{Webhook, WebhookDelivery};
+use crate::utils::signing;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+pub enum DeliveryError {
+    Status(u16),
+    Transport(String),
+}
+
+impl std::fmt::Display for DeliveryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DeliveryError::Status(status) => write!(f, "The endpoint responded with status {}", status),
+            DeliveryError::Transport(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+pub fn signature(secret: &str, timestamp: i64, body: &str) -> String {
+    format!("sha256={}", signing::hmac_hex(secret, &format!("{}.{}", timestamp, body)))
+}
+
+// Anything but a 2xx response, including redirects, counts as a failure
+pub fn deliver(agent: &ureq::Agent, webhook: &Webhook, delivery: &WebhookDelivery) -> Result<u16, DeliveryError> {
+    let timestamp = chrono::Utc::now().timestamp();
+    let response = agent
+        .post(&webhook.url)
+        .set("Content-Type", "application/json")
+        .set("User-Agent", "ryob-webhooks")
+        .set("X-Ryob-Event", &delivery.event)
+        .set("X-Ryob-Delivery", &delivery.id.value().to_string())
+        .set("X-Ryob-Timestamp", &timestamp.to_string())
+        .set("X-Ryob-Signature", &signature(&webhook.secret, timestamp, &delivery.payload))
+        .send_string(&delivery.payload);
+
+    match response {
+        Ok(response) if (200..300).contains(&response.status()) => Ok(response.status()),
+        Ok(response) => Err(DeliveryError::Status(response.status())),
+        Err(ureq::Error::Status(status, _)) => Err(DeliveryError::Status(status)),
+        Err(err) => Err(DeliveryError::Transport(format!("{}", err))),
+    }
+}
+
+pub fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new().timeout(TIMEOUT).redirects(0).build()
+}
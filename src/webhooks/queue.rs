@@ -0,0 +1,40 @@
+// Background delivery of the webhook_deliveries queue
+
+use super::DeliveryError;
+use crate::database::types::*;
+use crate::models::webhook::{Webhook, WebhookDelivery, WebhookError};
+use crate::utils::queue::{self, DeliveryQueue};
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+struct WebhookQueue {
+    agent: ureq::Agent,
+}
+
+impl DeliveryQueue for WebhookQueue {
+    type Item = (WebhookDelivery, Webhook);
+    type Error = WebhookError;
+
+    const NAME: &'static str = "queued webhooks";
+
+    fn claim_due(&self, connection: &DatabaseConnection, limit: i64) -> Result<Vec<(WebhookDelivery, Webhook)>, WebhookError> {
+        WebhookDelivery::claim_due(connection, limit)
+    }
+
+    fn deliver(&self, connection: &DatabaseConnection, (delivery, webhook): &(WebhookDelivery, Webhook)) -> Result<(), WebhookError> {
+        match super::deliver(&self.agent, webhook, delivery) {
+            Ok(status) => delivery.mark_delivered(connection, status),
+            Err(err @ DeliveryError::Status(status)) => delivery.mark_failed(connection, Some(status), &format!("{}", err)),
+            Err(err) => delivery.mark_failed(connection, None, &format!("{}", err)),
+        }
+    }
+
+    fn describe(&self, (delivery, _): &(WebhookDelivery, Webhook)) -> String {
+        format!("webhook delivery {:?}", delivery.id)
+    }
+}
+
+pub fn spawn_worker(pool: DatabasePool) -> std::thread::JoinHandle<()> {
+    queue::spawn_worker(pool, POLL_INTERVAL, || WebhookQueue { agent: super::agent() })
+}
@@ -0,0 +1,37 @@
+// Listens for new topics and posts on pages that link a live stream. Without this script the
+// pages work as usual and only need refreshing.
+(function () {
+    var script = document.currentScript;
+    if (!script || !window.EventSource) {
+        return;
+    }
+
+    var source = new EventSource(script.getAttribute("data-url"));
+
+    // New replies are added to the end of the topic, skipping any already on the page
+    source.addEventListener("post", function (event) {
+        var posts = document.querySelector(".ryob-posts");
+        if (!posts || document.getElementById("post-" + event.lastEventId)) {
+            return;
+        }
+        posts.insertAdjacentHTML("beforeend", event.data);
+    });
+
+    // New topics are only counted, the list is refreshed by following the notice. The page gives
+    // the notice in each plural form as data-one, data-other and so on, with {count} to fill in.
+    var plurals = window.Intl && Intl.PluralRules ? new Intl.PluralRules(document.documentElement.lang) : null;
+    var newTopics = {};
+    var newTopicCount = 0;
+    source.addEventListener("topic", function (event) {
+        var notice = document.querySelector(".ryob-live-notice");
+        if (!notice || newTopics[event.lastEventId]) {
+            return;
+        }
+        newTopics[event.lastEventId] = true;
+        newTopicCount += 1;
+        var form = plurals ? plurals.select(newTopicCount) : newTopicCount === 1 ? "one" : "other";
+        var text = notice.getAttribute("data-" + form) || notice.getAttribute("data-other");
+        notice.textContent = text.replace("{count}", newTopicCount);
+        notice.hidden = false;
+    });
+})();
\ No newline at end of file